@@ -0,0 +1,142 @@
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Instant;
+
+use anyhow::{Context, Result};
+use engine::{
+    GenerateMoves, POSITION_EVALUATOR, Position, SearchParams, TranspositionTable, search,
+};
+use tracing::{error, warn};
+
+use crate::{priority, uci};
+
+/// Reads the positions to analyse, one fen per line. Empty lines and lines starting with `#`
+/// are skipped.
+pub(crate) fn parse_analysis_file(contents: &str) -> Result<Vec<Position>> {
+    contents
+        .lines()
+        .enumerate()
+        .map(|(line_idx, line)| (line_idx, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(line_idx, fen)| {
+            Position::from_fen(fen)
+                .with_context(|| format!("Invalid fen on analysis file line {}", line_idx + 1))
+        })
+        .collect()
+}
+
+/// Searches every position in the file at `path` one after another, writing a result line per
+/// position and a summary at the end. Setting `terminate` stops the current search and skips
+/// the remaining positions. Returns the analysis thread, unless the file couldn't be read.
+pub(crate) fn spawn_analyze_file(
+    path: &Path,
+    params: SearchParams,
+    move_gen: impl GenerateMoves + Copy + Send + Sync + 'static,
+    transposition_table: Arc<Mutex<TranspositionTable>>,
+    terminate: Arc<AtomicBool>,
+    low_cpu_priority: bool,
+) -> Option<JoinHandle<()>> {
+    let positions = match fs::read_to_string(path)
+        .with_context(|| format!("Couldn't read analysis file {}", path.display()))
+        .and_then(|contents| parse_analysis_file(&contents))
+    {
+        Ok(positions) => positions,
+        Err(err) => {
+            warn!(target: "uci", "{:#}", err);
+            terminate.store(true, Ordering::Relaxed);
+            return None;
+        }
+    };
+
+    Some(std::thread::spawn(move || {
+        if low_cpu_priority {
+            priority::lower_thread_priority();
+        }
+        let mut transposition_table = transposition_table
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let start = Instant::now();
+        let mut num_analysed = 0;
+        let mut total_nodes = 0;
+
+        for (idx, position) in positions.iter().enumerate() {
+            if terminate.load(Ordering::Relaxed) {
+                break;
+            }
+            let (best_move, info) = match search(
+                position,
+                &params,
+                move_gen,
+                POSITION_EVALUATOR,
+                &mut transposition_table,
+                Arc::clone(&terminate),
+            ) {
+                Ok(result) => result,
+                Err(err) => {
+                    error!(target: "uci", "Search error for {}: {}", position.to_fen(), err);
+                    continue;
+                }
+            };
+            num_analysed += 1;
+            total_nodes += info.positions_processed;
+
+            let (best_move_str, score_str) = match best_move {
+                Some(best_move) => (
+                    best_move.to_string().to_lowercase(),
+                    info.move_evals[&best_move].to_string(),
+                ),
+                None => ("0000".to_string(), "none".to_string()),
+            };
+            uci!(
+                "info string analyzefile position {}/{} fen {} bestmove {} score {} depth {} nodes {} time {}",
+                idx + 1,
+                positions.len(),
+                position.to_fen(),
+                best_move_str,
+                score_str,
+                info.iteration_times.len(),
+                info.positions_processed,
+                info.time_elapsed.as_millis(),
+            );
+        }
+
+        uci!(
+            "info string analyzefile done positions {}/{} nodes {} time {}",
+            num_analysed,
+            positions.len(),
+            total_nodes,
+            start.elapsed().as_millis(),
+        );
+        terminate.store(true, Ordering::Relaxed);
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use testresult::TestResult;
+
+    #[test]
+    fn test_parse_analysis_file() -> TestResult {
+        let contents = "# endgames\n\n8/P7/8/8/8/8/8/k1K5 w - - 0 1\n  \nrnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1\n";
+        let positions = parse_analysis_file(contents)?;
+
+        assert_eq!(
+            positions,
+            vec![
+                Position::from_fen("8/P7/8/8/8/8/8/k1K5 w - - 0 1")?,
+                Position::start()
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_analysis_file_invalid_fen() {
+        let err = parse_analysis_file("8/P7/8/8/8/8/8/k1K5 w - - 0 1\n8/P7/8 w - -\n").unwrap_err();
+        assert!(err.to_string().contains("line 2"));
+    }
+}
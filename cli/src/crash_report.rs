@@ -0,0 +1,207 @@
+use std::backtrace::Backtrace;
+use std::collections::VecDeque;
+use std::fs;
+use std::panic::{self, PanicHookInfo};
+use std::path::PathBuf;
+use std::process;
+use std::sync::{Mutex, MutexGuard};
+
+use engine::{Move, Position, SearchParams};
+
+/// Number of most recent UCI commands included in a crash report.
+const MAX_RECORDED_COMMANDS: usize = 100;
+
+/// What the engine was doing, kept up to date so a crash can be reproduced from its report
+/// without asking for the GUI's logs.
+#[derive(Debug)]
+struct CrashContext {
+    crash_dir: Option<PathBuf>,
+    commands: VecDeque<String>,
+    start_fen: Option<String>,
+    moves: Vec<Move>,
+    fen: Option<String>,
+    search_params: Option<SearchParams>,
+}
+
+impl CrashContext {
+    const fn new() -> Self {
+        Self {
+            crash_dir: None,
+            commands: VecDeque::new(),
+            start_fen: None,
+            moves: vec![],
+            fen: None,
+            search_params: None,
+        }
+    }
+
+    fn record_command(&mut self, command: &str) {
+        if self.commands.len() == MAX_RECORDED_COMMANDS {
+            self.commands.pop_front();
+        }
+        self.commands.push_back(command.to_string());
+    }
+
+    fn report(&self, message: &str, location: &str, backtrace: &Backtrace) -> String {
+        let moves = self
+            .moves
+            .iter()
+            .map(|mve| mve.to_string().to_lowercase())
+            .collect::<Vec<_>>()
+            .join(" ");
+        let search_params = self
+            .search_params
+            .as_ref()
+            .map_or("none".to_string(), |params| format!("{:?}", params));
+        let commands = self
+            .commands
+            .iter()
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join("\n");
+        format!(
+            "{} {} crash report\n\
+             panicked at {}: {}\n\n\
+             start fen: {}\n\
+             moves: {}\n\
+             fen: {}\n\
+             search params: {}\n\n\
+             last {} commands:\n{}\n\n\
+             backtrace:\n{}\n",
+            engine::NAME,
+            env!("CARGO_PKG_VERSION"),
+            location,
+            message,
+            self.start_fen.as_deref().unwrap_or("startpos"),
+            moves,
+            self.fen.as_deref().unwrap_or("none"),
+            search_params,
+            self.commands.len(),
+            commands,
+            backtrace,
+        )
+    }
+}
+
+static CRASH_CONTEXT: Mutex<CrashContext> = Mutex::new(CrashContext::new());
+
+fn crash_context() -> MutexGuard<'static, CrashContext> {
+    CRASH_CONTEXT
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+pub(crate) fn record_command(command: &str) {
+    crash_context().record_command(command);
+}
+
+/// Records the position set by a `position` command, which was reached by playing `moves`
+/// from `start_fen` (or the start position).
+pub(crate) fn record_position(start_fen: Option<&str>, moves: &[Move], position: &Position) {
+    let mut context = crash_context();
+    context.start_fen = start_fen.map(str::to_string);
+    context.moves = moves.to_vec();
+    context.fen = Some(position.to_fen());
+}
+
+pub(crate) fn record_search_params(params: &SearchParams) {
+    crash_context().search_params = Some(params.clone());
+}
+
+/// Writes a crash report to `crash_dir` whenever the process panics, before running the
+/// previous panic hook.
+pub fn install_crash_report_hook(crash_dir: PathBuf) {
+    crash_context().crash_dir = Some(crash_dir);
+    let previous_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        write_crash_report(info);
+        previous_hook(info);
+    }));
+}
+
+/// Writes a crash report for the panic, if [`install_crash_report_hook`] was called. Returns
+/// where the report was written.
+pub(crate) fn write_crash_report(info: &PanicHookInfo) -> Option<PathBuf> {
+    let context = crash_context();
+    let crash_dir = context.crash_dir.as_ref()?;
+    let report = context.report(
+        &panic_message(info),
+        &panic_location(info),
+        &Backtrace::force_capture(),
+    );
+
+    let now_str = chrono::Local::now().format("%H.%M.%S_%Y.%m.%d");
+    let crash_path = crash_dir.join(format!("crash_{}_{}.txt", now_str, process::id()));
+    fs::create_dir_all(crash_dir).ok()?;
+    fs::write(&crash_path, report).ok()?;
+    eprintln!("Crash report written to {}", crash_path.display());
+    Some(crash_path)
+}
+
+pub(crate) fn panic_message(info: &PanicHookInfo) -> String {
+    if let Some(s) = info.payload().downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = info.payload().downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "unknown panic message".to_string()
+    }
+}
+
+pub(crate) fn panic_location(info: &PanicHookInfo) -> String {
+    if let Some(location) = info.location() {
+        format!(
+            "{}:{}:{}",
+            location.file(),
+            location.line(),
+            location.column()
+        )
+    } else {
+        "unknown location".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use engine::Square::*;
+
+    #[test]
+    fn test_record_command_keeps_most_recent() {
+        let mut context = CrashContext::new();
+        for idx in 0..MAX_RECORDED_COMMANDS + 5 {
+            context.record_command(&format!("go depth {}", idx));
+        }
+
+        assert_eq!(context.commands.len(), MAX_RECORDED_COMMANDS);
+        assert_eq!(context.commands.front().unwrap(), "go depth 5");
+        assert_eq!(
+            context.commands.back().unwrap(),
+            &format!("go depth {}", MAX_RECORDED_COMMANDS + 4)
+        );
+    }
+
+    #[test]
+    fn test_report() {
+        let mut context = CrashContext::new();
+        context.record_command("position startpos moves e2e4");
+        context.record_command("go depth 5");
+        let mut position = Position::start();
+        position.make_move(Move::new(E2, E4));
+        context.start_fen = None;
+        context.moves = vec![Move::new(E2, E4)];
+        context.fen = Some(position.to_fen());
+        context.search_params = Some(SearchParams {
+            max_depth: Some(5),
+            ..SearchParams::default()
+        });
+
+        let report = context.report("oops", "src/lib.rs:1:1", &Backtrace::disabled());
+
+        assert!(report.contains("panicked at src/lib.rs:1:1: oops"));
+        assert!(report.contains("start fen: startpos\nmoves: e2e4\n"));
+        assert!(report.contains(&format!("fen: {}\n", position.to_fen())));
+        assert!(report.contains("max_depth: Some(5)"));
+        assert!(report.contains("last 2 commands:\nposition startpos moves e2e4\ngo depth 5\n"));
+    }
+}
@@ -0,0 +1,204 @@
+use std::fmt;
+
+use anyhow::{Result, bail};
+
+/// Z score of a two sided 95% confidence interval.
+const CONFIDENCE_95_Z: f64 = 1.959964;
+
+/// Elo difference of a match, from the first engine's point of view.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EloEstimate {
+    pub elo: f64,
+    /// Half the width of the 95% confidence interval.
+    pub error_margin: f64,
+    /// Likelihood of superiority: the probability the first engine is the stronger one.
+    pub los: f64,
+}
+
+impl fmt::Display for EloEstimate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Elo {:+.1} +/- {:.1} (95%), LOS {:.1}%",
+            self.elo,
+            self.error_margin,
+            self.los * 100.
+        )
+    }
+}
+
+/// Wins, draws and losses of a match, from the first engine's point of view.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MatchResults {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+impl MatchResults {
+    pub fn num_games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    /// Fraction of the points scored, counting a draw as half a point.
+    pub fn score(&self) -> f64 {
+        (f64::from(self.wins) + f64::from(self.draws) / 2.) / f64::from(self.num_games())
+    }
+
+    /// Estimates the Elo difference treating every game as independent. Fails if no games were
+    /// played or every game was won or lost, since the Elo difference is unbounded then.
+    pub fn elo_estimate(&self) -> Result<EloEstimate> {
+        let outcomes = [(1., self.wins), (0.5, self.draws), (0., self.losses)];
+        let (score, std_error) = score_and_std_error(&outcomes)?;
+        let decisive_games = f64::from(self.wins + self.losses);
+        let los = if decisive_games == 0. {
+            0.5
+        } else {
+            normal_cdf(
+                f64::from(self.wins) - f64::from(self.losses),
+                decisive_games,
+            )
+        };
+        Ok(estimate(score, std_error, los))
+    }
+}
+
+/// Results of game pairs, where both games of a pair are played from the same opening with
+/// colors reversed. `pairs[points]` counts the pairs where the first engine scored `points / 2`
+/// of the pair's 2 points.
+///
+/// Counting pairs rather than games removes the correlation between the two games of an
+/// opening, which gives tighter error bars when the openings are unbalanced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Pentanomial {
+    pub pairs: [u32; 5],
+}
+
+impl Pentanomial {
+    pub fn num_pairs(&self) -> u32 {
+        self.pairs.iter().sum()
+    }
+
+    /// Estimates the Elo difference from the pair results. Fails if no pairs were played or
+    /// every pair was won or lost.
+    pub fn elo_estimate(&self) -> Result<EloEstimate> {
+        let outcomes = [
+            (0., self.pairs[0]),
+            (0.25, self.pairs[1]),
+            (0.5, self.pairs[2]),
+            (0.75, self.pairs[3]),
+            (1., self.pairs[4]),
+        ];
+        let (score, std_error) = score_and_std_error(&outcomes)?;
+        let los = normal_cdf(score - 0.5, std_error.powi(2));
+        Ok(estimate(score, std_error, los))
+    }
+}
+
+/// Mean score and its standard error, from how many times each score was reached.
+fn score_and_std_error(outcomes: &[(f64, u32)]) -> Result<(f64, f64)> {
+    let num_outcomes: f64 = outcomes.iter().map(|&(_, count)| f64::from(count)).sum();
+    if num_outcomes == 0. {
+        bail!("No games played");
+    }
+    let score = outcomes
+        .iter()
+        .map(|&(points, count)| points * f64::from(count))
+        .sum::<f64>()
+        / num_outcomes;
+    if score <= 0. || score >= 1. {
+        bail!("Elo difference is unbounded when every game is won or lost");
+    }
+    let variance = outcomes
+        .iter()
+        .map(|&(points, count)| (points - score).powi(2) * f64::from(count))
+        .sum::<f64>()
+        / num_outcomes;
+    Ok((score, (variance / num_outcomes).sqrt()))
+}
+
+fn estimate(score: f64, std_error: f64, los: f64) -> EloEstimate {
+    let lower = score_to_elo((score - CONFIDENCE_95_Z * std_error).max(f64::EPSILON));
+    let upper = score_to_elo((score + CONFIDENCE_95_Z * std_error).min(1. - f64::EPSILON));
+    EloEstimate {
+        elo: score_to_elo(score),
+        error_margin: (upper - lower) / 2.,
+        los,
+    }
+}
+
+/// Elo difference which gives an expected score of `score`.
+fn score_to_elo(score: f64) -> f64 {
+    -400. * (1. / score - 1.).log10()
+}
+
+/// Probability a normally distributed variable with mean `mean` and variance `variance` is
+/// positive.
+fn normal_cdf(mean: f64, variance: f64) -> f64 {
+    if variance == 0. {
+        return if mean > 0. { 1. } else { 0. };
+    }
+    0.5 * (1. + erf(mean / (2. * variance).sqrt()))
+}
+
+/// Source: Abramowitz and Stegun 7.1.26, accurate to 1.5e-7.
+fn erf(x: f64) -> f64 {
+    let t = 1. / (1. + 0.3275911 * x.abs());
+    let polynomial = t
+        * (0.254829592
+            + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    let abs_erf = 1. - polynomial * (-x * x).exp();
+    abs_erf.copysign(x)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+    use testresult::TestResult;
+
+    fn assert_close(got: f64, want: f64) {
+        assert!((got - want).abs() < 0.01, "got {}, want {}", got, want);
+    }
+
+    #[test_case(MatchResults { wins: 60, draws: 20, losses: 40 }, 58.45, 57.96, 0.9772 ; "stronger")]
+    #[test_case(MatchResults { wins: 100, draws: 100, losses: 100 }, 0., 32.19, 0.5 ; "equal")]
+    #[test_case(MatchResults { wins: 0, draws: 10, losses: 0 }, 0., 0., 0.5 ; "only draws")]
+    fn test_match_results_elo_estimate(
+        results: MatchResults,
+        elo_want: f64,
+        error_margin_want: f64,
+        los_want: f64,
+    ) -> TestResult {
+        let estimate = results.elo_estimate()?;
+        assert_close(estimate.elo, elo_want);
+        assert_close(estimate.error_margin, error_margin_want);
+        assert_close(estimate.los, los_want);
+        Ok(())
+    }
+
+    #[test_case(MatchResults::default() ; "no games")]
+    #[test_case(MatchResults { wins: 3, draws: 0, losses: 0 } ; "all wins")]
+    fn test_match_results_elo_estimate_unbounded(results: MatchResults) {
+        assert!(results.elo_estimate().is_err());
+    }
+
+    #[test]
+    fn test_pentanomial_elo_estimate() -> TestResult {
+        let estimate = Pentanomial {
+            pairs: [5, 20, 40, 25, 10],
+        }
+        .elo_estimate()?;
+        assert_close(estimate.elo, 26.11);
+        assert_close(estimate.error_margin, 34.83);
+        assert_close(estimate.los, 0.9305);
+        Ok(())
+    }
+
+    #[test_case(0., 0. ; "zero")]
+    #[test_case(1., 0.842701 ; "positive")]
+    #[test_case(-0.5, -0.520500 ; "negative")]
+    fn test_erf(x: f64, want: f64) {
+        assert!((erf(x) - want).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,229 @@
+use std::str::FromStr;
+use std::sync::{Arc, atomic::AtomicBool};
+
+use anyhow::{Context, Result, bail};
+use engine::{
+    MOVE_GEN, Move, POSITION_EVALUATOR, Position, RelativeEval, SearchParams, TranspositionTable,
+    search,
+};
+use serde_json::json;
+
+/// Mate scores are counted as this many centipawns when correlating evals, so a single mate
+/// doesn't swamp every other position.
+const MATE_CENTIPAWNS: i32 = 3000;
+
+/// A position in the strength gate suite, with the move and eval it's expected to find.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GateCase {
+    pub fen: String,
+    pub best_move: Move,
+    /// Reference eval in centipawns, relative to the side to move.
+    pub reference_eval: i32,
+}
+
+/// Parses a gate suite, with one `<fen>; <best move>; <reference eval>` case per line. Empty
+/// lines and lines starting with `#` are skipped.
+pub fn parse_gate_suite(suite: &str) -> Result<Vec<GateCase>> {
+    suite
+        .lines()
+        .enumerate()
+        .map(|(line_idx, line)| (line_idx, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(line_idx, line)| {
+            let fields: Vec<&str> = line.split(';').map(str::trim).collect();
+            let [fen, best_move, reference_eval] = fields[..] else {
+                bail!(
+                    "Gate suite line {} should have 3 fields: {}",
+                    line_idx + 1,
+                    line
+                );
+            };
+            Position::from_fen(fen)
+                .with_context(|| format!("Invalid fen on gate suite line {}", line_idx + 1))?;
+            Ok(GateCase {
+                fen: fen.to_string(),
+                best_move: Move::from_str(best_move)?,
+                reference_eval: reference_eval.parse().with_context(|| {
+                    format!("Invalid reference eval on gate suite line {}", line_idx + 1)
+                })?,
+            })
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GateResult {
+    /// Node budget each position was searched with.
+    pub nodes: u64,
+    /// Fraction of positions where the expected best move was found.
+    pub best_move_rate: f64,
+    /// Pearson correlation between the search's evals and the reference evals.
+    pub eval_correlation: f64,
+}
+
+impl GateResult {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&json!({
+            "nodes": self.nodes,
+            "best_move_rate": self.best_move_rate,
+            "eval_correlation": self.eval_correlation,
+        }))
+        .expect("Gate result should serialize")
+    }
+
+    pub fn from_json(s: &str) -> Result<Self> {
+        let value: serde_json::Value = serde_json::from_str(s)?;
+        let field = |name: &str| {
+            value[name]
+                .as_f64()
+                .with_context(|| format!("Missing `{}` in gate baseline", name))
+        };
+        Ok(Self {
+            nodes: value["nodes"]
+                .as_u64()
+                .context("Missing `nodes` in gate baseline")?,
+            best_move_rate: field("best_move_rate")?,
+            eval_correlation: field("eval_correlation")?,
+        })
+    }
+
+    /// Describes each metric that dropped more than `max_regression` below the baseline.
+    pub fn regressions(&self, baseline: &GateResult, max_regression: f64) -> Vec<String> {
+        [
+            (
+                "best move rate",
+                self.best_move_rate,
+                baseline.best_move_rate,
+            ),
+            (
+                "eval correlation",
+                self.eval_correlation,
+                baseline.eval_correlation,
+            ),
+        ]
+        .into_iter()
+        .filter(|&(_, got, want)| got < want - max_regression)
+        .map(|(name, got, want)| format!("{} regressed from {:.3} to {:.3}", name, want, got))
+        .collect()
+    }
+}
+
+/// Searches every case with a fixed node budget. Unlike a time limit this gives the same
+/// result on every machine, so it can be compared against a checked in baseline.
+pub fn run_gate(cases: &[GateCase], nodes: u64) -> Result<GateResult> {
+    let mut num_best_moves_found = 0;
+    let mut evals = Vec::with_capacity(cases.len());
+    let mut reference_evals = Vec::with_capacity(cases.len());
+    for case in cases {
+        let position = Position::from_fen(&case.fen)?;
+        let (best_move, info) = search(
+            &position,
+            &SearchParams {
+                max_nodes: Some(nodes),
+                ..SearchParams::default()
+            },
+            MOVE_GEN,
+            POSITION_EVALUATOR,
+            &mut TranspositionTable::new(),
+            Arc::new(AtomicBool::new(false)),
+        )?;
+        let Some(best_move) = best_move else {
+            bail!("Node budget too small to finish depth 1 for {}", case.fen);
+        };
+        if best_move == case.best_move {
+            num_best_moves_found += 1;
+        }
+        evals.push(eval_to_centipawns(info.move_evals[&best_move]).into());
+        reference_evals.push(case.reference_eval.into());
+    }
+
+    Ok(GateResult {
+        nodes,
+        best_move_rate: f64::from(num_best_moves_found) / cases.len().max(1) as f64,
+        eval_correlation: pearson_correlation(&evals, &reference_evals),
+    })
+}
+
+fn eval_to_centipawns(eval: RelativeEval) -> i32 {
+    eval.centipawns().unwrap_or(if eval > RelativeEval::DRAW {
+        MATE_CENTIPAWNS
+    } else {
+        -MATE_CENTIPAWNS
+    })
+}
+
+/// Returns 0 if either side has no variance, since the correlation is undefined.
+fn pearson_correlation(xs: &[f64], ys: &[f64]) -> f64 {
+    debug_assert_eq!(xs.len(), ys.len());
+    let n = xs.len() as f64;
+    let mean_x = xs.iter().sum::<f64>() / n;
+    let mean_y = ys.iter().sum::<f64>() / n;
+    let (mut covariance, mut variance_x, mut variance_y) = (0.0, 0.0, 0.0);
+    for (x, y) in xs.iter().zip(ys) {
+        covariance += (x - mean_x) * (y - mean_y);
+        variance_x += (x - mean_x).powi(2);
+        variance_y += (y - mean_y).powi(2);
+    }
+    if variance_x == 0.0 || variance_y == 0.0 {
+        return 0.0;
+    }
+    covariance / (variance_x * variance_y).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use engine::Square::*;
+    use test_case::test_case;
+    use testresult::TestResult;
+
+    #[test]
+    fn test_parse_gate_suite() -> TestResult {
+        let suite = "# comment\n\n8/P7/8/8/8/8/8/k1K5 w - - 0 1; a7a8q; 900\n";
+        assert_eq!(
+            parse_gate_suite(suite)?,
+            vec![GateCase {
+                fen: "8/P7/8/8/8/8/8/k1K5 w - - 0 1".to_string(),
+                best_move: Move::with_promotion(A7, A8, engine::Piece::Queen),
+                reference_eval: 900,
+            }]
+        );
+        Ok(())
+    }
+
+    #[test_case("8/P7/8/8/8/8/8/k1K5 w - - 0 1; a7a8q" ; "missing eval")]
+    #[test_case("8/P7/8/8/8/8/8/k1K5 w - - 0 1; a7a8q; lots" ; "invalid eval")]
+    #[test_case("8/P7/8/8/8/8/8/k1K5 x - - 0 1; a7a8q; 900" ; "invalid fen")]
+    fn test_parse_gate_suite_invalid(suite: &str) {
+        assert!(parse_gate_suite(suite).is_err());
+    }
+
+    #[test_case(&[1.0, 2.0, 3.0], &[10.0, 20.0, 30.0], 1.0 ; "perfect")]
+    #[test_case(&[1.0, 2.0, 3.0], &[30.0, 20.0, 10.0], -1.0 ; "inverse")]
+    #[test_case(&[1.0, 1.0, 1.0], &[10.0, 20.0, 30.0], 0.0 ; "no variance")]
+    fn test_pearson_correlation(xs: &[f64], ys: &[f64], want: f64) {
+        assert!((pearson_correlation(xs, ys) - want).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_gate_result_regressions() -> TestResult {
+        let baseline = GateResult {
+            nodes: 1000,
+            best_move_rate: 0.8,
+            eval_correlation: 0.9,
+        };
+        assert_eq!(GateResult::from_json(&baseline.to_json())?, baseline);
+
+        let got = GateResult {
+            best_move_rate: 0.7,
+            eval_correlation: 0.89,
+            ..baseline
+        };
+        assert_eq!(
+            got.regressions(&baseline, 0.05),
+            vec!["best move rate regressed from 0.800 to 0.700".to_string()]
+        );
+        assert!(got.regressions(&baseline, 0.2).is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,172 @@
+use std::fmt;
+use std::thread;
+
+use engine::SLIDING_PIECES_BACKEND;
+
+/// CPU features the engine can make use of.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CpuFeatures {
+    pub popcnt: bool,
+    pub bmi2: bool,
+    pub avx2: bool,
+}
+
+impl CpuFeatures {
+    /// Features supported by the CPU the engine is running on.
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    pub fn detect() -> Self {
+        Self {
+            popcnt: is_x86_feature_detected!("popcnt"),
+            bmi2: is_x86_feature_detected!("bmi2"),
+            avx2: is_x86_feature_detected!("avx2"),
+        }
+    }
+
+    #[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
+    pub fn detect() -> Self {
+        Self::default()
+    }
+
+    /// Features the binary was compiled to use. The compiler only emits instructions for these,
+    /// even if the CPU supports more.
+    pub const fn compiled() -> Self {
+        Self {
+            popcnt: cfg!(target_feature = "popcnt"),
+            bmi2: cfg!(target_feature = "bmi2"),
+            avx2: cfg!(target_feature = "avx2"),
+        }
+    }
+
+    /// Features that are in `self` but not in `other`.
+    pub fn difference(&self, other: &Self) -> Self {
+        Self {
+            popcnt: self.popcnt && !other.popcnt,
+            bmi2: self.bmi2 && !other.bmi2,
+            avx2: self.avx2 && !other.avx2,
+        }
+    }
+}
+
+impl fmt::Display for CpuFeatures {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<&str> = [
+            (self.popcnt, "popcnt"),
+            (self.bmi2, "bmi2"),
+            (self.avx2, "avx2"),
+        ]
+        .into_iter()
+        .filter_map(|(enabled, name)| enabled.then_some(name))
+        .collect();
+        if names.is_empty() {
+            write!(f, "none")
+        } else {
+            write!(f, "{}", names.join(","))
+        }
+    }
+}
+
+/// What the machine the engine runs on supports, and which code paths the build uses on it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostCapabilities {
+    pub cpu_features: CpuFeatures,
+    pub compiled_features: CpuFeatures,
+    pub num_cores: Option<usize>,
+    pub available_memory_bytes: Option<u64>,
+}
+
+impl HostCapabilities {
+    pub fn detect() -> Self {
+        Self {
+            cpu_features: CpuFeatures::detect(),
+            compiled_features: CpuFeatures::compiled(),
+            num_cores: thread::available_parallelism().ok().map(usize::from),
+            available_memory_bytes: detect_available_memory_bytes(),
+        }
+    }
+
+    /// Features the CPU supports that the build doesn't use, since it wasn't compiled for them.
+    pub fn unused_features(&self) -> CpuFeatures {
+        self.cpu_features.difference(&self.compiled_features)
+    }
+}
+
+impl fmt::Display for HostCapabilities {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "cpu {} build {} cores {} memory {} movegen {}",
+            self.cpu_features,
+            self.compiled_features,
+            self.num_cores
+                .map_or("unknown".to_string(), |num_cores| num_cores.to_string()),
+            self.available_memory_bytes
+                .map_or("unknown".to_string(), |bytes| format!(
+                    "{}MiB",
+                    bytes / (1024 * 1024)
+                )),
+            SLIDING_PIECES_BACKEND,
+        )
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn detect_available_memory_bytes() -> Option<u64> {
+    parse_mem_available(&std::fs::read_to_string("/proc/meminfo").ok()?)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn detect_available_memory_bytes() -> Option<u64> {
+    None
+}
+
+/// Parses the `MemAvailable` line of `/proc/meminfo`, which is given in kB.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_mem_available(meminfo: &str) -> Option<u64> {
+    let line = meminfo
+        .lines()
+        .find(|line| line.starts_with("MemAvailable:"))?;
+    let kilobytes: u64 = line
+        .trim_start_matches("MemAvailable:")
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse()
+        .ok()?;
+    Some(kilobytes * 1024)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("MemTotal:       16303420 kB\nMemAvailable:    8151710 kB\n", Some(8151710 * 1024) ; "available")]
+    #[test_case("MemTotal:       16303420 kB\n", None ; "missing")]
+    #[test_case("MemAvailable:    lots kB\n", None ; "invalid")]
+    fn test_parse_mem_available(meminfo: &str, want: Option<u64>) {
+        assert_eq!(parse_mem_available(meminfo), want);
+    }
+
+    #[test]
+    fn test_host_capabilities_display() {
+        let host = HostCapabilities {
+            cpu_features: CpuFeatures {
+                popcnt: true,
+                bmi2: true,
+                avx2: false,
+            },
+            compiled_features: CpuFeatures::default(),
+            num_cores: Some(8),
+            available_memory_bytes: None,
+        };
+
+        assert_eq!(
+            host.to_string(),
+            format!(
+                "cpu popcnt,bmi2 build none cores 8 memory unknown movegen {}",
+                SLIDING_PIECES_BACKEND
+            )
+        );
+        assert_eq!(host.unused_features(), host.cpu_features);
+    }
+}
@@ -0,0 +1,189 @@
+use std::env::consts::EXE_SUFFIX;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use engine::{AUTHOR, NAME};
+use serde_json::json;
+
+/// File name the engine is installed under, without the platform's executable suffix.
+const BINARY_NAME: &str = "flying-fish";
+
+/// Chess GUIs that engine definitions can be generated for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Gui {
+    Arena,
+    CuteChess,
+    Banksia,
+}
+
+impl Gui {
+    pub const ALL: [Gui; 3] = [Gui::Arena, Gui::CuteChess, Gui::Banksia];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Gui::Arena => "Arena",
+            Gui::CuteChess => "Cute Chess",
+            Gui::Banksia => "BanksiaGUI",
+        }
+    }
+
+    /// Name of the file the definition is written to.
+    pub fn file_name(self) -> &'static str {
+        match self {
+            Gui::Arena => "arena.ini",
+            Gui::CuteChess => "cutechess-engines.json",
+            Gui::Banksia => "banksia-engine.json",
+        }
+    }
+
+    /// Engine definition which can be imported into the GUI, for the engine installed at
+    /// `engine_path`.
+    pub fn engine_definition(self, engine_path: &Path) -> String {
+        let command = engine_path.to_string_lossy();
+        let working_dir = engine_path
+            .parent()
+            .map_or(String::new(), |dir| dir.to_string_lossy().into_owned());
+        match self {
+            Gui::Arena => format!(
+                "[Engine]\nName={}\nAuthor={}\nFilename={}\nDirectory={}\nProtocol=UCI\n",
+                NAME, AUTHOR, command, working_dir
+            ),
+            Gui::CuteChess => to_json(&json!([{
+                "name": NAME,
+                "command": command,
+                "workingDirectory": working_dir,
+                "protocol": "uci",
+            }])),
+            Gui::Banksia => to_json(&json!({
+                "name": NAME,
+                "author": AUTHOR,
+                "command": command,
+                "workingFolder": working_dir,
+                "protocol": "uci",
+            })),
+        }
+    }
+}
+
+fn to_json(value: &serde_json::Value) -> String {
+    serde_json::to_string_pretty(value).expect("Engine definition should serialize") + "\n"
+}
+
+/// `~/.local/bin` on Linux and macOS, and the user's programs directory on Windows.
+pub fn default_install_dir() -> Result<PathBuf> {
+    if cfg!(windows) {
+        let data_dir = dirs::data_local_dir().context("Local app data directory not set")?;
+        Ok(data_dir.join("Programs").join(BINARY_NAME))
+    } else {
+        let home_dir = dirs::home_dir().context("Home directory not set")?;
+        Ok(home_dir.join(".local").join("bin"))
+    }
+}
+
+/// Directory the GUI engine definitions are written to.
+pub fn default_config_dir() -> Result<PathBuf> {
+    let config_dir = dirs::config_dir().context("Config directory not set")?;
+    Ok(config_dir.join(BINARY_NAME))
+}
+
+/// Copies the engine binary at `binary_path` into `install_dir`, creating it if needed. Returns
+/// the installed binary's path.
+pub fn install_binary(binary_path: &Path, install_dir: &Path) -> Result<PathBuf> {
+    fs::create_dir_all(install_dir)
+        .with_context(|| format!("Couldn't create directory {:?}", install_dir))?;
+    let installed_path = install_dir.join(format!("{}{}", BINARY_NAME, EXE_SUFFIX));
+    // Copying a binary onto itself would truncate it.
+    if fs::canonicalize(binary_path).ok() != fs::canonicalize(&installed_path).ok() {
+        fs::copy(binary_path, &installed_path)
+            .with_context(|| format!("Couldn't copy {:?} to {:?}", binary_path, installed_path))?;
+    }
+    Ok(installed_path)
+}
+
+/// Writes the engine definition for every GUI into `config_dir`, returning the paths written.
+pub fn write_engine_definitions(engine_path: &Path, config_dir: &Path) -> Result<Vec<PathBuf>> {
+    fs::create_dir_all(config_dir)
+        .with_context(|| format!("Couldn't create directory {:?}", config_dir))?;
+    Gui::ALL
+        .into_iter()
+        .map(|gui| {
+            let path = config_dir.join(gui.file_name());
+            fs::write(&path, gui.engine_definition(engine_path))
+                .with_context(|| format!("Couldn't write {:?}", path))?;
+            Ok(path)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+    use testresult::TestResult;
+
+    /// Empty directory under the system's temporary directory.
+    fn temp_dir(name: &str) -> TestResult<PathBuf> {
+        let dir = std::env::temp_dir().join(format!("flying-fish-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir)?;
+        Ok(dir)
+    }
+
+    #[test_case(Gui::CuteChess ; "cute chess")]
+    #[test_case(Gui::Banksia ; "banksia")]
+    fn test_json_engine_definition(gui: Gui) -> TestResult {
+        let engine_path = Path::new("/opt/engines/flying-fish");
+        let definition: serde_json::Value =
+            serde_json::from_str(&gui.engine_definition(engine_path))?;
+
+        let engine = definition.get(0).unwrap_or(&definition);
+        assert_eq!(engine["name"], NAME);
+        assert_eq!(engine["command"], "/opt/engines/flying-fish");
+        assert_eq!(engine["protocol"], "uci");
+        Ok(())
+    }
+
+    #[test]
+    fn test_arena_engine_definition() {
+        let definition = Gui::Arena.engine_definition(Path::new("/opt/engines/flying-fish"));
+
+        assert!(definition.starts_with("[Engine]\n"));
+        assert!(definition.contains("Filename=/opt/engines/flying-fish\n"));
+        assert!(definition.contains("Directory=/opt/engines\n"));
+        assert!(definition.contains("Protocol=UCI\n"));
+    }
+
+    #[test]
+    fn test_install() -> TestResult {
+        let dir = temp_dir("install")?;
+        let binary_path = dir.join("build").join("cli");
+        fs::create_dir_all(binary_path.parent().unwrap())?;
+        fs::write(&binary_path, "binary")?;
+
+        let install_dir = dir.join("bin");
+        let installed_path = install_binary(&binary_path, &install_dir)?;
+        assert_eq!(
+            installed_path,
+            install_dir.join(format!("flying-fish{}", EXE_SUFFIX))
+        );
+        assert_eq!(fs::read_to_string(&installed_path)?, "binary");
+
+        // Reinstalling the installed binary leaves it as is.
+        install_binary(&installed_path, &install_dir)?;
+        assert_eq!(fs::read_to_string(&installed_path)?, "binary");
+
+        let config_dir = dir.join("config");
+        let written = write_engine_definitions(&installed_path, &config_dir)?;
+        assert_eq!(written.len(), Gui::ALL.len());
+        for (path, gui) in written.iter().zip(Gui::ALL) {
+            assert_eq!(
+                fs::read_to_string(path)?,
+                gui.engine_definition(&installed_path)
+            );
+        }
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
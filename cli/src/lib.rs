@@ -1,6 +1,30 @@
+mod analyze_file;
+mod crash_report;
+mod elo;
+mod gate;
+mod host;
+mod install;
 mod messages;
+mod openings;
+mod priority;
+mod replay;
+mod serve_perft;
 mod state;
+mod stdout_writer;
+mod testsuite;
 mod uci;
 
-pub use messages::{ReadUCICommand, UCICommandStdinReader};
-pub use uci::UCI;
+pub use crash_report::install_crash_report_hook;
+pub use elo::{EloEstimate, MatchResults, Pentanomial};
+pub use gate::{GateCase, GateResult, parse_gate_suite, run_gate};
+pub use host::{CpuFeatures, HostCapabilities};
+pub use install::{
+    Gui, default_config_dir, default_install_dir, install_binary, write_engine_definitions,
+};
+pub use messages::{ReadUCICommand, UCICommand, UCICommandParseError, UCICommandStdinReader};
+pub use openings::{BookFormat, PairedGame, load_openings, paired_schedule, screen_openings};
+pub use replay::{CapturedCommand, UCI_INPUT_TARGET, parse_captured_commands};
+pub use serve_perft::{ServePerftConfig, serve_perft};
+pub use stdout_writer::{QueuedStdout, flush_stdout};
+pub use testsuite::{TestsuiteResult, TestsuiteSummary, parse_testsuite, run_testsuite_position};
+pub use uci::{UCI, uci_info_callback};
@@ -1,24 +1,35 @@
 use std::{
+    collections::HashMap,
     env,
     fs::{self, File},
-    io::{self, BufRead},
-    os::unix::fs as unix_fs,
-    path::PathBuf,
+    io::{self, BufRead, Write},
+    net::TcpListener,
+    path::{Path, PathBuf},
     process,
     str::FromStr,
     sync::{Arc, atomic::AtomicBool},
+    thread,
+    time::{Duration, Instant},
 };
 
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
 use clap::{Parser, Subcommand};
 use engine::{
-    MOVE_GEN, POSITION_EVALUATOR, Position, SearchParams, TranspositionTable, perft, search,
+    GenerateMoves, MOVE_GEN, Move, POSITION_EVALUATOR, PgnResult, Piece, Position, SearchParams,
+    Side, TranspositionTable, audit_game, classify_move, move_to_algebraic_notation, parse_pgn,
+    perft, search, solve_mate, static_exchange_eval,
 };
 use mimalloc::MiMalloc;
 use tracing::{Level, debug, level_filters::LevelFilter, warn};
 use tracing_subscriber::{Registry, layer::SubscriberExt, prelude::*, util::SubscriberInitExt};
 
-use cli::UCI;
+use cli::{
+    BookFormat, CpuFeatures, GateResult, Gui, HostCapabilities, MatchResults, Pentanomial,
+    QueuedStdout, ServePerftConfig, TestsuiteSummary, UCI, UCI_INPUT_TARGET, default_config_dir,
+    default_install_dir, flush_stdout, install_binary, install_crash_report_hook, load_openings,
+    parse_captured_commands, parse_gate_suite, parse_testsuite, run_gate, run_testsuite_position,
+    screen_openings, serve_perft, uci_info_callback, write_engine_definitions,
+};
 
 #[global_allocator]
 static GLOBAL: MiMalloc = MiMalloc;
@@ -28,6 +39,11 @@ static GLOBAL: MiMalloc = MiMalloc;
 struct Cli {
     #[command(subcommand)]
     command: Option<Commands>,
+
+    /// Also write logs as JSON lines to this file, including search spans and UCI traffic.
+    /// Can also be set with the FLYING_FISH_JSON_LOG_PATH environment variable.
+    #[arg(long, global = true, value_name = "PATH")]
+    json_log: Option<PathBuf>,
 }
 
 #[derive(Debug, Subcommand)]
@@ -41,24 +57,221 @@ enum Commands {
         fen: String,
         depth: u8,
     },
+    /// Serve perft over HTTP, so move generation can be checked without a local build.
+    /// `POST /perft` with `{"fen": "<fen>", "depth": <depth>}` responds with the node count
+    /// below each legal move and in total.
+    ServePerft {
+        #[arg(long, default_value_t = 8080)]
+        port: u16,
+        /// Address to listen on. Use 0.0.0.0 to accept connections from other machines.
+        #[arg(long, default_value = "127.0.0.1")]
+        host: String,
+        /// Deepest perft a request can ask for.
+        #[arg(long, default_value_t = ServePerftConfig::DEFAULT.max_depth)]
+        max_depth: u8,
+        /// Requests handled at once.
+        #[arg(long, default_value_t = ServePerftConfig::DEFAULT.num_workers)]
+        workers: usize,
+    },
+    /// Static exchange evaluation of a move, printing the captures played and net material.
+    See {
+        fen: String,
+        #[arg(value_name = "MOVE")]
+        mve: String,
+    },
+    /// Explain why the engine wouldn't play a move, comparing it against the engine's choice.
+    Why {
+        fen: String,
+        #[arg(value_name = "MOVE")]
+        mve: String,
+        #[arg(long)]
+        depth: u8,
+    },
+    /// Re-feed the GUI commands captured in a log file into a fresh engine, keeping the
+    /// original timing between commands.
+    Replay {
+        log: PathBuf,
+    },
+    /// Find a forced mate in a position, printing the mating line.
+    Solve {
+        fen: String,
+        #[arg(long)]
+        mate_in: u8,
+        /// Positions to look at before giving up. Every one is kept in memory.
+        #[arg(long, default_value_t = 10_000_000)]
+        max_nodes: usize,
+    },
+    /// Opening explorer: show the moves played from a position in a PGN database, with how
+    /// often each was played and the results of those games.
+    Tree {
+        pgn: PathBuf,
+        /// Position to explore, defaults to the starting position.
+        #[arg(long)]
+        fen: Option<String>,
+    },
+    /// Replay the games in a PGN file, checking the incrementally updated zobrist hash against
+    /// one calculated from scratch and validating the position after every move. Reports the
+    /// first divergence found, which is worth attaching to bug reports.
+    Audit {
+        pgn: PathBuf,
+    },
+    /// Append a move played in a PGN game to the engine's blunder regression suite.
+    AddBlunder {
+        pgn: PathBuf,
+        /// The losing move in long algebraic notation, e.g. `g8f6`. The first time it was played
+        /// in the game is used.
+        #[arg(value_name = "MOVE")]
+        mve: String,
+        /// Which game in the PGN file the move was played in, starting from 1.
+        #[arg(long, default_value_t = 1)]
+        game: usize,
+        #[arg(long, default_value = "engine/tests/blunders.txt")]
+        suite: PathBuf,
+    },
+    /// Search a suite of positions with a fixed node budget and fail if the best move rate or
+    /// the eval correlation to the reference evals regressed compared to the baseline.
+    Gate {
+        #[arg(long)]
+        baseline: PathBuf,
+        #[arg(long, default_value = "gate/suite.txt")]
+        suite: PathBuf,
+        /// Node budget per position. Defaults to the baseline's.
+        #[arg(long)]
+        nodes: Option<u64>,
+        /// How far below the baseline each metric may drop.
+        #[arg(long, default_value_t = 0.02)]
+        max_regression: f64,
+        /// Write the results to the baseline file instead of comparing against it.
+        #[arg(long)]
+        update: bool,
+    },
+    /// Run a test suite of EPD positions like WAC or STS, searching each for a fixed time and
+    /// reporting how many were solved and the points scored.
+    Testsuite {
+        suite: PathBuf,
+        /// Milliseconds to search each position for.
+        #[arg(long, default_value_t = 1000)]
+        move_time: u64,
+    },
+    /// Build a balanced opening book for matches from an EPD or PGN book, keeping the openings
+    /// the engine evaluates as roughly equal. Prints one FEN per line, each of which should be
+    /// played twice with colors reversed.
+    Openings {
+        book: PathBuf,
+        /// Number of moves from the start of each PGN game to play to reach its opening.
+        #[arg(long, default_value_t = 8)]
+        plies: usize,
+        /// Largest eval in centipawns, for either side, a kept opening may have.
+        #[arg(long, default_value_t = 100)]
+        max_eval: i32,
+        /// Node budget each opening is evaluated with.
+        #[arg(long, default_value_t = 20_000)]
+        nodes: u64,
+    },
+    /// Elo difference with its 95% error margin and likelihood of superiority, from a match's
+    /// wins, draws and losses from the first engine's point of view.
+    Elo {
+        wins: u32,
+        draws: u32,
+        losses: u32,
+        /// Number of game pairs, played from the same opening with colors reversed, in which the
+        /// first engine scored 0, 1/2, 1, 3/2 and 2 points.
+        #[arg(long, num_args = 5, value_name = "PAIRS")]
+        pentanomial: Option<Vec<u32>>,
+    },
+    /// Copy this binary to a standard location and print engine definitions to import into
+    /// Arena, Cute Chess and BanksiaGUI.
+    Install {
+        /// Directory to install to. Defaults to `~/.local/bin`, or the user's programs directory
+        /// on Windows.
+        #[arg(long)]
+        dir: Option<PathBuf>,
+        /// Write the engine definitions to the config directory instead of printing them.
+        #[arg(long)]
+        write_definitions: bool,
+    },
 }
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
 
-    enable_logging()?;
+    let json_log_path = cli.json_log.or_else(|| {
+        env::var("FLYING_FISH_JSON_LOG_PATH")
+            .ok()
+            .map(PathBuf::from)
+    });
+    enable_logging(json_log_path)?;
 
-    match cli.command {
+    let res = match cli.command {
         Some(Commands::Search { fen, depth }) => cli_search(&fen, depth),
         Some(Commands::Perft { fen, depth }) => cli_perft(&fen, depth),
+        Some(Commands::ServePerft {
+            port,
+            host,
+            max_depth,
+            workers,
+        }) => cli_serve_perft(
+            &host,
+            port,
+            ServePerftConfig {
+                num_workers: workers,
+                max_depth,
+                ..ServePerftConfig::DEFAULT
+            },
+        ),
+        Some(Commands::See { fen, mve }) => cli_see(&fen, &mve),
+        Some(Commands::Replay { log }) => cli_replay(&log),
+        Some(Commands::Solve {
+            fen,
+            mate_in,
+            max_nodes,
+        }) => cli_solve(&fen, mate_in, max_nodes),
+        Some(Commands::Tree { pgn, fen }) => cli_tree(&pgn, fen.as_deref()),
+        Some(Commands::Why { fen, mve, depth }) => cli_why(&fen, &mve, depth),
+        Some(Commands::Audit { pgn }) => cli_audit(&pgn),
+        Some(Commands::AddBlunder {
+            pgn,
+            mve,
+            game,
+            suite,
+        }) => cli_add_blunder(&pgn, &mve, game, &suite),
+        Some(Commands::Gate {
+            baseline,
+            suite,
+            nodes,
+            max_regression,
+            update,
+        }) => cli_gate(&baseline, &suite, nodes, max_regression, update),
+        Some(Commands::Testsuite { suite, move_time }) => {
+            cli_testsuite(&suite, Duration::from_millis(move_time))
+        }
+        Some(Commands::Openings {
+            book,
+            plies,
+            max_eval,
+            nodes,
+        }) => cli_openings(&book, plies, max_eval, nodes),
+        Some(Commands::Elo {
+            wins,
+            draws,
+            losses,
+            pentanomial,
+        }) => cli_elo(wins, draws, losses, pentanomial.as_deref()),
+        Some(Commands::Install {
+            dir,
+            write_definitions,
+        }) => cli_install(dir, write_definitions),
         None => uci_main_loop(),
-    }
+    };
+    flush_stdout();
+    res
 }
 
 fn cli_search(fen: &str, depth: u8) -> Result<()> {
     let position = Position::from_fen(fen)?;
     let search_params = SearchParams {
         max_depth: Some(depth),
+        info_callback: Some(uci_info_callback()),
         ..Default::default()
     };
     let (best_move, _) = search(
@@ -69,6 +282,7 @@ fn cli_search(fen: &str, depth: u8) -> Result<()> {
         &mut TranspositionTable::new(),
         Arc::new(AtomicBool::new(false)),
     )?;
+    flush_stdout();
     println!(
         "{:?}",
         best_move
@@ -90,11 +304,481 @@ fn cli_perft(fen: &str, depth: u8) -> Result<()> {
     Ok(())
 }
 
+fn cli_serve_perft(host: &str, port: u16, config: ServePerftConfig) -> Result<()> {
+    let listener = TcpListener::bind((host, port))
+        .with_context(|| format!("Couldn't listen on {}:{}", host, port))?;
+    println!(
+        "Serving perft up to depth {} on http://{}",
+        config.max_depth,
+        listener.local_addr()?
+    );
+    serve_perft(listener, config)
+}
+
+fn cli_see(fen: &str, mve: &str) -> Result<()> {
+    let position =
+        Position::from_fen(fen).with_context(|| format!("Couldn't parse given fen: `{}`", fen))?;
+    let mve = Move::from_str(mve)?;
+    let exchange = static_exchange_eval(&position, mve)?;
+    println!(
+        "see {}: {}",
+        exchange.value,
+        moves_to_string(&exchange.sequence)
+    );
+    Ok(())
+}
+
+fn cli_solve(fen: &str, mate_in: u8, max_nodes: usize) -> Result<()> {
+    let position =
+        Position::from_fen(fen).with_context(|| format!("Couldn't parse given fen: `{}`", fen))?;
+    let max_plies = mate_in.saturating_mul(2).saturating_sub(1);
+    match solve_mate(&position, max_plies, MOVE_GEN, max_nodes) {
+        Ok(Some(line)) => println!(
+            "mate in {}: {}",
+            line.len().div_ceil(2),
+            moves_to_string(&line)
+        ),
+        Ok(None) => println!("No mate in {} found", mate_in),
+        Err(err) => println!("Unknown whether there's a mate in {}: {}", mate_in, err),
+    }
+    Ok(())
+}
+
+#[derive(Debug, Default)]
+struct TreeMoveStats {
+    games: usize,
+    white_wins: usize,
+    draws: usize,
+    black_wins: usize,
+}
+
+fn cli_tree(pgn_path: &Path, fen: Option<&str>) -> Result<()> {
+    let position = match fen {
+        Some(fen) => Position::from_fen(fen)
+            .with_context(|| format!("Couldn't parse given fen: `{}`", fen))?,
+        None => Position::start(),
+    };
+    let pgn = fs::read_to_string(pgn_path)
+        .with_context(|| format!("Couldn't read PGN file {:?}", pgn_path))?;
+    let games = parse_pgn(&pgn, MOVE_GEN)?;
+
+    // Positions are matched by hash, so transpositions from other move orders are included.
+    let mut move_stats: HashMap<Move, TreeMoveStats> = HashMap::new();
+    for game in &games {
+        for (game_position, &mve) in game.positions().zip(&game.moves) {
+            if game_position.zobrist_hash == position.zobrist_hash {
+                let stats = move_stats.entry(mve).or_default();
+                stats.games += 1;
+                match game.result {
+                    PgnResult::WhiteWins => stats.white_wins += 1,
+                    PgnResult::Draw => stats.draws += 1,
+                    PgnResult::BlackWins => stats.black_wins += 1,
+                    PgnResult::Unknown => {}
+                }
+                // Only count each game once, even if it repeats the position.
+                break;
+            }
+        }
+    }
+
+    let total_games: usize = move_stats.values().map(|stats| stats.games).sum();
+    println!(
+        "{} of {} games reached this position",
+        total_games,
+        games.len()
+    );
+    if total_games == 0 {
+        return Ok(());
+    }
+
+    let mut move_stats: Vec<_> = move_stats.into_iter().collect();
+    move_stats.sort_by(|(mve1, stats1), (mve2, stats2)| {
+        stats2.games.cmp(&stats1.games).then(mve1.cmp(mve2))
+    });
+
+    let percent = |count: usize, games: usize| 100.0 * count as f64 / games as f64;
+    println!(
+        "{:<8} {:>6} {:>6} {:>6} {:>6}",
+        "move", "games", "white", "draw", "black"
+    );
+    for (mve, stats) in move_stats {
+        println!(
+            "{:<8} {:>6} {:>5.1}% {:>5.1}% {:>5.1}%",
+            move_to_algebraic_notation(&position, mve, MOVE_GEN)?,
+            stats.games,
+            percent(stats.white_wins, stats.games),
+            percent(stats.draws, stats.games),
+            percent(stats.black_wins, stats.games),
+        );
+    }
+    Ok(())
+}
+
+fn cli_why(fen: &str, mve: &str, depth: u8) -> Result<()> {
+    let position =
+        Position::from_fen(fen).with_context(|| format!("Couldn't parse given fen: `{}`", fen))?;
+    let mve = Move::from_str(mve)?;
+    if !MOVE_GEN.gen_moves(&position).contains(&mve) {
+        bail!(
+            "Move {} isn't legal in this position",
+            mve.to_string().to_lowercase()
+        );
+    }
+
+    let search_depth = |search_moves: Option<Vec<Move>>| {
+        let search_params = SearchParams {
+            max_depth: Some(depth),
+            search_moves,
+            info_callback: Some(uci_info_callback()),
+            ..Default::default()
+        };
+        search(
+            &position,
+            &search_params,
+            MOVE_GEN,
+            POSITION_EVALUATOR,
+            &mut TranspositionTable::new(),
+            Arc::new(AtomicBool::new(false)),
+        )
+    };
+
+    let (best_move, best_info) = search_depth(None)?;
+    let best_move = best_move.context("Search didn't find a move")?;
+    let (_, move_info) = search_depth(Some(vec![mve]))?;
+
+    let best_eval = best_info.move_evals[&best_move];
+    let move_eval = move_info.move_evals[&mve];
+    flush_stdout();
+    println!(
+        "engine plays {} ({}): {}",
+        best_move.to_string().to_lowercase(),
+        best_eval,
+        moves_to_string(&best_info.pv)
+    );
+    println!(
+        "{} ({}): {}",
+        mve.to_string().to_lowercase(),
+        move_eval,
+        moves_to_string(&move_info.pv)
+    );
+    let mut classified_moves = vec![best_move];
+    if mve != best_move {
+        classified_moves.push(mve);
+    }
+    for classified_mve in classified_moves {
+        let classes = classify_move(&position, classified_mve, MOVE_GEN);
+        if !classes.is_empty() {
+            println!(
+                "{} {}",
+                classified_mve.to_string().to_lowercase(),
+                classes
+                    .iter()
+                    .map(|class| class.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+    }
+    if best_move == mve {
+        println!(
+            "{} is the engine's choice at depth {}",
+            mve.to_string().to_lowercase(),
+            depth
+        );
+        return Ok(());
+    }
+    if move_info.pv.len() > 1 {
+        println!("refutation: {}", moves_to_string(&move_info.pv[1..]));
+    }
+
+    // Find where the line starts losing material for the side to move.
+    let side = position.state.to_move;
+    let start_material = material_balance(&position, side);
+    let mut line_position = position.clone();
+    for (ply, line_mve) in move_info.pv.iter().enumerate() {
+        line_position.make_move(*line_mve);
+        let material_lost = start_material - material_balance(&line_position, side);
+        if material_lost > 0 {
+            println!(
+                "loses {} centipawns of material after ply {} ({})",
+                material_lost,
+                ply + 1,
+                line_mve.to_string().to_lowercase()
+            );
+            break;
+        }
+    }
+    Ok(())
+}
+
+fn cli_audit(pgn_path: &Path) -> Result<()> {
+    let pgn = fs::read_to_string(pgn_path)
+        .with_context(|| format!("Couldn't read PGN file {:?}", pgn_path))?;
+    let games = parse_pgn(&pgn, MOVE_GEN)?;
+
+    for (idx, game) in games.iter().enumerate() {
+        if let Err(err) = audit_game(game) {
+            let players = match (game.tag("White"), game.tag("Black")) {
+                (Some(white), Some(black)) => format!(" ({} vs {})", white, black),
+                _ => String::new(),
+            };
+            bail!("Game {}{}: {}", idx + 1, players, err);
+        }
+    }
+
+    let num_moves: usize = games.iter().map(|game| game.moves.len()).sum();
+    println!(
+        "Replayed {} moves in {} games, no divergences found",
+        num_moves,
+        games.len()
+    );
+    Ok(())
+}
+
+fn cli_add_blunder(
+    pgn_path: &Path,
+    mve: &str,
+    game_number: usize,
+    suite_path: &Path,
+) -> Result<()> {
+    let blunder = Move::from_str(mve)?;
+    let pgn = fs::read_to_string(pgn_path)
+        .with_context(|| format!("Couldn't read PGN file {:?}", pgn_path))?;
+    let games = parse_pgn(&pgn, MOVE_GEN)?;
+    let Some(game) = game_number.checked_sub(1).and_then(|idx| games.get(idx)) else {
+        bail!(
+            "PGN file has {} games, no game {}",
+            games.len(),
+            game_number
+        );
+    };
+
+    let mut position = game.start.clone();
+    for &game_move in &game.moves {
+        if game_move == blunder {
+            let source = match (game.tag("White"), game.tag("Black")) {
+                (Some(white), Some(black)) => format!("{} vs {}", white, black),
+                _ => pgn_path.display().to_string(),
+            };
+            let case = format!(
+                "{}; {}; {}\n",
+                position.to_fen(),
+                mve.to_lowercase(),
+                source
+            );
+            fs::OpenOptions::new()
+                .append(true)
+                .open(suite_path)
+                .and_then(|mut suite| suite.write_all(case.as_bytes()))
+                .with_context(|| format!("Couldn't append to blunder suite {:?}", suite_path))?;
+            print!("{}", case);
+            return Ok(());
+        }
+        position.make_move(game_move);
+    }
+    bail!("{} wasn't played in game {}", mve, game_number)
+}
+
+/// Node budget used when creating a new baseline.
+const DEFAULT_GATE_NODES: u64 = 50_000;
+
+fn cli_gate(
+    baseline_path: &Path,
+    suite_path: &Path,
+    nodes: Option<u64>,
+    max_regression: f64,
+    update: bool,
+) -> Result<()> {
+    let suite = fs::read_to_string(suite_path)
+        .with_context(|| format!("Couldn't read gate suite {:?}", suite_path))?;
+    let cases = parse_gate_suite(&suite)?;
+
+    if update {
+        let result = run_gate(&cases, nodes.unwrap_or(DEFAULT_GATE_NODES))?;
+        fs::write(baseline_path, result.to_json() + "\n")
+            .with_context(|| format!("Couldn't write gate baseline {:?}", baseline_path))?;
+        println!("{}", result.to_json());
+        return Ok(());
+    }
+
+    let baseline = GateResult::from_json(
+        &fs::read_to_string(baseline_path)
+            .with_context(|| format!("Couldn't read gate baseline {:?}", baseline_path))?,
+    )?;
+    let result = run_gate(&cases, nodes.unwrap_or(baseline.nodes))?;
+    println!(
+        "best move rate: {:.3} (baseline {:.3})",
+        result.best_move_rate, baseline.best_move_rate
+    );
+    println!(
+        "eval correlation: {:.3} (baseline {:.3})",
+        result.eval_correlation, baseline.eval_correlation
+    );
+
+    let regressions = result.regressions(&baseline, max_regression);
+    if !regressions.is_empty() {
+        bail!("Strength gate failed: {}", regressions.join(", "));
+    }
+    Ok(())
+}
+
+fn cli_testsuite(suite_path: &Path, move_time: Duration) -> Result<()> {
+    let suite = fs::read_to_string(suite_path)
+        .with_context(|| format!("Couldn't read test suite {:?}", suite_path))?;
+    let records = parse_testsuite(&suite)?;
+
+    let mut summary = TestsuiteSummary::default();
+    for (record_idx, record) in records.iter().enumerate() {
+        let result = run_testsuite_position(record, move_time)?;
+        summary.add(&result);
+        let best_move = match result.best_move {
+            Some(best_move) => move_to_algebraic_notation(&record.position, best_move, MOVE_GEN)?,
+            None => "(none)".to_string(),
+        };
+        println!(
+            "{}: {} {} ({}/{})",
+            record
+                .id
+                .clone()
+                .unwrap_or_else(|| format!("#{}", record_idx + 1)),
+            best_move,
+            if result.solved { "solved" } else { "failed" },
+            result.points,
+            result.max_points
+        );
+    }
+    println!(
+        "Solved {} of {} positions, scored {} of {} points",
+        summary.num_solved, summary.num_positions, summary.points, summary.max_points
+    );
+    Ok(())
+}
+
+fn cli_openings(book_path: &Path, plies: usize, max_eval: i32, nodes: u64) -> Result<()> {
+    let book = fs::read_to_string(book_path)
+        .with_context(|| format!("Couldn't read opening book {:?}", book_path))?;
+    let format = BookFormat::from_extension(book_path.extension().and_then(|ext| ext.to_str()));
+    let openings = load_openings(&book, format, plies)?;
+    let num_openings = openings.len();
+
+    let balanced = screen_openings(openings, max_eval, nodes)?;
+    for opening in &balanced {
+        println!("{}", opening.to_fen());
+    }
+    eprintln!(
+        "Kept {} of {} openings within {} centipawns",
+        balanced.len(),
+        num_openings,
+        max_eval
+    );
+    Ok(())
+}
+
+fn cli_elo(wins: u32, draws: u32, losses: u32, maybe_pentanomial: Option<&[u32]>) -> Result<()> {
+    let results = MatchResults {
+        wins,
+        draws,
+        losses,
+    };
+    let estimate = results.elo_estimate()?;
+    println!(
+        "{} games (W {}, D {}, L {}), score {:.1}%",
+        results.num_games(),
+        wins,
+        draws,
+        losses,
+        results.score() * 100.
+    );
+    println!("{}", estimate);
+
+    if let Some(pentanomial) = maybe_pentanomial {
+        let pentanomial = Pentanomial {
+            pairs: pentanomial
+                .try_into()
+                .context("Pentanomial needs 5 pair counts")?,
+        };
+        println!(
+            "{} pairs {:?}: {}",
+            pentanomial.num_pairs(),
+            pentanomial.pairs,
+            pentanomial.elo_estimate()?
+        );
+    }
+    Ok(())
+}
+
+fn cli_install(maybe_dir: Option<PathBuf>, write_definitions: bool) -> Result<()> {
+    let install_dir = maybe_dir.map_or_else(default_install_dir, Ok)?;
+    let binary_path = env::current_exe().context("Couldn't find path of this binary")?;
+    let engine_path = install_binary(&binary_path, &install_dir)?;
+    println!("Installed to {}", engine_path.display());
+
+    if write_definitions {
+        for path in write_engine_definitions(&engine_path, &default_config_dir()?)? {
+            println!("Wrote {}", path.display());
+        }
+        return Ok(());
+    }
+    for gui in Gui::ALL {
+        println!();
+        println!("{} ({}):", gui.name(), gui.file_name());
+        print!("{}", gui.engine_definition(&engine_path));
+    }
+    Ok(())
+}
+
+fn moves_to_string(moves: &[Move]) -> String {
+    moves
+        .iter()
+        .map(|mve| mve.to_string().to_lowercase())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Material of `side` minus the opponent's, using the usual 1/3/3/5/9 piece values.
+fn material_balance(position: &Position, side: Side) -> i32 {
+    position
+        .piece_locs()
+        .map(|(piece, piece_side, _)| {
+            let value = match piece {
+                Piece::Pawn => 100,
+                Piece::Knight | Piece::Bishop => 300,
+                Piece::Rook => 500,
+                Piece::Queen => 900,
+                Piece::King => 0,
+            };
+            if piece_side == side { value } else { -value }
+        })
+        .sum()
+}
+
+fn cli_replay(log_path: &Path) -> Result<()> {
+    let log = fs::read_to_string(log_path)
+        .with_context(|| format!("Couldn't read log file {:?}", log_path))?;
+    let captured_commands = parse_captured_commands(&log)?;
+
+    let mut uci = UCI::new(MOVE_GEN);
+    let start = Instant::now();
+    for captured_command in captured_commands {
+        thread::sleep(captured_command.offset.saturating_sub(start.elapsed()));
+
+        debug!(target: UCI_INPUT_TARGET, "{}", captured_command.command);
+        if let Err(err) = uci.handle_command(&captured_command.command) {
+            warn!(target: "uci", "{}", err);
+        }
+    }
+    // The log can end while a search is still running, which would exit before `bestmove`.
+    uci.finish_search();
+    Ok(())
+}
+
 fn uci_main_loop() -> Result<()> {
+    install_crash_report_hook(get_state_dir()?.join("crashes"));
+    log_host_capabilities();
     let mut uci = UCI::new(MOVE_GEN);
 
     for line in io::stdin().lock().lines().map(|r| r.unwrap()) {
-        debug!("{}", line);
+        debug!(target: UCI_INPUT_TARGET, "{}", line);
         let cmd_res = uci.handle_command(&line);
 
         if let Err(err) = cmd_res {
@@ -104,7 +788,19 @@ fn uci_main_loop() -> Result<()> {
     Ok(())
 }
 
-fn enable_logging() -> Result<()> {
+fn log_host_capabilities() {
+    let host = HostCapabilities::detect();
+    debug!("Host capabilities: {}", host);
+    let unused_features = host.unused_features();
+    if unused_features != CpuFeatures::default() {
+        warn!(
+            "CPU supports {} which this build doesn't use, build with `-C target-cpu=native` to use it",
+            unused_features
+        );
+    }
+}
+
+fn enable_logging(json_log_path: Option<PathBuf>) -> Result<()> {
     let log_path = if let Ok(log_path_str) = env::var("FLYING_FISH_LOG_PATH") {
         PathBuf::from_str(&log_path_str)?
     } else {
@@ -144,6 +840,7 @@ fn enable_logging() -> Result<()> {
         .without_time()
         .with_level(false)
         .with_target(false)
+        .with_writer(QueuedStdout)
         .with_filter(tracing_subscriber::filter::filter_fn(|meta| {
             meta.target() == "uci"
         }));
@@ -164,22 +861,44 @@ fn enable_logging() -> Result<()> {
         );
 
     let log_layer = tracing_subscriber::fmt::layer()
+        .with_ansi(false)
         .with_writer(log_file)
         .with_filter(LevelFilter::from_level(Level::DEBUG));
 
+    let json_log_layer = json_log_path
+        .map(|json_log_path| {
+            let json_log_file = File::create(&json_log_path)
+                .with_context(|| format!("Couldn't create file {:?}", json_log_path))?;
+            Ok::<_, anyhow::Error>(
+                tracing_subscriber::fmt::layer()
+                    .json()
+                    .with_current_span(true)
+                    .with_span_list(true)
+                    .with_writer(json_log_file)
+                    .with_filter(LevelFilter::from_level(Level::DEBUG)),
+            )
+        })
+        .transpose()?;
+
     Registry::default()
         .with(uci_layer)
         .with(stderr_layer)
         .with(log_layer)
+        .with(json_log_layer)
         .init();
 
     Ok(())
 }
 
 fn get_default_log_path(date_str: &str, pid: u32) -> Result<PathBuf> {
-    let mut log_path = dirs::home_dir().context("Home directory not set")?;
-    log_path.push(PathBuf::from(".local/state/flying-fish"));
-
+    let mut log_path = get_state_dir()?;
     log_path.push(format!("{}_{}.log", date_str, pid));
     Ok(log_path)
 }
+
+/// Directory for logs and crash reports.
+fn get_state_dir() -> Result<PathBuf> {
+    let mut state_dir = dirs::home_dir().context("Home directory not set")?;
+    state_dir.push(PathBuf::from(".local/state/flying-fish"));
+    Ok(state_dir)
+}
@@ -1,6 +1,7 @@
 use core::fmt;
 use std::fmt::Display;
 use std::io;
+use std::path::PathBuf;
 use std::str::FromStr;
 use std::time::Duration;
 
@@ -10,7 +11,7 @@ use winnow::Parser;
 use winnow::Result as WinnowResult;
 use winnow::ascii::{alphanumeric1, digit1};
 use winnow::combinator::{alt, opt, preceded, separated, terminated};
-use winnow::token::{one_of, rest, take_until, take_while};
+use winnow::token::{any, one_of, rest, take, take_until, take_while};
 
 pub trait ReadUCICommand {
     fn read_uci_command(&self) -> Result<String>;
@@ -26,8 +27,10 @@ impl ReadUCICommand for UCICommandStdinReader {
     }
 }
 
+// Commands are parsed once per line read, so the size of `Go`'s params doesn't matter.
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, PartialEq, Eq, Clone)]
-pub(crate) enum UCICommand {
+pub enum UCICommand {
     #[allow(clippy::upper_case_acronyms)]
     UCI,
     Debug {
@@ -64,6 +67,132 @@ pub(crate) enum UCICommand {
         depth: usize,
     },
     PerftBenchmark,
+    See {
+        mve: Move,
+    },
+    AnalyzeFile {
+        path: PathBuf,
+        move_time: Duration,
+    },
+    Help,
+}
+
+/// A command `help` lists. `name` is what the command starts with, and `usage` how it's sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct CommandHelp {
+    pub(crate) name: &'static str,
+    pub(crate) usage: &'static str,
+    pub(crate) description: &'static str,
+}
+
+impl CommandHelp {
+    const fn new(name: &'static str, usage: &'static str, description: &'static str) -> Self {
+        Self {
+            name,
+            usage,
+            description,
+        }
+    }
+}
+
+/// Commands from the UCI protocol.
+pub(crate) const STANDARD_COMMANDS: &[CommandHelp] = &[
+    CommandHelp::new("uci", "uci", "Identify the engine and list its options"),
+    CommandHelp::new(
+        "debug",
+        "debug on|off",
+        "Accepted, but doesn't change anything",
+    ),
+    CommandHelp::new(
+        "isready",
+        "isready",
+        "Reply readyok once ready for commands",
+    ),
+    CommandHelp::new(
+        "setoption",
+        "setoption name <name> [value <value>]",
+        "Set one of the options listed by uci",
+    ),
+    CommandHelp::new(
+        "register",
+        "register later | register name <name> code <code>",
+        "Accepted, the engine doesn't need registering",
+    ),
+    CommandHelp::new(
+        "ucinewgame",
+        "ucinewgame",
+        "Start a new game from the starting position",
+    ),
+    CommandHelp::new(
+        "position",
+        "position startpos|fen <fen> [moves <move>...]",
+        "Set the position to search, after playing the moves",
+    ),
+    CommandHelp::new(
+        "go",
+        "go [searchmoves <move>...] [ponder] [wtime|btime|winc|binc <ms>] [movestogo <n>] \
+         [depth <n>] [nodes <n>] [mate <n>] [movetime <ms>] [infinite]",
+        "Search the position and reply with bestmove",
+    ),
+    CommandHelp::new("stop", "stop", "Stop searching and reply with bestmove"),
+    CommandHelp::new(
+        "ponderhit",
+        "ponderhit",
+        "The ponder move was played, keep searching on the clock",
+    ),
+    CommandHelp::new("quit", "quit", "Exit the engine"),
+];
+
+/// Commands only this engine understands, for looking into it by hand.
+pub(crate) const NONSTANDARD_COMMANDS: &[CommandHelp] = &[
+    CommandHelp::new("help", "help", "List the commands the engine understands"),
+    CommandHelp::new(
+        "eval",
+        "eval",
+        "Print the static evaluation of the position, term by term",
+    ),
+    CommandHelp::new(
+        "go perft",
+        "go perft <depth>",
+        "Count the leaf nodes at a depth, split by the first move",
+    ),
+    CommandHelp::new(
+        "go perft_full",
+        "go perft_full <depth>",
+        "Count the nodes, captures, checks and mates at every depth",
+    ),
+    CommandHelp::new(
+        "perft_bench",
+        "perft_bench",
+        "Benchmark move generation on a few perft positions",
+    ),
+    CommandHelp::new(
+        "see",
+        "see <move>",
+        "Static exchange evaluation of a capture, with the captures played",
+    ),
+    CommandHelp::new(
+        "analyzefile",
+        "analyzefile <path> movetime <ms>",
+        "Search every FEN in a file, reporting each one as an info string",
+    ),
+];
+
+/// Lines `help` replies with, listing the standard and nonstandard commands with each one's
+/// description indented below it.
+pub(crate) fn help_lines() -> Vec<String> {
+    let mut lines = vec![];
+    for (heading, commands) in [
+        ("Standard UCI commands:", STANDARD_COMMANDS),
+        ("Nonstandard commands:", NONSTANDARD_COMMANDS),
+    ] {
+        lines.push(heading.to_string());
+        for command in commands {
+            lines.push(format!("  {}", command.usage));
+            lines.push(format!("      {}", command.description));
+        }
+    }
+    lines
 }
 
 impl fmt::Display for UCICommand {
@@ -108,6 +237,14 @@ impl fmt::Display for UCICommand {
             UCICommand::Perft { depth } => write!(f, "go perft {}", depth),
             UCICommand::PerftFull { depth } => write!(f, "go perft_full {}", depth),
             UCICommand::PerftBenchmark => write!(f, "perft_bench"),
+            UCICommand::See { mve } => write!(f, "see {}", mve.to_string().to_lowercase()),
+            UCICommand::AnalyzeFile { path, move_time } => write!(
+                f,
+                "analyzefile {} movetime {}",
+                path.display(),
+                move_time.as_millis()
+            ),
+            UCICommand::Help => write!(f, "help"),
         }
     }
 }
@@ -204,6 +341,9 @@ impl FromStr for UCICommand {
             parse_perft,
             parse_perft_full,
             parse_perft_benchmark,
+            parse_see,
+            parse_analyze_file,
+            parse_help,
             parse_go,
         ))
         .parse(input)
@@ -279,55 +419,60 @@ fn parse_position(input: &mut &str) -> WinnowResult<UCICommand> {
                 "startpos".value(None),
                 preceded("fen ", parse_position_fen.map(Some)),
             )),
-            opt(preceded(" moves ", rest)),
-        )
-            .try_map(|(fen, moves): (Option<String>, Option<&str>)| {
-                Ok::<UCICommand, <Square as FromStr>::Err>(UCICommand::Position {
-                    fen: fen.map(|s: String| s.to_string()),
-                    moves: moves.map(|moves: &str| {
-                        { moves.split(' ').map(parse_position_move) }
-                            .collect::<Result<Vec<Move>, _>>()
-                            .unwrap()
-                    }),
-                })
-            }),
+            opt(preceded(" moves ", separated(1.., parse_move, ' '))),
+        ),
     )
+    .map(|(fen, moves)| UCICommand::Position { fen, moves })
     .parse_next(input)
 }
 
-fn parse_position_move(mve_str: &str) -> Result<Move> {
-    let src_str = &mve_str[0..2];
-    let dest_str = &mve_str[2..4];
-    let src = Square::from_str(src_str.to_uppercase().as_str())?;
-    let dest = Square::from_str(dest_str.to_uppercase().as_str())?;
-
-    let promotion = if mve_str.len() == 5 {
-        let prom_char = mve_str.chars().last().unwrap();
-        let piece: Piece = prom_char.try_into()?;
-        Some(piece)
-    } else {
-        None
-    };
-    Ok(Move {
-        src,
-        dest,
-        promotion,
-    })
+/// Move in long algebraic notation, e.g. `e2e4` or `a7a8q`.
+fn parse_move(input: &mut &str) -> WinnowResult<Move> {
+    (
+        parse_square,
+        parse_square,
+        opt(any.try_map(Piece::try_from)),
+    )
+        .map(|(src, dest, promotion)| Move {
+            src,
+            dest,
+            promotion,
+        })
+        .parse_next(input)
+}
+
+fn parse_square(input: &mut &str) -> WinnowResult<Square> {
+    take(2usize)
+        .try_map(|square: &str| Square::from_str(&square.to_uppercase()))
+        .parse_next(input)
 }
 
+/// The move counters are optional, since some GUIs leave them out.
 fn parse_position_fen(input: &mut &str) -> WinnowResult<String> {
     (
         terminated(separated(8, alphanumeric1, '/'), ' '),
         terminated(one_of(['w', 'b']), ' '),
         terminated(take_while(0.., ('K', 'k', 'Q', 'q', '-')), ' '),
-        terminated(alt((alphanumeric1, "-")), ' '),
-        terminated(digit1, ' '),
-        digit1,
+        alt((alphanumeric1, "-")),
+        opt(preceded(' ', digit1)),
+        opt(preceded(' ', digit1)),
     )
         .map(
-            |(s1, s2, s3, s4, s5, s6): (Vec<&str>, char, &str, &str, &str, &str)| {
+            |(s1, s2, s3, s4, s5, s6): (
+                Vec<&str>,
+                char,
+                &str,
+                &str,
+                Option<&str>,
+                Option<&str>,
+            )| {
                 let pieces_str = s1.join("/");
-                format!("{} {} {} {} {} {}", pieces_str, s2, s3, s4, s5, s6)
+                let mut fen = format!("{} {} {} {}", pieces_str, s2, s3, s4);
+                for counter in [s5, s6].into_iter().flatten() {
+                    fen.push(' ');
+                    fen.push_str(counter);
+                }
+                fen
             },
         )
         .parse_next(input)
@@ -376,6 +521,32 @@ fn parse_perft_benchmark(input: &mut &str) -> WinnowResult<UCICommand> {
         .parse_next(input)
 }
 
+fn parse_see(input: &mut &str) -> WinnowResult<UCICommand> {
+    preceded("see ", rest)
+        .try_map(Move::from_str)
+        .map(|mve| UCICommand::See { mve })
+        .parse_next(input)
+}
+
+fn parse_help(input: &mut &str) -> WinnowResult<UCICommand> {
+    "help".value(UCICommand::Help).parse_next(input)
+}
+
+fn parse_analyze_file(input: &mut &str) -> WinnowResult<UCICommand> {
+    preceded(
+        "analyzefile ",
+        (
+            take_until(1.., " movetime "),
+            preceded(" movetime ", digit1.try_map(u64::from_str)),
+        ),
+    )
+    .map(|(path, millis): (&str, u64)| UCICommand::AnalyzeFile {
+        path: PathBuf::from(path),
+        move_time: Duration::from_millis(millis),
+    })
+    .parse_next(input)
+}
+
 // ======================================================
 // Winnow Parsing functions (go commands)
 // ======================================================
@@ -473,6 +644,7 @@ fn parse_go(input: &mut &str) -> WinnowResult<UCICommand> {
             }
         }),
         infinite: params.iter().any(|i| matches!(i, GoParameter::Infinite)),
+        ..Default::default()
     })
     .map(|search_params: SearchParams| UCICommand::Go {
         params: search_params,
@@ -481,24 +653,8 @@ fn parse_go(input: &mut &str) -> WinnowResult<UCICommand> {
 }
 
 fn parse_go_searchmoves(input: &mut &str) -> WinnowResult<GoParameter> {
-    preceded("searchmoves ", rest)
-        .try_map(|moves: &str| {
-            Ok::<GoParameter, <Square as FromStr>::Err>(GoParameter::SearchMoves {
-                moves: moves
-                    .split(' ')
-                    .map(|mve_str| {
-                        let (src_str, dest_str) = mve_str.split_at(2);
-                        let src = Square::from_str(src_str.to_uppercase().as_str())?;
-                        let dest = Square::from_str(dest_str.to_uppercase().as_str())?;
-                        Ok::<Move, <Square as FromStr>::Err>(Move {
-                            src,
-                            dest,
-                            promotion: None,
-                        })
-                    })
-                    .collect::<Result<Vec<Move>, _>>()?,
-            })
-        })
+    preceded("searchmoves ", separated(1.., parse_move, ' '))
+        .map(|moves| GoParameter::SearchMoves { moves })
         .parse_next(input)
 }
 
@@ -508,36 +664,28 @@ fn parse_go_ponder(input: &mut &str) -> WinnowResult<GoParameter> {
 
 fn parse_go_time(input: &mut &str) -> WinnowResult<GoParameter> {
     alt((
-        preceded("wtime ", digit1.try_map(|msec: &str| u64::from_str(msec)))
-            .map(|msec: u64| Duration::from_millis(msec))
-            .map(|time: Duration| GoParameter::Time {
-                time,
-                side: Side::White,
-            }),
-        preceded("btime ", digit1.try_map(|msec: &str| u64::from_str(msec)))
-            .map(|msec: u64| Duration::from_millis(msec))
-            .map(|time: Duration| GoParameter::Time {
-                time,
-                side: Side::Black,
-            }),
+        preceded("wtime ", parse_msec).map(|time: Duration| GoParameter::Time {
+            time,
+            side: Side::White,
+        }),
+        preceded("btime ", parse_msec).map(|time: Duration| GoParameter::Time {
+            time,
+            side: Side::Black,
+        }),
     ))
     .parse_next(input)
 }
 
 fn parse_go_inc(input: &mut &str) -> WinnowResult<GoParameter> {
     alt((
-        preceded("winc ", digit1.try_map(|msec: &str| u64::from_str(msec)))
-            .map(|msec: u64| Duration::from_millis(msec))
-            .map(|time: Duration| GoParameter::Inc {
-                time,
-                side: Side::White,
-            }),
-        preceded("binc ", digit1.try_map(|msec: &str| u64::from_str(msec)))
-            .map(|msec: u64| Duration::from_millis(msec))
-            .map(|time: Duration| GoParameter::Inc {
-                time,
-                side: Side::Black,
-            }),
+        preceded("winc ", parse_msec).map(|time: Duration| GoParameter::Inc {
+            time,
+            side: Side::White,
+        }),
+        preceded("binc ", parse_msec).map(|time: Duration| GoParameter::Inc {
+            time,
+            side: Side::Black,
+        }),
     ))
     .parse_next(input)
 }
@@ -570,13 +718,20 @@ fn parse_go_mate(input: &mut &str) -> WinnowResult<GoParameter> {
 }
 
 fn parse_go_movetime(input: &mut &str) -> WinnowResult<GoParameter> {
-    preceded(
-        "movetime ",
-        digit1.try_map(|msec: &str| u64::from_str(msec)),
-    )
-    .map(|msec: u64| Duration::from_millis(msec))
-    .map(|time: Duration| GoParameter::MoveTime { time })
-    .parse_next(input)
+    preceded("movetime ", parse_msec)
+        .map(|time: Duration| GoParameter::MoveTime { time })
+        .parse_next(input)
+}
+
+/// Milliseconds on a clock. Some GUIs send a negative time when a side has run out of time,
+/// which is treated as no time left.
+fn parse_msec(input: &mut &str) -> WinnowResult<Duration> {
+    (opt('-'), digit1.try_map(|msec: &str| u64::from_str(msec)))
+        .map(|(sign, msec): (Option<char>, u64)| match sign {
+            Some(_) => Duration::ZERO,
+            None => Duration::from_millis(msec),
+        })
+        .parse_next(input)
 }
 
 fn parse_go_infinite(input: &mut &str) -> WinnowResult<GoParameter> {
@@ -602,6 +757,7 @@ mod tests {
     #[test_case("register name Will code 1234", UCICommand::Register { name: "Will".to_string(), code: "1234".to_string() })]
     #[test_case("register later", UCICommand::RegisterLater)]
     #[test_case("ucinewgame", UCICommand::UCINewGame)]
+    #[test_case("position fen 8/8/4Rp2/5P2/1PP1pkP1/7P/1P1r4/7K b - - moves d2d1", UCICommand::Position { fen: Some("8/8/4Rp2/5P2/1PP1pkP1/7P/1P1r4/7K b - -".to_string()), moves: Some(vec![Move::new(D2, D1)])} ; "position fen without move counters")]
     #[test_case("position startpos moves e2e4 e7e5", UCICommand::Position { fen: None, moves: Some(vec![Move::new(E2, E4), Move::new(E7, E5)])} ; "position startpos moves e2e4 e7e5")]
     #[test_case("position fen 8/8/4Rp2/5P2/1PP1pkP1/7P/1P1r4/7K b - - 0 40", UCICommand::Position { fen: Some("8/8/4Rp2/5P2/1PP1pkP1/7P/1P1r4/7K b - - 0 40".to_string()), moves: None} ; "position fen 8/8/4Rp2/5P2/1PP1pkP1/7P/1P1r4/7K b - - 0 40")]
     #[test_case("position fen r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1 moves f3f6", UCICommand::Position { fen: Some("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1".to_string()), moves: Some(vec![Move::new(F3, F6)])} ; "position fen r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1 moves f3f6")]
@@ -610,6 +766,8 @@ mod tests {
     #[test_case("quit", UCICommand::Quit)]
     #[test_case("go searchmoves e2e4 e7e5", UCICommand::Go { params: SearchParams{ search_moves: Some(vec![Move::new(Square::E2, Square::E4), Move::new(Square::E7, Square::E5)]), ..SearchParams::default()}} ; "go searchmoves e2e4 e7e5")]
     #[test_case("go ponder", UCICommand::Go { params: SearchParams { ponder: true, ..SearchParams::default() }} ; "go ponder")]
+    #[test_case("go wtime -50 winc 100 movestogo 0", UCICommand::Go { params: SearchParams { white_time: Some(Duration::ZERO), white_inc: Some(Duration::from_millis(100)), moves_to_go: Some(0), ..SearchParams::default() }} ; "go negative wtime and zero movestogo")]
+    #[test_case("go movetime 0", UCICommand::Go { params: SearchParams { move_time: Some(Duration::ZERO), ..SearchParams::default() }} ; "go movetime 0")]
     #[test_case("go wtime 1000", UCICommand::Go { params: SearchParams { white_time: Some(Duration::from_millis(1000)), ..SearchParams::default() }} ; "go wtime 1000")]
     #[test_case("go btime 3", UCICommand::Go { params: SearchParams { black_time: Some(Duration::from_millis(3)), ..SearchParams::default() }} ; "go btime 3")]
     #[test_case("go winc 1000", UCICommand::Go { params: SearchParams { white_inc: Some(Duration::from_millis(1000)), ..SearchParams::default() }} ; "go winc 1000")]
@@ -618,10 +776,18 @@ mod tests {
     #[test_case("go depth 6", UCICommand::Go { params: SearchParams { max_depth: Some(6), ..SearchParams::default() }} ; "go depth 6")]
     #[test_case("go nodes 10000", UCICommand::Go { params: SearchParams { max_nodes: Some(10000), ..SearchParams::default() }} ; "go nodes 10000")]
     #[test_case("go mate 18", UCICommand::Go { params: SearchParams { mate: Some(18), ..SearchParams::default() }} ; "go mate 18")]
+    // Compatibility: some GUIs send depth together with mate. Both are kept and the search
+    // honors the stricter of the two.
+    #[test_case("go depth 6 mate 3", UCICommand::Go { params: SearchParams { max_depth: Some(6), mate: Some(3), ..SearchParams::default() }} ; "go depth 6 mate 3")]
     #[test_case("go movetime 100", UCICommand::Go { params: SearchParams { move_time: Some(Duration::from_millis(100)), ..SearchParams::default() }} ; "go movetime 100")]
     #[test_case("go infinite", UCICommand::Go { params: SearchParams { infinite: true, ..SearchParams::default() }} ; "go infinite")]
     #[test_case("go infinite wtime 1000", UCICommand::Go { params: SearchParams { infinite: true, white_time: Some(Duration::from_millis(1000)), ..SearchParams::default() }} ; "go infinite wtime 1000")]
+    #[test_case("see e4d5", UCICommand::See { mve: Move::new(E4, D5) })]
+    #[test_case("analyzefile positions.epd movetime 500", UCICommand::AnalyzeFile { path: PathBuf::from("positions.epd"), move_time: Duration::from_millis(500) } ; "analyzefile")]
+    #[test_case("analyzefile my positions.txt movetime 20", UCICommand::AnalyzeFile { path: PathBuf::from("my positions.txt"), move_time: Duration::from_millis(20) } ; "analyzefile path with spaces")]
     #[test_case("go depth 10 searchmoves a2a4 b2b4", UCICommand::Go { params: SearchParams { max_depth: Some(10), search_moves: Some(vec![Move::new(Square::A2, Square::A4), Move::new(Square::B2, Square::B4)]), ..SearchParams::default() }} ; "go depth 10 searchmoves a2a4 b2b4")]
+    #[test_case("go searchmoves e2e4 e7e8q depth 3", UCICommand::Go { params: SearchParams { search_moves: Some(vec![Move::new(E2, E4), Move::with_promotion(E7, E8, Piece::Queen)]), max_depth: Some(3), ..SearchParams::default() }} ; "go searchmoves before depth")]
+    #[test_case("help", UCICommand::Help)]
     fn test_from_str(input: &str, want: UCICommand) -> TestResult {
         let got = UCICommand::from_str(input)?;
 
@@ -629,10 +795,72 @@ mod tests {
         Ok(())
     }
 
+    #[test_case("position startpos moves e" ; "move too short")]
+    #[test_case("position startpos moves e2e4 zz" ; "invalid move after valid one")]
+    #[test_case("position startpos moves é2e4" ; "multibyte character")]
+    #[test_case("go searchmoves e" ; "searchmove too short")]
+    fn test_from_str_invalid(input: &str) {
+        assert!(UCICommand::from_str(input).is_err());
+    }
+
+    // One of every command, so a command missing from `help` is caught.
+    #[test_case(UCICommand::UCI ; "uci")]
+    #[test_case(UCICommand::Debug { on: true } ; "debug")]
+    #[test_case(UCICommand::IsReady ; "isready")]
+    #[test_case(UCICommand::SetOption { name: "Threads".to_string(), value: None } ; "setoption")]
+    #[test_case(UCICommand::Register { name: "Will".to_string(), code: "1234".to_string() } ; "register")]
+    #[test_case(UCICommand::RegisterLater ; "register later")]
+    #[test_case(UCICommand::UCINewGame ; "ucinewgame")]
+    #[test_case(UCICommand::Position { fen: None, moves: None } ; "position")]
+    #[test_case(UCICommand::Go { params: SearchParams::default() } ; "go")]
+    #[test_case(UCICommand::Stop ; "stop")]
+    #[test_case(UCICommand::PonderHit ; "ponderhit")]
+    #[test_case(UCICommand::Quit ; "quit")]
+    #[test_case(UCICommand::Eval ; "eval")]
+    #[test_case(UCICommand::Perft { depth: 3 } ; "perft")]
+    #[test_case(UCICommand::PerftFull { depth: 3 } ; "perft full")]
+    #[test_case(UCICommand::PerftBenchmark ; "perft bench")]
+    #[test_case(UCICommand::See { mve: Move::new(E4, D5) } ; "see")]
+    #[test_case(UCICommand::AnalyzeFile { path: PathBuf::from("positions.epd"), move_time: Duration::from_millis(500) } ; "analyzefile")]
+    #[test_case(UCICommand::Help ; "help")]
+    fn test_command_listed_in_help(command: UCICommand) {
+        let command_str = command.to_string();
+        assert!(
+            STANDARD_COMMANDS
+                .iter()
+                .chain(NONSTANDARD_COMMANDS)
+                .any(|help| command_str == help.name
+                    || command_str.starts_with(&format!("{} ", help.name))),
+            "{} isn't listed",
+            command_str
+        );
+    }
+
+    #[test]
+    fn test_help_lines() {
+        let lines = help_lines();
+        assert_eq!(lines[0], "Standard UCI commands:");
+        assert_eq!(
+            lines.len(),
+            2 * (STANDARD_COMMANDS.len() + NONSTANDARD_COMMANDS.len()) + 2
+        );
+        assert!(lines.iter().any(|line| line == "  eval"));
+    }
+
     #[test_case(
         "8/8/4Rp2/5P2/1PP1pkP1/7P/1P1r4/7K b - - 0 40".to_string(),
         "8/8/4Rp2/5P2/1PP1pkP1/7P/1P1r4/7K b - - 0 40".to_string()
     )]
+    #[test_case(
+        "8/8/4Rp2/5P2/1PP1pkP1/7P/1P1r4/7K b - -".to_string(),
+        "8/8/4Rp2/5P2/1PP1pkP1/7P/1P1r4/7K b - -".to_string()
+        ; "no move counters"
+    )]
+    #[test_case(
+        "8/8/4Rp2/5P2/1PP1pkP1/7P/1P1r4/7K b - - moves d2d1".to_string(),
+        "8/8/4Rp2/5P2/1PP1pkP1/7P/1P1r4/7K b - -".to_string()
+        ; "no move counters before moves"
+    )]
     fn test_from_str_fen(input: String, want: String) -> TestResult {
         let got = parse_position_fen(&mut input.as_str())?;
 
@@ -642,8 +870,8 @@ mod tests {
 
     #[test_case("b2b3", Move::new(B2, B3) ; "simple")]
     #[test_case("a2a1q", Move::with_promotion(A2, A1, Piece::Queen) ; "promotion")]
-    fn test_parse_position_move(input: &str, want: Move) -> TestResult {
-        let got = parse_position_move(input)?;
+    fn test_parse_move(mut input: &str, want: Move) -> TestResult {
+        let got = parse_move(&mut input)?;
 
         assert_eq!(got, want);
         Ok(())
@@ -1,7 +1,8 @@
 mod commands;
 mod responses;
 
-pub use commands::{ReadUCICommand, UCICommandParseError, UCICommandStdinReader};
+pub use commands::{ReadUCICommand, UCICommand, UCICommandParseError, UCICommandStdinReader};
 
-pub(crate) use commands::UCICommand;
-pub(crate) use responses::UCIResponse;
+pub(crate) use commands::help_lines;
+
+pub(crate) use responses::{UCIOption, UCIOptionType, UCIResponse};
@@ -27,9 +27,9 @@ impl std::fmt::Display for UCIResponse {
     }
 }
 
-impl Into<String> for UCIResponse {
-    fn into(self) -> String {
-        self.to_string()
+impl From<UCIResponse> for String {
+    fn from(val: UCIResponse) -> Self {
+        val.to_string()
     }
 }
 
@@ -40,6 +40,16 @@ pub struct UCIOption {
     default: Option<String>,
 }
 
+impl UCIOption {
+    pub(crate) fn new(name: &str, type_: UCIOptionType, default: Option<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            type_,
+            default,
+        }
+    }
+}
+
 #[allow(dead_code)]
 #[derive(Debug)]
 pub enum UCIOptionType {
@@ -52,8 +62,14 @@ pub enum UCIOptionType {
 
 impl std::fmt::Display for UCIOption {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let type_upper = format!("{:?}", self.type_);
-        write!(f, "option {} type {}", self.name, type_upper.to_lowercase())?;
+        let type_str = match &self.type_ {
+            UCIOptionType::Check => "check",
+            UCIOptionType::Spin { .. } => "spin",
+            UCIOptionType::Combo { .. } => "combo",
+            UCIOptionType::Button => "button",
+            UCIOptionType::String => "string",
+        };
+        write!(f, "name {} type {}", self.name, type_str)?;
         if let Some(default) = &self.default {
             write!(f, " default {}", default)?;
         }
@@ -62,16 +78,28 @@ impl std::fmt::Display for UCIOption {
                 range_start,
                 range_end,
             } => {
-                write!(f, "min {} max {}", range_start, range_end)?;
+                write!(f, " min {} max {}", range_start, range_end)?;
             }
             UCIOptionType::Combo { options } => {
-                let options_str_vec: Vec<String> =
-                    options.iter().map(|o| format!("option {}", o)).collect();
-                let options_str = options_str_vec.join(" ");
-                write!(f, "{}", options_str)?;
+                for option in options {
+                    write!(f, " var {}", option)?;
+                }
             }
             _ => {}
         };
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case(UCIOption::new("MultiPV", UCIOptionType::Spin { range_start: 1, range_end: 256 }, Some("1".to_string())), "option name MultiPV type spin default 1 min 1 max 256" ; "spin")]
+    #[test_case(UCIOption::new("Ponder", UCIOptionType::Check, Some("false".to_string())), "option name Ponder type check default false" ; "check")]
+    #[test_case(UCIOption::new("Style", UCIOptionType::Combo { options: vec!["Solid".to_string(), "Risky".to_string()] }, Some("Solid".to_string())), "option name Style type combo default Solid var Solid var Risky" ; "combo")]
+    fn test_option_response(option: UCIOption, want: &str) {
+        assert_eq!(UCIResponse::Option { option }.to_string(), want);
+    }
+}
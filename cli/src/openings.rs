@@ -0,0 +1,193 @@
+use std::collections::HashSet;
+use std::sync::{Arc, atomic::AtomicBool};
+
+use anyhow::{Context, Result};
+use engine::{
+    MOVE_GEN, POSITION_EVALUATOR, Position, SearchParams, Side, TranspositionTable,
+    parse_epd_record, parse_pgn, search,
+};
+
+/// Format of an opening book.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BookFormat {
+    /// One position per line. EPD operations other than `hmvc` are ignored.
+    Epd,
+    /// Games whose first moves are played to reach each opening.
+    Pgn,
+}
+
+impl BookFormat {
+    /// Guesses the format from a book's file extension, defaulting to EPD.
+    pub fn from_extension(extension: Option<&str>) -> Self {
+        match extension {
+            Some(extension) if extension.eq_ignore_ascii_case("pgn") => Self::Pgn,
+            _ => Self::Epd,
+        }
+    }
+}
+
+/// Reads the openings in a book. For a PGN book each opening is the position after the first
+/// `plies` moves of a game, and games shorter than that are skipped. Openings reached more than
+/// once are only kept the first time.
+pub fn load_openings(book: &str, format: BookFormat, plies: usize) -> Result<Vec<Position>> {
+    let openings = match format {
+        BookFormat::Epd => book
+            .lines()
+            .enumerate()
+            .map(|(line_idx, line)| (line_idx, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+            .map(|(line_idx, line)| {
+                Ok(parse_epd_record(line, MOVE_GEN)
+                    .with_context(|| format!("Invalid position on book line {}", line_idx + 1))?
+                    .position)
+            })
+            .collect::<Result<Vec<_>>>()?,
+        BookFormat::Pgn => parse_pgn(book, MOVE_GEN)?
+            .iter()
+            .filter_map(|game| game.positions().nth(plies))
+            .collect(),
+    };
+
+    let mut seen = HashSet::new();
+    Ok(openings
+        .into_iter()
+        .filter(|opening| seen.insert(opening.zobrist_hash))
+        .collect())
+}
+
+/// Searches each opening with a fixed node budget, keeping the ones where neither side is ahead
+/// by more than `max_eval` centipawns. Unbalanced openings decide the game before either engine
+/// has played a move, which only adds noise to a match.
+pub fn screen_openings(
+    openings: Vec<Position>,
+    max_eval: i32,
+    nodes: u64,
+) -> Result<Vec<Position>> {
+    let mut balanced = vec![];
+    // Openings where the game is already over can't be played from.
+    for opening in openings
+        .into_iter()
+        .filter(|opening| !opening.game_result(MOVE_GEN).is_over())
+    {
+        let (best_move, info) = search(
+            &opening,
+            &SearchParams {
+                max_nodes: Some(nodes),
+                ..SearchParams::default()
+            },
+            MOVE_GEN,
+            POSITION_EVALUATOR,
+            &mut TranspositionTable::new(),
+            Arc::new(AtomicBool::new(false)),
+        )?;
+        let is_balanced = best_move.is_some_and(|best_move| {
+            info.move_evals.get(&best_move).is_some_and(|eval| {
+                eval.centipawns()
+                    .is_some_and(|centipawns| centipawns.abs() <= max_eval)
+            })
+        });
+        if is_balanced {
+            balanced.push(opening);
+        }
+    }
+    Ok(balanced)
+}
+
+/// A game in a match, played from `openings[opening_idx]` with the first engine playing
+/// `first_engine_side`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PairedGame {
+    pub opening_idx: usize,
+    pub first_engine_side: Side,
+}
+
+/// Schedules two games for every opening, one with each engine playing white, so an opening
+/// that favours one side can't favour either engine. The games of a pair are next to each
+/// other, so a match stopped early has played whole pairs.
+pub fn paired_schedule(num_openings: usize) -> Vec<PairedGame> {
+    (0..num_openings)
+        .flat_map(|opening_idx| {
+            [Side::White, Side::Black].map(|first_engine_side| PairedGame {
+                opening_idx,
+                first_engine_side,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+    use testresult::TestResult;
+
+    const PGN_BOOK: &str = r#"[Event "Book"]
+
+1. e4 e5 2. Nf3 Nc6 *
+
+[Event "Book"]
+
+1. d4 d5 2. c4 *
+
+[Event "Book"]
+
+1. Nf3 e5 2. e4 Nc6 *
+"#;
+
+    #[test_case(Some("pgn"), BookFormat::Pgn ; "pgn")]
+    #[test_case(Some("PGN"), BookFormat::Pgn ; "uppercase pgn")]
+    #[test_case(Some("epd"), BookFormat::Epd ; "epd")]
+    #[test_case(None, BookFormat::Epd ; "no extension")]
+    fn test_book_format_from_extension(extension: Option<&str>, want: BookFormat) {
+        assert_eq!(BookFormat::from_extension(extension), want);
+    }
+
+    #[test_case(2, 3 ; "two plies")]
+    #[test_case(4, 1 ; "short games skipped and transpositions kept once")]
+    fn test_load_pgn_openings(plies: usize, num_openings_want: usize) -> TestResult {
+        let openings = load_openings(PGN_BOOK, BookFormat::Pgn, plies)?;
+        assert_eq!(openings.len(), num_openings_want);
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_epd_openings() -> TestResult {
+        let book = "# Balanced openings\n\
+            rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - id \"open game\";\n\
+            \n\
+            rnbqkbnr/ppp1pppp/8/3p4/3P4/8/PPP1PPPP/RNBQKBNR w KQkq -\n";
+        let openings = load_openings(book, BookFormat::Epd, 0)?;
+        assert_eq!(
+            openings,
+            vec![
+                Position::from_fen("rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 1")?,
+                Position::from_fen("rnbqkbnr/ppp1pppp/8/3p4/3P4/8/PPP1PPPP/RNBQKBNR w KQkq - 0 1")?,
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_screen_openings() -> TestResult {
+        let balanced = Position::start();
+        // Black is missing their queen.
+        let unbalanced =
+            Position::from_fen("rnb1kbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1")?;
+        let mated =
+            Position::from_fen("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3")?;
+        let screened = screen_openings(vec![balanced.clone(), unbalanced, mated], 100, 2_000)?;
+        assert_eq!(screened, vec![balanced]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_paired_schedule() {
+        let schedule = paired_schedule(2);
+        assert_eq!(schedule.len(), 4);
+        for pair in schedule.chunks(2) {
+            assert_eq!(pair[0].opening_idx, pair[1].opening_idx);
+            assert_eq!(pair[0].first_engine_side, Side::White);
+            assert_eq!(pair[1].first_engine_side, Side::Black);
+        }
+    }
+}
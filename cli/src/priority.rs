@@ -0,0 +1,22 @@
+/// Niceness of search threads with the `CpuPriority` option set to `Low`, from 0 for normal
+/// priority to 19 for the lowest.
+#[cfg(unix)]
+const LOW_PRIORITY_NICENESS: libc::c_int = 10;
+
+/// Lowers the calling thread's CPU priority, so the OS runs other programs first. On Linux
+/// this only applies to the calling thread and the threads it spawns afterwards, while on
+/// other Unixes it applies to the whole process and can't be raised again. Does nothing on
+/// platforms other than Unix.
+pub(crate) fn lower_thread_priority() {
+    #[cfg(unix)]
+    {
+        // SAFETY: setpriority takes its arguments by value and has no other requirements.
+        let res = unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, LOW_PRIORITY_NICENESS) };
+        if res != 0 {
+            tracing::warn!(
+                "Couldn't lower CPU priority: {}",
+                std::io::Error::last_os_error()
+            );
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, FixedOffset};
+
+/// Tracing target that commands received from the GUI are logged under, so they can be
+/// replayed later.
+pub const UCI_INPUT_TARGET: &str = "uci_input";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CapturedCommand {
+    /// Time since the first captured command.
+    pub offset: Duration,
+    pub command: String,
+}
+
+/// Extracts the GUI commands from a log file written by the engine, either the plain text log
+/// or the JSON lines log. Lines from other targets are skipped.
+pub fn parse_captured_commands(log: &str) -> Result<Vec<CapturedCommand>> {
+    let mut timestamped_commands = vec![];
+    for (line_idx, line) in log.lines().enumerate() {
+        let maybe_command = if line.trim_start().starts_with('{') {
+            parse_json_line(line)
+        } else {
+            parse_text_line(line)
+        }
+        .with_context(|| format!("Couldn't parse log line {}: {}", line_idx + 1, line))?;
+
+        if let Some(timestamped_command) = maybe_command {
+            timestamped_commands.push(timestamped_command);
+        }
+    }
+
+    let Some((first_timestamp, _)) = timestamped_commands.first().cloned() else {
+        return Ok(vec![]);
+    };
+    Ok(timestamped_commands
+        .into_iter()
+        .map(|(timestamp, command)| CapturedCommand {
+            offset: (timestamp - first_timestamp).to_std().unwrap_or_default(),
+            command,
+        })
+        .collect())
+}
+
+fn parse_json_line(line: &str) -> Result<Option<(DateTime<FixedOffset>, String)>> {
+    let record: serde_json::Value = serde_json::from_str(line)?;
+    if record["target"] != UCI_INPUT_TARGET {
+        return Ok(None);
+    }
+    let timestamp = record["timestamp"].as_str().context("Missing timestamp")?;
+    let command = record["fields"]["message"]
+        .as_str()
+        .context("Missing message")?;
+    Ok(Some((
+        DateTime::parse_from_rfc3339(timestamp)?,
+        command.to_string(),
+    )))
+}
+
+/// Plain text lines look like `2025-01-01T10:00:00.000000Z DEBUG uci_input: go depth 5`.
+fn parse_text_line(line: &str) -> Result<Option<(DateTime<FixedOffset>, String)>> {
+    let target_marker = format!(" {}: ", UCI_INPUT_TARGET);
+    let Some((prefix, command)) = line.split_once(&target_marker) else {
+        return Ok(None);
+    };
+    let timestamp = prefix
+        .split_whitespace()
+        .next()
+        .context("Missing timestamp")?;
+    Ok(Some((
+        DateTime::parse_from_rfc3339(timestamp)?,
+        command.to_string(),
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+    use testresult::TestResult;
+
+    #[test_case(
+        "2025-01-01T10:00:00.000000Z DEBUG uci_input: uci
+2025-01-01T10:00:00.000100Z  INFO uci: id name Flying Fish
+2025-01-01T10:00:01.500000Z DEBUG uci_input: go depth 5" ; "text"
+    )]
+    #[test_case(
+        r#"{"timestamp":"2025-01-01T10:00:00.000000Z","level":"DEBUG","fields":{"message":"uci"},"target":"uci_input"}
+{"timestamp":"2025-01-01T10:00:00.000100Z","level":"INFO","fields":{"message":"id name Flying Fish"},"target":"uci"}
+{"timestamp":"2025-01-01T10:00:01.500000Z","level":"DEBUG","fields":{"message":"go depth 5"},"target":"uci_input"}"# ; "json"
+    )]
+    fn test_parse_captured_commands(log: &str) -> TestResult {
+        let got = parse_captured_commands(log)?;
+        let want = vec![
+            CapturedCommand {
+                offset: Duration::ZERO,
+                command: "uci".to_string(),
+            },
+            CapturedCommand {
+                offset: Duration::from_millis(1500),
+                command: "go depth 5".to_string(),
+            },
+        ];
+        assert_eq!(got, want);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_captured_commands_invalid_timestamp() {
+        let got = parse_captured_commands("yesterday DEBUG uci_input: uci");
+        assert!(got.is_err());
+    }
+}
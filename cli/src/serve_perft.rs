@@ -0,0 +1,353 @@
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex, mpsc};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+use engine::{MOVE_GEN, Position, perft};
+use serde_json::{Value, json};
+use tracing::{debug, warn};
+
+/// Largest request body read, which is plenty for a FEN and a depth.
+const MAX_BODY_LEN: usize = 16 * 1024;
+
+/// Limits on the work the server takes on, since anyone who can reach it can send requests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ServePerftConfig {
+    /// Connections handled at once. Further connections wait until a worker is free.
+    pub num_workers: usize,
+    /// Deepest perft a request can ask for, so one request can't keep a worker busy for hours.
+    pub max_depth: u8,
+    /// Most results kept in the cache, the least recently used is dropped to make room.
+    pub cache_capacity: usize,
+    /// How long a client can take to send its request or read the response before it's
+    /// dropped, so idle connections can't keep the workers busy.
+    pub io_timeout: Duration,
+}
+
+impl ServePerftConfig {
+    pub const DEFAULT: ServePerftConfig = ServePerftConfig {
+        num_workers: 4,
+        max_depth: 5,
+        cache_capacity: 1024,
+        io_timeout: Duration::from_secs(10),
+    };
+}
+
+impl Default for ServePerftConfig {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Divide results already computed, keyed by the position's FEN and the depth. Once full, the
+/// least recently used result is dropped.
+#[derive(Debug)]
+struct PerftCache {
+    capacity: usize,
+    state: Mutex<PerftCacheState>,
+}
+
+#[derive(Debug, Default)]
+struct PerftCacheState {
+    /// Each result, and the `clock` value when it was last used.
+    entries: HashMap<(String, u8), (Value, u64)>,
+    clock: u64,
+}
+
+impl PerftCache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            state: Mutex::new(PerftCacheState::default()),
+        }
+    }
+
+    fn get(&self, key: &(String, u8)) -> Option<Value> {
+        let mut state = self.state.lock().unwrap();
+        state.clock += 1;
+        let clock = state.clock;
+        state.entries.get_mut(key).map(|(response, last_used)| {
+            *last_used = clock;
+            response.clone()
+        })
+    }
+
+    fn insert(&self, key: (String, u8), response: Value) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut state = self.state.lock().unwrap();
+        state.clock += 1;
+        let clock = state.clock;
+        if state.entries.len() >= self.capacity && !state.entries.contains_key(&key) {
+            // A linear scan is fine for the few results a cache holds.
+            let least_recently_used = state
+                .entries
+                .iter()
+                .min_by_key(|(_, (_, last_used))| *last_used)
+                .map(|(key, _)| key.clone());
+            if let Some(least_recently_used) = least_recently_used {
+                state.entries.remove(&least_recently_used);
+            }
+        }
+        state.entries.insert(key, (response, clock));
+    }
+
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.state.lock().unwrap().entries.len()
+    }
+}
+
+/// Serves perft over HTTP until the listener fails. Connections are handled by a fixed pool of
+/// workers, which share results through a cache.
+///
+/// `POST /perft` takes a JSON body like `{"fen": "<fen>", "depth": 4}` and responds with the
+/// total node count and the divide, the nodes below each legal move in UCI notation:
+/// `{"fen": "<fen>", "depth": 4, "nodes": 197281, "moves": {"a2a3": 8457, ...}}`. Errors are
+/// responded to with `{"error": "<message>"}`.
+pub fn serve_perft(listener: TcpListener, config: ServePerftConfig) -> Result<()> {
+    let cache = Arc::new(PerftCache::new(config.cache_capacity));
+    // Accepting blocks while every worker is busy and one connection is already waiting, so
+    // further connections queue up in the listener's backlog.
+    let (tx_stream, rx_stream) = mpsc::sync_channel::<TcpStream>(1);
+    let rx_stream = Arc::new(Mutex::new(rx_stream));
+    for _ in 0..config.num_workers.max(1) {
+        let rx_stream = Arc::clone(&rx_stream);
+        let cache = Arc::clone(&cache);
+        thread::spawn(move || {
+            loop {
+                let Ok(stream) = rx_stream.lock().unwrap().recv() else {
+                    break;
+                };
+                if let Err(err) = handle_connection(stream, &cache, &config) {
+                    warn!("perft request failed: {:#}", err);
+                }
+            }
+        });
+    }
+
+    for stream in listener.incoming() {
+        let stream = stream.context("Couldn't accept connection")?;
+        tx_stream
+            .send(stream)
+            .context("Every perft worker stopped")?;
+    }
+    Ok(())
+}
+
+fn handle_connection(
+    stream: TcpStream,
+    cache: &PerftCache,
+    config: &ServePerftConfig,
+) -> Result<()> {
+    stream.set_read_timeout(Some(config.io_timeout))?;
+    stream.set_write_timeout(Some(config.io_timeout))?;
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut content_len = 0;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':')
+            && name.trim().eq_ignore_ascii_case("content-length")
+        {
+            content_len = value.trim().parse().context("Invalid Content-Length")?;
+        }
+    }
+
+    let (status, body) = if content_len > MAX_BODY_LEN {
+        (413, json!({ "error": "request body too large" }))
+    } else {
+        let mut body = vec![0; content_len];
+        reader.read_exact(&mut body)?;
+        let mut request_parts = request_line.split_whitespace();
+        let method = request_parts.next().unwrap_or_default();
+        let path = request_parts.next().unwrap_or_default();
+        debug!("{} {}", method, path);
+        respond(method, path, &body, cache, config.max_depth)
+    };
+
+    let body = body.to_string();
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        413 => "Payload Too Large",
+        _ => "Internal Server Error",
+    };
+    write!(
+        &stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason,
+        body.len(),
+        body
+    )?;
+    Ok(())
+}
+
+/// Status code and JSON body for a request.
+fn respond(
+    method: &str,
+    path: &str,
+    body: &[u8],
+    cache: &PerftCache,
+    max_depth: u8,
+) -> (u16, Value) {
+    match (method, path) {
+        ("POST", "/perft") => match cached_perft(body, cache, max_depth) {
+            Ok(response) => (200, response),
+            Err(err) => (400, json!({ "error": format!("{:#}", err) })),
+        },
+        (_, "/perft") => (405, json!({ "error": "use POST" })),
+        _ => (404, json!({ "error": format!("no endpoint {}", path) })),
+    }
+}
+
+fn cached_perft(body: &[u8], cache: &PerftCache, max_depth: u8) -> Result<Value> {
+    let request: Value = serde_json::from_slice(body).context("Invalid JSON")?;
+    let fen = request["fen"].as_str().context("Missing `fen`")?;
+    let depth = request["depth"]
+        .as_u64()
+        .and_then(|depth| u8::try_from(depth).ok())
+        .context("Missing or invalid `depth`")?;
+    if !(1..=max_depth).contains(&depth) {
+        bail!("`depth` should be from 1 to {}", max_depth);
+    }
+    let position =
+        Position::from_fen(fen).with_context(|| format!("Couldn't parse given fen: `{}`", fen))?;
+
+    let key = (position.to_fen(), depth);
+    if let Some(response) = cache.get(&key) {
+        return Ok(response);
+    }
+    // Two requests for the same position at once both compute it, rather than one holding the
+    // lock and blocking requests for other positions.
+    let (move_counts, tot_moves) = perft(&position, usize::from(depth), MOVE_GEN);
+    let moves: serde_json::Map<String, Value> = move_counts
+        .into_iter()
+        .map(|(mve, nodes)| (mve.to_string().to_lowercase(), nodes.into()))
+        .collect();
+    let response = json!({
+        "fen": key.0,
+        "depth": depth,
+        "nodes": tot_moves,
+        "moves": moves,
+    });
+    cache.insert(key, response.clone());
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::SocketAddr;
+    use test_case::test_case;
+    use testresult::TestResult;
+
+    const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+    const MAX_DEPTH: u8 = ServePerftConfig::DEFAULT.max_depth;
+
+    #[test]
+    fn test_respond_perft() {
+        let cache = PerftCache::new(2);
+        let body = json!({ "fen": KIWIPETE, "depth": 2 }).to_string();
+        let (status, response) = respond("POST", "/perft", body.as_bytes(), &cache, MAX_DEPTH);
+        assert_eq!(status, 200);
+        assert_eq!(response["nodes"], 2039);
+        assert_eq!(response["moves"].as_object().unwrap().len(), 48);
+        assert_eq!(response["moves"]["e1g1"], 43);
+
+        // Served from the cache the second time.
+        assert_eq!(cache.len(), 1);
+        assert_eq!(
+            respond("POST", "/perft", body.as_bytes(), &cache, MAX_DEPTH),
+            (200, response)
+        );
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn test_perft_cache_drops_least_recently_used() {
+        let cache = PerftCache::new(2);
+        let key = |depth| (KIWIPETE.to_string(), depth);
+        cache.insert(key(1), json!(1));
+        cache.insert(key(2), json!(2));
+        assert_eq!(cache.get(&key(1)), Some(json!(1)));
+
+        cache.insert(key(3), json!(3));
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get(&key(2)), None);
+        assert_eq!(cache.get(&key(1)), Some(json!(1)));
+        assert_eq!(cache.get(&key(3)), Some(json!(3)));
+    }
+
+    #[test_case("POST", "/perft", r#"{"fen": "8/8/8 w - -", "depth": 2}"#, 400 ; "invalid fen")]
+    #[test_case("POST", "/perft", r#"{"fen": "8/8/8/8/8/8/8/k1K5 w - -", "depth": 6}"#, 400 ; "too deep")]
+    #[test_case("POST", "/perft", r#"{"fen": "8/8/8/8/8/8/8/k1K5 w - -"}"#, 400 ; "missing depth")]
+    #[test_case("POST", "/perft", "perft 2", 400 ; "not json")]
+    #[test_case("GET", "/perft", "", 405 ; "wrong method")]
+    #[test_case("POST", "/search", "", 404 ; "unknown endpoint")]
+    fn test_respond_error(method: &str, path: &str, body: &str, want_status: u16) {
+        let cache = PerftCache::new(1);
+        let (status, response) = respond(method, path, body.as_bytes(), &cache, MAX_DEPTH);
+        assert_eq!(status, want_status);
+        assert!(response["error"].is_string());
+    }
+
+    fn request_perft(addr: SocketAddr, depth: u8) -> Result<String> {
+        let body = json!({ "fen": Position::start().to_fen(), "depth": depth }).to_string();
+        let mut stream = TcpStream::connect(addr)?;
+        write!(
+            stream,
+            "POST /perft HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        )?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response)?;
+        Ok(response)
+    }
+
+    #[test]
+    fn test_serve_perft() -> TestResult {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        thread::spawn(move || serve_perft(listener, ServePerftConfig::default()));
+
+        let response = request_perft(addr, 3)?;
+
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        let (_, response_body) = response.split_once("\r\n\r\n").ok_or("no body")?;
+        let response_body: Value = serde_json::from_str(response_body)?;
+        assert_eq!(response_body["nodes"], 8902);
+        Ok(())
+    }
+
+    #[test]
+    fn test_serve_perft_drops_idle_connections() -> TestResult {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        let config = ServePerftConfig {
+            num_workers: 1,
+            io_timeout: Duration::from_millis(100),
+            ..ServePerftConfig::DEFAULT
+        };
+        thread::spawn(move || serve_perft(listener, config));
+
+        // Never sends a request, so it would keep the only worker forever without the timeout.
+        let _idle = TcpStream::connect(addr)?;
+        let response = request_perft(addr, 1)?;
+
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        Ok(())
+    }
+}
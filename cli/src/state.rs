@@ -2,20 +2,27 @@ use anyhow::Result;
 use statig::prelude::*;
 use std::backtrace::Backtrace;
 use std::collections::HashMap;
+use std::ops::RangeInclusive;
 use std::panic;
 use std::process;
+use std::str::FromStr;
 use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
 use std::time::{Duration, Instant};
 use std::{sync::atomic::AtomicBool, thread};
 use tracing::{debug, error, warn};
 
 use engine::{
-    AUTHOR, EvaluatePosition, GenerateMoves, MOVE_GEN, Move, NAME, POSITION_EVALUATOR, Position,
-    SearchError, SearchParams, TranspositionTable, perft, perft_full, search,
+    AUTHOR, EvaluatePosition, GenerateMoves, MOVE_GEN, Move, NAME, POSITION_EVALUATOR, PonderHit,
+    Position, SearchError, SearchParams, SearchTuning, TranspositionTable, perft, perft_full,
+    search, static_exchange_eval,
 };
 
-use crate::messages::{UCICommand, UCIResponse};
-use crate::uci;
+use crate::host::HostCapabilities;
+use crate::messages::{UCICommand, UCIOption, UCIOptionType, UCIResponse, help_lines};
+use crate::stdout_writer::flush_stdout;
+use crate::uci::uci_info_callback;
+use crate::{analyze_file, crash_report, priority, uci};
 
 #[derive(Debug)]
 pub(crate) struct UCIState<G>
@@ -28,6 +35,21 @@ where
     // to be able store this as statig state local storage because that requires the
     // item to be a reference.
     maybe_terminate: Option<Arc<AtomicBool>>,
+    // Set while a `go ponder` search is running, until `ponderhit` is received.
+    maybe_ponder_hit: Option<PonderHit>,
+    // The thread reporting the last search's best move, and whether that search only stops when
+    // told to. Kept so a replay can wait for the search before exiting.
+    maybe_search_thread: Mutex<Option<(JoinHandle<()>, bool)>>,
+    multi_pv: u8,
+    threads: u16,
+    tuning: SearchTuning,
+    analysis_mode: bool,
+    // Clear the transposition table on every `position` command, so a search only depends on
+    // the position it's given and not on what was searched before.
+    clear_state_on_position: bool,
+    info_interval_nodes: u64,
+    max_cpu_percent: u8,
+    low_cpu_priority: bool,
 }
 
 impl<G> UCIState<G>
@@ -39,10 +61,253 @@ where
             move_gen,
             transposition_table: Arc::new(Mutex::new(TranspositionTable::new())),
             maybe_terminate: None,
+            maybe_ponder_hit: None,
+            maybe_search_thread: Mutex::new(None),
+            multi_pv: 1,
+            threads: 1,
+            tuning: SearchTuning::default(),
+            analysis_mode: false,
+            clear_state_on_position: false,
+            info_interval_nodes: SearchParams::DEFAULT_INFO_INTERVAL_NODES,
+            max_cpu_percent: *MAX_CPU_PERCENT_RANGE.end(),
+            low_cpu_priority: false,
+        }
+    }
+
+    /// Takes the thread of the last search, if it may still be running, and whether that search
+    /// only stops when told to.
+    pub(crate) fn take_search_thread(&self) -> Option<(JoinHandle<()>, bool)> {
+        self.maybe_search_thread
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .take()
+    }
+
+    fn set_option(&mut self, name: &str, value: Option<&str>) {
+        if name.eq_ignore_ascii_case(MULTI_PV_OPTION) {
+            if let Some(multi_pv) = parse_option_value(MULTI_PV_OPTION, value, 1..=u8::MAX) {
+                self.multi_pv = multi_pv;
+            }
+        } else if name.eq_ignore_ascii_case(THREADS_OPTION) {
+            if let Some(threads) = parse_option_value(THREADS_OPTION, value, THREADS_RANGE) {
+                self.threads = threads;
+            }
+        } else if name.eq_ignore_ascii_case(ANALYSE_MODE_OPTION) {
+            match value.map(str::parse::<bool>) {
+                Some(Ok(analysis_mode)) => self.analysis_mode = analysis_mode,
+                _ => warn!(target: "uci", "Invalid value for {}: {:?}", ANALYSE_MODE_OPTION, value),
+            }
+        } else if name.eq_ignore_ascii_case(CLEAR_STATE_ON_POSITION_OPTION) {
+            match value.map(str::parse::<bool>) {
+                Some(Ok(clear_state_on_position)) => {
+                    self.clear_state_on_position = clear_state_on_position
+                }
+                _ => warn!(
+                    target: "uci",
+                    "Invalid value for {}: {:?}", CLEAR_STATE_ON_POSITION_OPTION, value
+                ),
+            }
+        } else if name.eq_ignore_ascii_case(MOVE_OVERHEAD_OPTION) {
+            if let Some(overhead) =
+                parse_option_value(MOVE_OVERHEAD_OPTION, value, MOVE_OVERHEAD_RANGE)
+            {
+                self.tuning.move_overhead = Duration::from_millis(overhead.into());
+            }
+        } else if name.eq_ignore_ascii_case(SOFT_TIME_PERCENT_OPTION) {
+            if let Some(percent) =
+                parse_option_value(SOFT_TIME_PERCENT_OPTION, value, TIME_PERCENT_RANGE)
+            {
+                self.tuning.soft_time_percent = percent.into();
+            }
+        } else if name.eq_ignore_ascii_case(HARD_TIME_PERCENT_OPTION) {
+            if let Some(percent) =
+                parse_option_value(HARD_TIME_PERCENT_OPTION, value, TIME_PERCENT_RANGE)
+            {
+                self.tuning.hard_time_percent = percent.into();
+            }
+        } else if name.eq_ignore_ascii_case(INFO_INTERVAL_NODES_OPTION) {
+            if let Some(nodes) =
+                parse_option_value(INFO_INTERVAL_NODES_OPTION, value, INFO_INTERVAL_NODES_RANGE)
+            {
+                self.info_interval_nodes = nodes.into();
+            }
+        } else if name.eq_ignore_ascii_case(MAX_CPU_PERCENT_OPTION) {
+            if let Some(percent) =
+                parse_option_value(MAX_CPU_PERCENT_OPTION, value, MAX_CPU_PERCENT_RANGE)
+            {
+                self.max_cpu_percent = percent;
+            }
+        } else if name.eq_ignore_ascii_case(CPU_PRIORITY_OPTION) {
+            match value {
+                Some(value) if value.eq_ignore_ascii_case(CPU_PRIORITY_NORMAL) => {
+                    self.low_cpu_priority = false
+                }
+                Some(value) if value.eq_ignore_ascii_case(CPU_PRIORITY_LOW) => {
+                    self.low_cpu_priority = true
+                }
+                _ => warn!(target: "uci", "Invalid value for {}: {:?}", CPU_PRIORITY_OPTION, value),
+            }
+        } else if name.eq_ignore_ascii_case(QSEARCH_DEPTH_FACTOR_OPTION) {
+            if let Some(factor) = parse_option_value(QSEARCH_DEPTH_FACTOR_OPTION, value, 1..=10) {
+                self.tuning.qsearch_depth_factor = factor;
+            }
+        } else if name.eq_ignore_ascii_case(QSEARCH_SEE_MARGIN_OPTION) {
+            if let Some(margin) = parse_option_value(QSEARCH_SEE_MARGIN_OPTION, value, 0..=10_000) {
+                self.tuning.qsearch_see_margin = margin;
+            }
+        } else if name.eq_ignore_ascii_case(LAZY_EVAL_MARGIN_OPTION) {
+            if let Some(margin) = parse_option_value(LAZY_EVAL_MARGIN_OPTION, value, 0..=10_000) {
+                self.tuning.lazy_eval_margin = margin;
+            }
+        } else if name.eq_ignore_ascii_case(ROOT_MOVE_PRUNING_OPTION) {
+            match value.map(str::parse::<bool>) {
+                Some(Ok(root_move_pruning)) => self.tuning.root_move_pruning = root_move_pruning,
+                _ => warn!(
+                    target: "uci",
+                    "Invalid value for {}: {:?}", ROOT_MOVE_PRUNING_OPTION, value
+                ),
+            }
+        } else if name.eq_ignore_ascii_case(ROOT_PRUNE_MARGIN_OPTION) {
+            if let Some(margin) = parse_option_value(ROOT_PRUNE_MARGIN_OPTION, value, 0..=10_000) {
+                self.tuning.root_prune_margin = margin;
+            }
+        } else {
+            warn!(target: "uci", "Unknown option: {}", name);
+        }
+    }
+}
+
+/// Parses a spin option's value, logging a warning if it's missing or out of range.
+fn parse_option_value<T>(name: &str, value: Option<&str>, range: RangeInclusive<T>) -> Option<T>
+where
+    T: FromStr + PartialOrd,
+{
+    match value.map(str::parse::<T>) {
+        Some(Ok(parsed)) if range.contains(&parsed) => Some(parsed),
+        _ => {
+            warn!(target: "uci", "Invalid value for {}: {:?}", name, value);
+            None
         }
     }
 }
 
+const MULTI_PV_OPTION: &str = "MultiPV";
+const THREADS_OPTION: &str = "Threads";
+const THREADS_RANGE: RangeInclusive<u16> = 1..=256;
+/// Set by GUIs while the user is analysing rather than playing a game.
+const ANALYSE_MODE_OPTION: &str = "UCI_AnalyseMode";
+const CLEAR_STATE_ON_POSITION_OPTION: &str = "ClearStateOnPosition";
+/// Milliseconds kept in reserve on every move for lag between the engine and the GUI.
+const MOVE_OVERHEAD_OPTION: &str = "Move Overhead";
+const MOVE_OVERHEAD_RANGE: RangeInclusive<u16> = 0..=5_000;
+/// Percentages of the move's share of the clock to aim for, and to never go over.
+const SOFT_TIME_PERCENT_OPTION: &str = "SoftTimePercent";
+const HARD_TIME_PERCENT_OPTION: &str = "HardTimePercent";
+const TIME_PERCENT_RANGE: RangeInclusive<u16> = 1..=1_000;
+/// Nodes between the info lines sent while a depth is being searched, 0 to only send one per
+/// completed depth.
+const INFO_INTERVAL_NODES_OPTION: &str = "InfoIntervalNodes";
+const INFO_INTERVAL_NODES_RANGE: RangeInclusive<u32> = 0..=100_000_000;
+/// Percentage of a CPU each search thread may use, for running in the background without
+/// slowing down everything else.
+const MAX_CPU_PERCENT_OPTION: &str = "MaxCpuPercent";
+const MAX_CPU_PERCENT_RANGE: RangeInclusive<u8> = 1..=100;
+/// With `Low`, search threads ask the OS to run other programs first.
+const CPU_PRIORITY_OPTION: &str = "CpuPriority";
+const CPU_PRIORITY_NORMAL: &str = "Normal";
+const CPU_PRIORITY_LOW: &str = "Low";
+
+// Hidden options for tuning the search. They aren't listed in response to `uci` since they're
+// only meant for testers, but can still be set with `setoption`.
+const QSEARCH_DEPTH_FACTOR_OPTION: &str = "QSearchDepthFactor";
+const QSEARCH_SEE_MARGIN_OPTION: &str = "QSearchSeeMargin";
+const LAZY_EVAL_MARGIN_OPTION: &str = "LazyEvalMargin";
+/// Turning root move pruning off makes every root move get searched to the full depth, for
+/// correctness testing.
+const ROOT_MOVE_PRUNING_OPTION: &str = "RootMovePruning";
+const ROOT_PRUNE_MARGIN_OPTION: &str = "RootPruneMargin";
+
+fn uci_options() -> Vec<UCIOption> {
+    vec![
+        UCIOption::new(
+            MULTI_PV_OPTION,
+            UCIOptionType::Spin {
+                range_start: 1,
+                range_end: u8::MAX.into(),
+            },
+            Some("1".to_string()),
+        ),
+        UCIOption::new(
+            THREADS_OPTION,
+            UCIOptionType::Spin {
+                range_start: (*THREADS_RANGE.start()).into(),
+                range_end: (*THREADS_RANGE.end()).into(),
+            },
+            Some("1".to_string()),
+        ),
+        UCIOption::new(
+            ANALYSE_MODE_OPTION,
+            UCIOptionType::Check,
+            Some("false".to_string()),
+        ),
+        UCIOption::new(
+            CLEAR_STATE_ON_POSITION_OPTION,
+            UCIOptionType::Check,
+            Some("false".to_string()),
+        ),
+        UCIOption::new(
+            MOVE_OVERHEAD_OPTION,
+            UCIOptionType::Spin {
+                range_start: (*MOVE_OVERHEAD_RANGE.start()).into(),
+                range_end: (*MOVE_OVERHEAD_RANGE.end()).into(),
+            },
+            Some(SearchTuning::DEFAULT.move_overhead.as_millis().to_string()),
+        ),
+        UCIOption::new(
+            SOFT_TIME_PERCENT_OPTION,
+            UCIOptionType::Spin {
+                range_start: (*TIME_PERCENT_RANGE.start()).into(),
+                range_end: (*TIME_PERCENT_RANGE.end()).into(),
+            },
+            Some(SearchTuning::DEFAULT.soft_time_percent.to_string()),
+        ),
+        UCIOption::new(
+            HARD_TIME_PERCENT_OPTION,
+            UCIOptionType::Spin {
+                range_start: (*TIME_PERCENT_RANGE.start()).into(),
+                range_end: (*TIME_PERCENT_RANGE.end()).into(),
+            },
+            Some(SearchTuning::DEFAULT.hard_time_percent.to_string()),
+        ),
+        UCIOption::new(
+            INFO_INTERVAL_NODES_OPTION,
+            UCIOptionType::Spin {
+                range_start: *INFO_INTERVAL_NODES_RANGE.start() as i32,
+                range_end: *INFO_INTERVAL_NODES_RANGE.end() as i32,
+            },
+            Some(SearchParams::DEFAULT_INFO_INTERVAL_NODES.to_string()),
+        ),
+        UCIOption::new(
+            MAX_CPU_PERCENT_OPTION,
+            UCIOptionType::Spin {
+                range_start: (*MAX_CPU_PERCENT_RANGE.start()).into(),
+                range_end: (*MAX_CPU_PERCENT_RANGE.end()).into(),
+            },
+            Some(MAX_CPU_PERCENT_RANGE.end().to_string()),
+        ),
+        UCIOption::new(
+            CPU_PRIORITY_OPTION,
+            UCIOptionType::Combo {
+                options: vec![
+                    CPU_PRIORITY_NORMAL.to_string(),
+                    CPU_PRIORITY_LOW.to_string(),
+                ],
+            },
+            Some(CPU_PRIORITY_NORMAL.to_string()),
+        ),
+    ]
+}
+
 #[state_machine(
     initial = "State::initial()",
     state(derive(PartialEq, Eq, Debug)),
@@ -55,8 +320,15 @@ where
     #[superstate]
     fn top_level(&mut self, event: &UCICommand) -> Outcome<State> {
         if *event == UCICommand::Quit {
+            flush_stdout();
             process::exit(0);
         }
+        if *event == UCICommand::Help {
+            for line in help_lines() {
+                uci!("{}", line);
+            }
+            return Handled;
+        }
 
         warn!(target: "uci", "Unexpected command for current state: {}", event.to_string());
 
@@ -78,6 +350,10 @@ where
                         author: AUTHOR.to_string(),
                     },
                 );
+                for option in uci_options() {
+                    uci!("{}", UCIResponse::Option { option });
+                }
+                uci!("info string {}", HostCapabilities::detect());
                 uci!("{}", UCIResponse::UCIOk);
 
                 Transition(State::uci_enabled(Position::start()))
@@ -110,52 +386,108 @@ where
         match event {
             UCICommand::UCINewGame => Transition(State::uci_enabled(Position::start())),
             UCICommand::Position { fen, moves } => {
-                let mut pos = match fen {
-                    Some(fen) => Position::from_fen(fen).unwrap(),
+                let mut pos = match fen.as_deref().map(Position::from_fen) {
+                    Some(Ok(pos)) => pos,
+                    Some(Err(err)) => {
+                        uci!("info string ignoring invalid fen: {}", err);
+                        return Handled;
+                    }
                     None => Position::start(),
                 };
                 if let Some(moves) = moves {
                     for mve in moves {
-                        pos.make_move(*mve);
+                        // Play on from the last legal move, rather than crashing on a move the
+                        // GUI shouldn't have sent.
+                        if let Err(err) = pos.try_make_move(*mve, self.move_gen) {
+                            error!(target: "uci", "Ignoring moves from {}: {}", mve, err);
+                            break;
+                        }
                     }
                 }
+                crash_report::record_position(
+                    fen.as_deref(),
+                    moves.as_deref().unwrap_or_default(),
+                    &pos,
+                );
+                if self.clear_state_on_position {
+                    self.transposition_table
+                        .lock()
+                        .unwrap_or_else(|poisoned| poisoned.into_inner())
+                        .clear();
+                }
                 Transition(State::uci_enabled(pos))
             }
             UCICommand::Go { params } => {
-                if let Some(terminate) = &self.maybe_terminate {
-                    if !terminate.load(std::sync::atomic::Ordering::Relaxed) {
-                        warn!(target: "uci", "Can't start new search until previous search completes");
-                        return Handled;
-                    }
+                if let Some(terminate) = &self.maybe_terminate
+                    && !terminate.load(std::sync::atomic::Ordering::Relaxed)
+                {
+                    warn!(target: "uci", "Can't start new search until previous search completes");
+                    return Handled;
                 }
                 let terminate = Arc::new(AtomicBool::new(false));
+                self.maybe_terminate = Some(Arc::clone(&terminate));
 
-                spawn_search(
+                let mut params = self.with_options(params.clone());
+                self.maybe_ponder_hit = None;
+                if params.ponder {
+                    let ponder_hit = PonderHit::new();
+                    params.ponder_hit = Some(ponder_hit.clone());
+                    self.maybe_ponder_hit = Some(ponder_hit);
+                }
+                crash_report::record_search_params(&params);
+
+                let is_unbounded = params.infinite || params.ponder;
+                let search_thread = spawn_search(
                     position.clone(),
-                    params.clone(),
+                    params,
                     self.move_gen,
                     Arc::clone(&self.transposition_table),
                     terminate,
+                    self.low_cpu_priority,
                 );
+                *self
+                    .maybe_search_thread
+                    .get_mut()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner()) =
+                    Some((search_thread, is_unbounded));
 
                 Handled
             }
+            UCICommand::SetOption { name, value } => {
+                self.set_option(name, value.as_deref());
+                Handled
+            }
+            UCICommand::PonderHit => {
+                if let Some(ponder_hit) = self.maybe_ponder_hit.take() {
+                    ponder_hit.trigger();
+                } else {
+                    warn!(target: "uci", "Received ponderhit without a ponder search running");
+                }
+                Handled
+            }
             UCICommand::Stop => {
                 if let Some(terminate) = &self.maybe_terminate {
                     terminate.store(true, std::sync::atomic::Ordering::Relaxed);
                     self.maybe_terminate = None;
+                    self.maybe_ponder_hit = None;
                 } else {
-                    panic!("maybe_terminate should not be None when stop is received");
+                    warn!(target: "uci", "Received stop without a search running");
                 }
                 Handled
             }
             UCICommand::Quit => {
                 debug!("Exiting with position fen: {}", position.to_fen());
+                flush_stdout();
                 process::exit(0);
             }
             UCICommand::Eval => {
-                let eval = POSITION_EVALUATOR.evaluate(position, MOVE_GEN);
-                uci!("uci string {}", eval);
+                uci!("{}", POSITION_EVALUATOR.trace(position));
+                let eval = POSITION_EVALUATOR.evaluate(position, MOVE_GEN, None);
+                uci!(
+                    "uci string {} (white: {})",
+                    eval,
+                    eval.to_absolute(position.state.to_move)
+                );
                 Handled
             }
             UCICommand::Perft { depth } => {
@@ -175,10 +507,77 @@ where
                 self.perft_benchmark().unwrap();
                 Handled
             }
+            UCICommand::See { mve } => {
+                match static_exchange_eval(position, *mve) {
+                    Ok(exchange) => {
+                        let sequence_str = exchange
+                            .sequence
+                            .iter()
+                            .map(|mve| mve.to_string().to_lowercase())
+                            .collect::<Vec<_>>()
+                            .join(" ");
+                        uci!(
+                            "info string see {} sequence {}",
+                            exchange.value,
+                            sequence_str
+                        );
+                    }
+                    Err(err) => warn!(target: "uci", "Couldn't run see for {}: {}", mve, err),
+                }
+                Handled
+            }
+            UCICommand::AnalyzeFile { path, move_time } => {
+                if let Some(terminate) = &self.maybe_terminate
+                    && !terminate.load(std::sync::atomic::Ordering::Relaxed)
+                {
+                    warn!(target: "uci", "Can't start analysis until previous search completes");
+                    return Handled;
+                }
+                let terminate = Arc::new(AtomicBool::new(false));
+                self.maybe_terminate = Some(Arc::clone(&terminate));
+                self.maybe_ponder_hit = None;
+
+                let params = self.with_options(SearchParams {
+                    move_time: Some(*move_time),
+                    ..SearchParams::default()
+                });
+                crash_report::record_search_params(&params);
+
+                let analysis_thread = analyze_file::spawn_analyze_file(
+                    path,
+                    params,
+                    self.move_gen,
+                    Arc::clone(&self.transposition_table),
+                    terminate,
+                    self.low_cpu_priority,
+                );
+                *self
+                    .maybe_search_thread
+                    .get_mut()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner()) =
+                    analysis_thread.map(|analysis_thread| (analysis_thread, false));
+                Handled
+            }
             _ => Super,
         }
     }
 
+    /// Applies the options set with `setoption` to the params of a search.
+    fn with_options(&self, params: SearchParams) -> SearchParams {
+        SearchParams {
+            multi_pv: Some(self.multi_pv),
+            threads: Some(self.threads),
+            tuning: self.tuning.clone(),
+            analysis_mode: self.analysis_mode,
+            info_callback: Some(uci_info_callback()),
+            info_interval_nodes: Some(self.info_interval_nodes),
+            // Using a whole CPU doesn't need throttling.
+            max_cpu_percent: (self.max_cpu_percent < *MAX_CPU_PERCENT_RANGE.end())
+                .then_some(self.max_cpu_percent),
+            ..params
+        }
+    }
+
     fn perft_benchmark(&mut self) -> Result<()> {
         let mut nps_res = vec![];
         for (fen, depth) in PERFT_BENCHMARK_FENS_AND_DEPTHS {
@@ -243,31 +642,22 @@ fn spawn_search(
     move_gen: impl GenerateMoves + Copy + Send + Sync + 'static,
     transposition_table: Arc<Mutex<TranspositionTable>>,
     terminate: Arc<AtomicBool>,
-) {
+    low_cpu_priority: bool,
+) -> JoinHandle<()> {
     let panic_info = Arc::new(Mutex::new(None));
     let panic_info_clone = Arc::clone(&panic_info);
 
     let search_thread_handle = thread::spawn(move || -> Result<(), SearchError> {
-        panic::set_hook(Box::new(move |info| {
-            let location = if let Some(location) = info.location() {
-                format!(
-                    "{}:{}:{}",
-                    location.file(),
-                    location.line(),
-                    location.column()
-                )
-            } else {
-                "unknown location".to_string()
-            };
-
-            let message = if let Some(s) = info.payload().downcast_ref::<&str>() {
-                s.to_string()
-            } else if let Some(s) = info.payload().downcast_ref::<String>() {
-                s.clone()
-            } else {
-                "unknown panic message".to_string()
-            };
+        // Helper threads are spawned from this one, so they run at its priority too.
+        if low_cpu_priority {
+            priority::lower_thread_priority();
+        }
 
+        panic::set_hook(Box::new(move |info| {
+            // This replaces the hook installed at startup, so write the crash report here too.
+            crash_report::write_crash_report(info);
+            let location = crash_report::panic_location(info);
+            let message = crash_report::panic_message(info);
             let backtrace = Backtrace::force_capture();
 
             *panic_info_clone.lock().unwrap() = Some((message, location, backtrace));
@@ -281,7 +671,7 @@ fn spawn_search(
             inner
         });
 
-        let (best_move, _) = search(
+        let (best_move, search_info) = search(
             &search_position,
             &params,
             move_gen,
@@ -289,6 +679,16 @@ fn spawn_search(
             &mut transposition_table,
             Arc::clone(&terminate),
         )?;
+        debug!(
+            "Search took {:?} total, time to depth: {:?}, effective branching factors: {:.2?}",
+            search_info.time_elapsed,
+            search_info.time_to_depth(),
+            search_info.effective_branching_factors()
+        );
+        uci!(
+            "{}",
+            search_info.summary(&search_position, best_move, move_gen)
+        );
         uci!(
             "{}",
             &UCIResponse::BestMove {
@@ -320,5 +720,169 @@ fn spawn_search(
                 uci!("bestmove 0000");
             }
         }
-    });
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use engine::{EvalType, RelativeEval};
+    use statig::prelude::IntoStateMachineExt;
+    use test_case::test_case;
+
+    #[test_case(None, true ; "kept by default")]
+    #[test_case(Some("false"), true ; "kept when disabled")]
+    #[test_case(Some("true"), false ; "cleared when enabled")]
+    fn test_clear_state_on_position(option_value: Option<&str>, tt_entry_kept_want: bool) {
+        let mut state_machine = UCIState::new(MOVE_GEN).state_machine();
+        state_machine.handle(&UCICommand::UCI);
+        if let Some(value) = option_value {
+            state_machine.handle(&UCICommand::SetOption {
+                name: CLEAR_STATE_ON_POSITION_OPTION.to_string(),
+                value: Some(value.to_string()),
+            });
+        }
+
+        let position = Position::start();
+        let mve = MOVE_GEN.gen_moves(&position)[0];
+        state_machine
+            .inner()
+            .transposition_table
+            .lock()
+            .unwrap()
+            .store(
+                &position,
+                RelativeEval::DRAW,
+                EvalType::Exact,
+                mve,
+                1,
+                false,
+            );
+
+        state_machine.handle(&UCICommand::Position {
+            fen: None,
+            moves: None,
+        });
+        let tt_entry = state_machine
+            .inner()
+            .transposition_table
+            .lock()
+            .unwrap()
+            .probe(&position);
+        assert_eq!(tt_entry.is_some(), tt_entry_kept_want);
+    }
+
+    #[test_case(None, SearchParams::DEFAULT_INFO_INTERVAL_NODES ; "default")]
+    #[test_case(Some("1000"), 1000 ; "set")]
+    #[test_case(Some("0"), 0 ; "turned off")]
+    #[test_case(Some("-1"), SearchParams::DEFAULT_INFO_INTERVAL_NODES ; "out of range ignored")]
+    fn test_info_interval_nodes_option(option_value: Option<&str>, interval_want: u64) {
+        let mut state = UCIState::new(MOVE_GEN);
+        if let Some(value) = option_value {
+            state.set_option(INFO_INTERVAL_NODES_OPTION, Some(value));
+        }
+        let params = state.with_options(SearchParams::default());
+        assert_eq!(params.info_interval_nodes, Some(interval_want));
+    }
+
+    #[test]
+    fn test_position_stops_at_illegal_move() {
+        let mut state_machine = UCIState::new(MOVE_GEN).state_machine();
+        state_machine.handle(&UCICommand::UCI);
+        let moves = ["e2e4", "e7e5", "e4e5", "d2d4"].map(|mve| Move::from_str(mve).unwrap());
+        state_machine.handle(&UCICommand::Position {
+            fen: None,
+            moves: Some(moves.to_vec()),
+        });
+
+        // e4e5 is blocked, so only the moves before it are played.
+        let mut want = Position::start();
+        for &mve in &moves[..2] {
+            want.make_move(mve);
+        }
+        let State::UciEnabled { position } = state_machine.state() else {
+            panic!("Should be ready to search");
+        };
+        assert_eq!(*position, want);
+    }
+
+    #[test]
+    fn test_position_ignores_invalid_fen() {
+        let mut state_machine = UCIState::new(MOVE_GEN).state_machine();
+        state_machine.handle(&UCICommand::UCI);
+        let mve = Move::from_str("e2e4").unwrap();
+        state_machine.handle(&UCICommand::Position {
+            fen: None,
+            moves: Some(vec![mve]),
+        });
+        state_machine.handle(&UCICommand::Position {
+            fen: Some("9/8/8/8/8/8/8/8 w - -".to_string()),
+            moves: None,
+        });
+
+        let mut want = Position::start();
+        want.make_move(mve);
+        let State::UciEnabled { position } = state_machine.state() else {
+            panic!("Should be ready to search");
+        };
+        assert_eq!(*position, want);
+    }
+
+    #[test]
+    fn test_position_moves_keep_history() {
+        let mut state_machine = UCIState::new(MOVE_GEN).state_machine();
+        state_machine.handle(&UCICommand::UCI);
+        let shuffle = [
+            Move::from_str("g1f3").unwrap(),
+            Move::from_str("g8f6").unwrap(),
+            Move::from_str("f3g1").unwrap(),
+            Move::from_str("f6g8").unwrap(),
+        ];
+        state_machine.handle(&UCICommand::Position {
+            fen: None,
+            moves: Some(shuffle.repeat(2)),
+        });
+
+        // The start position was reached 3 times, so the search can score repeating it.
+        let State::UciEnabled { position } = state_machine.state() else {
+            panic!("Should be ready to search");
+        };
+        assert_eq!(position.history.len(), 8);
+        assert!(position.is_threefold_repetition());
+    }
+
+    #[test_case(None, None ; "unlimited by default")]
+    #[test_case(Some("25"), Some(25) ; "set")]
+    #[test_case(Some("100"), None ; "whole cpu")]
+    #[test_case(Some("0"), None ; "out of range ignored")]
+    fn test_max_cpu_percent_option(option_value: Option<&str>, max_cpu_percent_want: Option<u8>) {
+        let mut state = UCIState::new(MOVE_GEN);
+        if let Some(value) = option_value {
+            state.set_option(MAX_CPU_PERCENT_OPTION, Some(value));
+        }
+        let params = state.with_options(SearchParams::default());
+        assert_eq!(params.max_cpu_percent, max_cpu_percent_want);
+    }
+
+    #[test_case(&["low"], true ; "low")]
+    #[test_case(&["Low", "Normal"], false ; "back to normal")]
+    #[test_case(&["Low", "Lowest"], true ; "invalid value ignored")]
+    fn test_cpu_priority_option(values: &[&str], low_cpu_priority_want: bool) {
+        let mut state = UCIState::new(MOVE_GEN);
+        for value in values {
+            state.set_option(CPU_PRIORITY_OPTION, Some(value));
+        }
+        assert_eq!(state.low_cpu_priority, low_cpu_priority_want);
+    }
+
+    #[test_case("move overhead", "250", SearchTuning { move_overhead: Duration::from_millis(250), ..SearchTuning::DEFAULT } ; "move overhead")]
+    #[test_case(SOFT_TIME_PERCENT_OPTION, "80", SearchTuning { soft_time_percent: 80, ..SearchTuning::DEFAULT } ; "soft time percent")]
+    #[test_case(HARD_TIME_PERCENT_OPTION, "300", SearchTuning { hard_time_percent: 300, ..SearchTuning::DEFAULT } ; "hard time percent")]
+    #[test_case(MOVE_OVERHEAD_OPTION, "-1", SearchTuning::DEFAULT ; "out of range ignored")]
+    #[test_case(ROOT_MOVE_PRUNING_OPTION, "false", SearchTuning { root_move_pruning: false, ..SearchTuning::DEFAULT } ; "root move pruning")]
+    fn test_tuning_options(name: &str, value: &str, tuning_want: SearchTuning) {
+        let mut state = UCIState::new(MOVE_GEN);
+        state.set_option(name, Some(value));
+        assert_eq!(state.tuning, tuning_want);
+    }
 }
@@ -0,0 +1,246 @@
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::sync::{Condvar, Mutex, MutexGuard, Once};
+use std::thread;
+
+use tracing_subscriber::fmt::MakeWriter;
+
+/// Number of lines which can wait to be written before the engine has to wait for the GUI.
+const QUEUE_CAPACITY: usize = 256;
+
+/// Lines waiting to be written to stdout by the writer thread.
+#[derive(Debug)]
+struct OutputQueue {
+    state: Mutex<QueueState>,
+    changed: Condvar,
+    capacity: usize,
+}
+
+#[derive(Debug)]
+struct QueueState {
+    lines: VecDeque<Vec<u8>>,
+    /// Set while the writer thread writes lines it took off the queue.
+    is_writing: bool,
+}
+
+impl OutputQueue {
+    const fn new(capacity: usize) -> Self {
+        Self {
+            state: Mutex::new(QueueState {
+                lines: VecDeque::new(),
+                is_writing: false,
+            }),
+            changed: Condvar::new(),
+            capacity,
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, QueueState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn wait<'a>(&self, state: MutexGuard<'a, QueueState>) -> MutexGuard<'a, QueueState> {
+        self.changed
+            .wait(state)
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Queues a line. A periodic info line replaces the one still waiting to be written, and is
+    /// dropped if the queue is full. Any other line, like `bestmove`, is never dropped: periodic
+    /// info lines are dropped to make room for it, or else this waits for the writer thread.
+    fn push(&self, line: Vec<u8>) {
+        let mut state = self.lock();
+        if is_periodic_info(&line) {
+            state.lines.retain(|queued| !is_periodic_info(queued));
+            if state.lines.len() >= self.capacity {
+                return;
+            }
+        } else {
+            while state.lines.len() >= self.capacity {
+                match state
+                    .lines
+                    .iter()
+                    .position(|queued| is_periodic_info(queued))
+                {
+                    Some(idx) => {
+                        state.lines.remove(idx);
+                    }
+                    None => state = self.wait(state),
+                }
+            }
+        }
+        state.lines.push_back(line);
+        self.changed.notify_all();
+    }
+
+    /// Writes queued lines to `out` forever. Write errors are ignored, since there's nobody left
+    /// to tell once the GUI stops reading.
+    fn run_writer(&self, mut out: impl Write) {
+        loop {
+            let lines: Vec<_> = {
+                let mut state = self.lock();
+                while state.lines.is_empty() {
+                    state = self.wait(state);
+                }
+                state.is_writing = true;
+                state.lines.drain(..).collect()
+            };
+            self.changed.notify_all();
+
+            for line in lines {
+                let _ = out.write_all(&line);
+            }
+            let _ = out.flush();
+
+            self.lock().is_writing = false;
+            self.changed.notify_all();
+        }
+    }
+
+    /// Waits until every queued line has been written.
+    fn flush(&self) {
+        let mut state = self.lock();
+        while !state.lines.is_empty() || state.is_writing {
+            state = self.wait(state);
+        }
+    }
+}
+
+/// Progress reports sent in the middle of an iteration don't have a PV, and are superseded by
+/// the next report.
+fn is_periodic_info(line: &[u8]) -> bool {
+    let line = line.trim_ascii_end();
+    line.starts_with(b"info depth ") && line.ends_with(b" pv")
+}
+
+static STDOUT_QUEUE: OutputQueue = OutputQueue::new(QUEUE_CAPACITY);
+static WRITER_THREAD: Once = Once::new();
+
+fn stdout_queue() -> &'static OutputQueue {
+    WRITER_THREAD.call_once(|| {
+        thread::Builder::new()
+            .name("stdout-writer".to_string())
+            .spawn(|| STDOUT_QUEUE.run_writer(io::stdout()))
+            .expect("Couldn't spawn stdout writer thread");
+    });
+    &STDOUT_QUEUE
+}
+
+/// Waits until all UCI output has been written to stdout. Must be called before exiting, or
+/// before printing to stdout directly.
+pub fn flush_stdout() {
+    stdout_queue().flush();
+}
+
+/// Writer for UCI output which hands each line to a dedicated thread, so a GUI that reads stdout
+/// slowly can't block the search.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct QueuedStdout;
+
+impl<'a> MakeWriter<'a> for QueuedStdout {
+    type Writer = QueuedLine;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        QueuedLine(vec![])
+    }
+}
+
+/// Collects one formatted event, which is queued when it's dropped.
+pub struct QueuedLine(Vec<u8>);
+
+impl Write for QueuedLine {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for QueuedLine {
+    fn drop(&mut self) {
+        if !self.0.is_empty() {
+            stdout_queue().push(std::mem::take(&mut self.0));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, mpsc};
+
+    use super::*;
+    use test_case::test_case;
+
+    const PERIODIC_1: &str = "info depth 5 seldepth 9 multipv 1 score cp 20 nodes 250000 nps 1000000 hashfull 0 tbhits 0 tthitrate 0.50 time 250 pv \n";
+    const PERIODIC_2: &str = "info depth 5 seldepth 9 multipv 1 score cp 20 nodes 500000 nps 1000000 hashfull 0 tbhits 0 tthitrate 0.50 time 500 pv \n";
+    const ITERATION: &str = "info depth 5 seldepth 9 multipv 1 score cp 20 nodes 600000 nps 1000000 hashfull 0 tbhits 0 tthitrate 0.50 time 600 pv e2e4 e7e5\n";
+    const BESTMOVE: &str = "bestmove e2e4\n";
+
+    fn queued_lines(queue: &OutputQueue) -> Vec<String> {
+        queue
+            .lock()
+            .lines
+            .iter()
+            .map(|line| String::from_utf8(line.clone()).unwrap())
+            .collect()
+    }
+
+    #[test_case(PERIODIC_1, true ; "periodic")]
+    #[test_case(ITERATION, false ; "with pv")]
+    #[test_case(BESTMOVE, false ; "bestmove")]
+    #[test_case("info string pv\n", false ; "info string")]
+    fn test_is_periodic_info(line: &str, want: bool) {
+        assert_eq!(is_periodic_info(line.as_bytes()), want);
+    }
+
+    #[test_case(4, &[PERIODIC_1, ITERATION, PERIODIC_2], &[ITERATION, PERIODIC_2] ; "periodic info coalesced")]
+    #[test_case(2, &[ITERATION, BESTMOVE, PERIODIC_1], &[ITERATION, BESTMOVE] ; "periodic info dropped when full")]
+    #[test_case(2, &[ITERATION, PERIODIC_1, BESTMOVE], &[ITERATION, BESTMOVE] ; "periodic info dropped for bestmove")]
+    fn test_output_queue_push(capacity: usize, lines: &[&str], queued_want: &[&str]) {
+        let queue = OutputQueue::new(capacity);
+        for line in lines {
+            queue.push(line.as_bytes().to_vec());
+        }
+
+        assert_eq!(queued_lines(&queue), queued_want);
+    }
+
+    /// Sends everything written to it over a channel.
+    struct ChannelWriter(mpsc::Sender<Vec<u8>>);
+
+    impl Write for ChannelWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.send(buf.to_vec()).unwrap();
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_output_queue_writes_lines_in_order() {
+        let queue = Arc::new(OutputQueue::new(1));
+        let (sender, receiver) = mpsc::channel();
+        let writer_queue = Arc::clone(&queue);
+        thread::spawn(move || writer_queue.run_writer(ChannelWriter(sender)));
+
+        // The queue only holds one line, so this waits for the writer thread instead of dropping
+        // the bestmove.
+        queue.push(ITERATION.as_bytes().to_vec());
+        queue.push(BESTMOVE.as_bytes().to_vec());
+        queue.flush();
+
+        let written: Vec<_> = receiver.try_iter().collect();
+        assert_eq!(
+            written,
+            vec![ITERATION.as_bytes().to_vec(), BESTMOVE.as_bytes().to_vec()]
+        );
+    }
+}
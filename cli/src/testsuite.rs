@@ -0,0 +1,226 @@
+use std::collections::HashMap;
+use std::sync::{Arc, atomic::AtomicBool};
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+use engine::{
+    EpdRecord, MOVE_GEN, Move, POSITION_EVALUATOR, RelativeEval, SearchParams, TranspositionTable,
+    parse_epd_record, san_to_move, search,
+};
+
+/// Reads a test suite with one EPD record per line, like WAC or STS. Empty lines and lines
+/// starting with `#` are skipped. Every record needs a `bm`, `am` or `dm` operation to be
+/// solvable.
+pub fn parse_testsuite(suite: &str) -> Result<Vec<EpdRecord>> {
+    suite
+        .lines()
+        .enumerate()
+        .map(|(line_idx, line)| (line_idx, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(line_idx, line)| {
+            let record = parse_epd_record(line, MOVE_GEN)
+                .with_context(|| format!("Invalid EPD on test suite line {}", line_idx + 1))?;
+            if record.best_moves.is_empty()
+                && record.avoid_moves.is_empty()
+                && record.direct_mate.is_none()
+            {
+                bail!(
+                    "Test suite line {} has no bm, am or dm operation",
+                    line_idx + 1
+                );
+            }
+            Ok(record)
+        })
+        .collect()
+}
+
+/// Outcome of searching one position of a test suite.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestsuiteResult {
+    /// None if there was no legal move, or the search was too short to finish depth 1.
+    pub best_move: Option<Move>,
+    pub solved: bool,
+    /// Points scored, and the most that could be scored. STS style records score the points
+    /// their `c0` operation gives the move played, and other records 1 point if solved.
+    pub points: u32,
+    pub max_points: u32,
+}
+
+/// Totals over a whole test suite.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TestsuiteSummary {
+    pub num_positions: usize,
+    pub num_solved: usize,
+    pub points: u32,
+    pub max_points: u32,
+}
+
+impl TestsuiteSummary {
+    pub fn add(&mut self, result: &TestsuiteResult) {
+        self.num_positions += 1;
+        self.num_solved += usize::from(result.solved);
+        self.points += result.points;
+        self.max_points += result.max_points;
+    }
+}
+
+/// Searches a test suite position for `move_time` with a fresh transposition table, and
+/// checks the move found against the record's operations.
+pub fn run_testsuite_position(record: &EpdRecord, move_time: Duration) -> Result<TestsuiteResult> {
+    let (best_move, info) = search(
+        &record.position,
+        &SearchParams {
+            move_time: Some(move_time),
+            ..SearchParams::default()
+        },
+        MOVE_GEN,
+        POSITION_EVALUATOR,
+        &mut TranspositionTable::new(),
+        Arc::new(AtomicBool::new(false)),
+    )?;
+    let eval = best_move.and_then(|best_move| info.move_evals.get(&best_move).copied());
+    let solved = best_move.is_some_and(|best_move| is_solved(record, best_move, eval));
+
+    let (points, max_points) = match sts_points(record)? {
+        Some(move_points) => (
+            best_move
+                .and_then(|best_move| move_points.get(&best_move).copied())
+                .unwrap_or(0),
+            move_points.values().copied().max().unwrap_or(0),
+        ),
+        None => (u32::from(solved), 1),
+    };
+    Ok(TestsuiteResult {
+        best_move,
+        solved,
+        points,
+        max_points,
+    })
+}
+
+/// Whether a move solves a record: it has to be one of the `bm` moves and none of the `am`
+/// moves, and find a mate at least as short as `dm`'s.
+fn is_solved(record: &EpdRecord, best_move: Move, eval: Option<RelativeEval>) -> bool {
+    let is_best = record.best_moves.is_empty() || record.best_moves.contains(&best_move);
+    let is_avoided = record.avoid_moves.contains(&best_move);
+    let is_mate_found = record.direct_mate.is_none_or(|direct_mate| {
+        eval.and_then(|eval| eval.is_mate())
+            .is_some_and(|mate| mate > 0 && mate.unsigned_abs() <= direct_mate)
+    });
+    is_best && !is_avoided && is_mate_found
+}
+
+/// Points for each move in an STS style `c0` operation, e.g. `"f5=10, Be5+=2, Bf2=3"`, or none
+/// if the record doesn't have one.
+/// Source: https://www.chessprogramming.org/Strategic_Test_Suite
+fn sts_points(record: &EpdRecord) -> Result<Option<HashMap<Move, u32>>> {
+    let Some(comment) = record.operation("c0").and_then(|operands| operands.first()) else {
+        return Ok(None);
+    };
+    comment
+        .split(',')
+        .map(|entry| {
+            let (san, points) = entry
+                .trim()
+                .split_once('=')
+                .with_context(|| format!("Invalid STS points `{}`", entry.trim()))?;
+            let mve = san_to_move(&record.position, san, MOVE_GEN)?;
+            let points = points
+                .parse()
+                .with_context(|| format!("Invalid STS points `{}`", entry.trim()))?;
+            Ok((mve, points))
+        })
+        .collect::<Result<_>>()
+        .map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use engine::Square::*;
+    use test_case::test_case;
+    use testresult::TestResult;
+
+    const MATE_IN_ONE: &str = "6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - bm Rd8#; id \"back rank\";";
+
+    #[test]
+    fn test_parse_testsuite() -> TestResult {
+        let suite = format!("# comment\n\n{}\n", MATE_IN_ONE);
+        let records = parse_testsuite(&suite)?;
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].id.as_deref(), Some("back rank"));
+        Ok(())
+    }
+
+    #[test_case("6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - id \"nothing to solve\";" ; "no solution")]
+    #[test_case("6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - bm Rd9;" ; "invalid best move")]
+    fn test_parse_testsuite_invalid(suite: &str) {
+        assert!(parse_testsuite(suite).is_err());
+    }
+
+    #[test_case(MATE_IN_ONE, Move::new(D1, D8), None, true ; "best move")]
+    #[test_case(MATE_IN_ONE, Move::new(D1, D7), None, false ; "not best move")]
+    #[test_case("6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - am Rd7;", Move::new(D1, D8), None, true ; "not avoided")]
+    #[test_case("6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - am Rd7;", Move::new(D1, D7), None, false ; "avoided")]
+    #[test_case("6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - dm 1;", Move::new(D1, D8), Some(RelativeEval::mate_in(1)), true ; "mate found")]
+    #[test_case("6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - dm 1;", Move::new(D1, D8), Some(RelativeEval::mate_in(2)), false ; "longer mate")]
+    #[test_case("6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - dm 1;", Move::new(D1, D8), Some(RelativeEval::cp(500)), false ; "no mate")]
+    fn test_is_solved(
+        epd: &str,
+        best_move: Move,
+        eval: Option<RelativeEval>,
+        want: bool,
+    ) -> TestResult {
+        let record = parse_epd_record(epd, MOVE_GEN)?;
+        assert_eq!(is_solved(&record, best_move, eval), want);
+        Ok(())
+    }
+
+    #[test]
+    fn test_sts_points() -> TestResult {
+        let record = parse_epd_record(
+            "6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - bm Rd8#; c0 \"Rd8#=10, Rd7=3, h3=1\";",
+            MOVE_GEN,
+        )?;
+        let points = sts_points(&record)?.ok_or("no points")?;
+        assert_eq!(points.len(), 3);
+        assert_eq!(points[&Move::new(D1, D8)], 10);
+        assert_eq!(points[&Move::new(H2, H3)], 1);
+        assert_eq!(sts_points(&parse_epd_record(MATE_IN_ONE, MOVE_GEN)?)?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_run_testsuite_position() -> TestResult {
+        let record = parse_epd_record(MATE_IN_ONE, MOVE_GEN)?;
+        let result = run_testsuite_position(&record, Duration::from_millis(100))?;
+        assert_eq!(
+            result,
+            TestsuiteResult {
+                best_move: Some(Move::new(D1, D8)),
+                solved: true,
+                points: 1,
+                max_points: 1,
+            }
+        );
+
+        let mut summary = TestsuiteSummary::default();
+        summary.add(&result);
+        summary.add(&TestsuiteResult {
+            best_move: None,
+            solved: false,
+            points: 0,
+            max_points: 10,
+        });
+        assert_eq!(
+            summary,
+            TestsuiteSummary {
+                num_positions: 2,
+                num_solved: 1,
+                points: 1,
+                max_points: 11,
+            }
+        );
+        Ok(())
+    }
+}
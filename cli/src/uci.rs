@@ -1,11 +1,14 @@
 use std::str::FromStr;
 
-use engine::GenerateMoves;
+use engine::{GenerateMoves, InfoCallback};
 use statig::prelude::{IntoStateMachineExt, StateMachine};
+use tracing::warn;
 
 use crate::{
+    crash_report,
     messages::{UCICommand, UCICommandParseError},
     state::UCIState,
+    stdout_writer::flush_stdout,
 };
 
 #[macro_export]
@@ -15,6 +18,13 @@ macro_rules! uci {
     };
 }
 
+/// Writes the search's progress as UCI `info` lines.
+pub fn uci_info_callback() -> InfoCallback {
+    InfoCallback::new(|info| {
+        uci!("{}", info);
+    })
+}
+
 #[allow(clippy::upper_case_acronyms)]
 pub struct UCI<G>
 where
@@ -35,8 +45,23 @@ where
     }
 
     pub fn handle_command(&mut self, command: &str) -> Result<(), UCICommandParseError> {
+        crash_report::record_command(command);
         let command = UCICommand::from_str(command)?;
         self.state_machine.handle(&command);
         Ok(())
     }
+
+    /// Waits for the running search to write its best move, then for all output to be written.
+    /// A search that only stops when told to, like `go infinite`, is stopped first.
+    pub fn finish_search(&mut self) {
+        if let Some((search_thread, is_unbounded)) = self.state_machine.take_search_thread() {
+            if is_unbounded && !search_thread.is_finished() {
+                self.state_machine.handle(&UCICommand::Stop);
+            }
+            if search_thread.join().is_err() {
+                warn!(target: "uci", "Search thread panicked");
+            }
+        }
+        flush_stdout();
+    }
 }
@@ -2,13 +2,14 @@ use std::{
     io::Write,
     sync::{Arc, Mutex},
     thread,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use cli::UCI;
-use engine::{AUTHOR, MOVE_GEN, NAME};
-use tracing_subscriber::{layer::SubscriberExt, prelude::*};
+use engine::{AUTHOR, GenerateMoves, MOVE_GEN, Move, NAME, Position, Square};
+use tracing_subscriber::{filter::filter_fn, prelude::*};
 
+/// Saves the lines the engine writes to the `uci` tracing target, which is what it sends the GUI.
 #[derive(Clone, Debug)]
 struct UCIResponseSaver {
     responses: Arc<Mutex<Vec<String>>>,
@@ -21,30 +22,42 @@ impl UCIResponseSaver {
         }
     }
 
-    fn with_responses(responses: Arc<Mutex<Vec<String>>>) -> Self {
-        Self { responses }
-    }
-
     fn get_new_responses(&self) -> Vec<String> {
         let mut responses = self.responses.lock().unwrap();
         let result = responses.clone();
         responses.clear();
         result
     }
-}
 
-fn get_new_responses(logs: Arc<Mutex<Vec<String>>>) -> Vec<String> {
-    let mut responses = logs.lock().unwrap();
-    let result = responses.clone();
-    responses.clear();
-    result
+    /// Waits until the engine sends a line starting with `prefix`, returning everything sent
+    /// since the last call.
+    fn wait_for_response(&self, prefix: &str) -> Vec<String> {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let mut responses = vec![];
+        while !responses
+            .iter()
+            .any(|resp: &String| resp.starts_with(prefix))
+        {
+            assert!(
+                Instant::now() < deadline,
+                "No {} response, got: {:?}",
+                prefix,
+                responses
+            );
+            thread::sleep(Duration::from_millis(1));
+            responses.extend(self.get_new_responses());
+        }
+        responses
+    }
 }
 
 impl Write for UCIResponseSaver {
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
-        println!("HIT!!!!!!!");
         let uci_res = String::from_utf8(buf.to_vec()).unwrap();
-        self.responses.lock().unwrap().push(uci_res);
+        self.responses
+            .lock()
+            .unwrap()
+            .extend(uci_res.lines().map(str::to_string));
         Ok(buf.len())
     }
 
@@ -53,56 +66,65 @@ impl Write for UCIResponseSaver {
     }
 }
 
-// #[test]
-// fn test_happy_path() {
-//     // let response_saver = Arc::new(Mutex::new(UCIResponseSaver::new()));
-//     let responses = Arc::new(Mutex::new(vec![]));
-//     let make_writer = move || {
-//         UCIResponseSaver::with_responses(Arc::clone(&responses));
-//     };
-//     let subscriber = tracing_subscriber::fmt::layer()
-//         .with_writer(make_writer)
-//         .without_time()
-//         .with_level(false)
-//         .with_target(false)
-//         .with_filter(tracing_subscriber::filter::filter_fn(|meta| {
-//             meta.target() == "uci"
-//         }));
-//     tracing_subscriber::Registry::default()
-//         .with(subscriber)
-//         .init();
-//
-//     let move_gen = HYPERBOLA_QUINTESSENCE_MOVE_GEN;
-//     let mut uci = UCI::new(move_gen);
-//
-//     uci.handle_command("uci").unwrap();
-//
-//     let responses = response_saver.get_new_responses();
-//
-//     let id_name = format!("id name {}", NAME);
-//     let id_author = format!("id author {}", AUTHOR);
-//
-//     assert_eq!(&responses[0..2], &[id_name, id_author]);
-//     assert_eq!(responses.last().unwrap(), "uciok");
-//
-//     uci.handle_command("debug on").unwrap();
-//
-//     for resp in responses[2..responses.len() - 1].iter() {
-//         assert!(resp.starts_with("option"));
-//     }
-//
-//     uci.handle_command("isready").unwrap();
-//
-//     let responses = response_saver.get_new_responses();
-//     assert_eq!(responses, vec!["readyok"]);
-//
-//     uci.handle_command("ucinewgame").unwrap();
-//     uci.handle_command("go infinite").unwrap();
-//
-//     thread::sleep(Duration::new(0, 1_000_000)); // 1 ms
-//     uci.handle_command("stop").unwrap();
-//     thread::sleep(Duration::new(0, 1_000_000));
-//
-//     let responses = response_saver.get_new_responses();
-//     assert_eq!(responses, vec!["bestmove b2b4"]);
-// }
+#[test]
+fn test_happy_path() {
+    let response_saver = UCIResponseSaver::new();
+    let make_writer = {
+        let response_saver = response_saver.clone();
+        move || response_saver.clone()
+    };
+    // Set globally, since the search thread also sends responses.
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_writer(make_writer)
+                .without_time()
+                .with_level(false)
+                .with_target(false)
+                .with_ansi(false)
+                .with_filter(filter_fn(|meta| meta.target() == "uci")),
+        )
+        .init();
+
+    let mut uci = UCI::new(MOVE_GEN);
+
+    uci.handle_command("uci").unwrap();
+    let responses = response_saver.get_new_responses();
+    let id_name = format!("id name {}", NAME);
+    let id_author = format!("id author {}", AUTHOR);
+    assert_eq!(&responses[0..2], &[id_name, id_author]);
+    assert_eq!(responses.last().unwrap(), "uciok");
+
+    uci.handle_command("isready").unwrap();
+    assert_eq!(response_saver.get_new_responses(), vec!["readyok"]);
+
+    uci.handle_command("ucinewgame").unwrap();
+    uci.handle_command("position startpos moves e2e4").unwrap();
+    uci.handle_command("go infinite").unwrap();
+    thread::sleep(Duration::from_millis(10));
+    uci.handle_command("stop").unwrap();
+
+    let responses = response_saver.wait_for_response("bestmove");
+    let bestmove = responses.last().unwrap();
+    assert!(
+        responses[..responses.len() - 1]
+            .iter()
+            .all(|resp| resp.starts_with("info")),
+        "{:?}",
+        responses
+    );
+    let mve = bestmove
+        .strip_prefix("bestmove ")
+        .and_then(|rest| rest.split(' ').next())
+        .unwrap();
+    let mut position = Position::start();
+    position.make_move(Move::new(Square::E2, Square::E4));
+    assert!(
+        MOVE_GEN
+            .gen_moves(&position)
+            .iter()
+            .any(|legal| legal.to_string().to_lowercase() == mve),
+        "Illegal best move: {}",
+        bestmove
+    );
+}
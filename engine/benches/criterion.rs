@@ -1,9 +1,11 @@
+use std::hint::black_box;
 use std::sync::{Arc, atomic::AtomicBool};
 use std::time::{Duration, Instant};
 
 use criterion::{Criterion, criterion_group, criterion_main};
 use engine::{
-    MOVE_GEN, POSITION_EVALUATOR, Position, SearchParams, TranspositionTable, perft, search,
+    BitBoard, GenerateSlidingMoves, HYPERBOLA_QUINTESSENCE, MAGIC_BITBOARD, MOVE_GEN,
+    POSITION_EVALUATOR, Piece, Position, SearchParams, Square, TranspositionTable, perft, search,
 };
 
 const PERFT_BENCHMARK_FENS_AND_DEPTHS: &[(&str, usize, &str)] = &[
@@ -125,5 +127,38 @@ pub fn benchmark_search(c: &mut Criterion) {
     }
 }
 
-criterion_group!(benches, benchmark_perft, benchmark_search);
+/// Attacks of every sliding piece on every square, with the blockers from each perft position.
+fn gen_all_sliding_attacks(move_gen: impl GenerateSlidingMoves, occupancies: &[BitBoard]) {
+    for &occupancy in occupancies {
+        for square in Square::ALL {
+            for piece in [Piece::Bishop, Piece::Rook, Piece::Queen] {
+                black_box(move_gen.gen_moves(piece, square, black_box(occupancy)));
+            }
+        }
+    }
+}
+
+/// Compares the sliding piece generators directly, since perft spends most of its time
+/// elsewhere. `MOVE_GEN` uses the one in `SLIDING_PIECES_BACKEND`.
+pub fn benchmark_sliding_attacks(c: &mut Criterion) {
+    let occupancies: Vec<BitBoard> = PERFT_BENCHMARK_FENS_AND_DEPTHS
+        .iter()
+        .map(|(fen, _, _)| Position::from_fen(fen).unwrap().occupancy_bb())
+        .collect();
+
+    let mut group = c.benchmark_group("sliding attacks");
+    group.bench_function("magic bitboards", |b| {
+        b.iter(|| gen_all_sliding_attacks(MAGIC_BITBOARD, &occupancies))
+    });
+    group.bench_function("hyperbola quintessence", |b| {
+        b.iter(|| gen_all_sliding_attacks(HYPERBOLA_QUINTESSENCE, &occupancies))
+    });
+}
+
+criterion_group!(
+    benches,
+    benchmark_perft,
+    benchmark_search,
+    benchmark_sliding_attacks
+);
 criterion_main!(benches);
@@ -1,5 +1,6 @@
-use thiserror;
+use std::str::FromStr;
 
+use crate::bitboard::Square;
 use crate::move_gen::GenerateMoves;
 use crate::position::{Move, Piece, Position};
 
@@ -10,18 +11,168 @@ pub enum AlgebraicNotationError {
 
     #[error("invalid move {0}")]
     InvalidMove(String),
+
+    #[error("invalid SAN move {0}")]
+    InvalidSan(String),
+
+    #[error("no legal move matches {0}")]
+    NoMatchingMove(String),
+
+    #[error("ambiguous move {0}")]
+    AmbiguousMove(String),
 }
 
 pub fn move_to_algebraic_notation(
     position: &Position,
     mve: Move,
     move_gen: impl GenerateMoves,
+) -> Result<String, AlgebraicNotationError> {
+    let mut sans = line_to_san(position, &[mve], move_gen)?;
+    Ok(sans.pop().expect("One move should give one SAN move"))
+}
+
+/// Converts a line of moves played from `position`, like a PV or a game, to SAN.
+///
+/// Moves are made along the line in one copy of the position, and the legal moves of each
+/// position are generated once: they're used both to tell whether the move into it was mate
+/// and to disambiguate the move out of it.
+pub fn line_to_san(
+    position: &Position,
+    line: &[Move],
+    move_gen: impl GenerateMoves,
+) -> Result<Vec<String>, AlgebraicNotationError> {
+    let mut position = position.clone();
+    let mut legal_moves = move_gen.gen_moves(&position);
+    let mut sans = Vec::with_capacity(line.len());
+    for &mve in line {
+        let mut san = san_without_check(&position, mve, &legal_moves)?;
+        position.make_move(mve);
+        legal_moves = move_gen.gen_moves(&position);
+
+        if !move_gen.gen_checkers(&position).is_empty() {
+            san.push(if legal_moves.is_empty() { '#' } else { '+' });
+        }
+        sans.push(san);
+    }
+    Ok(sans)
+}
+
+/// Finds the legal move a SAN move refers to, e.g. `Nbd7`, `exd6 e.p.`, `e8=Q+` or `O-O-O`.
+///
+/// Check, mate and annotation suffixes are ignored, and so is whether the move is written as a
+/// capture. The source square only needs to be given as far as it tells the legal moves apart.
+pub fn san_to_move(
+    position: &Position,
+    san: &str,
+    move_gen: impl GenerateMoves,
+) -> Result<Move, AlgebraicNotationError> {
+    let invalid_san = || AlgebraicNotationError::InvalidSan(san.to_string());
+    let trimmed = san.trim().trim_end_matches(['+', '#', '!', '?']);
+    let trimmed = trimmed
+        .strip_suffix("e.p.")
+        .map_or(trimmed, |trimmed| trimmed.trim_end_matches(['+', '#', ' ']));
+    if !trimmed.is_ascii() {
+        return Err(invalid_san());
+    }
+    let side = position.state.to_move;
+    let moves = move_gen.gen_moves(position);
+    let piece_at = |square: Square| position.is_piece_at(square, side);
+
+    let castle_dest_file = match trimmed {
+        "O-O" | "0-0" => Some(6),
+        "O-O-O" | "0-0-0" => Some(2),
+        _ => None,
+    };
+    if let Some(dest_file) = castle_dest_file {
+        return moves
+            .into_iter()
+            .find(|mve| {
+                piece_at(mve.src) == Some(Piece::King)
+                    && mve.src.abs_diff(mve.dest) == 2
+                    && mve.dest as u8 % 8 == dest_file
+            })
+            .ok_or_else(|| AlgebraicNotationError::NoMatchingMove(san.to_string()));
+    }
+
+    let (trimmed, promotion) = match trimmed
+        .char_indices()
+        .rev()
+        .find(|(_, ch)| ch.is_ascii_digit())
+    {
+        Some((idx, _)) => {
+            let promotion = trimmed[idx + 1..].trim_start_matches('=');
+            let mut promotion_chars = promotion.chars();
+            let promotion = match (promotion_chars.next(), promotion_chars.next()) {
+                (Some(ch), None) => Some(
+                    Piece::try_from(ch.to_ascii_lowercase())
+                        .ok()
+                        .filter(|piece| !matches!(piece, Piece::Pawn | Piece::King))
+                        .ok_or_else(invalid_san)?,
+                ),
+                (None, _) => None,
+                _ => return Err(invalid_san()),
+            };
+            (&trimmed[..=idx], promotion)
+        }
+        None => return Err(invalid_san()),
+    };
+
+    let (piece, trimmed) = match trimmed.chars().next() {
+        Some(ch) if ch.is_ascii_uppercase() => (
+            Piece::try_from(ch.to_ascii_lowercase()).map_err(|_| invalid_san())?,
+            &trimmed[1..],
+        ),
+        _ => (Piece::Pawn, trimmed),
+    };
+    if trimmed.len() < 2 {
+        return Err(invalid_san());
+    }
+    let (disambiguation, dest) = trimmed.split_at(trimmed.len() - 2);
+    let dest = Square::from_str(&dest.to_uppercase()).map_err(|_| invalid_san())?;
+    let disambiguation = disambiguation.trim_end_matches(['x', ':']);
+    if !disambiguation
+        .chars()
+        .all(|ch| ('a'..='h').contains(&ch) || ('1'..='8').contains(&ch))
+    {
+        return Err(invalid_san());
+    }
+    let src_file = disambiguation.chars().find(|ch| ('a'..='h').contains(ch));
+    let src_rank = disambiguation.chars().find(|ch| ('1'..='8').contains(ch));
+
+    let mut candidates = moves.into_iter().filter(|mve| {
+        let src_str = mve.src.to_string().to_ascii_lowercase();
+        mve.dest == dest
+            && mve.promotion == promotion
+            && piece_at(mve.src) == Some(piece)
+            && src_file.is_none_or(|file| src_str.starts_with(file))
+            && src_rank.is_none_or(|rank| src_str.ends_with(rank))
+    });
+    let mve = candidates
+        .next()
+        .ok_or_else(|| AlgebraicNotationError::NoMatchingMove(san.to_string()))?;
+    match candidates.next() {
+        Some(_) => Err(AlgebraicNotationError::AmbiguousMove(san.to_string())),
+        None => Ok(mve),
+    }
+}
+
+/// SAN for a move, without the check or mate suffix. `legal_moves` are the legal moves in
+/// `position`.
+fn san_without_check(
+    position: &Position,
+    mve: Move,
+    legal_moves: &[Move],
 ) -> Result<String, AlgebraicNotationError> {
     let side = position.state.to_move;
     let opp_side = side.opposite_side();
     let src_piece = position
         .is_piece_at(mve.src, position.state.to_move)
         .ok_or(AlgebraicNotationError::NoPieceAtSrc(mve.src.to_string()))?;
+    if !legal_moves.contains(&mve) {
+        return Err(AlgebraicNotationError::InvalidMove(
+            mve.to_string().to_lowercase(),
+        ));
+    }
 
     let move_abs_diff = mve.src.abs_diff(mve.dest);
 
@@ -47,20 +198,21 @@ pub fn move_to_algebraic_notation(
     }
 
     // En passant
-    if src_piece == Piece::Pawn && (move_abs_diff != 8 && move_abs_diff != 16) {
-        if position.is_piece_at(mve.dest, opp_side).is_none() {
-            let src_str = mve.src.to_string();
-            let file_char = src_str.chars().next().unwrap().to_ascii_lowercase();
-            res.push(file_char);
-            res.push('x');
-        }
+    if src_piece == Piece::Pawn
+        && (move_abs_diff != 8 && move_abs_diff != 16)
+        && position.is_piece_at(mve.dest, opp_side).is_none()
+    {
+        let src_str = mve.src.to_string();
+        let file_char = src_str.chars().next().unwrap().to_ascii_lowercase();
+        res.push(file_char);
+        res.push('x');
     }
 
     // Remove ambiguous moves
     if src_piece == Piece::Rook || src_piece == Piece::Queen {
-        let pos_moves = move_gen.gen_moves(&position);
-        let filtered_pos_moves: Vec<Move> = pos_moves
-            .into_iter()
+        let filtered_pos_moves: Vec<Move> = legal_moves
+            .iter()
+            .copied()
             .filter(|&other_mve| other_mve.dest == mve.dest) // Only care about moves with the same dest
             .filter(|&other_mve| other_mve.src != mve.src) // Filter moves from the piece we are
             // looking at
@@ -70,7 +222,6 @@ pub fn move_to_algebraic_notation(
                 move_piece == src_piece
             })
             .collect();
-        println!("src={:?}, others={:?}", mve, filtered_pos_moves);
 
         let ambiguous_rank = filtered_pos_moves
             .iter()
@@ -96,32 +247,19 @@ pub fn move_to_algebraic_notation(
     // Pawn promotion
     if let Some(promotion) = mve.promotion {
         let prom_char: char = promotion.into();
+        res.push('=');
         res.push(prom_char.to_ascii_uppercase());
     }
 
-    let mut move_pos = position.clone();
-    move_pos.make_move(mve);
-
-    if !move_gen.gen_checkers(&move_pos).is_empty() {
-        res.push('+');
-    } else {
-        let possible_moves_after = move_gen.gen_moves(&move_pos);
-        if possible_moves_after.is_empty() {
-            res.push('#');
-        }
-    }
-
     Ok(res)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::collections::HashSet;
     use test_case::test_case;
     use testresult::TestResult;
 
-    use crate::bitboard::BitBoard;
     use crate::bitboard::Square::*;
     use crate::move_gen::MOVE_GEN;
 
@@ -135,7 +273,7 @@ mod tests {
     #[test_case(Position::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R b KQkq - 0 1").unwrap(), Move::new(E8, C8), "O-O-O".to_string() ; "castle queen side black")]
     #[test_case(Position::from_fen("8/8/8/8/k2Pp3/8/8/7K b - d3 0 1").unwrap(), Move::new(E4, D3), "exd3".to_string() ; "en passant")]
     #[test_case(Position::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap(), Move::new(D2, D4), "d4".to_string() ; "pawn double push")]
-    #[test_case(Position::from_fen("8/8/3P4/8/k7/8/4p2K/8 b - - 0 3").unwrap(), Move::with_promotion(E2, E1, Piece::Queen), "e1Q".to_string() ; "promotion")]
+    #[test_case(Position::from_fen("8/8/3P4/8/k7/8/4p2K/8 b - - 0 3").unwrap(), Move::with_promotion(E2, E1, Piece::Queen), "e1=Q".to_string() ; "promotion")]
     #[test_case(Position::from_fen("3R3R/8/8/8/8/8/8/K1k5 w - - 0 1").unwrap(), Move::new(D8, F8), "Rdf8".to_string() ; "ambiguous rank")]
     #[test_case(Position::from_fen("7R/8/8/8/7R/8/8/K1k5 w - - 0 1").unwrap(), Move::new(H4, H6), "R4h6".to_string() ; "ambiguous file")]
     #[test_case(Position::from_fen("5Q1Q/8/7Q/8/8/8/8/K2k4 w - - 0 1").unwrap(), Move::new(F8, F6), "Qf8f6".to_string() ; "ambiguous rank file 1")]
@@ -148,4 +286,101 @@ mod tests {
         assert_eq!(got, want);
         Ok(())
     }
+
+    #[test]
+    fn test_line_to_san() -> TestResult {
+        let line = [
+            Move::new(E2, E4),
+            Move::new(E7, E5),
+            Move::new(D1, H5),
+            Move::new(B8, C6),
+            Move::new(F1, C4),
+            Move::new(G8, F6),
+            Move::new(H5, F7),
+        ];
+
+        let got = line_to_san(&Position::start(), &line, MOVE_GEN)?;
+
+        assert_eq!(got, ["e4", "e5", "Qh5", "Nc6", "Bc4", "Nf6", "Qxf7#"]);
+        Ok(())
+    }
+
+    #[test_case("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/2N2N2/PPPP1PPP/R1BQKB1R b KQkq - 0 1", "Nge7", Move::new(G8, E7) ; "disambiguated by file")]
+    #[test_case("3R3R/8/8/8/8/8/8/K1k5 w - - 0 1", "Rdf8", Move::new(D8, F8) ; "disambiguated by rank")]
+    #[test_case("5Q1Q/8/7Q/8/8/8/8/K2k4 w - - 0 1", "Qh8f6", Move::new(H8, F6) ; "disambiguated by square")]
+    #[test_case("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", "O-O-O", Move::new(E1, C1) ; "castle queen side")]
+    #[test_case("r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1", "O-O", Move::new(E8, G8) ; "castle king side black")]
+    #[test_case("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", "0-0+", Move::new(E1, G1) ; "castle with zeros")]
+    #[test_case("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "exd6", Move::new(E5, D6) ; "en passant")]
+    #[test_case("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "exd6 e.p.", Move::new(E5, D6) ; "en passant with suffix")]
+    #[test_case("3k4/4P3/8/8/8/8/8/4K3 w - - 0 1", "e8=Q+", Move::with_promotion(E7, E8, Piece::Queen) ; "promotion with check")]
+    #[test_case("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1", "b8N", Move::with_promotion(B7, B8, Piece::Knight) ; "promotion without equals")]
+    #[test_case("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", "Ng6!?", Move::new(E5, G6) ; "capture without x and annotation")]
+    fn test_san_to_move(fen: &str, san: &str, want: Move) -> TestResult {
+        let position = Position::from_fen(fen)?;
+        assert_eq!(san_to_move(&position, san, MOVE_GEN)?, want);
+        Ok(())
+    }
+
+    #[test_case("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/2N2N2/PPPP1PPP/R1BQKB1R b KQkq - 0 1", "Ne7" ; "ambiguous")]
+    fn test_san_to_move_ambiguous(fen: &str, san: &str) -> TestResult {
+        let position = Position::from_fen(fen)?;
+        assert!(matches!(
+            san_to_move(&position, san, MOVE_GEN),
+            Err(AlgebraicNotationError::AmbiguousMove(_))
+        ));
+        Ok(())
+    }
+
+    #[test_case("Nd5" ; "illegal")]
+    #[test_case("O-O" ; "castling not allowed")]
+    #[test_case("e5=Q" ; "promotion off the last rank")]
+    fn test_san_to_move_no_matching_move(san: &str) {
+        assert!(matches!(
+            san_to_move(&Position::start(), san, MOVE_GEN),
+            Err(AlgebraicNotationError::NoMatchingMove(_))
+        ));
+    }
+
+    #[test_case("" ; "empty")]
+    #[test_case("Zf3" ; "unknown piece")]
+    #[test_case("Ni3" ; "off the board")]
+    #[test_case("e4=K" ; "promotion to king")]
+    #[test_case("e8=QR" ; "two promotion pieces")]
+    fn test_san_to_move_invalid(san: &str) {
+        assert!(matches!(
+            san_to_move(&Position::start(), san, MOVE_GEN),
+            Err(AlgebraicNotationError::InvalidSan(_))
+        ));
+    }
+
+    #[test]
+    fn test_san_to_move_round_trip() -> TestResult {
+        let position = Position::from_fen(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        )?;
+        for mve in MOVE_GEN.gen_moves(&position) {
+            let san = move_to_algebraic_notation(&position, mve, MOVE_GEN)?;
+            assert_eq!(san_to_move(&position, &san, MOVE_GEN)?, mve, "{}", san);
+        }
+        Ok(())
+    }
+
+    #[test_case(Position::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap(), Move::new(A1, A8), "Ra8+".to_string() ; "check")]
+    #[test_case(Position::from_fen("4k3/R7/8/8/8/8/8/1R2K3 w - - 0 1").unwrap(), Move::new(B1, B8), "Rb8#".to_string() ; "mate")]
+    fn test_move_to_algebraic_notation_check(pos: Position, mve: Move, want: String) -> TestResult {
+        assert_eq!(move_to_algebraic_notation(&pos, mve, MOVE_GEN)?, want);
+        Ok(())
+    }
+
+    #[test]
+    fn test_line_to_san_illegal_move() {
+        let line = [Move::new(E2, E4), Move::new(E2, E4)];
+
+        let got = line_to_san(&Position::start(), &line, MOVE_GEN);
+
+        assert!(matches!(got, Err(AlgebraicNotationError::NoPieceAtSrc(_))));
+        let got = line_to_san(&Position::start(), &[Move::new(E1, E2)], MOVE_GEN);
+        assert!(matches!(got, Err(AlgebraicNotationError::InvalidMove(_))));
+    }
 }
@@ -0,0 +1,141 @@
+use crate::pgn::PgnGame;
+use crate::position::{Move, Position, ZobristHash};
+
+/// First place a replayed game's position stopped being consistent. `fen` is the position
+/// before `mve` was played, so the divergence can be reproduced by playing `mve` from it.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum AuditError {
+    #[error(
+        "incremental zobrist hash {incremental} doesn't match {from_scratch} calculated from scratch after ply {ply} `{mve}` from `{fen}`"
+    )]
+    HashMismatch {
+        ply: usize,
+        mve: Move,
+        fen: String,
+        incremental: ZobristHash,
+        from_scratch: ZobristHash,
+    },
+
+    #[error(
+        "unmaking ply {ply} `{mve}` from `{fen}` didn't restore zobrist hash {want}, got {got}"
+    )]
+    UnmakeMismatch {
+        ply: usize,
+        mve: Move,
+        fen: String,
+        want: ZobristHash,
+        got: ZobristHash,
+    },
+
+    #[error("invalid position after ply {ply} `{mve}` from `{fen}`: {reason}")]
+    InvalidPosition {
+        ply: usize,
+        mve: Move,
+        fen: String,
+        reason: String,
+    },
+}
+
+/// Replays a game's moves, checking after every move that the incrementally updated zobrist
+/// hash matches one calculated from scratch, that unmaking the move restores the hash and that
+/// the position is valid. Returns the first divergence found.
+pub fn audit_game(game: &PgnGame) -> Result<(), AuditError> {
+    let mut position = game.start.clone();
+    for (ply, &mve) in game.moves.iter().enumerate() {
+        let fen = position.to_fen();
+        let before_hash = position.zobrist_hash;
+
+        let unmake_move_state = position.make_move(mve);
+        audit_position(&position, ply, mve, &fen)?;
+
+        position.unmake_move(unmake_move_state);
+        if position.zobrist_hash != before_hash {
+            return Err(AuditError::UnmakeMismatch {
+                ply,
+                mve,
+                fen,
+                want: before_hash,
+                got: position.zobrist_hash,
+            });
+        }
+
+        position.make_move(mve);
+    }
+    Ok(())
+}
+
+fn audit_position(position: &Position, ply: usize, mve: Move, fen: &str) -> Result<(), AuditError> {
+    let from_scratch = ZobristHash::calculate(&position.pieces, &position.state);
+    if position.zobrist_hash != from_scratch {
+        return Err(AuditError::HashMismatch {
+            ply,
+            mve,
+            fen: fen.to_string(),
+            incremental: position.zobrist_hash,
+            from_scratch,
+        });
+    }
+    position
+        .validate_position(mve)
+        .map_err(|reason| AuditError::InvalidPosition {
+            ply,
+            mve,
+            fen: fen.to_string(),
+            reason,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitboard::Square::*;
+    use crate::move_gen::MOVE_GEN;
+    use crate::pgn::parse_pgn;
+    use crate::position::{Piece, Side};
+    use testresult::TestResult;
+
+    const GAME: &str = r#"[Event "Casual"]
+[Result "1-0"]
+
+1. e4 d5 2. exd5 Qxd5 3. Nc3 Qa5 4. d4 c6 5. Nf3 Bg4 6. Bf4 e6 7. h3 Bxf3 8. Qxf3 Bb4
+9. Be2 Nd7 10. a3 O-O-O 11. axb4 Qxa1+ 12. Kd2 Qxh1 1-0
+"#;
+
+    #[test]
+    fn test_audit_game() -> TestResult {
+        let games = parse_pgn(GAME, MOVE_GEN)?;
+        assert_eq!(audit_game(&games[0]), Ok(()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_audit_game_hash_mismatch() -> TestResult {
+        let mut game = parse_pgn(GAME, MOVE_GEN)?.remove(0);
+        game.start.zobrist_hash = ZobristHash::empty();
+
+        let err = audit_game(&game).unwrap_err();
+        assert!(matches!(
+            err,
+            AuditError::HashMismatch { ply: 0, mve, ref fen, .. }
+                if mve == Move::new(E2, E4) && *fen == Position::start().to_fen()
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_audit_game_invalid_position() -> TestResult {
+        let mut game = parse_pgn(GAME, MOVE_GEN)?.remove(0);
+        // A white queen on the same square as the a2 pawn.
+        game.start
+            .get_piece_bb_mut(Side::White, Piece::Queen)
+            .set_square(A2);
+        game.start.zobrist_hash = ZobristHash::calculate(&game.start.pieces, &game.start.state);
+
+        let err = audit_game(&game).unwrap_err();
+        assert!(matches!(
+            err,
+            AuditError::InvalidPosition { ply: 0, mve, .. } if mve == Move::new(E2, E4)
+        ));
+        Ok(())
+    }
+}
@@ -2,13 +2,15 @@ use std::fmt;
 use std::ops::{
     BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Not, Sub, SubAssign,
 };
+use std::str::FromStr;
 
-use strum_macros::{Display, EnumIter, EnumString, FromRepr};
+use crate::position::Side;
 
 #[allow(dead_code)]
 #[rustfmt::skip]
 #[repr(u8)]
-#[derive(Clone, Copy, Debug, PartialEq, Eq, EnumIter, EnumString, FromRepr, Display, PartialOrd, Ord, Hash)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "strum", derive(strum_macros::EnumIter))]
 pub enum Square {
     A1, B1, C1, D1, E1, F1, G1, H1,
     A2, B2, C2, D2, E2, F2, G2, H2,
@@ -21,6 +23,27 @@ pub enum Square {
 }
 
 impl Square {
+    /// Every square, in order from A1 to H8.
+    #[rustfmt::skip]
+    pub const ALL: [Square; 64] = [
+        Square::A1, Square::B1, Square::C1, Square::D1, Square::E1, Square::F1, Square::G1, Square::H1,
+        Square::A2, Square::B2, Square::C2, Square::D2, Square::E2, Square::F2, Square::G2, Square::H2,
+        Square::A3, Square::B3, Square::C3, Square::D3, Square::E3, Square::F3, Square::G3, Square::H3,
+        Square::A4, Square::B4, Square::C4, Square::D4, Square::E4, Square::F4, Square::G4, Square::H4,
+        Square::A5, Square::B5, Square::C5, Square::D5, Square::E5, Square::F5, Square::G5, Square::H5,
+        Square::A6, Square::B6, Square::C6, Square::D6, Square::E6, Square::F6, Square::G6, Square::H6,
+        Square::A7, Square::B7, Square::C7, Square::D7, Square::E7, Square::F7, Square::G7, Square::H7,
+        Square::A8, Square::B8, Square::C8, Square::D8, Square::E8, Square::F8, Square::G8, Square::H8,
+    ];
+
+    pub const fn from_repr(idx: u8) -> Option<Square> {
+        if idx < 64 {
+            Some(Self::ALL[idx as usize])
+        } else {
+            None
+        }
+    }
+
     pub(crate) const fn abs_diff(self, other: Square) -> u8 {
         (self as u8).abs_diff(other as u8)
     }
@@ -67,6 +90,41 @@ impl Square {
     pub const fn flip(self) -> Square {
         Square::from_repr(self as u8 ^ 56).expect("Somehow flipped square doesn't have value")
     }
+
+    /// Whether a pawn of `side` promotes when it moves to this square.
+    pub const fn is_promotion_rank(self, side: Side) -> bool {
+        let promotion_rank = match side {
+            Side::White => BitBoard::RANK_8,
+            Side::Black => BitBoard::RANK_1,
+        };
+        promotion_rank.const_bit_and(BitBoard::from_square(self)).0 != 0
+    }
+}
+
+/// Uppercase file then rank, like `E4`.
+impl fmt::Display for Square {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (rank, file) = self.to_rank_file();
+        write!(f, "{}{}", char::from(b'A' + file), rank + 1)
+    }
+}
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+#[error("Invalid square: {0}")]
+pub struct ParseSquareError(String);
+
+/// Parses the same format [`Square`] displays as.
+impl FromStr for Square {
+    type Err = ParseSquareError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.as_bytes() {
+            &[file @ b'A'..=b'H', rank @ b'1'..=b'8'] => {
+                Ok(Square::from_u8((rank - b'1') * 8 + (file - b'A')))
+            }
+            _ => Err(ParseSquareError(s.to_string())),
+        }
+    }
 }
 
 #[repr(isize)]
@@ -82,12 +140,52 @@ pub(crate) enum Direction {
 pub struct BitBoard(u64);
 
 impl BitBoard {
+    pub const RANK_1: BitBoard = BitBoard(0xFF);
+    pub const RANK_2: BitBoard = BitBoard(0xFF << 8);
+    pub const RANK_3: BitBoard = BitBoard(0xFF << 16);
+    pub const RANK_4: BitBoard = BitBoard(0xFF << 24);
+    pub const RANK_5: BitBoard = BitBoard(0xFF << 32);
+    pub const RANK_6: BitBoard = BitBoard(0xFF << 40);
+    pub const RANK_7: BitBoard = BitBoard(0xFF << 48);
+    pub const RANK_8: BitBoard = BitBoard(0xFF << 56);
+    /// Ranks 1 to 8.
+    pub const RANKS: [BitBoard; 8] = [
+        Self::RANK_1,
+        Self::RANK_2,
+        Self::RANK_3,
+        Self::RANK_4,
+        Self::RANK_5,
+        Self::RANK_6,
+        Self::RANK_7,
+        Self::RANK_8,
+    ];
+
+    pub const FILE_A: BitBoard = BitBoard(0x0101_0101_0101_0101);
+    pub const FILE_B: BitBoard = BitBoard(0x0101_0101_0101_0101 << 1);
+    pub const FILE_C: BitBoard = BitBoard(0x0101_0101_0101_0101 << 2);
+    pub const FILE_D: BitBoard = BitBoard(0x0101_0101_0101_0101 << 3);
+    pub const FILE_E: BitBoard = BitBoard(0x0101_0101_0101_0101 << 4);
+    pub const FILE_F: BitBoard = BitBoard(0x0101_0101_0101_0101 << 5);
+    pub const FILE_G: BitBoard = BitBoard(0x0101_0101_0101_0101 << 6);
+    pub const FILE_H: BitBoard = BitBoard(0x0101_0101_0101_0101 << 7);
+    /// Files A to H.
+    pub const FILES: [BitBoard; 8] = [
+        Self::FILE_A,
+        Self::FILE_B,
+        Self::FILE_C,
+        Self::FILE_D,
+        Self::FILE_E,
+        Self::FILE_F,
+        Self::FILE_G,
+        Self::FILE_H,
+    ];
+
     pub(crate) const fn empty() -> Self {
         BitBoard(0)
     }
 
     pub(crate) const fn full() -> Self {
-        BitBoard(u64::max_value())
+        BitBoard(u64::MAX)
     }
 
     pub(crate) const fn from_square(square: Square) -> Self {
@@ -111,6 +209,16 @@ impl BitBoard {
         BitBoard(val)
     }
 
+    /// Parses whitespace separated squares, like `"a1 b2 c3"`. Files can be either case.
+    pub fn from_notation(notation: &str) -> Result<Self, ParseSquareError> {
+        notation
+            .split_whitespace()
+            .map(|square| Square::from_str(&square.to_ascii_uppercase()))
+            .try_fold(BitBoard::empty(), |bb, square| {
+                Ok(bb | BitBoard::from_square(square?))
+            })
+    }
+
     pub(crate) const fn from_square_shifts(
         square: Square,
         shift_dirs_list: &[&[Direction]],
@@ -265,6 +373,7 @@ impl BitBoard {
         lsb
     }
 
+    #[cfg_attr(not(feature = "hyperbola-quintessence"), allow(dead_code))]
     pub(crate) fn swap_bytes(&self) -> BitBoard {
         BitBoard(self.0.swap_bytes())
     }
@@ -384,12 +493,27 @@ impl fmt::Debug for BitBoard {
     }
 }
 
+/// Diagram of the board from white's side, with set squares marked `X`.
+impl fmt::Display for BitBoard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for rank in (0..8).rev() {
+            write!(f, "{}", rank + 1)?;
+            for file in 0..8 {
+                let square = Square::from_u8(rank * 8 + file);
+                let ch = if self.is_square_set(square) { 'X' } else { '.' };
+                write!(f, " {}", ch)?;
+            }
+            writeln!(f)?;
+        }
+        write!(f, "  a b c d e f g h")
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::Square::*;
     use super::*;
 
-    use strum::IntoEnumIterator;
     use test_case::test_case;
 
     #[test]
@@ -399,6 +523,51 @@ mod tests {
         assert_eq!(got, want);
     }
 
+    #[test]
+    fn test_square_display_from_str() {
+        for sq in Square::ALL {
+            assert_eq!(Square::from_str(&sq.to_string()), Ok(sq));
+        }
+        assert_eq!(E4.to_string(), "E4");
+        assert!(Square::from_str("e4").is_err());
+        assert!(Square::from_str("I1").is_err());
+        assert!(Square::from_str("E").is_err());
+    }
+
+    #[test]
+    fn test_rank_and_file_masks() {
+        assert_eq!(
+            BitBoard::RANK_1,
+            BitBoard::from_squares(&[A1, B1, C1, D1, E1, F1, G1, H1])
+        );
+        assert_eq!(
+            BitBoard::RANK_8,
+            BitBoard::from_squares(&[A8, B8, C8, D8, E8, F8, G8, H8])
+        );
+        assert_eq!(
+            BitBoard::FILE_A,
+            BitBoard::from_squares(&[A1, A2, A3, A4, A5, A6, A7, A8])
+        );
+        assert_eq!(
+            BitBoard::FILE_H,
+            BitBoard::from_squares(&[H1, H2, H3, H4, H5, H6, H7, H8])
+        );
+        for sq in Square::ALL {
+            let (rank, file) = sq.to_rank_file();
+            let rank_and_file =
+                BitBoard::RANKS[usize::from(rank)] & BitBoard::FILES[usize::from(file)];
+            assert_eq!(rank_and_file, BitBoard::from_square(sq));
+        }
+    }
+
+    #[test_case(E8, Side::White, true ; "white promotes on rank 8")]
+    #[test_case(E1, Side::White, false ; "white doesn't promote on rank 1")]
+    #[test_case(A1, Side::Black, true ; "black promotes on rank 1")]
+    #[test_case(H7, Side::Black, false ; "black doesn't promote on rank 7")]
+    fn test_is_promotion_rank(square: Square, side: Side, want: bool) {
+        assert_eq!(square.is_promotion_rank(side), want);
+    }
+
     #[test]
     fn test_debug() {
         let got = BitBoard::from_squares(&[A8, B7, C6, D5, E4, F3, G2, H1]);
@@ -406,16 +575,47 @@ mod tests {
         assert_eq!(format!("{:?}", got), want);
     }
 
+    #[test]
+    fn test_display() {
+        let got = BitBoard::from_squares(&[A1, B2, H8, E4]);
+        let want = "\
+8 . . . . . . . X
+7 . . . . . . . .
+6 . . . . . . . .
+5 . . . . . . . .
+4 . . . . X . . .
+3 . . . . . . . .
+2 . X . . . . . .
+1 X . . . . . . .
+  a b c d e f g h";
+        assert_eq!(got.to_string(), want);
+    }
+
+    #[test_case("a1 b2 c3", BitBoard::from_squares(&[A1, B2, C3]) ; "lowercase")]
+    #[test_case(" E4\tD5\n", BitBoard::from_squares(&[E4, D5]) ; "uppercase and whitespace")]
+    #[test_case("h8 h8", BitBoard::from_square(H8) ; "repeated")]
+    #[test_case("", BitBoard::empty() ; "empty")]
+    fn test_from_notation(notation: &str, want: BitBoard) {
+        assert_eq!(BitBoard::from_notation(notation), Ok(want));
+    }
+
+    #[test_case("a1 i9" ; "invalid square")]
+    #[test_case("a1,b2" ; "not whitespace separated")]
+    fn test_from_notation_invalid(notation: &str) {
+        assert!(BitBoard::from_notation(notation).is_err());
+    }
+
     #[test_case([B8, G6, A4, F1] ; "first")]
     fn test_is_piece_at(piece_squares: [Square; 4]) {
-        let all_other_squares: Vec<Square> = Square::iter()
+        let all_other_squares: Vec<Square> = Square::ALL
+            .into_iter()
             .filter(|s| !piece_squares.contains(s))
             .collect();
 
         let bb = BitBoard::from_squares(&piece_squares);
         let inv_bb = BitBoard::from_squares(&all_other_squares);
 
-        for sq in Square::iter() {
+        for sq in Square::ALL {
             if piece_squares.contains(&sq) {
                 assert!(bb.is_square_set(sq));
                 assert!(!inv_bb.is_square_set(sq));
@@ -432,7 +632,7 @@ mod tests {
     #[test_case(&[A1], 1 ; "first square")]
     fn test_bitboard_from_val(piece_squares: &[Square], val: u64) {
         let bb = BitBoard::from_val(val);
-        for sq in Square::iter() {
+        for sq in Square::ALL {
             if piece_squares.contains(&sq) {
                 assert!(bb.is_square_set(sq));
             } else {
@@ -0,0 +1,160 @@
+use std::cell::Cell;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// Source of the current time for search timekeeping, so the search never reads
+/// [`Instant`] directly and can be embedded where it isn't available (e.g. WASM).
+pub trait Clock: Send + Sync {
+    /// Time passed since some fixed point. Must never go backwards.
+    fn now(&self) -> Duration;
+
+    /// Pauses the calling thread, which the search only does when its CPU usage is limited.
+    fn sleep(&self, duration: Duration) {
+        std::thread::sleep(duration);
+    }
+}
+
+/// Clock backed by [`Instant`], measuring from when it was created.
+#[derive(Debug, Clone, Copy)]
+pub struct StdClock {
+    origin: Instant,
+}
+
+impl StdClock {
+    pub fn new() -> Self {
+        Self {
+            origin: Instant::now(),
+        }
+    }
+}
+
+impl Default for StdClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for StdClock {
+    fn now(&self) -> Duration {
+        self.origin.elapsed()
+    }
+}
+
+/// Clock which only moves when told to, for deterministic tests of time management.
+///
+/// It can also advance by a fixed step every time it's read, so a search sees time passing
+/// at a rate proportional to the work it does rather than to how fast the machine is.
+#[derive(Debug, Default)]
+pub struct MockClock {
+    now_nanos: AtomicU64,
+    step_nanos: u64,
+}
+
+impl MockClock {
+    /// Clock frozen at zero.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Clock starting at zero which advances by `step` after every read.
+    pub fn auto_advancing(step: Duration) -> Self {
+        Self {
+            now_nanos: AtomicU64::new(0),
+            step_nanos: duration_to_nanos(step),
+        }
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        self.now_nanos
+            .fetch_add(duration_to_nanos(duration), Ordering::Relaxed);
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Duration {
+        Duration::from_nanos(self.now_nanos.fetch_add(self.step_nanos, Ordering::Relaxed))
+    }
+
+    /// Advances the clock rather than waiting.
+    fn sleep(&self, duration: Duration) {
+        self.advance(duration);
+    }
+}
+
+fn duration_to_nanos(duration: Duration) -> u64 {
+    duration.as_nanos().try_into().unwrap_or(u64::MAX)
+}
+
+/// Measures the time passed since it was started, according to a [`Clock`].
+pub(crate) struct Stopwatch<'a> {
+    clock: &'a dyn Clock,
+    start: Duration,
+    /// When the thread last finished sleeping in [`Stopwatch::throttle`], or the start.
+    working_since: Cell<Duration>,
+}
+
+impl<'a> Stopwatch<'a> {
+    pub(crate) fn start(clock: &'a dyn Clock) -> Self {
+        let start = clock.now();
+        Self {
+            clock,
+            start,
+            working_since: Cell::new(start),
+        }
+    }
+
+    pub(crate) fn elapsed(&self) -> Duration {
+        self.clock.now().saturating_sub(self.start)
+    }
+
+    /// Sleeps for long enough that the time worked since the last call is `max_cpu_percent`
+    /// of the time passed, so a thread calling this regularly only uses that much of a CPU.
+    pub(crate) fn throttle(&self, max_cpu_percent: u8) {
+        let max_cpu_percent = u32::from(max_cpu_percent.clamp(1, 100));
+        let worked = self.clock.now().saturating_sub(self.working_since.get());
+        self.clock
+            .sleep(worked * (100 - max_cpu_percent) / max_cpu_percent);
+        self.working_since.set(self.clock.now());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stopwatch_throttle() {
+        let clock = MockClock::new();
+        let stopwatch = Stopwatch::start(&clock);
+        clock.advance(Duration::from_millis(30));
+        stopwatch.throttle(25);
+        assert_eq!(stopwatch.elapsed(), Duration::from_millis(120));
+
+        // Only the time worked since the last pause counts.
+        clock.advance(Duration::from_millis(10));
+        stopwatch.throttle(50);
+        assert_eq!(stopwatch.elapsed(), Duration::from_millis(140));
+
+        stopwatch.throttle(100);
+        assert_eq!(stopwatch.elapsed(), Duration::from_millis(140));
+    }
+
+    #[test]
+    fn test_mock_clock() {
+        let clock = MockClock::new();
+        assert_eq!(clock.now(), Duration::ZERO);
+        clock.advance(Duration::from_millis(5));
+        assert_eq!(clock.now(), Duration::from_millis(5));
+        assert_eq!(clock.now(), Duration::from_millis(5));
+    }
+
+    #[test]
+    fn test_stopwatch_auto_advancing() {
+        let clock = MockClock::auto_advancing(Duration::from_millis(1));
+        clock.advance(Duration::from_secs(1));
+
+        let stopwatch = Stopwatch::start(&clock);
+        assert_eq!(stopwatch.elapsed(), Duration::from_millis(1));
+        assert_eq!(stopwatch.elapsed(), Duration::from_millis(2));
+    }
+}
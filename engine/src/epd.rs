@@ -0,0 +1,224 @@
+use crate::algebraic_notation::san_to_move;
+use crate::move_gen::GenerateMoves;
+use crate::position::{FenParseError, Move, Position};
+
+#[derive(thiserror::Error, Debug)]
+pub enum EpdError {
+    #[error("EPD record should start with 4 FEN fields: {0}")]
+    MissingFields(String),
+
+    #[error("invalid position `{0}`: {1}")]
+    InvalidFen(String, FenParseError),
+
+    #[error("unterminated string in `{0}`")]
+    UnterminatedString(String),
+
+    #[error("invalid `{opcode}` operand `{operand}`")]
+    InvalidOperand { opcode: String, operand: String },
+}
+
+/// A position from an EPD file, with the operations test suites use parsed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EpdRecord {
+    pub position: Position,
+    /// `id`: name of the record, e.g. `"WAC.001"`.
+    pub id: Option<String>,
+    /// `bm`: moves that solve the position.
+    pub best_moves: Vec<Move>,
+    /// `am`: moves that fail the position.
+    pub avoid_moves: Vec<Move>,
+    /// `dm`: the side to move mates in this many moves.
+    pub direct_mate: Option<u8>,
+    /// Every operation in the record, including the ones above, as its opcode and operands.
+    /// Quoted operands are unquoted.
+    pub operations: Vec<(String, Vec<String>)>,
+}
+
+impl EpdRecord {
+    /// Operands of the first operation with `opcode`.
+    pub fn operation(&self, opcode: &str) -> Option<&[String]> {
+        find_operation(&self.operations, opcode)
+    }
+}
+
+/// Parses one EPD record: the first 4 fields of a FEN, followed by operations ending in `;`,
+/// e.g. `... w - - bm Nf3 Nc3; id "test.001";`.
+///
+/// EPD doesn't have move clocks, so the half move clock is taken from a `hmvc` operation if
+/// there is one and is 0 otherwise. Moves in `bm` and `am` are SAN.
+/// Source: https://www.chessprogramming.org/Extended_Position_Description
+pub fn parse_epd_record(
+    line: &str,
+    move_gen: impl GenerateMoves + Copy,
+) -> Result<EpdRecord, EpdError> {
+    let line = line.trim();
+    let mut fields = Vec::with_capacity(4);
+    let mut rest = line;
+    for _ in 0..4 {
+        let (field, after) = rest
+            .trim_start()
+            .split_once(char::is_whitespace)
+            .unwrap_or((rest.trim_start(), ""));
+        if field.is_empty() {
+            return Err(EpdError::MissingFields(line.to_string()));
+        }
+        fields.push(field);
+        rest = after;
+    }
+
+    let operations = parse_operations(rest)?;
+    let operand = |opcode: &str| find_operation(&operations, opcode);
+    let invalid_operand = |opcode: &str, operand: &str| EpdError::InvalidOperand {
+        opcode: opcode.to_string(),
+        operand: operand.to_string(),
+    };
+
+    let half_move_clock = match operand("hmvc") {
+        Some([clock]) => clock
+            .parse::<u8>()
+            .map_err(|_| invalid_operand("hmvc", clock))?,
+        Some(operands) => return Err(invalid_operand("hmvc", &operands.join(" "))),
+        None => 0,
+    };
+    let fen = format!("{} {} 1", fields.join(" "), half_move_clock);
+    let position = Position::from_fen(&fen).map_err(|err| EpdError::InvalidFen(fen, err))?;
+
+    let parse_moves = |opcode: &str| {
+        operand(opcode)
+            .unwrap_or_default()
+            .iter()
+            .map(|san| {
+                san_to_move(&position, san, move_gen).map_err(|_| invalid_operand(opcode, san))
+            })
+            .collect::<Result<Vec<_>, _>>()
+    };
+    let best_moves = parse_moves("bm")?;
+    let avoid_moves = parse_moves("am")?;
+    let direct_mate = match operand("dm") {
+        Some([moves]) => Some(moves.parse().map_err(|_| invalid_operand("dm", moves))?),
+        Some(operands) => return Err(invalid_operand("dm", &operands.join(" "))),
+        None => None,
+    };
+    let id = operand("id").map(|operands| operands.join(" "));
+
+    Ok(EpdRecord {
+        position,
+        id,
+        best_moves,
+        avoid_moves,
+        direct_mate,
+        operations,
+    })
+}
+
+fn find_operation<'a>(
+    operations: &'a [(String, Vec<String>)],
+    opcode: &str,
+) -> Option<&'a [String]> {
+    operations
+        .iter()
+        .find(|(name, _)| name == opcode)
+        .map(|(_, operands)| operands.as_slice())
+}
+
+/// Splits operations on the `;` ending each, and each operation into its opcode and operands.
+/// A quoted operand can contain spaces and `;`.
+fn parse_operations(operations: &str) -> Result<Vec<(String, Vec<String>)>, EpdError> {
+    let mut parsed = vec![];
+    let mut tokens: Vec<String> = vec![];
+    let mut chars = operations.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            ';' => {
+                let mut tokens = std::mem::take(&mut tokens).into_iter();
+                if let Some(opcode) = tokens.next() {
+                    parsed.push((opcode, tokens.collect()));
+                }
+            }
+            '"' => {
+                let mut string = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some(ch) => string.push(ch),
+                        None => return Err(EpdError::UnterminatedString(operations.to_string())),
+                    }
+                }
+                tokens.push(string);
+            }
+            _ if ch.is_whitespace() => {}
+            _ => {
+                let mut token = ch.to_string();
+                while let Some(&ch) = chars.peek() {
+                    if ch.is_whitespace() || ch == ';' || ch == '"' {
+                        break;
+                    }
+                    token.push(ch);
+                    chars.next();
+                }
+                tokens.push(token);
+            }
+        }
+    }
+    // The last operation's `;` is often left out.
+    let mut tokens = tokens.into_iter();
+    if let Some(opcode) = tokens.next() {
+        parsed.push((opcode, tokens.collect()));
+    }
+    Ok(parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitboard::Square::*;
+    use crate::move_gen::MOVE_GEN;
+    use test_case::test_case;
+    use testresult::TestResult;
+
+    #[test]
+    fn test_parse_epd_record() -> TestResult {
+        let record = parse_epd_record(
+            r#"2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; id "WAC.001";"#,
+            MOVE_GEN,
+        )?;
+        assert_eq!(
+            record.position,
+            Position::from_fen("2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - 0 1")?
+        );
+        assert_eq!(record.id.as_deref(), Some("WAC.001"));
+        assert_eq!(record.best_moves, vec![Move::new(G3, G6)]);
+        assert!(record.avoid_moves.is_empty());
+        assert_eq!(record.direct_mate, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_epd_record_operations() -> TestResult {
+        let record = parse_epd_record(
+            r#"4k3/8/8/8/8/8/8/R3K2R w KQ - am O-O Ra2; dm 2; c0 "Ra8+=10, Rh8+=5; maybe"; hmvc 12"#,
+            MOVE_GEN,
+        )?;
+        assert_eq!(
+            record.avoid_moves,
+            vec![Move::new(E1, G1), Move::new(A1, A2)]
+        );
+        assert_eq!(record.direct_mate, Some(2));
+        assert_eq!(
+            record.operation("c0"),
+            Some(["Ra8+=10, Rh8+=5; maybe".to_string()].as_slice())
+        );
+        assert_eq!(record.position.state.half_move_clock, 12);
+        assert_eq!(record.operation("bm"), None);
+        Ok(())
+    }
+
+    #[test_case("4k3/8/8/8/8/8/8/4K3 w -" ; "missing fields")]
+    #[test_case("4k3/8/8/8/8/8/8/4K3 w - - bm Ke3" ; "illegal best move")]
+    #[test_case("4k3/8/8/8/8/8/8/4K3 w - - dm two" ; "invalid mate")]
+    #[test_case("4k3/8/8/8/8/8/8/4K3 w - - id \"unterminated" ; "unterminated id")]
+    #[test_case("4k3/8/8/8/8/8/8/4K3 x - - id \"bad side\"" ; "invalid position")]
+    fn test_parse_epd_record_invalid(line: &str) {
+        assert!(parse_epd_record(line, MOVE_GEN).is_err());
+    }
+}
@@ -1,380 +0,0 @@
-use std::fmt::Display;
-use std::ops::{Add, Sub};
-
-use crate::GenerateMoves;
-use crate::bitboard::Square;
-use crate::position::{Piece, Position, Side};
-
-/// An evaluation of a position. Is always from the side to move's perspective.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
-pub struct Eval(pub(crate) i32);
-
-impl Display for Eval {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        if let Some(mate) = self.is_mate() {
-            write!(f, "mate {}", mate)
-        } else {
-            write!(f, "cp {}", self.0)
-        }
-    }
-}
-
-impl Eval {
-    pub const DRAW: Eval = Eval(0);
-    pub const MAX: Eval = Eval::mate_in(1);
-    pub const MIN: Eval = Eval::mate_in(0);
-
-    const MATE_BASE: i32 = 30_000;
-
-    pub const fn mate_in(moves: i8) -> Self {
-        if moves > 0 {
-            Self(Self::MATE_BASE - (moves as i32))
-        } else {
-            Self(-Self::MATE_BASE - (moves as i32))
-        }
-    }
-
-    pub(crate) fn value(&self) -> i32 {
-        self.0
-    }
-
-    /// Returns how many moves (not plys) the evaluation represents, or none if it's not mate.
-    pub fn is_mate(&self) -> Option<i8> {
-        let from_mate = if self.0 >= 0 {
-            Self::MATE_BASE - self.0
-        } else {
-            -(Self::MATE_BASE + self.0)
-        };
-        if from_mate.abs() <= 250 {
-            return Some(from_mate.try_into().unwrap_or_else(|_| {
-                panic!(
-                    "Bug with Eval mate calculation: couldn't convert to i32: {}",
-                    from_mate
-                )
-            }));
-        }
-        None
-    }
-
-    pub fn flip(&self) -> Eval {
-        if let Some(mate_moves) = self.is_mate() {
-            if mate_moves <= 0 {
-                return Eval::mate_in(-(mate_moves) + 1);
-            } else {
-                return Eval::mate_in(-mate_moves);
-            }
-        }
-        Eval(-self.0)
-    }
-}
-
-impl Add<i32> for Eval {
-    type Output = Eval;
-    fn add(self, rhs: i32) -> Self::Output {
-        Eval(self.0 - rhs)
-    }
-}
-
-impl Sub<i32> for Eval {
-    type Output = Eval;
-    fn sub(self, rhs: i32) -> Self::Output {
-        Eval(self.0 - rhs)
-    }
-}
-
-pub trait EvaluatePosition {
-    fn evaluate(&self, position: &Position, move_gen: impl GenerateMoves) -> Eval;
-}
-
-#[derive(Clone, Copy)]
-pub struct PositionEvaluator;
-
-impl EvaluatePosition for PositionEvaluator {
-    /// Return evaluation relative to the side to move
-    fn evaluate(&self, position: &Position, move_gen: impl GenerateMoves) -> Eval {
-        let phase_weight = calculate_phase_weight(&position);
-
-        let eval_score = position.piece_locs().fold(0, |acc, (piece, side, square)| {
-            // For black, we need to flip index in order to use correct value
-            let square = if side == Side::White {
-                square.flip()
-            } else {
-                square
-            };
-
-            let (mg_val, eg_val) = get_piece_value(piece);
-            let piece_val =
-                (mg_val * phase_weight + eg_val * (PHASE_WEIGHT_MAX - phase_weight)) / 24;
-
-            let (mg_bonus, eg_bonus) = get_piece_square_bonus(piece, square);
-            let bonus =
-                (mg_bonus * phase_weight + eg_bonus * (PHASE_WEIGHT_MAX - phase_weight)) / 24;
-
-            let tot_val = piece_val + bonus;
-
-            if side == Side::White {
-                acc + tot_val
-            } else {
-                acc - tot_val
-            }
-        });
-        let eval_score = if position.state.to_move == Side::Black {
-            -eval_score
-        } else {
-            eval_score
-        };
-        Eval(eval_score)
-    }
-}
-
-/// Get piece value, both middlegame and endgame.
-fn get_piece_value(piece: Piece) -> (i32, i32) {
-    match piece {
-        Piece::Pawn => (82, 94),
-        Piece::Knight => (337, 281),
-        Piece::Bishop => (365, 297),
-        Piece::Rook => (477, 512),
-        Piece::Queen => (1025, 936),
-        Piece::King => (2000, 2000), // Don't use i32::MAX in case of overflows
-    }
-}
-
-const PHASE_WEIGHT_MAX: i32 = 24;
-fn calculate_phase_weight(position: &Position) -> i32 {
-    let val = position
-        .piece_locs()
-        .fold(0, |acc, (piece, _, _)| acc + piece_phase_weight(piece));
-    if val > PHASE_WEIGHT_MAX {
-        PHASE_WEIGHT_MAX
-    } else {
-        val
-    }
-}
-
-fn piece_phase_weight(piece: Piece) -> i32 {
-    match piece {
-        Piece::Pawn => 0,
-        Piece::Knight | Piece::Bishop => 1,
-        Piece::Rook => 2,
-        Piece::Queen => 4,
-        Piece::King => 0,
-    }
-}
-
-/// Values source: https://www.chessprogramming.org/PeSTO%27s_Evaluation_Function
-#[rustfmt::skip]
-const PST_MG: [i32; 384] = [
-    // Pawn (0-63)
-      0,   0,   0,   0,   0,   0,  0,   0,
-     98, 134,  61,  95,  68, 126, 34, -11,
-     -6,   7,  26,  31,  65,  56, 25, -20,
-    -14,  13,   6,  21,  23,  12, 17, -23,
-    -27,  -2,  -5,  12,  17,   6, 10, -25,
-    -26,  -4,  -4, -10,   3,   3, 33, -12,
-    -35,  -1, -20, -23, -15,  24, 38, -22,
-      0,   0,   0,   0,   0,   0,  0,   0,
-
-    // Knight (64-127)
-    -167, -89, -34, -49,  61, -97, -15, -107,
-     -73, -41,  72,  36,  23,  62,   7,  -17,
-     -47,  60,  37,  65,  84, 129,  73,   44,
-      -9,  17,  19,  53,  37,  69,  18,   22,
-     -13,   4,  16,  13,  28,  19,  21,   -8,
-     -23,  -9,  12,  10,  19,  17,  25,  -16,
-     -29, -53, -12,  -3,  -1,  18, -14,  -19,
-    -105, -21, -58, -33, -17, -28, -19,  -23,
-
-    // Bishop (128-191)
-    -29,   4, -82, -37, -25, -42,   7,  -8,
-    -26,  16, -18, -13,  30,  59,  18, -47,
-    -16,  37,  43,  40,  35,  50,  37,  -2,
-     -4,   5,  19,  50,  37,  37,   7,  -2,
-     -6,  13,  13,  26,  34,  12,  10,   4,
-      0,  15,  15,  15,  14,  27,  18,  10,
-      4,  15,  16,   0,   7,  21,  33,   1,
-    -33,  -3, -14, -21, -13, -12, -39, -21,
-
-    // Rook (192-255)
-       32,  42,  32,  51, 63,  9,  31,  43,
-       27,  32,  58,  62, 80, 67,  26,  44,
-       -5,  19,  26,  36, 17, 45,  61,  16,
-      -24, -11,   7,  26, 24, 35,  -8, -20,
-      -36, -26, -12,  -1,  9, -7,   6, -23,
-      -45, -25, -16, -17,  3,  0,  -5, -33,
-      -44, -16, -20,  -9, -1, 11,  -6, -71,
-      -19, -13,   1,  17, 16,  7, -37, -26,
-
-    // Queen (256-319)
-    -28,   0,  29,  12,  59,  44,  43,  45,
-    -24, -39,  -5,   1, -16,  57,  28,  54,
-    -13, -17,   7,   8,  29,  56,  47,  57,
-    -27, -27, -16, -16,  -1,  17,  -2,   1,
-     -9, -26,  -9, -10,  -2,  -4,   3,  -3,
-    -14,   2, -11,  -2,  -5,   2,  14,   5,
-    -35,  -8,  11,   2,   8,  15,  -3,   1,
-     -1, -18,  -9,  10, -15, -25, -31, -50,
-
-    // King (320-383)
-    -65,  23,  16, -15, -56, -34,   2,  13,
-     29,  -1, -20,  -7,  -8,  -4, -38, -29,
-     -9,  24,   2, -16, -20,   6,  22, -22,
-    -17, -20, -12, -27, -30, -25, -14, -36,
-    -49,  -1, -27, -39, -46, -44, -33, -51,
-    -14, -14, -22, -46, -44, -30, -15, -27,
-      1,   7,  -8, -64, -43, -16,   9,   8,
-    -15,  36,  12, -54,   8, -28,  24,  14,
-];
-
-#[rustfmt::skip]
-const PST_EG: [i32; 384] = [
-    // Pawn (0-63)
-      0,   0,   0,   0,   0,   0,   0,   0,
-    178, 173, 158, 134, 147, 132, 165, 187,
-     94, 100,  85,  67,  56,  53,  82,  84,
-     32,  24,  13,   5,  -2,   4,  17,  17,
-     13,   9,  -3,  -7,  -7,  -8,   3,  -1,
-      4,   7,  -6,   1,   0,  -5,  -1,  -8,
-     13,   8,   8,  10,  13,   0,   2,  -7,
-      0,   0,   0,   0,   0,   0,   0,   0,
-
-    // Knight (64-127)
-    -58, -38, -13, -28, -31, -27, -63, -99,
-    -25,  -8, -25,  -2,  -9, -25, -24, -52,
-    -24, -20,  10,   9,  -1,  -9, -19, -41,
-    -17,   3,  22,  22,  22,  11,   8, -18,
-    -18,  -6,  16,  25,  16,  17,   4, -18,
-    -23,  -3,  -1,  15,  10,  -3, -20, -22,
-    -42, -20, -10,  -5,  -2, -20, -23, -44,
-    -29, -51, -23, -15, -22, -18, -50, -64,
-
-    // Bishop (128-191)
-    -14, -21, -11,  -8, -7,  -9, -17, -24,
-     -8,  -4,   7, -12, -3, -13,  -4, -14,
-      2,  -8,   0,  -1, -2,   6,   0,   4,
-     -3,   9,  12,   9, 14,  10,   3,   2,
-     -6,   3,  13,  19,  7,  10,  -3,  -9,
-    -12,  -3,   8,  10, 13,   3,  -7, -15,
-    -14, -18,  -7,  -1,  4,  -9, -15, -27,
-    -23,  -9, -23,  -5, -9, -16,  -5, -17,
-
-    // Rook (192-255)
-    13, 10, 18, 15, 12,  12,   8,   5,
-    11, 13, 13, 11, -3,   3,   8,   3,
-     7,  7,  7,  5,  4,  -3,  -5,  -3,
-     4,  3, 13,  1,  2,   1,  -1,   2,
-     3,  5,  8,  4, -5,  -6,  -8, -11,
-    -4,  0, -5, -1, -7, -12,  -8, -16,
-    -6, -6,  0,  2, -9,  -9, -11,  -3,
-    -9,  2,  3, -1, -5, -13,   4, -20,
-
-    // Queen (256-319)
-     -9,  22,  22,  27,  27,  19,  10,  20,
-    -17,  20,  32,  41,  58,  25,  30,   0,
-    -20,   6,   9,  49,  47,  35,  19,   9,
-      3,  22,  24,  45,  57,  40,  57,  36,
-    -18,  28,  19,  47,  31,  34,  39,  23,
-    -16, -27,  15,   6,   9,  17,  10,   5,
-    -22, -23, -30, -16, -16, -23, -36, -32,
-    -33, -28, -22, -43,  -5, -32, -20, -41,
-
-    // King (320-383)
-    -74, -35, -18, -18, -11,  15,   4, -17,
-    -12,  17,  14,  17,  17,  38,  23,  11,
-     10,  17,  23,  15,  20,  45,  44,  13,
-     -8,  22,  24,  27,  26,  33,  26,   3,
-    -18,  -4,  21,  24,  27,  23,   9, -11,
-    -19,  -3,  11,  21,  23,  16,   7,  -9,
-    -27, -11,   4,  13,  14,   4,  -5, -17,
-    -53, -34, -21, -11, -28, -14, -24, -43,
-];
-
-fn get_piece_square_bonus(piece: Piece, square: Square) -> (i32, i32) {
-    let idx = (piece as usize) * 64 + (square as usize);
-    (PST_MG[idx], PST_EG[idx])
-}
-
-pub static POSITION_EVALUATOR: PositionEvaluator = PositionEvaluator {};
-
-#[cfg(test)]
-mod tests {
-    use crate::move_gen::MOVE_GEN;
-
-    use super::*;
-
-    use test_case::test_case;
-    use testresult::TestResult;
-
-    #[test_case(Eval(10), Eval(-10))]
-    #[test_case(Eval::mate_in(0), Eval::mate_in(1))]
-    #[test_case(Eval::mate_in(1), Eval::mate_in(-1))]
-    #[test_case(Eval::mate_in(-1), Eval::mate_in(2))]
-    #[test_case(Eval::mate_in(4), Eval::mate_in(-4))]
-    #[test_case(Eval::mate_in(-7), Eval::mate_in(8))]
-    #[test_case(Eval::DRAW, Eval::DRAW)]
-    fn test_eval_flip(eval_input: Eval, eval_want: Eval) {
-        let eval_got = eval_input.flip();
-
-        assert_eq!(eval_got, eval_want);
-    }
-
-    #[test]
-    fn test_eval_ord() {
-        let evals_order_want = vec![
-            Eval::mate_in(0),
-            Eval::mate_in(-1),
-            Eval::mate_in(-2),
-            Eval(-20),
-            Eval(-1),
-            Eval::DRAW,
-            Eval(1),
-            Eval(20),
-            Eval::mate_in(2),
-            Eval::mate_in(1),
-        ];
-        let mut evals = vec![
-            Eval::mate_in(0),
-            Eval::mate_in(1),
-            Eval::mate_in(-1),
-            Eval::mate_in(2),
-            Eval::mate_in(-2),
-            Eval(-20),
-            Eval(-1),
-            Eval::DRAW,
-            Eval(1),
-            Eval(20),
-        ];
-
-        evals.sort();
-
-        assert_eq!(evals, evals_order_want);
-    }
-
-    #[test]
-    fn test_obvious_eval() -> TestResult {
-        let position = Position::from_fen("2k5/Q7/8/8/8/8/8/7K w - - 0 1")?;
-        let move_gen = MOVE_GEN;
-        let eval = POSITION_EVALUATOR.evaluate(&position, move_gen);
-
-        // Should be at least 5 pawns better than the opponent
-        assert!(eval > Eval(500));
-
-        Ok(())
-    }
-
-    #[test_case(Eval::mate_in(0), Some(0) ; "0")]
-    #[test_case(Eval::mate_in(1), Some(1) ; "1")]
-    #[test_case(Eval::mate_in(-1), Some(-1) ; "neg 1")]
-    #[test_case(Eval::mate_in(2), Some(2) ; "2")]
-    #[test_case(Eval::mate_in(-2), Some(-2) ; "neg 2")]
-    #[test_case(Eval::mate_in(-7), Some(-7) ; "neg 7")]
-    #[test_case(Eval::mate_in(10), Some(10) ; "10")]
-    #[test_case(Eval::mate_in(101), Some(101) ; "101")]
-    #[test_case(Eval::mate_in(101), Some(101) ; "neg 101")]
-    #[test_case(Eval::DRAW, None)]
-    #[test_case(Eval(10), None)]
-    fn test_is_mate(eval: Eval, is_mate_want: Option<i8>) {
-        let is_mate_got = eval.is_mate();
-
-        assert_eq!(is_mate_got, is_mate_want);
-    }
-}
@@ -0,0 +1,85 @@
+use crate::bitboard::BitBoard;
+
+/// Squares ahead of a pawn on its own and the adjacent files, by side and then square. A pawn
+/// is passed when there are no enemy pawns on them.
+/// Source: https://www.chessprogramming.org/Passed_Pawns_(Bitboards)
+pub(super) const PASSED_PAWN_MASKS: [[BitBoard; 64]; 2] = calc_pawn_front_masks(true);
+/// Squares ahead of a pawn on its own file, by side and then square.
+pub(super) const FRONT_FILE_MASKS: [[BitBoard; 64]; 2] = calc_pawn_front_masks(false);
+/// Files next to each file, from A to H.
+pub(super) const ADJACENT_FILES: [BitBoard; 8] = calc_adjacent_files();
+
+const fn calc_adjacent_files() -> [BitBoard; 8] {
+    let mut adjacent_files = [BitBoard::empty(); 8];
+    let mut file = 0;
+    while file < 8 {
+        if file > 0 {
+            adjacent_files[file] = adjacent_files[file].const_bit_or(BitBoard::FILES[file - 1]);
+        }
+        if file < 7 {
+            adjacent_files[file] = adjacent_files[file].const_bit_or(BitBoard::FILES[file + 1]);
+        }
+        file += 1;
+    }
+    adjacent_files
+}
+
+const fn calc_pawn_front_masks(include_adjacent_files: bool) -> [[BitBoard; 64]; 2] {
+    let adjacent_files = calc_adjacent_files();
+    let mut masks = [[BitBoard::empty(); 64]; 2];
+
+    let mut idx = 0;
+    while idx < 64 {
+        let (rank, file) = (idx / 8, idx % 8);
+        let mut files = BitBoard::FILES[file];
+        if include_adjacent_files {
+            files = files.const_bit_or(adjacent_files[file]);
+        }
+
+        // White pawns move up the board and black pawns down it.
+        let mut white_ahead = BitBoard::empty();
+        let mut black_ahead = BitBoard::empty();
+        let mut other_rank = 0;
+        while other_rank < 8 {
+            if other_rank > rank {
+                white_ahead = white_ahead.const_bit_or(BitBoard::RANKS[other_rank]);
+            } else if other_rank < rank {
+                black_ahead = black_ahead.const_bit_or(BitBoard::RANKS[other_rank]);
+            }
+            other_rank += 1;
+        }
+
+        masks[0][idx] = white_ahead.const_bit_and(files);
+        masks[1][idx] = black_ahead.const_bit_and(files);
+        idx += 1;
+    }
+    masks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitboard::Square::{self, *};
+    use crate::position::Side;
+    use test_case::test_case;
+
+    #[test_case(Side::White, E5, BitBoard::from_squares(&[D6, E6, F6, D7, E7, F7, D8, E8, F8]) ; "white")]
+    #[test_case(Side::Black, A3, BitBoard::from_squares(&[A2, B2, A1, B1]) ; "black on the edge")]
+    #[test_case(Side::White, H8, BitBoard::empty() ; "last rank")]
+    fn test_passed_pawn_masks(side: Side, square: Square, want: BitBoard) {
+        assert_eq!(PASSED_PAWN_MASKS[side as usize][square as usize], want);
+    }
+
+    #[test_case(Side::White, C6, BitBoard::from_squares(&[C7, C8]) ; "white")]
+    #[test_case(Side::Black, C6, BitBoard::from_squares(&[C5, C4, C3, C2, C1]) ; "black")]
+    fn test_front_file_masks(side: Side, square: Square, want: BitBoard) {
+        assert_eq!(FRONT_FILE_MASKS[side as usize][square as usize], want);
+    }
+
+    #[test]
+    fn test_adjacent_files() {
+        assert_eq!(ADJACENT_FILES[0], BitBoard::FILE_B);
+        assert_eq!(ADJACENT_FILES[3], BitBoard::FILE_C | BitBoard::FILE_E);
+        assert_eq!(ADJACENT_FILES[7], BitBoard::FILE_G);
+    }
+}
@@ -0,0 +1,837 @@
+use std::fmt::Display;
+use std::ops::{Add, Sub};
+
+use tabled::{Table, Tabled};
+
+use crate::GenerateMoves;
+use crate::bitboard::{BitBoard, Square};
+use crate::move_gen::leaping_pieces::LEAPING_PIECES;
+use crate::move_gen::{GenerateSlidingMoves, SLIDING_PIECES_MOVE_GEN};
+use crate::position::{Piece, Position, Side};
+
+use self::pawns::side_pawns;
+
+mod masks;
+mod pawns;
+
+/// An evaluation of a position from the side to move's perspective, positive when the side to
+/// move is better. This is what the search and evaluation functions work with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct RelativeEval(pub(crate) i32);
+
+/// Old name for [`RelativeEval`], kept so code written against the stable API still compiles.
+pub type Eval = RelativeEval;
+
+/// An evaluation of a position from white's perspective, no matter which side is to move.
+///
+/// Only convert to this at the edges (e.g. showing an evaluation to a user). Converting between
+/// this and [`RelativeEval`] needs the side to move, so the compiler catches a score of one kind
+/// being used where the other is expected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct AbsoluteEval(pub(crate) i32);
+
+/// Formats as a UCI score, e.g. `cp 35` or `mate -2`.
+impl Display for RelativeEval {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(mate) = self.is_mate() {
+            write!(f, "mate {}", mate)
+        } else {
+            write!(f, "cp {}", self.0)
+        }
+    }
+}
+
+impl RelativeEval {
+    pub const DRAW: RelativeEval = RelativeEval(0);
+    pub const MAX: RelativeEval = RelativeEval::mate_in(1);
+    pub const MIN: RelativeEval = RelativeEval::mate_in(0);
+
+    const MATE_BASE: i32 = 30_000;
+    /// Furthest away a mate can be and still be reported as one.
+    const MAX_MATE_MOVES: i32 = 250;
+    /// Largest centipawn score, so that it can't be mistaken for a mate.
+    const MAX_CP: i32 = Self::MATE_BASE - Self::MAX_MATE_MOVES - 1;
+
+    /// Centipawn score, clamped so that it can't reach the range used for mate scores.
+    pub const fn cp(centipawns: i32) -> Self {
+        if centipawns > Self::MAX_CP {
+            Self(Self::MAX_CP)
+        } else if centipawns < -Self::MAX_CP {
+            Self(-Self::MAX_CP)
+        } else {
+            Self(centipawns)
+        }
+    }
+
+    pub const fn mate_in(moves: i8) -> Self {
+        if moves > 0 {
+            Self(Self::MATE_BASE - (moves as i32))
+        } else {
+            Self(-Self::MATE_BASE - (moves as i32))
+        }
+    }
+
+    /// Returns how many moves (not plys) the evaluation represents, or none if it's not mate.
+    pub fn is_mate(&self) -> Option<i8> {
+        let from_mate = if self.0 >= 0 {
+            Self::MATE_BASE - self.0
+        } else {
+            -(Self::MATE_BASE + self.0)
+        };
+        if from_mate.abs() <= Self::MAX_MATE_MOVES {
+            return Some(from_mate.try_into().unwrap_or_else(|_| {
+                panic!(
+                    "Bug with Eval mate calculation: couldn't convert to i32: {}",
+                    from_mate
+                )
+            }));
+        }
+        None
+    }
+
+    /// Centipawn score, or none if the evaluation is a mate.
+    pub fn centipawns(&self) -> Option<i32> {
+        self.is_mate().is_none().then_some(self.0)
+    }
+
+    /// Same evaluation from white's perspective, given the side to move in the position.
+    pub fn to_absolute(self, to_move: Side) -> AbsoluteEval {
+        AbsoluteEval(match to_move {
+            Side::White => self.0,
+            Side::Black => -self.0,
+        })
+    }
+
+    /// Same evaluation from the perspective of the side that moved into the position, i.e. the
+    /// parent node in the search. Mates are one more move away from there.
+    pub fn flip(&self) -> RelativeEval {
+        if let Some(mate_moves) = self.is_mate() {
+            if mate_moves <= 0 {
+                return RelativeEval::mate_in(-(mate_moves) + 1);
+            } else {
+                return RelativeEval::mate_in(-mate_moves);
+            }
+        }
+        RelativeEval(-self.0)
+    }
+
+    /// `score` after adding to or subtracting from this evaluation, clamped to the range of the
+    /// kind of score this is.
+    fn with_offset_score(self, score: i32) -> RelativeEval {
+        if self.is_mate().is_some() {
+            RelativeEval(score.clamp(RelativeEval::MIN.0, RelativeEval::MAX.0))
+        } else {
+            RelativeEval::cp(score)
+        }
+    }
+}
+
+impl AbsoluteEval {
+    /// Centipawn score, positive when white is better.
+    pub const fn cp(centipawns: i32) -> Self {
+        Self(RelativeEval::cp(centipawns).0)
+    }
+
+    /// Same evaluation from the perspective of `to_move`, the side to move in the position.
+    pub fn to_relative(self, to_move: Side) -> RelativeEval {
+        RelativeEval(match to_move {
+            Side::White => self.0,
+            Side::Black => -self.0,
+        })
+    }
+}
+
+/// Formats the same way as [`RelativeEval`], with mates for black being negative.
+impl Display for AbsoluteEval {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.to_relative(Side::White).fmt(f)
+    }
+}
+
+/// Centipawn scores stay centipawn scores, clamped like [`RelativeEval::cp`], so adding a margin
+/// can't turn one into a mate. Mate scores saturate at [`RelativeEval::MIN`] and
+/// [`RelativeEval::MAX`], so adding to one moves it closer to or further from mate but never past
+/// the bounds of the search window.
+impl Add<i32> for RelativeEval {
+    type Output = RelativeEval;
+    fn add(self, rhs: i32) -> Self::Output {
+        self.with_offset_score(self.0.saturating_add(rhs))
+    }
+}
+
+/// Clamps the same way as [`Add`].
+impl Sub<i32> for RelativeEval {
+    type Output = RelativeEval;
+    fn sub(self, rhs: i32) -> Self::Output {
+        self.with_offset_score(self.0.saturating_sub(rhs))
+    }
+}
+
+/// Alpha-beta window an evaluation is requested for. Terms that are expensive to compute may be
+/// skipped once the cheap terms are more than `lazy_margin` centipawns outside of it, since they
+/// can't bring the eval back inside the window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EvalBounds {
+    pub alpha: RelativeEval,
+    pub beta: RelativeEval,
+    pub lazy_margin: i32,
+}
+
+impl EvalBounds {
+    fn is_far_outside(&self, eval: RelativeEval) -> bool {
+        eval + self.lazy_margin <= self.alpha || eval - self.lazy_margin >= self.beta
+    }
+}
+
+pub trait EvaluatePosition {
+    /// Return evaluation relative to the side to move. Without `bounds` the full eval is
+    /// computed, otherwise it's only exact inside the window widened by the lazy margin.
+    fn evaluate(
+        &self,
+        position: &Position,
+        move_gen: impl GenerateMoves,
+        bounds: Option<EvalBounds>,
+    ) -> RelativeEval;
+}
+
+#[derive(Clone, Copy)]
+pub struct PositionEvaluator;
+
+impl EvaluatePosition for PositionEvaluator {
+    fn evaluate(
+        &self,
+        position: &Position,
+        _move_gen: impl GenerateMoves,
+        bounds: Option<EvalBounds>,
+    ) -> RelativeEval {
+        let to_move = position.state.to_move;
+        let phase_weight = calculate_phase_weight(position);
+
+        let material_and_psqt = self.evaluate_material_and_psqt(position, phase_weight);
+        let lazy_eval = AbsoluteEval::cp(material_and_psqt).to_relative(to_move);
+        if bounds.is_some_and(|bounds| bounds.is_far_outside(lazy_eval)) {
+            return lazy_eval;
+        }
+
+        let mobility = self.evaluate_mobility(position, phase_weight);
+        let pawns = side_pawns(position, Side::White, phase_weight)
+            - side_pawns(position, Side::Black, phase_weight);
+        AbsoluteEval::cp(material_and_psqt + mobility + pawns).to_relative(to_move)
+    }
+}
+
+impl PositionEvaluator {
+    /// Incremental terms, which only depend on where each piece is.
+    fn evaluate_material_and_psqt(&self, position: &Position, phase_weight: i32) -> i32 {
+        position.piece_locs().fold(0, |acc, (piece, side, square)| {
+            let (piece_val, bonus) = piece_material_and_psqt(piece, side, square, phase_weight);
+            let tot_val = piece_val + bonus;
+
+            if side == Side::White {
+                acc + tot_val
+            } else {
+                acc - tot_val
+            }
+        })
+    }
+
+    /// Squares attacked by each minor and major piece, not counting squares occupied by its own
+    /// side or attacked by enemy pawns. Needs attack generation for every piece, so it's
+    /// skipped by lazy eval, along with the pawn terms.
+    fn evaluate_mobility(&self, position: &Position, phase_weight: i32) -> i32 {
+        side_mobility(position, Side::White, phase_weight)
+            - side_mobility(position, Side::Black, phase_weight)
+    }
+
+    /// Full evaluation split into its terms for each side, for showing how an eval was reached.
+    /// Too slow to use in the search.
+    pub fn trace(&self, position: &Position) -> EvalTrace {
+        let phase_weight = calculate_phase_weight(position);
+
+        let mut material = TermScore::default();
+        let mut piece_squares = TermScore::default();
+        for (piece, side, square) in position.piece_locs() {
+            let (piece_val, bonus) = piece_material_and_psqt(piece, side, square, phase_weight);
+            // Both sides always have a king, so its value cancels out and would only hide the
+            // material each side has.
+            if piece != Piece::King {
+                *material.side_mut(side) += piece_val;
+            }
+            *piece_squares.side_mut(side) += bonus;
+        }
+
+        let mobility = TermScore {
+            white: side_mobility(position, Side::White, phase_weight),
+            black: side_mobility(position, Side::Black, phase_weight),
+        };
+
+        let pawns = TermScore {
+            white: side_pawns(position, Side::White, phase_weight),
+            black: side_pawns(position, Side::Black, phase_weight),
+        };
+
+        EvalTrace {
+            terms: vec![
+                (EvalTerm::Material, material),
+                (EvalTerm::PieceSquares, piece_squares),
+                (EvalTerm::Mobility, mobility),
+                (EvalTerm::Pawns, pawns),
+            ],
+            phase_weight,
+        }
+    }
+}
+
+/// Tapered value of a piece and its piece square bonus, from its own side's perspective.
+fn piece_material_and_psqt(
+    piece: Piece,
+    side: Side,
+    square: Square,
+    phase_weight: i32,
+) -> (i32, i32) {
+    // For black, we need to flip index in order to use correct value
+    let square = if side == Side::White {
+        square.flip()
+    } else {
+        square
+    };
+
+    let (mg_val, eg_val) = get_piece_value(piece);
+    let (mg_bonus, eg_bonus) = get_piece_square_bonus(piece, square);
+    (
+        taper(mg_val, eg_val, phase_weight),
+        taper(mg_bonus, eg_bonus, phase_weight),
+    )
+}
+
+/// Mobility of one side's minor and major pieces, see [`PositionEvaluator::evaluate_mobility`].
+fn side_mobility(position: &Position, side: Side, phase_weight: i32) -> i32 {
+    let occupancy = position.occupancy_bb();
+    let enemy = side.opposite_side();
+    let enemy_pawn_atks = position
+        .get_piece_bb(enemy, Piece::Pawn)
+        .squares()
+        .fold(BitBoard::empty(), |atks, square| {
+            atks | LEAPING_PIECES.gen_pawn_atks(square, enemy)
+        });
+    let mobility_area = !(position.get_side_bb(side) | enemy_pawn_atks);
+
+    [Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen]
+        .into_iter()
+        .flat_map(|piece| {
+            position
+                .get_piece_bb(side, piece)
+                .squares()
+                .map(move |square| (piece, square))
+        })
+        .map(|(piece, square)| {
+            let atks = match piece {
+                Piece::Knight => LEAPING_PIECES.gen_knight_moves(square),
+                _ => SLIDING_PIECES_MOVE_GEN.gen_moves(piece, square, occupancy),
+            };
+            let num_squares = i32::from((atks & mobility_area).num_squares_set());
+            let (mg_weight, eg_weight) = get_mobility_weight(piece);
+            taper(mg_weight, eg_weight, phase_weight) * num_squares
+        })
+        .sum()
+}
+
+/// Term of the evaluation, as reported by [`PositionEvaluator::trace`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EvalTerm {
+    Material,
+    PieceSquares,
+    Mobility,
+    /// Passed and candidate passed pawns.
+    Pawns,
+}
+
+impl EvalTerm {
+    pub fn name(self) -> &'static str {
+        match self {
+            EvalTerm::Material => "Material",
+            EvalTerm::PieceSquares => "Piece squares",
+            EvalTerm::Mobility => "Mobility",
+            EvalTerm::Pawns => "Pawns",
+        }
+    }
+}
+
+/// Centipawns a term is worth to each side, after tapering by the game phase.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TermScore {
+    pub white: i32,
+    pub black: i32,
+}
+
+impl TermScore {
+    /// From white's perspective, the way the term counts towards the eval.
+    pub fn total(&self) -> i32 {
+        self.white - self.black
+    }
+
+    fn side_mut(&mut self, side: Side) -> &mut i32 {
+        match side {
+            Side::White => &mut self.white,
+            Side::Black => &mut self.black,
+        }
+    }
+}
+
+/// Breakdown of a full evaluation, see [`PositionEvaluator::trace`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EvalTrace {
+    pub terms: Vec<(EvalTerm, TermScore)>,
+    /// How much of the middlegame is left, from 0 in a pawn endgame up to [`Self::MAX_PHASE`].
+    pub phase_weight: i32,
+}
+
+impl EvalTrace {
+    pub const MAX_PHASE: i32 = PHASE_WEIGHT_MAX;
+
+    /// Sum of the terms, which is the full evaluation.
+    pub fn total(&self) -> AbsoluteEval {
+        AbsoluteEval::cp(self.terms.iter().map(|(_, score)| score.total()).sum())
+    }
+}
+
+#[derive(Tabled)]
+struct EvalTraceRow {
+    #[tabled(rename = "Term")]
+    term: &'static str,
+    #[tabled(rename = "White")]
+    white: i32,
+    #[tabled(rename = "Black")]
+    black: i32,
+    #[tabled(rename = "Total")]
+    total: i32,
+}
+
+/// A table of the terms in centipawns, followed by the phase and the total from white's
+/// perspective.
+impl Display for EvalTrace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let total_score = TermScore {
+            white: self.terms.iter().map(|(_, score)| score.white).sum(),
+            black: self.terms.iter().map(|(_, score)| score.black).sum(),
+        };
+        let rows = self
+            .terms
+            .iter()
+            .map(|&(term, score)| EvalTraceRow {
+                term: term.name(),
+                white: score.white,
+                black: score.black,
+                total: score.total(),
+            })
+            .chain([EvalTraceRow {
+                term: "Total",
+                white: total_score.white,
+                black: total_score.black,
+                total: total_score.total(),
+            }]);
+        writeln!(f, "{}", Table::new(rows))?;
+        writeln!(f, "Phase: {}/{}", self.phase_weight, Self::MAX_PHASE)?;
+        write!(f, "Total: {} (white side)", self.total())
+    }
+}
+
+/// Interpolate between a middlegame and endgame value by the phase weight.
+fn taper(mg_val: i32, eg_val: i32, phase_weight: i32) -> i32 {
+    (mg_val * phase_weight + eg_val * (PHASE_WEIGHT_MAX - phase_weight)) / PHASE_WEIGHT_MAX
+}
+
+/// Get mobility value per attacked square, both middlegame and endgame.
+fn get_mobility_weight(piece: Piece) -> (i32, i32) {
+    match piece {
+        Piece::Knight => (2, 2),
+        Piece::Bishop => (3, 3),
+        Piece::Rook => (1, 2),
+        Piece::Queen => (1, 1),
+        Piece::Pawn | Piece::King => (0, 0),
+    }
+}
+
+/// Get piece value, both middlegame and endgame.
+fn get_piece_value(piece: Piece) -> (i32, i32) {
+    match piece {
+        Piece::Pawn => (82, 94),
+        Piece::Knight => (337, 281),
+        Piece::Bishop => (365, 297),
+        Piece::Rook => (477, 512),
+        Piece::Queen => (1025, 936),
+        Piece::King => (2000, 2000), // Don't use i32::MAX in case of overflows
+    }
+}
+
+const PHASE_WEIGHT_MAX: i32 = 24;
+fn calculate_phase_weight(position: &Position) -> i32 {
+    let val = position
+        .piece_locs()
+        .fold(0, |acc, (piece, _, _)| acc + piece_phase_weight(piece));
+    if val > PHASE_WEIGHT_MAX {
+        PHASE_WEIGHT_MAX
+    } else {
+        val
+    }
+}
+
+fn piece_phase_weight(piece: Piece) -> i32 {
+    match piece {
+        Piece::Pawn => 0,
+        Piece::Knight | Piece::Bishop => 1,
+        Piece::Rook => 2,
+        Piece::Queen => 4,
+        Piece::King => 0,
+    }
+}
+
+/// Values source: https://www.chessprogramming.org/PeSTO%27s_Evaluation_Function
+#[rustfmt::skip]
+const PST_MG: [i32; 384] = [
+    // Pawn (0-63)
+      0,   0,   0,   0,   0,   0,  0,   0,
+     98, 134,  61,  95,  68, 126, 34, -11,
+     -6,   7,  26,  31,  65,  56, 25, -20,
+    -14,  13,   6,  21,  23,  12, 17, -23,
+    -27,  -2,  -5,  12,  17,   6, 10, -25,
+    -26,  -4,  -4, -10,   3,   3, 33, -12,
+    -35,  -1, -20, -23, -15,  24, 38, -22,
+      0,   0,   0,   0,   0,   0,  0,   0,
+
+    // Knight (64-127)
+    -167, -89, -34, -49,  61, -97, -15, -107,
+     -73, -41,  72,  36,  23,  62,   7,  -17,
+     -47,  60,  37,  65,  84, 129,  73,   44,
+      -9,  17,  19,  53,  37,  69,  18,   22,
+     -13,   4,  16,  13,  28,  19,  21,   -8,
+     -23,  -9,  12,  10,  19,  17,  25,  -16,
+     -29, -53, -12,  -3,  -1,  18, -14,  -19,
+    -105, -21, -58, -33, -17, -28, -19,  -23,
+
+    // Bishop (128-191)
+    -29,   4, -82, -37, -25, -42,   7,  -8,
+    -26,  16, -18, -13,  30,  59,  18, -47,
+    -16,  37,  43,  40,  35,  50,  37,  -2,
+     -4,   5,  19,  50,  37,  37,   7,  -2,
+     -6,  13,  13,  26,  34,  12,  10,   4,
+      0,  15,  15,  15,  14,  27,  18,  10,
+      4,  15,  16,   0,   7,  21,  33,   1,
+    -33,  -3, -14, -21, -13, -12, -39, -21,
+
+    // Rook (192-255)
+       32,  42,  32,  51, 63,  9,  31,  43,
+       27,  32,  58,  62, 80, 67,  26,  44,
+       -5,  19,  26,  36, 17, 45,  61,  16,
+      -24, -11,   7,  26, 24, 35,  -8, -20,
+      -36, -26, -12,  -1,  9, -7,   6, -23,
+      -45, -25, -16, -17,  3,  0,  -5, -33,
+      -44, -16, -20,  -9, -1, 11,  -6, -71,
+      -19, -13,   1,  17, 16,  7, -37, -26,
+
+    // Queen (256-319)
+    -28,   0,  29,  12,  59,  44,  43,  45,
+    -24, -39,  -5,   1, -16,  57,  28,  54,
+    -13, -17,   7,   8,  29,  56,  47,  57,
+    -27, -27, -16, -16,  -1,  17,  -2,   1,
+     -9, -26,  -9, -10,  -2,  -4,   3,  -3,
+    -14,   2, -11,  -2,  -5,   2,  14,   5,
+    -35,  -8,  11,   2,   8,  15,  -3,   1,
+     -1, -18,  -9,  10, -15, -25, -31, -50,
+
+    // King (320-383)
+    -65,  23,  16, -15, -56, -34,   2,  13,
+     29,  -1, -20,  -7,  -8,  -4, -38, -29,
+     -9,  24,   2, -16, -20,   6,  22, -22,
+    -17, -20, -12, -27, -30, -25, -14, -36,
+    -49,  -1, -27, -39, -46, -44, -33, -51,
+    -14, -14, -22, -46, -44, -30, -15, -27,
+      1,   7,  -8, -64, -43, -16,   9,   8,
+    -15,  36,  12, -54,   8, -28,  24,  14,
+];
+
+#[rustfmt::skip]
+const PST_EG: [i32; 384] = [
+    // Pawn (0-63)
+      0,   0,   0,   0,   0,   0,   0,   0,
+    178, 173, 158, 134, 147, 132, 165, 187,
+     94, 100,  85,  67,  56,  53,  82,  84,
+     32,  24,  13,   5,  -2,   4,  17,  17,
+     13,   9,  -3,  -7,  -7,  -8,   3,  -1,
+      4,   7,  -6,   1,   0,  -5,  -1,  -8,
+     13,   8,   8,  10,  13,   0,   2,  -7,
+      0,   0,   0,   0,   0,   0,   0,   0,
+
+    // Knight (64-127)
+    -58, -38, -13, -28, -31, -27, -63, -99,
+    -25,  -8, -25,  -2,  -9, -25, -24, -52,
+    -24, -20,  10,   9,  -1,  -9, -19, -41,
+    -17,   3,  22,  22,  22,  11,   8, -18,
+    -18,  -6,  16,  25,  16,  17,   4, -18,
+    -23,  -3,  -1,  15,  10,  -3, -20, -22,
+    -42, -20, -10,  -5,  -2, -20, -23, -44,
+    -29, -51, -23, -15, -22, -18, -50, -64,
+
+    // Bishop (128-191)
+    -14, -21, -11,  -8, -7,  -9, -17, -24,
+     -8,  -4,   7, -12, -3, -13,  -4, -14,
+      2,  -8,   0,  -1, -2,   6,   0,   4,
+     -3,   9,  12,   9, 14,  10,   3,   2,
+     -6,   3,  13,  19,  7,  10,  -3,  -9,
+    -12,  -3,   8,  10, 13,   3,  -7, -15,
+    -14, -18,  -7,  -1,  4,  -9, -15, -27,
+    -23,  -9, -23,  -5, -9, -16,  -5, -17,
+
+    // Rook (192-255)
+    13, 10, 18, 15, 12,  12,   8,   5,
+    11, 13, 13, 11, -3,   3,   8,   3,
+     7,  7,  7,  5,  4,  -3,  -5,  -3,
+     4,  3, 13,  1,  2,   1,  -1,   2,
+     3,  5,  8,  4, -5,  -6,  -8, -11,
+    -4,  0, -5, -1, -7, -12,  -8, -16,
+    -6, -6,  0,  2, -9,  -9, -11,  -3,
+    -9,  2,  3, -1, -5, -13,   4, -20,
+
+    // Queen (256-319)
+     -9,  22,  22,  27,  27,  19,  10,  20,
+    -17,  20,  32,  41,  58,  25,  30,   0,
+    -20,   6,   9,  49,  47,  35,  19,   9,
+      3,  22,  24,  45,  57,  40,  57,  36,
+    -18,  28,  19,  47,  31,  34,  39,  23,
+    -16, -27,  15,   6,   9,  17,  10,   5,
+    -22, -23, -30, -16, -16, -23, -36, -32,
+    -33, -28, -22, -43,  -5, -32, -20, -41,
+
+    // King (320-383)
+    -74, -35, -18, -18, -11,  15,   4, -17,
+    -12,  17,  14,  17,  17,  38,  23,  11,
+     10,  17,  23,  15,  20,  45,  44,  13,
+     -8,  22,  24,  27,  26,  33,  26,   3,
+    -18,  -4,  21,  24,  27,  23,   9, -11,
+    -19,  -3,  11,  21,  23,  16,   7,  -9,
+    -27, -11,   4,  13,  14,   4,  -5, -17,
+    -53, -34, -21, -11, -28, -14, -24, -43,
+];
+
+fn get_piece_square_bonus(piece: Piece, square: Square) -> (i32, i32) {
+    let idx = (piece as usize) * 64 + (square as usize);
+    (PST_MG[idx], PST_EG[idx])
+}
+
+pub static POSITION_EVALUATOR: PositionEvaluator = PositionEvaluator {};
+
+#[cfg(test)]
+mod tests {
+    use crate::move_gen::MOVE_GEN;
+
+    use super::*;
+
+    use test_case::test_case;
+    use testresult::TestResult;
+
+    #[test_case(RelativeEval(10), RelativeEval(-10))]
+    #[test_case(RelativeEval::mate_in(0), RelativeEval::mate_in(1))]
+    #[test_case(RelativeEval::mate_in(1), RelativeEval::mate_in(-1))]
+    #[test_case(RelativeEval::mate_in(-1), RelativeEval::mate_in(2))]
+    #[test_case(RelativeEval::mate_in(4), RelativeEval::mate_in(-4))]
+    #[test_case(RelativeEval::mate_in(-7), RelativeEval::mate_in(8))]
+    #[test_case(RelativeEval::DRAW, RelativeEval::DRAW)]
+    fn test_eval_flip(eval_input: RelativeEval, eval_want: RelativeEval) {
+        let eval_got = eval_input.flip();
+
+        assert_eq!(eval_got, eval_want);
+    }
+
+    #[test]
+    fn test_eval_ord() {
+        let evals_order_want = vec![
+            RelativeEval::mate_in(0),
+            RelativeEval::mate_in(-1),
+            RelativeEval::mate_in(-2),
+            RelativeEval(-20),
+            RelativeEval(-1),
+            RelativeEval::DRAW,
+            RelativeEval(1),
+            RelativeEval(20),
+            RelativeEval::mate_in(2),
+            RelativeEval::mate_in(1),
+        ];
+        let mut evals = vec![
+            RelativeEval::mate_in(0),
+            RelativeEval::mate_in(1),
+            RelativeEval::mate_in(-1),
+            RelativeEval::mate_in(2),
+            RelativeEval::mate_in(-2),
+            RelativeEval(-20),
+            RelativeEval(-1),
+            RelativeEval::DRAW,
+            RelativeEval(1),
+            RelativeEval(20),
+        ];
+
+        evals.sort();
+
+        assert_eq!(evals, evals_order_want);
+    }
+
+    #[test]
+    fn test_obvious_eval() -> TestResult {
+        let position = Position::from_fen("2k5/Q7/8/8/8/8/8/7K w - - 0 1")?;
+        let move_gen = MOVE_GEN;
+        let eval = POSITION_EVALUATOR.evaluate(&position, move_gen, None);
+
+        // Should be at least 5 pawns better than the opponent
+        assert!(eval > RelativeEval(500));
+
+        Ok(())
+    }
+
+    #[test_case(RelativeEval::mate_in(0), Some(0) ; "0")]
+    #[test_case(RelativeEval::mate_in(1), Some(1) ; "1")]
+    #[test_case(RelativeEval::mate_in(-1), Some(-1) ; "neg 1")]
+    #[test_case(RelativeEval::mate_in(2), Some(2) ; "2")]
+    #[test_case(RelativeEval::mate_in(-2), Some(-2) ; "neg 2")]
+    #[test_case(RelativeEval::mate_in(-7), Some(-7) ; "neg 7")]
+    #[test_case(RelativeEval::mate_in(10), Some(10) ; "10")]
+    #[test_case(RelativeEval::mate_in(101), Some(101) ; "101")]
+    #[test_case(RelativeEval::mate_in(101), Some(101) ; "neg 101")]
+    #[test_case(RelativeEval::DRAW, None)]
+    #[test_case(RelativeEval(10), None)]
+    fn test_is_mate(eval: RelativeEval, is_mate_want: Option<i8>) {
+        let is_mate_got = eval.is_mate();
+
+        assert_eq!(is_mate_got, is_mate_want);
+    }
+
+    #[test_case(RelativeEval::MAX, 1, RelativeEval::MAX ; "max saturates")]
+    #[test_case(RelativeEval::MIN, -1, RelativeEval::MIN ; "min saturates")]
+    #[test_case(RelativeEval::MAX, i32::MAX, RelativeEval::MAX ; "no overflow")]
+    #[test_case(RelativeEval::MIN, i32::MIN, RelativeEval::MIN ; "no underflow")]
+    #[test_case(RelativeEval::DRAW, 50, RelativeEval::cp(50) ; "draw")]
+    #[test_case(RelativeEval::mate_in(3), 1, RelativeEval::mate_in(2) ; "towards mate")]
+    #[test_case(RelativeEval::cp(RelativeEval::MAX_CP - 10), 200, RelativeEval::cp(RelativeEval::MAX_CP) ; "cp plus margin stays cp")]
+    #[test_case(RelativeEval::cp(-RelativeEval::MAX_CP + 10), -200, RelativeEval::cp(-RelativeEval::MAX_CP) ; "cp minus margin stays cp")]
+    fn test_eval_add(eval: RelativeEval, rhs: i32, eval_want: RelativeEval) {
+        assert_eq!(eval + rhs, eval_want);
+        assert_eq!(eval - rhs.saturating_neg(), eval_want);
+        assert_eq!((eval + rhs).is_mate().is_some(), eval.is_mate().is_some());
+    }
+
+    #[test_case(i32::MAX, RelativeEval::cp(RelativeEval::MAX_CP) ; "clamped max")]
+    #[test_case(i32::MIN, RelativeEval::cp(-RelativeEval::MAX_CP) ; "clamped min")]
+    #[test_case(-35, RelativeEval(-35) ; "unchanged")]
+    fn test_eval_cp(centipawns: i32, eval_want: RelativeEval) {
+        let eval = RelativeEval::cp(centipawns);
+        assert_eq!(eval, eval_want);
+        assert_eq!(eval.is_mate(), None);
+        assert!(RelativeEval::MIN < eval && eval < RelativeEval::MAX);
+    }
+
+    #[test_case(RelativeEval::cp(35), Side::White, AbsoluteEval::cp(35) ; "white cp")]
+    #[test_case(RelativeEval::cp(35), Side::Black, AbsoluteEval::cp(-35) ; "black cp")]
+    #[test_case(RelativeEval::mate_in(2), Side::Black, RelativeEval::mate_in(-2).to_absolute(Side::White) ; "black mates")]
+    #[test_case(RelativeEval::MIN, Side::Black, AbsoluteEval(30_000) ; "black is mated")]
+    fn test_eval_to_absolute(eval: RelativeEval, to_move: Side, absolute_want: AbsoluteEval) {
+        let absolute = eval.to_absolute(to_move);
+        assert_eq!(absolute, absolute_want);
+        assert_eq!(absolute.to_relative(to_move), eval);
+    }
+
+    #[test]
+    fn test_evaluate_is_relative_to_side_to_move() -> TestResult {
+        let white_to_move = Position::from_fen("2k5/Q7/8/8/8/8/8/7K w - - 0 1")?;
+        let black_to_move = Position::from_fen("2k5/Q7/8/8/8/8/8/7K b - - 0 1")?;
+        let white_eval = POSITION_EVALUATOR.evaluate(&white_to_move, MOVE_GEN, None);
+        let black_eval = POSITION_EVALUATOR.evaluate(&black_to_move, MOVE_GEN, None);
+
+        assert_eq!(
+            white_eval.to_absolute(Side::White),
+            black_eval.to_absolute(Side::Black)
+        );
+        assert!(black_eval < RelativeEval::DRAW);
+        Ok(())
+    }
+
+    #[test]
+    fn test_mobility_rewards_active_pieces() -> TestResult {
+        // Same material and piece squares for the bishops, but white's is blocked in by its own
+        // pawns.
+        let position = Position::from_fen("4k3/8/8/3p4/2p5/1P6/B7/4K2b w - - 0 1")?;
+        let phase_weight = calculate_phase_weight(&position);
+
+        assert!(POSITION_EVALUATOR.evaluate_mobility(&position, phase_weight) < 0);
+        Ok(())
+    }
+
+    #[test_case(RelativeEval::cp(-10_000), RelativeEval::cp(10_000), false ; "wide window")]
+    #[test_case(RelativeEval::cp(1_000), RelativeEval::cp(1_001), true ; "far below alpha")]
+    #[test_case(RelativeEval::cp(-1_001), RelativeEval::cp(-1_000), true ; "far above beta")]
+    fn test_lazy_eval(alpha: RelativeEval, beta: RelativeEval, lazy_want: bool) -> TestResult {
+        let position = Position::from_fen(
+            "r1bqkbnr/pppp1ppp/2n5/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R b KQkq - 3 3",
+        )?;
+        let bounds = EvalBounds {
+            alpha,
+            beta,
+            lazy_margin: 300,
+        };
+        let phase_weight = calculate_phase_weight(&position);
+        let lazy_eval = AbsoluteEval::cp(
+            POSITION_EVALUATOR.evaluate_material_and_psqt(&position, phase_weight),
+        )
+        .to_relative(Side::Black);
+        let full_eval = POSITION_EVALUATOR.evaluate(&position, MOVE_GEN, None);
+        assert_ne!(lazy_eval, full_eval);
+
+        let eval = POSITION_EVALUATOR.evaluate(&position, MOVE_GEN, Some(bounds));
+
+        assert_eq!(eval, if lazy_want { lazy_eval } else { full_eval });
+        Ok(())
+    }
+
+    #[test_case("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1" ; "start")]
+    #[test_case("r1bqkbnr/pppp1ppp/2n5/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R b KQkq - 3 3" ; "black to move")]
+    #[test_case("4k3/8/8/3p4/2p5/1P6/B7/4K2b w - - 0 1" ; "endgame")]
+    fn test_trace_matches_evaluate(fen: &str) -> TestResult {
+        let position = Position::from_fen(fen)?;
+        let trace = POSITION_EVALUATOR.trace(&position);
+        let eval = POSITION_EVALUATOR.evaluate(&position, MOVE_GEN, None);
+
+        assert_eq!(trace.total(), eval.to_absolute(position.state.to_move));
+        Ok(())
+    }
+
+    #[test]
+    fn test_trace() -> TestResult {
+        // White is up a knight, with both kings on their starting squares.
+        let position = Position::from_fen("4k3/8/8/8/8/8/8/1N2K3 w - - 0 1")?;
+        let trace = POSITION_EVALUATOR.trace(&position);
+
+        assert_eq!(trace.phase_weight, 1);
+        let (term, material) = trace.terms[0];
+        assert_eq!(term, EvalTerm::Material);
+        assert_eq!(
+            material,
+            TermScore {
+                white: taper(337, 281, 1),
+                black: 0
+            }
+        );
+
+        let shown = trace.to_string();
+        assert!(shown.contains("Piece squares"));
+        assert!(shown.contains("Phase: 1/24"));
+        assert!(shown.ends_with(&format!("Total: {} (white side)", trace.total())));
+        Ok(())
+    }
+
+    #[test_case(RelativeEval::cp(35), "cp 35" ; "positive cp")]
+    #[test_case(RelativeEval::cp(-120), "cp -120" ; "negative cp")]
+    #[test_case(RelativeEval::DRAW, "cp 0" ; "draw")]
+    #[test_case(RelativeEval::MAX, "mate 1" ; "max")]
+    #[test_case(RelativeEval::MIN, "mate 0" ; "min")]
+    #[test_case(RelativeEval::mate_in(-2), "mate -2" ; "mated")]
+    fn test_eval_display(eval: RelativeEval, want: &str) {
+        assert_eq!(eval.to_string(), want);
+    }
+}
@@ -0,0 +1,159 @@
+use crate::bitboard::Square;
+use crate::position::{Piece, Position, Side};
+
+use super::masks::{ADJACENT_FILES, FRONT_FILE_MASKS, PASSED_PAWN_MASKS};
+use super::taper;
+
+/// Passed pawn bonus by rank from the pawn's side's perspective, middlegame and endgame.
+const PASSED_PAWN_BONUS: [(i32, i32); 8] = [
+    (0, 0),
+    (5, 10),
+    (5, 15),
+    (10, 25),
+    (20, 40),
+    (35, 65),
+    (55, 95),
+    (0, 0),
+];
+/// Candidate passed pawn bonus by rank, like [`PASSED_PAWN_BONUS`].
+const CANDIDATE_PAWN_BONUS: [(i32, i32); 8] = [
+    (0, 0),
+    (1, 2),
+    (1, 3),
+    (3, 5),
+    (5, 9),
+    (8, 15),
+    (0, 0),
+    (0, 0),
+];
+/// Endgame bonus per square the enemy king is away from the square in front of a passed pawn,
+/// and penalty per square the own king is away. Scaled up the further the pawn has advanced.
+const ENEMY_KING_DISTANCE_WEIGHT: i32 = 4;
+const OWN_KING_DISTANCE_WEIGHT: i32 = 2;
+/// Kings only matter for passed pawns from this rank on.
+const KING_PROXIMITY_MIN_RANK: usize = 3;
+
+/// Passed and candidate passed pawns of one side, tapered by the phase.
+///
+/// A passed pawn has no enemy pawns in front of it on its own or the adjacent files. Its bonus
+/// grows with its rank, is halved when an enemy piece blocks it, and in the endgame depends on
+/// which king is closer to the square in front of it.
+///
+/// A candidate passed pawn isn't passed yet, but has no pawns in front of it on its own file,
+/// and at least as many friendly pawns beside or behind it on the adjacent files as there are
+/// enemy pawns in front of it on them. So it can become passed by trading pawns.
+/// Source: https://www.chessprogramming.org/Passed_Pawn
+/// Source: https://www.chessprogramming.org/Candidate_Passed_Pawn
+pub(super) fn side_pawns(position: &Position, side: Side, phase_weight: i32) -> i32 {
+    let enemy = side.opposite_side();
+    let own_pawns = position.get_piece_bb(side, Piece::Pawn);
+    let enemy_pawns = position.get_piece_bb(enemy, Piece::Pawn);
+    let own_king = position.get_piece_bb(side, Piece::King).get_lsb();
+    let enemy_king = position.get_piece_bb(enemy, Piece::King).get_lsb();
+
+    let (mut mg_score, mut eg_score) = (0, 0);
+    for square in own_pawns.squares() {
+        let (rank, file) = square.to_rank_file();
+        let relative_rank = usize::from(match side {
+            Side::White => rank,
+            Side::Black => 7 - rank,
+        });
+        let passed_mask = PASSED_PAWN_MASKS[side as usize][square as usize];
+        let front_file_mask = FRONT_FILE_MASKS[side as usize][square as usize];
+
+        // Only the front pawn of doubled pawns counts as passed.
+        if (passed_mask & enemy_pawns).is_empty()
+            && (front_file_mask & own_pawns).is_empty()
+            && let Some(stop_square) = stop_square(square, side)
+        {
+            let (mut mg_bonus, mut eg_bonus) = PASSED_PAWN_BONUS[relative_rank];
+            if position.get_side_bb(enemy).is_square_set(stop_square) {
+                mg_bonus /= 2;
+                eg_bonus /= 2;
+            }
+            if relative_rank >= KING_PROXIMITY_MIN_RANK {
+                let weight = (relative_rank - KING_PROXIMITY_MIN_RANK + 1) as i32;
+                eg_bonus += weight
+                    * (ENEMY_KING_DISTANCE_WEIGHT * distance(enemy_king, stop_square)
+                        - OWN_KING_DISTANCE_WEIGHT * distance(own_king, stop_square));
+            }
+            mg_score += mg_bonus;
+            eg_score += eg_bonus;
+        } else if (front_file_mask & (own_pawns | enemy_pawns)).is_empty() {
+            let sentries = passed_mask & enemy_pawns;
+            let supporters = own_pawns & ADJACENT_FILES[usize::from(file)] & !passed_mask;
+            if supporters.num_squares_set() >= sentries.num_squares_set() {
+                let (mg_bonus, eg_bonus) = CANDIDATE_PAWN_BONUS[relative_rank];
+                mg_score += mg_bonus;
+                eg_score += eg_bonus;
+            }
+        }
+    }
+    taper(mg_score, eg_score, phase_weight)
+}
+
+/// Square in front of a pawn, or none if it's on the last rank.
+fn stop_square(square: Square, side: Side) -> Option<Square> {
+    let idx = match side {
+        Side::White => square as u8 + 8,
+        Side::Black => (square as u8).checked_sub(8)?,
+    };
+    Square::from_repr(idx)
+}
+
+/// Number of king moves between two squares.
+fn distance(square: Square, other: Square) -> i32 {
+    let (rank, file) = square.to_rank_file();
+    let (other_rank, other_file) = other.to_rank_file();
+    i32::from(rank.abs_diff(other_rank).max(file.abs_diff(other_file)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+    use testresult::TestResult;
+
+    /// White's king is 2 squares from d6 and black's 5.
+    const PASSED_PAWN: &str = "8/8/8/3P4/4K3/8/8/k7 w - - 0 1";
+
+    /// Pawn score of the side to move in an endgame.
+    fn endgame_pawns(fen: &str) -> TestResult<i32> {
+        let position = Position::from_fen(fen)?;
+        Ok(side_pawns(&position, position.state.to_move, 0))
+    }
+
+    #[test]
+    fn test_passed_pawn() -> TestResult {
+        // Rank bonus of 40, plus 2 * (4 * 5 - 2 * 2) for the kings.
+        assert_eq!(endgame_pawns(PASSED_PAWN)?, 72);
+        Ok(())
+    }
+
+    #[test_case("8/8/3n4/3P4/4K3/8/8/k7 w - - 0 1" ; "blockaded")]
+    #[test_case("8/8/8/3P4/4K3/3k4/8/8 w - - 0 1" ; "enemy king closer")]
+    #[test_case("8/8/8/3P4/8/8/8/k6K w - - 0 1" ; "own king further")]
+    #[test_case("8/8/8/8/3PK3/8/8/k7 w - - 0 1" ; "less advanced")]
+    fn test_passed_pawn_worth_less(fen: &str) -> TestResult {
+        assert!(endgame_pawns(fen)? < endgame_pawns(PASSED_PAWN)?);
+        Ok(())
+    }
+
+    #[test_case("8/4p3/8/3P4/4K3/8/8/k7 w - - 0 1", 0 ; "stopped by adjacent pawn")]
+    #[test_case("8/3p4/8/3P4/4K3/8/8/k7 w - - 0 1", 0 ; "blocked by pawn on own file")]
+    #[test_case("8/8/8/8/3P4/3P4/8/k6K w - - 0 1", 33 ; "only front doubled pawn passed")]
+    #[test_case("8/8/8/6p1/8/8/1P6/k6K b - - 0 1", 25 ; "black")]
+    fn test_passed_pawns(fen: &str, want: i32) -> TestResult {
+        assert_eq!(endgame_pawns(fen)?, want);
+        Ok(())
+    }
+
+    // The e3 pawn is blocked by the e6 pawn, so only the d4 pawn can be a candidate.
+    #[test_case("8/8/4p3/8/3P4/4P3/8/k6K w - - 0 1", 5 ; "supported")]
+    #[test_case("8/8/4p3/8/3P4/8/8/k6K w - - 0 1", 0 ; "unsupported")]
+    #[test_case("8/8/2p1p3/8/3P4/4P3/8/k6K w - - 0 1", 0 ; "more sentries than supporters")]
+    fn test_candidate_passed_pawn(fen: &str, want: i32) -> TestResult {
+        assert_eq!(endgame_pawns(fen)?, want);
+        Ok(())
+    }
+}
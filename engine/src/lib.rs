@@ -1,23 +1,84 @@
 mod algebraic_notation;
+mod audit;
 mod bitboard;
+#[cfg(feature = "search")]
+mod clock;
+mod epd;
 mod evaluation;
 mod metrics;
+mod move_classification;
 mod move_gen;
+#[cfg(feature = "search")]
 mod perft;
+mod pgn;
 mod position;
+pub mod prelude;
+#[cfg(feature = "search")]
 mod search;
+mod see;
+#[cfg(any(test, feature = "testing"))]
+#[doc(hidden)]
+pub mod testing;
+#[cfg(feature = "search")]
 mod transposition_table;
 
 pub const NAME: &str = "Flying Fish";
 pub const AUTHOR: &str = "Will Forman";
 
+#[doc(hidden)]
 pub const TRACING_TARGET_SEARCH: &str = "chess::search";
 
-pub use algebraic_notation::move_to_algebraic_notation;
-pub use bitboard::Square;
-pub use evaluation::{Eval, EvaluatePosition, POSITION_EVALUATOR};
-pub use move_gen::{GenerateMoves, MOVE_GEN, MoveGen};
+// The stable API, see `prelude`.
+pub use bitboard::{BitBoard, ParseSquareError, Square};
+pub use evaluation::{
+    AbsoluteEval, Eval, EvalBounds, EvaluatePosition, POSITION_EVALUATOR, RelativeEval,
+};
+pub use move_gen::{
+    GenerateMoves, MAX_MOVES, MOVE_GEN, MoveGen, MoveList, attacks_from, attacks_to_by,
+};
+pub use position::{
+    DrawReason, FenParseError, GameResult, Move, Piece, PieceDelta, Position, PositionError, Side,
+    ZobristHash,
+};
+#[cfg(feature = "search")]
+pub use search::{
+    InfoCallback, MoveFilter, PonderHit, SearchError, SearchInfo, SearchParams, SearchResultInfo,
+    SearchSummary, SearchTuning, search,
+};
+#[cfg(feature = "search")]
+pub use transposition_table::TranspositionTable;
+
+#[doc(hidden)]
+pub use algebraic_notation::{
+    AlgebraicNotationError, line_to_san, move_to_algebraic_notation, san_to_move,
+};
+#[doc(hidden)]
+pub use audit::{AuditError, audit_game};
+#[cfg(feature = "search")]
+#[doc(hidden)]
+pub use clock::{Clock, MockClock, StdClock};
+#[doc(hidden)]
+pub use epd::{EpdError, EpdRecord, parse_epd_record};
+#[doc(hidden)]
+pub use evaluation::{EvalTerm, EvalTrace, TermScore};
+#[doc(hidden)]
+pub use move_classification::{MoveClass, classify_move};
+#[doc(hidden)]
+pub use move_gen::{
+    GenerateSlidingMoves, SLIDING_PIECES_BACKEND,
+    hyperbola_quintessence::SLIDING_PIECES_MOVE_GEN as HYPERBOLA_QUINTESSENCE,
+    magic_bitboard::MAGIC_BITBOARD,
+};
+#[cfg(feature = "search")]
+#[doc(hidden)]
 pub use perft::{PerftDepthResult, PerftResult, perft, perft_full};
-pub use position::{Move, Piece, Position, PositionError, Side, ZobristHash};
-pub use search::{SearchError, SearchParams, SearchResultInfo, search};
-pub use transposition_table::{EvalType, TranspositionTable};
+#[doc(hidden)]
+pub use pgn::{PgnError, PgnGame, PgnResult, parse_pgn};
+#[cfg(feature = "search")]
+#[doc(hidden)]
+pub use search::{MateSolverError, search_with_clock, solve_mate};
+#[doc(hidden)]
+pub use see::{StaticExchange, static_exchange_eval};
+#[cfg(feature = "search")]
+#[doc(hidden)]
+pub use transposition_table::{EvalType, TTEntryView};
@@ -0,0 +1,203 @@
+use std::fmt;
+
+use crate::bitboard::Square;
+use crate::move_gen::GenerateMoves;
+use crate::position::{Move, Piece, Position, Side};
+use crate::see::static_exchange_eval;
+
+/// Something a move does, in the terms a person would use to describe it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoveClass {
+    /// Puts the opponent in check.
+    Check,
+    Capture(Piece),
+    /// Wins this many centipawns of material according to static exchange evaluation.
+    WinsMaterial(i32),
+    /// Moves a knight or bishop off its starting square.
+    Develops,
+    Castles,
+    Promotes(Piece),
+    /// Leaves a pawn with no opposing pawns in front of it on its own or adjacent files, which
+    /// it didn't have before.
+    CreatesPassedPawn,
+    /// The moved piece attacks this piece, and could capture it next move without losing
+    /// material.
+    Threatens(Piece),
+}
+
+impl fmt::Display for MoveClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MoveClass::Check => write!(f, "gives check"),
+            MoveClass::Capture(piece) => write!(f, "captures a {}", piece_name(*piece)),
+            MoveClass::WinsMaterial(centipawns) => {
+                write!(f, "wins {} centipawns of material", centipawns)
+            }
+            MoveClass::Develops => write!(f, "develops a piece"),
+            MoveClass::Castles => write!(f, "castles"),
+            MoveClass::Promotes(piece) => write!(f, "promotes to a {}", piece_name(*piece)),
+            MoveClass::CreatesPassedPawn => write!(f, "creates a passed pawn"),
+            MoveClass::Threatens(piece) => write!(f, "threatens a {}", piece_name(*piece)),
+        }
+    }
+}
+
+fn piece_name(piece: Piece) -> String {
+    piece.to_string().to_lowercase()
+}
+
+/// Classifies a legal move in the position it's played in. Classes are returned in the order
+/// they're declared in [`MoveClass`].
+pub fn classify_move(
+    position: &Position,
+    mve: Move,
+    move_gen: impl GenerateMoves,
+) -> Vec<MoveClass> {
+    let side = position.state.to_move;
+    let Some(moved_piece) = position.is_piece_at(mve.src, side) else {
+        return vec![];
+    };
+    let mut classes = vec![];
+
+    let mut after_move = position.clone();
+    after_move.make_move(mve);
+    let gives_check = !move_gen.gen_checkers(&after_move).is_empty();
+    if gives_check {
+        classes.push(MoveClass::Check);
+    }
+
+    if position.is_capture(mve) {
+        let captured_piece = position
+            .is_piece_at(mve.dest, side.opposite_side())
+            // Otherwise it's en passant.
+            .unwrap_or(Piece::Pawn);
+        classes.push(MoveClass::Capture(captured_piece));
+        if let Ok(exchange) = static_exchange_eval(position, mve)
+            && exchange.value > 0
+        {
+            classes.push(MoveClass::WinsMaterial(exchange.value));
+        }
+    }
+
+    let (src_rank, src_file) = mve.src.to_rank_file();
+    let (_, dest_file) = mve.dest.to_rank_file();
+    let back_rank = match side {
+        Side::White => 0,
+        Side::Black => 7,
+    };
+    let is_minor_piece_start = match moved_piece {
+        Piece::Knight => src_file == 1 || src_file == 6,
+        Piece::Bishop => src_file == 2 || src_file == 5,
+        _ => false,
+    };
+    if is_minor_piece_start && src_rank == back_rank {
+        classes.push(MoveClass::Develops);
+    }
+
+    if moved_piece == Piece::King && src_file.abs_diff(dest_file) == 2 {
+        classes.push(MoveClass::Castles);
+    }
+
+    if let Some(promotion) = mve.promotion {
+        classes.push(MoveClass::Promotes(promotion));
+    }
+
+    let passed_pawns_before = passed_pawns(position, side);
+    let creates_passed_pawn = passed_pawns(&after_move, side).into_iter().any(|pawn| {
+        let pawn_before = if pawn == mve.dest { mve.src } else { pawn };
+        !passed_pawns_before.contains(&pawn_before)
+    });
+    if creates_passed_pawn {
+        classes.push(MoveClass::CreatesPassedPawn);
+    }
+
+    // Look for captures the moved piece could make if it were its turn again. This doesn't
+    // make sense if the opponent is in check, since they have to deal with that first.
+    if !gives_check {
+        after_move.make_null_move();
+        let mut threatened: Vec<Piece> = move_gen
+            .gen_moves(&after_move)
+            .into_iter()
+            .filter(|threat| threat.src == mve.dest)
+            .filter_map(|threat| {
+                let victim = after_move.is_piece_at(threat.dest, side.opposite_side())?;
+                let exchange = static_exchange_eval(&after_move, threat).ok()?;
+                (victim != Piece::King && exchange.value > 0).then_some(victim)
+            })
+            .collect();
+        threatened.sort_by_key(|&piece| std::cmp::Reverse(piece));
+        threatened.dedup();
+        classes.extend(threatened.into_iter().map(MoveClass::Threatens));
+    }
+
+    classes
+}
+
+/// Squares of `side`'s pawns which have no opposing pawns in front of them on their own or
+/// adjacent files.
+fn passed_pawns(position: &Position, side: Side) -> Vec<Square> {
+    let opposing_pawns: Vec<(u8, u8)> = position
+        .piece_locs()
+        .filter(|&(piece, pawn_side, _)| piece == Piece::Pawn && pawn_side != side)
+        .map(|(_, _, square)| square.to_rank_file())
+        .collect();
+    position
+        .piece_locs()
+        .filter(|&(piece, pawn_side, _)| piece == Piece::Pawn && pawn_side == side)
+        .map(|(_, _, square)| square)
+        .filter(|square| {
+            let (rank, file) = square.to_rank_file();
+            !opposing_pawns
+                .iter()
+                .any(|&(opposing_rank, opposing_file)| {
+                    let is_in_front = match side {
+                        Side::White => opposing_rank > rank,
+                        Side::Black => opposing_rank < rank,
+                    };
+                    is_in_front && opposing_file.abs_diff(file) <= 1
+                })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Square::*;
+    use crate::move_gen::MOVE_GEN;
+    use test_case::test_case;
+    use testresult::TestResult;
+
+    #[test_case("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", Move::new(G1, F3), vec![MoveClass::Develops] ; "develops knight")]
+    #[test_case("rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2", Move::new(F1, C4), vec![MoveClass::Develops] ; "develops bishop")]
+    #[test_case("rnbqkbnr/pppppppp/8/8/8/5N2/PPPPPPPP/RNBQKB1R w KQkq - 0 1", Move::new(F3, G5), vec![] ; "already developed")]
+    #[test_case("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", Move::new(E1, G1), vec![MoveClass::Castles] ; "castles")]
+    #[test_case("4k3/8/8/3p4/4P3/8/8/4K3 w - - 0 1", Move::new(E4, D5), vec![MoveClass::Capture(Piece::Pawn), MoveClass::WinsMaterial(100), MoveClass::CreatesPassedPawn] ; "capture creates passed pawn")]
+    #[test_case("4k3/8/8/8/8/8/P7/4K3 w - - 0 1", Move::new(A2, A4), vec![] ; "pawn was already passed")]
+    #[test_case("4k3/8/2p5/3q4/8/8/8/3RK3 w - - 0 1", Move::new(D1, D5), vec![MoveClass::Capture(Piece::Queen), MoveClass::WinsMaterial(400)] ; "capture with recapture")]
+    #[test_case("3rk3/8/8/3q4/8/8/8/3QK3 w - - 0 1", Move::new(D1, D5), vec![MoveClass::Capture(Piece::Queen)] ; "even trade")]
+    #[test_case("4k3/8/8/8/8/8/8/R3K3 w - - 0 1", Move::new(A1, A8), vec![MoveClass::Check] ; "check")]
+    #[test_case("4k3/P7/8/8/8/8/8/4K3 w - - 0 1", Move::with_promotion(A7, A8, Piece::Queen), vec![MoveClass::Check, MoveClass::Promotes(Piece::Queen)] ; "promotes with check")]
+    #[test_case("4k3/8/r3q3/8/8/8/2N5/7K w - - 0 1", Move::new(C2, A3), vec![] ; "no threat")]
+    #[test_case("4k3/8/r3q3/8/8/8/2N5/7K w - - 0 1", Move::new(C2, D4), vec![MoveClass::Threatens(Piece::Queen)] ; "threatens queen")]
+    #[test_case("4k3/8/8/r7/3q4/8/8/2N1K3 w - - 0 1", Move::new(C1, B3), vec![MoveClass::Threatens(Piece::Queen), MoveClass::Threatens(Piece::Rook)] ; "fork")]
+    #[test_case("4k3/8/2p5/3p4/8/8/4N3/4K3 w - - 0 1", Move::new(E2, C3), vec![] ; "defended pieces aren't threatened")]
+    fn test_classify_move(fen: &str, mve: Move, want: Vec<MoveClass>) -> TestResult {
+        let position = Position::from_fen(fen)?;
+        assert!(MOVE_GEN.gen_moves(&position).contains(&mve));
+        assert_eq!(classify_move(&position, mve, MOVE_GEN), want);
+        Ok(())
+    }
+
+    #[test]
+    fn test_move_class_display() {
+        assert_eq!(
+            MoveClass::Capture(Piece::Knight).to_string(),
+            "captures a knight"
+        );
+        assert_eq!(
+            MoveClass::WinsMaterial(200).to_string(),
+            "wins 200 centipawns of material"
+        );
+    }
+}
@@ -1,11 +1,9 @@
-
 use arrayvec::ArrayVec;
-use strum::IntoEnumIterator;
 
 use crate::bitboard::Square::*;
 use crate::bitboard::{BitBoard, Direction, Square};
-use crate::move_gen::hyperbola_quintessence::SlidingPiecesMoveGen;
 use crate::move_gen::masks::{split_bishop_ray, split_rook_ray};
+use crate::move_gen::traits::{GenerateSlidingMoves, MoveList};
 use crate::position::{Move, Piece, Position, Side};
 use crate::move_gen::leaping_pieces::LeapingPiecesMoveGen;
 
@@ -15,7 +13,7 @@ fn gen_king_moves(
     king_square: Square,
     occupancy: BitBoard,
     leaping_pieces: LeapingPiecesMoveGen,
-    sliding_pieces: SlidingPiecesMoveGen,
+    sliding_pieces: impl GenerateSlidingMoves,
 ) -> BitBoard {
     let mut moves = leaping_pieces.gen_king_moves(king_square);
     let king_danger_squares = gen_attacked_squares(
@@ -79,7 +77,7 @@ fn gen_attacked_squares(
     position: &Position,
     side: Side,
     leaping_pieces: LeapingPiecesMoveGen,
-    sliding_pieces: SlidingPiecesMoveGen,
+    sliding_pieces: impl GenerateSlidingMoves,
 ) -> BitBoard {
     // Get occupancy but exclude king to handle kings moving away from checking sliding piece
     let occupancy = position.occupancy_bb()
@@ -87,7 +85,7 @@ fn gen_attacked_squares(
 
     let mut attacked_squares = BitBoard::empty();
 
-    for piece_type in Piece::iter() {
+    for piece_type in Piece::ALL {
         let mut pieces = position.get_piece_bb(side, piece_type);
 
         while !pieces.is_empty() {
@@ -111,7 +109,7 @@ fn gen_attacked_squares(
 fn get_pin_rays(
     position: &Position,
     side: Side,
-    sliding_pieces: SlidingPiecesMoveGen,
+    sliding_pieces: impl GenerateSlidingMoves,
 ) -> (BitBoard, BitBoard) {
     let opp_side = side.opposite_side();
 
@@ -167,7 +165,7 @@ fn get_pin_rays(
 pub(super) fn get_checkers(
     position: &Position,
     leaping_pieces: LeapingPiecesMoveGen,
-    sliding_pieces: SlidingPiecesMoveGen,
+    sliding_pieces: impl GenerateSlidingMoves,
 ) -> BitBoard {
     let side = position.state.to_move;
     let opp_side = side.opposite_side();
@@ -183,7 +181,7 @@ pub(super) fn get_checkers(
 
     let mut checkers = BitBoard::empty();
 
-    for piece_type in Piece::iter() {
+    for piece_type in Piece::ALL {
         let moves = match piece_type {
             Piece::Knight => leaping_pieces.gen_knight_moves(king_square),
             Piece::Bishop | Piece::Rook | Piece::Queen => {
@@ -199,12 +197,60 @@ pub(super) fn get_checkers(
     checkers
 }
 
+/// All pieces of both sides attacking `square`, with sliding attacks computed through
+/// `occupancy` so callers can reveal x-ray attackers by clearing squares.
+pub(crate) fn get_attackers_to(
+    position: &Position,
+    square: Square,
+    occupancy: BitBoard,
+    leaping_pieces: LeapingPiecesMoveGen,
+    sliding_pieces: impl GenerateSlidingMoves,
+) -> BitBoard {
+    let diagonal_sliders = position.get_piece_bb(Side::White, Piece::Bishop)
+        | position.get_piece_bb(Side::Black, Piece::Bishop)
+        | position.get_piece_bb(Side::White, Piece::Queen)
+        | position.get_piece_bb(Side::Black, Piece::Queen);
+    let straight_sliders = position.get_piece_bb(Side::White, Piece::Rook)
+        | position.get_piece_bb(Side::Black, Piece::Rook)
+        | position.get_piece_bb(Side::White, Piece::Queen)
+        | position.get_piece_bb(Side::Black, Piece::Queen);
+    let knights = position.get_piece_bb(Side::White, Piece::Knight)
+        | position.get_piece_bb(Side::Black, Piece::Knight);
+    let kings = position.get_piece_bb(Side::White, Piece::King)
+        | position.get_piece_bb(Side::Black, Piece::King);
+
+    // A pawn attacks a square if a pawn of the other side on that square would attack it.
+    let pawns = (leaping_pieces.gen_pawn_atks(square, Side::Black)
+        & position.get_piece_bb(Side::White, Piece::Pawn))
+        | (leaping_pieces.gen_pawn_atks(square, Side::White)
+            & position.get_piece_bb(Side::Black, Piece::Pawn));
+
+    let attackers = pawns
+        | (leaping_pieces.gen_knight_moves(square) & knights)
+        | (leaping_pieces.gen_king_moves(square) & kings)
+        | (sliding_pieces.gen_moves(Piece::Bishop, square, occupancy) & diagonal_sliders)
+        | (sliding_pieces.gen_moves(Piece::Rook, square, occupancy) & straight_sliders);
+
+    attackers & occupancy
+}
+
 pub(super) fn gen_moves(
     position: &Position,
     leaping_pieces: LeapingPiecesMoveGen,
-    sliding_pieces: SlidingPiecesMoveGen,
-) -> ArrayVec<Move, 218> {
-    let mut moves = ArrayVec::new();
+    sliding_pieces: impl GenerateSlidingMoves,
+) -> MoveList {
+    let mut moves = MoveList::new();
+    gen_moves_into(position, &mut moves, leaping_pieces, sliding_pieces);
+    moves
+}
+
+pub(super) fn gen_moves_into(
+    position: &Position,
+    moves: &mut MoveList,
+    leaping_pieces: LeapingPiecesMoveGen,
+    sliding_pieces: impl GenerateSlidingMoves,
+) {
+    moves.clear();
 
     let side = position.state.to_move;
     let opp_side = side.opposite_side();
@@ -235,10 +281,8 @@ pub(super) fn gen_moves(
             sliding_pieces,
         );
         moves_bb &= !friendly_pieces;
-        return moves_bb
-            .to_squares()
-            .map(|sq| Move::new(king_square, sq))
-            .collect();
+        moves.extend(moves_bb.to_squares().map(|sq| Move::new(king_square, sq)));
+        return;
     }
 
     if num_checkers == 1 {
@@ -264,7 +308,7 @@ pub(super) fn gen_moves(
         }
     }
 
-    for piece_type in Piece::iter() {
+    for piece_type in Piece::ALL {
         let pieces = position.get_piece_bb(side, piece_type);
 
         for piece_square in pieces.to_squares() {
@@ -331,10 +375,12 @@ pub(super) fn gen_moves(
                 moves_bb &= capture_mask | push_mask;
             }
 
-            if let Some(en_passant_target) = position.state.en_passant_target {
-                if num_checkers == 1 && !(piece_type == Piece::Pawn || piece_type == Piece::King) && moves_bb.is_square_set(en_passant_target) {
-                    moves_bb.clear_square(en_passant_target);
-                }
+            if let Some(en_passant_target) = position.state.en_passant_target
+                && num_checkers == 1
+                && !(piece_type == Piece::Pawn || piece_type == Piece::King)
+                && moves_bb.is_square_set(en_passant_target)
+            {
+                moves_bb.clear_square(en_passant_target);
             }
 
             // Handle the case where a piece is pinned in one direction and there is a piece
@@ -358,9 +404,12 @@ pub(super) fn gen_moves(
 
             // For each promotion, we need to add 4 moves to the list,
             // 1 for each piece type
+            let pre_promotion_rank = match side {
+                Side::White => BitBoard::RANK_7,
+                Side::Black => BitBoard::RANK_2,
+            };
             let moves_list: ArrayVec<Move, 32> = if piece_type == Piece::Pawn
-                && ((side == Side::White && (piece_square >= A7 && piece_square <= H7))
-                    || (side == Side::Black && (piece_square >= A2 && piece_square <= H2)))
+                && pre_promotion_rank.is_square_set(piece_square)
             {
                 moves_bb
                     .to_squares()
@@ -383,8 +432,6 @@ pub(super) fn gen_moves(
             moves.extend(moves_list);
         }
     }
-
-    moves
 }
 
 #[cfg(test)]
@@ -395,7 +442,7 @@ mod tests {
 
     use test_case::test_case;
 
-    use crate::move_gen::hyperbola_quintessence::SLIDING_PIECES_MOVE_GEN;
+    use crate::move_gen::SLIDING_PIECES_MOVE_GEN;
     use crate::move_gen::leaping_pieces::LEAPING_PIECES;
 
     macro_rules! assert_eq_collections {
@@ -752,4 +799,25 @@ mod tests {
 
         assert!(!position.state.castling_rights.black_king_side);
     }
+
+    #[test_case(Position::start() ; "start")]
+    #[test_case(Position::from_fen("8/8/8/8/k2Pp3/8/8/7K b - d3 0 1").unwrap() ; "en passant")]
+    #[test_case(Position::from_fen("8/8/4k3/8/5N2/8/3b4/7K b - - 0 1").unwrap() ; "check")]
+    #[test_case(Position::from_fen("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3").unwrap() ; "checkmate")]
+    fn test_gen_moves_into_reused_buffer(position: Position) {
+        // A buffer left full of another position's moves.
+        let mut moves = gen_moves(
+            &Position::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1")
+                .unwrap(),
+            LEAPING_PIECES,
+            SLIDING_PIECES_MOVE_GEN,
+        );
+
+        gen_moves_into(&position, &mut moves, LEAPING_PIECES, SLIDING_PIECES_MOVE_GEN);
+
+        assert_eq!(
+            moves,
+            gen_moves(&position, LEAPING_PIECES, SLIDING_PIECES_MOVE_GEN)
+        );
+    }
 }
@@ -1,5 +1,6 @@
 
 use super::masks::{MASKS_LIST, MaskType, RANK_ATKS};
+use super::traits::GenerateSlidingMoves;
 use crate::bitboard::{BitBoard, Square};
 use crate::position::Piece;
 
@@ -25,15 +26,15 @@ impl SlidingPiecesMoveGen {
         let file: usize = (sq_idx & 7).into(); // sq_idx % 8
         let rank_x8 = sq_idx & 56; // Rank times 8
 
-        let rank_occ_x2: usize = ((occ_val >> rank_x8) & 2 * 63).try_into().unwrap(); // 2 times the inner six bit occupancy used as index
-        let atks: u64 = RANK_ATKS[usize::from(4 * rank_occ_x2 + file)].into();
+        let rank_occ_x2: usize = ((occ_val >> rank_x8) & (2 * 63)).try_into().unwrap(); // 2 times the inner six bit occupancy used as index
+        let atks: u64 = RANK_ATKS[4 * rank_occ_x2 + file].into();
 
-        BitBoard::from_val((atks << rank_x8).into())
+        BitBoard::from_val(atks << rank_x8)
     }
 }
 
-impl SlidingPiecesMoveGen {
-    pub(crate) fn gen_moves(&self, piece: Piece, square: Square, occupancy: BitBoard) -> BitBoard {
+impl GenerateSlidingMoves for SlidingPiecesMoveGen {
+    fn gen_moves(&self, piece: Piece, square: Square, occupancy: BitBoard) -> BitBoard {
         let masks = MASKS_LIST.get(square);
         let bit_mask = masks.get(MaskType::Bit);
 
@@ -52,20 +53,15 @@ impl SlidingPiecesMoveGen {
                     | self.get_moves(occupancy, masks.get(MaskType::Diagonal), bit_mask)
                     | self.get_moves(occupancy, masks.get(MaskType::AntiDiagonal), bit_mask)
             }
-            _ => panic!(
-                "piece type: want [bishop, rook, queen], got {}",
-                piece.to_string()
-            ),
+            _ => panic!("piece type: want [bishop, rook, queen], got {}", piece),
         }
     }
 }
 
-pub(crate) static SLIDING_PIECES_MOVE_GEN: SlidingPiecesMoveGen = SlidingPiecesMoveGen {};
+pub static SLIDING_PIECES_MOVE_GEN: SlidingPiecesMoveGen = SlidingPiecesMoveGen {};
 
 #[cfg(test)]
 mod tests {
-    use crate::move_gen::leaping_pieces::LeapingPiecesMoveGen;
-
     use super::Square::*;
     use super::*;
     use test_case::test_case;
@@ -1,102 +1,223 @@
-//use std::{cell::LazyCell, sync::LazyLock};
-//
-//use arrayvec::ArrayVec;
-//use strum::IntoEnumIterator;
-//
-//use crate::{bitboard::BitBoard, GenerateMoves, Piece, Square, HYPERBOLA_QUINTESSENCE_MOVE_GEN};
-//
-//use super::{masks::MASKS_LIST, traits::GenerateSlidingMoves};
-//
-//#[derive(Default)]
-//pub struct MagicBitboard;
-//
-//static MAGIC_BITBOARD: LazyLock<MagicBitboard> = LazyLock::new(|| MagicBitboard::default());
-//
-//fn gen_rook_moves(move_gen: &impl GenerateSlidingMoves) -> [BitBoard; 64 * 2_usize.pow(14)] {
-//    let mut rook_moves = [BitBoard::empty(); 64 * 2_usize.pow(14)];
-//
-//    for sq in Square::iter() {
-//        let blocker_boards = gen_blocker_boards(sq);
-//        for (board_idx, &board) in blocker_boards.iter().enumerate() {
-//            let moves = move_gen.gen_moves(Piece::Rook, sq, board);
-//            let move_idx = (sq as usize) * 2_usize.pow(14) + board_idx;
-//            rook_moves[move_idx] = moves;
-//        }
-//    }
-//    rook_moves
-//}
-//
-//struct MagicEntry {
-//    mask: BitBoard,
-//    magic: u64,
-//    shift: u8,
-//    offset: u32,
-//}
-//
-//// Magics from Ethereal: https://github.com/AndyGrant/Ethereal
-//
-//#[rustfmt::skip]
-//const ROOK_MAGICS: [MagicEntry; 64] = [
-//    0xA180022080400230, 0x0040100040022000, 0x0080088020001002, 0x0080080280841000,
-//    0x4200042010460008, 0x04800A0003040080, 0x0400110082041008, 0x008000A041000880,
-//    0x10138001A080C010, 0x0000804008200480, 0x00010011012000C0, 0x0022004128102200,
-//    0x000200081201200C, 0x202A001048460004, 0x0081000100420004, 0x4000800380004500,
-//    0x0000208002904001, 0x0090004040026008, 0x0208808010002001, 0x2002020020704940,
-//    0x8048010008110005, 0x6820808004002200, 0x0A80040008023011, 0x00B1460000811044,
-//    0x4204400080008EA0, 0xB002400180200184, 0x2020200080100380, 0x0010080080100080,
-//    0x2204080080800400, 0x0000A40080360080, 0x02040604002810B1, 0x008C218600004104,
-//    0x8180004000402000, 0x488C402000401001, 0x4018A00080801004, 0x1230002105001008,
-//    0x8904800800800400, 0x0042000C42003810, 0x008408110400B012, 0x0018086182000401,
-//    0x2240088020C28000, 0x001001201040C004, 0x0A02008010420020, 0x0010003009010060,
-//    0x0004008008008014, 0x0080020004008080, 0x0282020001008080, 0x50000181204A0004,
-//    0x48FFFE99FECFAA00, 0x48FFFE99FECFAA00, 0x497FFFADFF9C2E00, 0x613FFFDDFFCE9200,
-//    0xFFFFFFE9FFE7CE00, 0xFFFFFFF5FFF3E600, 0x0010301802830400, 0x510FFFF5F63C96A0,
-//    0xEBFFFFB9FF9FC526, 0x61FFFEDDFEEDAEAE, 0x53BFFFEDFFDEB1A2, 0x127FFFB9FFDFB5F6,
-//    0x411FFFDDFFDBF4D6, 0x0801000804000603, 0x0003FFEF27EEBE74, 0x7645FFFECBFEA79E,
-//];
-//
-//#[rustfmt::skip]
-//const BISHOP_MAGICS: [MagicEntry; 64] = [
-//    0xFFEDF9FD7CFCFFFF, 0xFC0962854A77F576, 0x5822022042000000, 0x2CA804A100200020,
-//    0x0204042200000900, 0x2002121024000002, 0xFC0A66C64A7EF576, 0x7FFDFDFCBD79FFFF,
-//    0xFC0846A64A34FFF6, 0xFC087A874A3CF7F6, 0x1001080204002100, 0x1810080489021800,
-//    0x0062040420010A00, 0x5028043004300020, 0xFC0864AE59B4FF76, 0x3C0860AF4B35FF76,
-//    0x73C01AF56CF4CFFB, 0x41A01CFAD64AAFFC, 0x040C0422080A0598, 0x4228020082004050,
-//    0x0200800400E00100, 0x020B001230021040, 0x7C0C028F5B34FF76, 0xFC0A028E5AB4DF76,
-//    0x0020208050A42180, 0x001004804B280200, 0x2048020024040010, 0x0102C04004010200,
-//    0x020408204C002010, 0x02411100020080C1, 0x102A008084042100, 0x0941030000A09846,
-//    0x0244100800400200, 0x4000901010080696, 0x0000280404180020, 0x0800042008240100,
-//    0x0220008400088020, 0x04020182000904C9, 0x0023010400020600, 0x0041040020110302,
-//    0xDCEFD9B54BFCC09F, 0xF95FFA765AFD602B, 0x1401210240484800, 0x0022244208010080,
-//    0x1105040104000210, 0x2040088800C40081, 0x43FF9A5CF4CA0C01, 0x4BFFCD8E7C587601,
-//    0xFC0FF2865334F576, 0xFC0BF6CE5924F576, 0x80000B0401040402, 0x0020004821880A00,
-//    0x8200002022440100, 0x0009431801010068, 0xC3FFB7DC36CA8C89, 0xC3FF8A54F4CA2C89,
-//    0xFFFFFCFCFD79EDFF, 0xFC0863FCCB147576, 0x040C000022013020, 0x2000104000420600,
-//    0x0400000260142410, 0x0800633408100500, 0xFC087E8E4BB2F736, 0x43FF9E4EF4CA2C89,
-//];
-//
-//const ROOK_MOVES: &[BitBoard] = todo!();
-//const BISHOP_MOVES: &[BitBoard] = todo!();
-//
-//const fn magic_index(entry: &MagicEntry, blockers: BitBoard) -> usize {
-//    let blockers = blockers & entry.mask;
-//    let hash = blockers.to_val().wrapping_mul(entry.magic);
-//    let index = (hash >> entry.shift) as usize;
-//    entry.offset as usize + index
-//}
-//
-//const fn get_rook_moves(square: Square, blockers: BitBoard) -> BitBoard {
-//    let magic = &ROOK_MAGICS[square as usize];
-//    ROOK_MOVES[magic_index(magic, blockers)]
-//}
-//
-//const fn get_bishop_moves(square: Square, blockers: BitBoard) -> BitBoard {
-//    let magic = &BISHOP_MAGICS[square as usize];
-//    BISHOP_MOVES[magic_index(magic, blockers)]
-//}
-//
-//impl GenerateSlidingMoves for MagicBitboard {
-//    fn gen_moves(&self, piece: Piece, square: Square, occupancy: BitBoard) -> BitBoard {
-//        BitBoard::empty()
-//    }
-//}
+use std::sync::LazyLock;
+
+use super::traits::GenerateSlidingMoves;
+use crate::bitboard::{BitBoard, Square};
+use crate::position::Piece;
+
+/// Sliding piece attacks looked up in tables indexed by a perfect hash of the blockers on each
+/// square's rays. Source: https://www.chessprogramming.org/Magic_Bitboards
+#[derive(Clone, Copy)]
+pub struct MagicBitboard {}
+
+pub static MAGIC_BITBOARD: MagicBitboard = MagicBitboard {};
+
+impl GenerateSlidingMoves for MagicBitboard {
+    fn gen_moves(&self, piece: Piece, square: Square, occupancy: BitBoard) -> BitBoard {
+        match piece {
+            Piece::Bishop => BISHOP_TABLE.get(square, occupancy),
+            Piece::Rook => ROOK_TABLE.get(square, occupancy),
+            Piece::Queen => BISHOP_TABLE.get(square, occupancy) | ROOK_TABLE.get(square, occupancy),
+            _ => panic!("piece type: want [bishop, rook, queen], got {}", piece),
+        }
+    }
+}
+
+/// Finds a square's attacks in its part of the table: the blockers on its rays, excluding the
+/// board's edges since a piece there blocks nothing, times the magic gives an index in the top
+/// `64 - shift` bits.
+#[derive(Clone, Copy)]
+struct MagicEntry {
+    mask: u64,
+    magic: u64,
+    shift: u32,
+    offset: usize,
+}
+
+struct MagicTable {
+    entries: [MagicEntry; 64],
+    attacks: Vec<u64>,
+}
+
+impl MagicTable {
+    /// Fills in every square's attacks for every set of blockers on its rays.
+    ///
+    /// Panics if a magic maps two sets of blockers with different attacks to the same index.
+    fn new(magics: &[u64; 64], directions: &[(i8, i8); 4]) -> Self {
+        let mut entries = [MagicEntry {
+            mask: 0,
+            magic: 0,
+            shift: 0,
+            offset: 0,
+        }; 64];
+        let mut attacks = vec![];
+
+        for (sq_idx, (entry, &magic)) in entries.iter_mut().zip(magics).enumerate() {
+            let mask = calc_ray_attacks(sq_idx, 0, directions, true);
+            *entry = MagicEntry {
+                mask,
+                magic,
+                shift: 64 - mask.count_ones(),
+                offset: attacks.len(),
+            };
+            attacks.resize(attacks.len() + (1 << mask.count_ones()), 0);
+
+            // Visit every subset of the mask, see
+            // https://www.chessprogramming.org/Traversing_Subsets_of_a_Set
+            let mut blockers: u64 = 0;
+            loop {
+                let idx = entry.offset + (blockers.wrapping_mul(magic) >> entry.shift) as usize;
+                let blockers_attacks = calc_ray_attacks(sq_idx, blockers, directions, false);
+                // Attacks are never empty, so an empty entry hasn't been filled yet.
+                assert!(
+                    attacks[idx] == 0 || attacks[idx] == blockers_attacks,
+                    "magic for square {} has a collision",
+                    sq_idx
+                );
+                attacks[idx] = blockers_attacks;
+
+                blockers = blockers.wrapping_sub(mask) & mask;
+                if blockers == 0 {
+                    break;
+                }
+            }
+        }
+
+        MagicTable { entries, attacks }
+    }
+
+    fn get(&self, square: Square, occupancy: BitBoard) -> BitBoard {
+        let entry = &self.entries[square as usize];
+        let idx =
+            ((occupancy.to_val() & entry.mask).wrapping_mul(entry.magic) >> entry.shift) as usize;
+        BitBoard::from_val(self.attacks[entry.offset + idx])
+    }
+}
+
+const ROOK_DIRECTIONS: [(i8, i8); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+const BISHOP_DIRECTIONS: [(i8, i8); 4] = [(1, 1), (1, -1), (-1, 1), (-1, -1)];
+
+// Filled in the first time they're used, which takes a few milliseconds. Const evaluating them
+// would make every build of the crate several seconds slower.
+static ROOK_TABLE: LazyLock<MagicTable> =
+    LazyLock::new(|| MagicTable::new(&ROOK_MAGICS, &ROOK_DIRECTIONS));
+static BISHOP_TABLE: LazyLock<MagicTable> =
+    LazyLock::new(|| MagicTable::new(&BISHOP_MAGICS, &BISHOP_DIRECTIONS));
+
+// Found by trying random sparse numbers (the AND of three random numbers) for each square until
+// one gave every blocker set its own index, or one shared only with blockers with the same
+// attacks.
+#[rustfmt::skip]
+const ROOK_MAGICS: [u64; 64] = [
+    0x0380002A1281C000, 0x0200102302408200, 0x3480200289100080, 0x0480100208008004,
+    0x0280080180040002, 0x0600100600040831, 0x0400300401084082, 0x1A00020040810024,
+    0x0082002080420101, 0x0202002080410200, 0x0210801000200882, 0x2408801000080080,
+    0x5090800800840080, 0x0222000488908200, 0x0004001002080104, 0x0C20800080005900,
+    0x924380800820C011, 0x0040484010002000, 0x0020008020801000, 0x1020808010000804,
+    0x0402850008009100, 0x8054008002008004, 0x400004005F100802, 0x00C65A0004164A81,
+    0x0C00408200210200, 0x041002C240002000, 0x0020004100210010, 0x0600100080080082,
+    0xC208008880040080, 0x0400020080040080, 0xE000420400614810, 0x0020008200104104,
+    0x0800804000800038, 0x0290002008400048, 0x2080200282801000, 0x0C1600100A004120,
+    0xC100800800800402, 0x04A0020080800400, 0x0208480184000210, 0x1801010082000044,
+    0x1000400080008024, 0x100120100040C000, 0xA025002002450010, 0xC240080010008080,
+    0x842B010801050010, 0x0080040002008080, 0x0040821001840008, 0x0000412040920004,
+    0x0421400680002480, 0x0100400080200080, 0x0018801042002200, 0x0800480080100280,
+    0x0685800402080080, 0x0089008400020900, 0x5044302802018400, 0x0200005084110200,
+    0x0020310080012441, 0x0000204104120086, 0x00004010800A2202, 0x2002082010000501,
+    0x0002006010440882, 0x8002004150381402, 0x050004A502181004, 0xC200002081004402,
+];
+
+#[rustfmt::skip]
+const BISHOP_MAGICS: [u64; 64] = [
+    0x0020202210404086, 0x0082480101020000, 0x00044902120000A0, 0x8008285302400064,
+    0x8002021000008100, 0x040288200A000000, 0x0080440208400840, 0x1B02010042022000,
+    0x4080C14808008080, 0x3200901031090021, 0x0080086808488000, 0x48150404218C2200,
+    0x2000040504409000, 0x0040084110100900, 0x0002040101082042, 0x8E00202108088408,
+    0x00040A0810041800, 0x0002A00802140408, 0x8088041008881013, 0x9000800802094032,
+    0x544400CE01215008, 0x0804212200900800, 0x0041001401280200, 0x4100800100411090,
+    0x000EA80C41886800, 0x000A1800B1010808, 0x0805100021040820, 0x4021080344004010,
+    0x2102840008802000, 0x0810010040240101, 0x0084004000882408, 0x0000848401004840,
+    0x2028201000044408, 0x000090484004A800, 0x4041040100A88800, 0x0010C20080180082,
+    0x0021100400008020, 0x0002174501020088, 0x8085040404093300, 0xC048044840090500,
+    0x1811010920204000, 0x02C2085B0C014820, 0x0000082488007000, 0x8004020122088400,
+    0x00403A0202005412, 0x8C40080089010020, 0x020408009400A100, 0x0402008101029208,
+    0x2004008404208000, 0x08008080A8208000, 0x0201004A08040804, 0xA12000020A020002,
+    0x8004113102022104, 0x0262040408120200, 0x08D002B001120000, 0x2810042804822481,
+    0x0030110410122814, 0x8082042684100800, 0x00C0201210840400, 0x681440000C208810,
+    0x4400000120042400, 0x0022022060420224, 0x0100102008010050, 0x0002200200821081,
+];
+
+/// Squares attacked from `sq_idx` in `directions`, stopping at the first square in `blockers`.
+/// With `is_mask`, the last square in each direction is left out.
+fn calc_ray_attacks(
+    sq_idx: usize,
+    blockers: u64,
+    directions: &[(i8, i8); 4],
+    is_mask: bool,
+) -> u64 {
+    let mut attacks = 0;
+    for &(rank_step, file_step) in directions {
+        let mut rank = (sq_idx / 8) as i8 + rank_step;
+        let mut file = (sq_idx % 8) as i8 + file_step;
+        while (0..8).contains(&rank) && (0..8).contains(&file) {
+            let next_rank = rank + rank_step;
+            let next_file = file + file_step;
+            if is_mask && !((0..8).contains(&next_rank) && (0..8).contains(&next_file)) {
+                break;
+            }
+            let bit = 1 << (rank * 8 + file);
+            attacks |= bit;
+            if blockers & bit != 0 {
+                break;
+            }
+            rank = next_rank;
+            file = next_file;
+        }
+    }
+    attacks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Square::*;
+    use super::*;
+    use crate::move_gen::hyperbola_quintessence::SLIDING_PIECES_MOVE_GEN;
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+    use test_case::test_case;
+
+    #[test_case(Piece::Bishop, D4, BitBoard::from_squares(&[B2, A7, E5]), BitBoard::from_squares(&[B2, C3, E5, C5, B6, A7, E3, F2, G1]) ; "bishop blockers")]
+    #[test_case(Piece::Rook, A1, BitBoard::from_squares(&[]), BitBoard::from_squares(&[A2, A3, A4, A5, A6, A7, A8, B1, C1, D1, E1, F1, G1, H1]) ; "rook no blockers")]
+    #[test_case(Piece::Rook, D4, BitBoard::from_squares(&[A4, D7, D8, F4, D3, D2, D1]), BitBoard::from_squares(&[D3, D5, D6, D7, A4, B4, C4, E4, F4]) ; "rook irrelevant blockers")]
+    #[test_case(Piece::Queen, H8, BitBoard::from_squares(&[H8, G7, H1]), BitBoard::from_squares(&[G7, A8, B8, C8, D8, E8, F8, G8, H1, H2, H3, H4, H5, H6, H7]) ; "queen")]
+    fn test_gen_moves(piece: Piece, square: Square, occupancy: BitBoard, want: BitBoard) {
+        assert_eq!(MAGIC_BITBOARD.gen_moves(piece, square, occupancy), want);
+    }
+
+    #[test]
+    fn test_table_sizes() {
+        // Every square has `2^(number of squares in its mask)` entries.
+        assert_eq!(ROOK_TABLE.attacks.len(), 102_400);
+        assert_eq!(BISHOP_TABLE.attacks.len(), 5_248);
+    }
+
+    #[test]
+    fn test_gen_moves_matches_hyperbola_quintessence() {
+        let mut rng = SmallRng::seed_from_u64(0);
+        for _ in 0..1_000 {
+            // Sparse, like a real board.
+            let occupancy = BitBoard::from_val(rng.random::<u64>() & rng.random::<u64>());
+            for square in Square::ALL {
+                for piece in [Piece::Bishop, Piece::Rook, Piece::Queen] {
+                    assert_eq!(
+                        MAGIC_BITBOARD.gen_moves(piece, square, occupancy),
+                        SLIDING_PIECES_MOVE_GEN.gen_moves(piece, square, occupancy),
+                        "{} on {} with occupancy {:?}",
+                        piece,
+                        square,
+                        occupancy
+                    );
+                }
+            }
+        }
+    }
+}
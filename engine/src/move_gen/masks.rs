@@ -63,54 +63,30 @@ pub(super) const fn calc_masks_list() -> MasksList {
     }
 
     let mut file: usize = 0;
-    let mut curr_file = BitBoard::from_squares(&[
-        Square::A1,
-        Square::A2,
-        Square::A3,
-        Square::A4,
-        Square::A5,
-        Square::A6,
-        Square::A7,
-        Square::A8,
-    ]);
-
     while file < 8 {
         let mut rank = 0;
         while rank < 8 {
             let idx = rank * 8 + file;
             let bit_mask = masks_list[idx].bit;
-            let file_mask = curr_file.const_bit_and(bit_mask.const_bit_not());
+            let file_mask = BitBoard::FILES[file].const_bit_and(bit_mask.const_bit_not());
 
             masks_list[idx].file = file_mask;
             rank += 1;
         }
-        curr_file.shift(Direction::IncFile);
         file += 1;
     }
 
     let mut rank: usize = 0;
-    let mut curr_rank = BitBoard::from_squares(&[
-        Square::A1,
-        Square::B1,
-        Square::C1,
-        Square::D1,
-        Square::E1,
-        Square::F1,
-        Square::G1,
-        Square::H1,
-    ]);
-
     while rank < 8 {
         let mut file = 0;
         while file < 8 {
             let idx = rank * 8 + file;
             let bit_mask = masks_list[idx].bit;
-            let rank_mask = curr_rank.const_bit_and(bit_mask.const_bit_not());
+            let rank_mask = BitBoard::RANKS[rank].const_bit_and(bit_mask.const_bit_not());
 
             masks_list[idx].rank = rank_mask;
             file += 1;
         }
-        curr_rank.shift(Direction::IncRank);
         rank += 1;
     }
 
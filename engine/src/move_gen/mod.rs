@@ -5,25 +5,107 @@ pub mod magic_bitboard;
 mod masks;
 mod traits;
 
-use arrayvec::ArrayVec;
+use crate::bitboard::{BitBoard, Square};
+use crate::position::{Piece, Position, Side};
 
-use crate::position::{Move, Position};
-
-use self::hyperbola_quintessence::SLIDING_PIECES_MOVE_GEN;
+#[cfg(feature = "hyperbola-quintessence")]
+pub(crate) use self::hyperbola_quintessence::SLIDING_PIECES_MOVE_GEN;
 use self::leaping_pieces::LEAPING_PIECES;
-pub use self::traits::GenerateMoves;
+#[cfg(not(feature = "hyperbola-quintessence"))]
+pub(crate) use self::magic_bitboard::MAGIC_BITBOARD as SLIDING_PIECES_MOVE_GEN;
+pub use self::traits::GenerateSlidingMoves;
+pub use self::traits::{GenerateMoves, MAX_MOVES, MoveList};
 
 #[derive(Clone, Copy)]
 pub struct MoveGen;
 
 impl GenerateMoves for MoveGen {
-    fn gen_moves(&self, position: &Position) -> ArrayVec<Move, 218> {
+    fn gen_moves(&self, position: &Position) -> MoveList {
         all_pieces::gen_moves(position, LEAPING_PIECES, SLIDING_PIECES_MOVE_GEN)
     }
 
-    fn gen_checkers(&self, position: &Position) -> crate::bitboard::BitBoard {
+    fn gen_moves_into(&self, position: &Position, moves: &mut MoveList) {
+        all_pieces::gen_moves_into(position, moves, LEAPING_PIECES, SLIDING_PIECES_MOVE_GEN)
+    }
+
+    fn gen_checkers(&self, position: &Position) -> BitBoard {
         all_pieces::get_checkers(position, LEAPING_PIECES, SLIDING_PIECES_MOVE_GEN)
     }
 }
 
 pub static MOVE_GEN: MoveGen = MoveGen {};
+
+/// How [`MOVE_GEN`] generates sliding piece attacks, for reporting which code path is in use.
+/// Magic bitboards unless the `hyperbola-quintessence` feature is enabled.
+#[cfg(not(feature = "hyperbola-quintessence"))]
+pub const SLIDING_PIECES_BACKEND: &str = "magic-bitboards";
+#[cfg(feature = "hyperbola-quintessence")]
+pub const SLIDING_PIECES_BACKEND: &str = "hyperbola-quintessence";
+
+/// Squares a `piece` of `side` on `square` attacks. Sliding pieces attack up to and including
+/// the first square set in `occupancy` in each direction. Pawns attack the two squares
+/// diagonally in front of them, so only their attacks depend on `side`.
+///
+/// Attacks don't depend on what's on the attacked squares, so squares of `side`'s own pieces
+/// are included: the piece defends them.
+pub fn attacks_from(square: Square, side: Side, piece: Piece, occupancy: BitBoard) -> BitBoard {
+    match piece {
+        Piece::Pawn => LEAPING_PIECES.gen_pawn_atks(square, side),
+        Piece::Knight => LEAPING_PIECES.gen_knight_moves(square),
+        Piece::King => LEAPING_PIECES.gen_king_moves(square),
+        Piece::Bishop | Piece::Rook | Piece::Queen => {
+            SLIDING_PIECES_MOVE_GEN.gen_moves(piece, square, occupancy)
+        }
+    }
+}
+
+/// `side`'s pieces of type `piece` in `position` that attack `square`, e.g. to show which
+/// pieces attack or defend it. Pinned pieces are included, and `square` can be empty.
+pub fn attacks_to_by(position: &Position, square: Square, side: Side, piece: Piece) -> BitBoard {
+    // Attacks are symmetric, except that pawns attack forward: a pawn attacks a square if a pawn
+    // of the other side on that square would attack it.
+    let from_side = match piece {
+        Piece::Pawn => side.opposite_side(),
+        _ => side,
+    };
+    attacks_from(square, from_side, piece, position.occupancy_bb())
+        & position.get_piece_bb(side, piece)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitboard::Square::*;
+    use test_case::test_case;
+    use testresult::TestResult;
+
+    #[test_case(E4, Side::White, Piece::Pawn, &[D5, F5] ; "white pawn")]
+    #[test_case(E4, Side::Black, Piece::Pawn, &[D3, F3] ; "black pawn")]
+    #[test_case(A1, Side::White, Piece::Knight, &[B3, C2] ; "knight")]
+    #[test_case(H8, Side::Black, Piece::King, &[G8, G7, H7] ; "king")]
+    #[test_case(A1, Side::White, Piece::Rook, &[A2, A3, B1, C1] ; "rook stopped by occupancy")]
+    #[test_case(C1, Side::White, Piece::Bishop, &[B2, A3, D2, E3] ; "bishop stopped by occupancy")]
+    fn test_attacks_from(square: Square, side: Side, piece: Piece, want: &[Square]) {
+        let occupancy = BitBoard::from_squares(&[A3, E3, C1]);
+        assert_eq!(
+            attacks_from(square, side, piece, occupancy),
+            BitBoard::from_squares(want)
+        );
+    }
+
+    #[test_case(E5, Side::White, Piece::Pawn, &[D4] ; "white pawns")]
+    #[test_case(E5, Side::Black, Piece::Pawn, &[F6] ; "black pawns")]
+    #[test_case(E5, Side::White, Piece::Knight, &[F3, C4] ; "knights")]
+    #[test_case(E5, Side::White, Piece::Rook, &[] ; "rook blocked")]
+    #[test_case(E5, Side::Black, Piece::Queen, &[] ; "queen blocked")]
+    #[test_case(E5, Side::Black, Piece::Bishop, &[] ; "no bishops")]
+    #[test_case(E4, Side::White, Piece::Rook, &[E1] ; "defended own piece")]
+    fn test_attacks_to_by(square: Square, side: Side, piece: Piece, want: &[Square]) -> TestResult {
+        let position = Position::from_fen("4k2q/8/5p2/8/2NPP3/5N2/3P4/4RK2 w - - 0 1")?;
+        assert_eq!(
+            attacks_to_by(&position, square, side, piece),
+            BitBoard::from_squares(want)
+        );
+        Ok(())
+    }
+}
@@ -1,9 +1,28 @@
 use arrayvec::ArrayVec;
 
-use crate::bitboard::BitBoard;
-use crate::position::{Move, Position};
+use crate::bitboard::{BitBoard, Square};
+use crate::position::{Move, Piece, Position};
+
+/// Most legal moves any chess position has.
+pub const MAX_MOVES: usize = 218;
+
+/// Buffer big enough for the legal moves of any position.
+pub type MoveList = ArrayVec<Move, MAX_MOVES>;
 
 pub trait GenerateMoves {
-    fn gen_moves(&self, position: &Position) -> ArrayVec<Move, 218>;
+    fn gen_moves(&self, position: &Position) -> MoveList;
     fn gen_checkers(&self, position: &Position) -> BitBoard;
+
+    /// Like `gen_moves`, but replaces the contents of a buffer the caller owns, so recursive
+    /// callers can keep one buffer per ply instead of returning a new list from every node.
+    fn gen_moves_into(&self, position: &Position, moves: &mut MoveList) {
+        moves.clear();
+        moves.extend(self.gen_moves(position));
+    }
+}
+
+/// Attacks of a bishop, rook or queen on `square`, up to and including the first square set in
+/// `occupancy` in each direction.
+pub trait GenerateSlidingMoves: Copy {
+    fn gen_moves(&self, piece: Piece, square: Square, occupancy: BitBoard) -> BitBoard;
 }
@@ -7,7 +7,7 @@ use std::{
 
 use tabled::{Table, Tabled};
 
-use crate::move_gen::GenerateMoves;
+use crate::move_gen::{GenerateMoves, MoveList};
 use crate::position::{Piece, Position};
 use crate::{Move, bitboard::BitBoard};
 
@@ -66,7 +66,7 @@ impl Display for PerftResult {
         writeln!(f, "total nodes: {}", self.tot_nodes)?;
         writeln!(f, "time elapsed: {}", self.time_elapsed.as_secs_f32())?;
         writeln!(f, "nodes/s: {}", self.nodes_per_second)?;
-        writeln!(f, "{}", Table::new(&self.depth_results).to_string())?;
+        writeln!(f, "{}", Table::new(&self.depth_results))?;
         Ok(())
     }
 }
@@ -79,6 +79,8 @@ pub fn perft(
     let mut position = position.clone();
     let moves = move_gen.gen_moves(&position);
     let mut perft_results: HashMap<Move, usize> = HashMap::with_capacity(moves.len());
+    // One buffer for each ply below the root, reused by every node at that ply.
+    let mut move_lists = vec![MoveList::new(); depth.saturating_sub(1)];
 
     for mve in moves {
         let unmake_move_state = position.make_move(mve);
@@ -89,7 +91,7 @@ pub fn perft(
             }
         }
 
-        let moves_count = perft_helper(&mut position, 1, depth, move_gen);
+        let moves_count = perft_helper(&mut position, &mut move_lists, move_gen);
         perft_results.insert(mve, moves_count);
 
         position.unmake_move(unmake_move_state);
@@ -100,19 +102,20 @@ pub fn perft(
     (perft_results, tot_moves)
 }
 
+/// Counts the leaves `move_lists.len()` plies below `position`, generating the moves of each ply
+/// into its own buffer.
 fn perft_helper(
     position: &mut Position,
-    curr_depth: usize,
-    max_depth: usize,
+    move_lists: &mut [MoveList],
     move_gen: impl GenerateMoves + Copy,
 ) -> usize {
-    if curr_depth == max_depth {
+    let Some((moves, child_move_lists)) = move_lists.split_first_mut() else {
         return 1;
-    }
+    };
 
     let mut moves_count = 0;
-    let moves = move_gen.gen_moves(position);
-    for mve in moves {
+    move_gen.gen_moves_into(position, moves);
+    for &mve in moves.iter() {
         let unmake_move_state = position.make_move(mve);
 
         #[cfg(debug_assertions)]
@@ -122,7 +125,7 @@ fn perft_helper(
             }
         }
 
-        let curr_move_moves_count = perft_helper(position, curr_depth + 1, max_depth, move_gen);
+        let curr_move_moves_count = perft_helper(position, child_move_lists, move_gen);
         moves_count += curr_move_moves_count;
         position.unmake_move(unmake_move_state);
     }
@@ -0,0 +1,608 @@
+use std::iter::{self, Peekable};
+use std::str::Chars;
+use std::time::Duration;
+
+use crate::algebraic_notation::{AlgebraicNotationError, line_to_san, san_to_move};
+use crate::move_gen::GenerateMoves;
+use crate::position::{FenParseError, Move, Position, Side};
+
+#[derive(thiserror::Error, Debug)]
+pub enum PgnError {
+    #[error("invalid tag pair: [{0}]")]
+    InvalidTag(String),
+
+    #[error("unterminated {0}")]
+    Unterminated(&'static str),
+
+    #[error("invalid FEN tag `{0}`: {1}")]
+    InvalidFen(String, FenParseError),
+
+    #[error("illegal or ambiguous move `{san}` in position `{fen}`")]
+    InvalidMove { san: String, fen: String },
+
+    #[error("can't write game as PGN: {0}")]
+    Export(#[from] AlgebraicNotationError),
+}
+
+/// Tags every exported game starts with, and the values written when a tag is missing.
+const SEVEN_TAG_ROSTER: [(&str, &str); 7] = [
+    ("Event", "?"),
+    ("Site", "?"),
+    ("Date", "????.??.??"),
+    ("Round", "?"),
+    ("White", "?"),
+    ("Black", "?"),
+    ("Result", "*"),
+];
+/// Exported movetext is wrapped to lines no longer than this.
+const MAX_LINE_LEN: usize = 80;
+
+/// Result of a game, as given by its movetext termination marker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PgnResult {
+    WhiteWins,
+    BlackWins,
+    Draw,
+    /// Game is still in progress, abandoned or the result isn't known (`*`).
+    Unknown,
+}
+
+impl PgnResult {
+    fn from_token(token: &str) -> Option<Self> {
+        match token {
+            "1-0" => Some(Self::WhiteWins),
+            "0-1" => Some(Self::BlackWins),
+            "1/2-1/2" => Some(Self::Draw),
+            "*" => Some(Self::Unknown),
+            _ => None,
+        }
+    }
+
+    fn to_token(self) -> &'static str {
+        match self {
+            Self::WhiteWins => "1-0",
+            Self::BlackWins => "0-1",
+            Self::Draw => "1/2-1/2",
+            Self::Unknown => "*",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PgnGame {
+    pub tags: Vec<(String, String)>,
+    /// Position the game started from, either the standard one or the `FEN` tag.
+    pub start: Position,
+    /// Mainline moves, variations aren't included.
+    pub moves: Vec<Move>,
+    /// Clock time left after each move, from `[%clk h:mm:ss]` comments. Missing entries, and
+    /// moves past the end, have no clock comment.
+    pub clocks: Vec<Option<Duration>>,
+    pub result: PgnResult,
+}
+
+impl PgnGame {
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag_name, _)| tag_name == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Replays the game, giving the start position and then the position after each move. Each
+    /// position keeps the history of the ones before it, so repetitions in the game count.
+    pub fn positions(&self) -> impl Iterator<Item = Position> + '_ {
+        let mut position = self.start.clone();
+        iter::once(self.start.clone()).chain(self.moves.iter().map(move |&mve| {
+            position.make_move(mve);
+            position.clone()
+        }))
+    }
+
+    /// Writes the game in PGN export format, checking that each move is legal. The seven tag
+    /// roster comes first, with `?` for unknown values and the `Result` tag taken from
+    /// `result`, followed by the game's other tags. Games that don't start from the standard
+    /// position and have no `FEN` tag get `SetUp` and `FEN` tags. Moves with a clock time are
+    /// followed by a `{[%clk h:mm:ss]}` comment.
+    pub fn to_pgn(&self, move_gen: impl GenerateMoves) -> Result<String, PgnError> {
+        let sans = line_to_san(&self.start, &self.moves, move_gen)?;
+
+        let mut tags: Vec<(&str, String)> = SEVEN_TAG_ROSTER
+            .iter()
+            .map(|&(name, default)| (name, self.tag(name).unwrap_or(default).to_string()))
+            .collect();
+        tags[6].1 = self.result.to_token().to_string();
+        if self.start != Position::start() && self.tag("FEN").is_none() {
+            tags.push(("SetUp", "1".to_string()));
+            tags.push(("FEN", self.start.to_fen()));
+        }
+        for (name, value) in &self.tags {
+            if !SEVEN_TAG_ROSTER
+                .iter()
+                .any(|&(roster_name, _)| roster_name == name)
+            {
+                tags.push((name, value.clone()));
+            }
+        }
+
+        let mut pgn = String::new();
+        for (name, value) in tags {
+            let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+            pgn += &format!("[{} \"{}\"]\n", name, value);
+        }
+        pgn.push('\n');
+
+        // Move numbers stay with their move, so a line is never split between them.
+        let mut full_move_counter = u32::from(self.start.state.full_move_counter);
+        let mut to_move = self.start.state.to_move;
+        let mut tokens = vec![];
+        // Black's moves get their own move number at the start and after a comment.
+        let mut after_comment = true;
+        for (move_idx, san) in sans.into_iter().enumerate() {
+            tokens.push(match to_move {
+                Side::White => format!("{}. {}", full_move_counter, san),
+                Side::Black if after_comment => format!("{}... {}", full_move_counter, san),
+                Side::Black => san,
+            });
+            after_comment = false;
+            if let Some(clock) = self.clocks.get(move_idx).copied().flatten() {
+                tokens.push(format!("{{[%clk {}]}}", format_clock(clock)));
+                after_comment = true;
+            }
+            if to_move == Side::Black {
+                full_move_counter += 1;
+            }
+            to_move = to_move.opposite_side();
+        }
+        tokens.push(self.result.to_token().to_string());
+
+        let mut line_len = 0;
+        for token in tokens {
+            if line_len > 0 && line_len + 1 + token.len() > MAX_LINE_LEN {
+                pgn.push('\n');
+                line_len = 0;
+            } else if line_len > 0 {
+                pgn.push(' ');
+                line_len += 1;
+            }
+            line_len += token.len();
+            pgn += &token;
+        }
+        pgn.push('\n');
+        Ok(pgn)
+    }
+}
+
+/// Parses every game in a PGN database, checking that each mainline move is legal.
+///
+/// Comments, NAGs and variations are skipped, apart from `[%clk]` commands in comments after a
+/// move.
+pub fn parse_pgn(
+    input: &str,
+    move_gen: impl GenerateMoves + Copy,
+) -> Result<Vec<PgnGame>, PgnError> {
+    let mut games = vec![];
+    let mut game = GameBuilder::default();
+    let mut chars = input.chars().peekable();
+
+    while let Some(&ch) = chars.peek() {
+        match ch {
+            _ if ch.is_whitespace() => {
+                chars.next();
+            }
+            '[' => {
+                chars.next();
+                let tag = take_until(&mut chars, ']').ok_or(PgnError::Unterminated("tag"))?;
+                if game.position.is_some() {
+                    // Previous game didn't have a result, this must be the start of the next one.
+                    games.push(std::mem::take(&mut game).finish(PgnResult::Unknown)?);
+                }
+                game.tags.push(parse_tag(&tag)?);
+            }
+            '{' => {
+                chars.next();
+                let comment =
+                    take_until(&mut chars, '}').ok_or(PgnError::Unterminated("comment"))?;
+                if let Some(clock) = parse_clock_comment(&comment)
+                    && let Some(last_clock) = game.clocks.last_mut()
+                {
+                    *last_clock = Some(clock);
+                }
+            }
+            ';' => {
+                take_until(&mut chars, '\n');
+            }
+            '(' => skip_variation(&mut chars)?,
+            _ => {
+                let token = take_token(&mut chars);
+                if let Some(result) = PgnResult::from_token(&token) {
+                    games.push(std::mem::take(&mut game).finish(result)?);
+                } else if !token.starts_with('$') && token != "e.p." {
+                    let san = token.trim_start_matches(|ch: char| ch.is_ascii_digit() || ch == '.');
+                    if !san.is_empty() {
+                        game.push_san(san, move_gen)?;
+                    }
+                }
+            }
+        }
+    }
+
+    if !game.tags.is_empty() || game.position.is_some() {
+        games.push(game.finish(PgnResult::Unknown)?);
+    }
+    Ok(games)
+}
+
+#[derive(Default)]
+struct GameBuilder {
+    tags: Vec<(String, String)>,
+    /// Start position and the position after the moves so far. Only set once the first move
+    /// is played, after all the tags have been read.
+    position: Option<(Position, Position)>,
+    moves: Vec<Move>,
+    clocks: Vec<Option<Duration>>,
+}
+
+impl GameBuilder {
+    fn start_position(&self) -> Result<Position, PgnError> {
+        match self.tags.iter().find(|(name, _)| name == "FEN") {
+            Some((_, fen)) => {
+                Position::from_fen(fen).map_err(|err| PgnError::InvalidFen(fen.clone(), err))
+            }
+            None => Ok(Position::start()),
+        }
+    }
+
+    fn push_san(&mut self, san: &str, move_gen: impl GenerateMoves + Copy) -> Result<(), PgnError> {
+        if self.position.is_none() {
+            let start = self.start_position()?;
+            self.position = Some((start.clone(), start));
+        }
+        let (_, position) = self.position.as_mut().unwrap();
+
+        let mve = san_to_move(position, san, move_gen).map_err(|_| PgnError::InvalidMove {
+            san: san.to_string(),
+            fen: position.to_fen(),
+        })?;
+        position.make_move(mve);
+        self.moves.push(mve);
+        self.clocks.push(None);
+        Ok(())
+    }
+
+    fn finish(self, result: PgnResult) -> Result<PgnGame, PgnError> {
+        let start = match self.position {
+            Some((start, _)) => start,
+            None => self.start_position()?,
+        };
+        Ok(PgnGame {
+            tags: self.tags,
+            start,
+            moves: self.moves,
+            clocks: self.clocks,
+            result,
+        })
+    }
+}
+
+fn parse_tag(tag: &str) -> Result<(String, String), PgnError> {
+    let err = || PgnError::InvalidTag(tag.to_string());
+    let (name, value) = tag.trim().split_once(char::is_whitespace).ok_or_else(err)?;
+    let value = value
+        .trim()
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .ok_or_else(err)?;
+    Ok((
+        name.to_string(),
+        value.replace("\\\"", "\"").replace("\\\\", "\\"),
+    ))
+}
+
+/// Formats a clock time as `h:mm:ss`, dropping fractions of a second.
+fn format_clock(clock: Duration) -> String {
+    let secs = clock.as_secs();
+    format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+/// Finds the clock time in a comment's `[%clk h:mm:ss]` command. The seconds can have a
+/// fraction.
+fn parse_clock_comment(comment: &str) -> Option<Duration> {
+    let (_, clock) = comment.split_once("[%clk")?;
+    let (clock, _) = clock.split_once(']')?;
+    let mut fields = clock.trim().split(':');
+    let hours: u64 = fields.next()?.parse().ok()?;
+    let mins: u64 = fields.next()?.parse().ok()?;
+    let secs: f64 = fields.next()?.parse().ok()?;
+    if fields.next().is_some() || mins >= 60 || !(0.0..60.0).contains(&secs) {
+        return None;
+    }
+    Some(Duration::from_secs(hours * 3600 + mins * 60) + Duration::from_secs_f64(secs))
+}
+
+/// Consumes characters up to and including `end`, returning the ones before it. Returns none if
+/// the input ends first.
+fn take_until(chars: &mut Peekable<Chars>, end: char) -> Option<String> {
+    let mut taken = String::new();
+    for ch in chars.by_ref() {
+        if ch == end {
+            return Some(taken);
+        }
+        taken.push(ch);
+    }
+    None
+}
+
+fn take_token(chars: &mut Peekable<Chars>) -> String {
+    let mut token = String::new();
+    while let Some(&ch) = chars.peek() {
+        if ch.is_whitespace() || "[]{}();".contains(ch) {
+            break;
+        }
+        token.push(ch);
+        chars.next();
+    }
+    token
+}
+
+/// Skips a variation, including any nested variations and comments inside it.
+fn skip_variation(chars: &mut Peekable<Chars>) -> Result<(), PgnError> {
+    let mut depth = 0;
+    while let Some(ch) = chars.next() {
+        match ch {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Ok(());
+                }
+            }
+            '{' => {
+                take_until(chars, '}').ok_or(PgnError::Unterminated("comment"))?;
+            }
+            _ => {}
+        }
+    }
+    Err(PgnError::Unterminated("variation"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitboard::Square::*;
+    use crate::move_gen::MOVE_GEN;
+    use crate::position::{DrawReason, Piece};
+    use test_case::test_case;
+    use testresult::TestResult;
+
+    const TWO_GAMES: &str = r#"[Event "Casual"]
+[White "A"]
+[Black "B"]
+[Result "1-0"]
+
+1. e4 e5 2. Nf3 {the main line} Nc6 (2... d6 3. d4 (3. Bc4) exd4) 3. Bb5 $1 a6 1-0
+
+[Event "Casual"]
+[Result "1/2-1/2"]
+
+1.d4 d5 2.c4 ; a comment
+dxc4 1/2-1/2
+"#;
+
+    #[test]
+    fn test_parse_pgn() -> TestResult {
+        let games = parse_pgn(TWO_GAMES, MOVE_GEN)?;
+        assert_eq!(games.len(), 2);
+
+        assert_eq!(games[0].tag("White"), Some("A"));
+        assert_eq!(games[0].result, PgnResult::WhiteWins);
+        assert_eq!(
+            games[0].moves,
+            vec![
+                Move::new(E2, E4),
+                Move::new(E7, E5),
+                Move::new(G1, F3),
+                Move::new(B8, C6),
+                Move::new(F1, B5),
+                Move::new(A7, A6),
+            ]
+        );
+
+        assert_eq!(games[1].result, PgnResult::Draw);
+        assert_eq!(games[1].moves.len(), 4);
+        assert_eq!(games[1].moves[3], Move::new(D5, C4));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_pgn_fen_tag() -> TestResult {
+        let pgn = r#"[FEN "4k3/P7/8/8/8/8/8/4K3 w - - 0 1"]
+
+1. a8=Q+ Kd7 *"#;
+        let games = parse_pgn(pgn, MOVE_GEN)?;
+        assert_eq!(games[0].start.to_fen(), "4k3/P7/8/8/8/8/8/4K3 w - - 0 1");
+        assert_eq!(
+            games[0].moves,
+            vec![
+                Move::with_promotion(A7, A8, Piece::Queen),
+                Move::new(E8, D7)
+            ]
+        );
+        assert_eq!(games[0].result, PgnResult::Unknown);
+        Ok(())
+    }
+
+    #[test_case("1. e4 e6 2. Ke3", "Ke3" ; "illegal move")]
+    #[test_case("1. Nd2", "Nd2" ; "no piece can move there")]
+    fn test_parse_pgn_invalid_move(pgn: &str, san_want: &str) {
+        match parse_pgn(pgn, MOVE_GEN) {
+            Err(PgnError::InvalidMove { san, .. }) => assert_eq!(san, san_want),
+            res => panic!("Expected invalid move error, got {:?}", res),
+        }
+    }
+
+    #[test]
+    fn test_parse_pgn_en_passant_suffix() -> TestResult {
+        let games = parse_pgn("1. e4 Nf6 2. e5 d5 3. exd6 e.p. *", MOVE_GEN)?;
+        assert_eq!(games[0].moves.last(), Some(&Move::new(E5, D6)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_positions() -> TestResult {
+        let games = parse_pgn(TWO_GAMES, MOVE_GEN)?;
+        let positions: Vec<Position> = games[1].positions().collect();
+        assert_eq!(positions.len(), games[1].moves.len() + 1);
+        assert_eq!(positions[0], Position::start());
+        assert_eq!(
+            positions[4],
+            Position::from_fen("rnbqkbnr/ppp1pppp/8/8/2pP4/8/PP2PPPP/RNBQKBNR w KQkq - 0 3")?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_positions_keep_history() -> TestResult {
+        let games = parse_pgn("1. Nf3 Nf6 2. Ng1 Ng8 3. Nf3 Nf6 4. Ng1 Ng8 *", MOVE_GEN)?;
+        let last = games[0].positions().last().ok_or("no positions")?;
+        assert_eq!(last.claimable_draw(), Some(DrawReason::ThreefoldRepetition));
+        Ok(())
+    }
+
+    #[test]
+    fn test_to_pgn() -> TestResult {
+        let game = PgnGame {
+            tags: vec![
+                ("White".to_string(), "A \"the \\ fish\"".to_string()),
+                ("Result".to_string(), "*".to_string()),
+                ("TimeControl".to_string(), "40/60".to_string()),
+            ],
+            start: Position::start(),
+            moves: vec![
+                Move::new(F2, F3),
+                Move::new(E7, E5),
+                Move::new(G2, G4),
+                Move::new(D8, H4),
+            ],
+            clocks: vec![
+                Some(Duration::from_secs(151)),
+                None,
+                Some(Duration::from_millis(3_723_900)),
+            ],
+            result: PgnResult::BlackWins,
+        };
+        let want = r#"[Event "?"]
+[Site "?"]
+[Date "????.??.??"]
+[Round "?"]
+[White "A \"the \\ fish\""]
+[Black "?"]
+[Result "0-1"]
+[TimeControl "40/60"]
+
+1. f3 {[%clk 0:02:31]} 1... e5 2. g4 {[%clk 1:02:03]} 2... Qh4# 0-1
+"#;
+        assert_eq!(game.to_pgn(MOVE_GEN)?, want);
+
+        let parsed = parse_pgn(want, MOVE_GEN)?;
+        assert_eq!(
+            parsed[0].clocks,
+            vec![
+                Some(Duration::from_secs(151)),
+                None,
+                Some(Duration::from_secs(3723)),
+                None
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_to_pgn_from_fen() -> TestResult {
+        let start = Position::from_fen("4k3/8/8/8/8/8/7p/R3K3 b Q - 0 40")?;
+        let game = PgnGame {
+            tags: vec![],
+            start: start.clone(),
+            moves: vec![
+                Move::new(E8, E7),
+                Move::new(E1, C1),
+                Move::with_promotion(H2, H1, Piece::Queen),
+            ],
+            clocks: vec![],
+            result: PgnResult::Unknown,
+        };
+        let pgn = game.to_pgn(MOVE_GEN)?;
+        assert!(pgn.contains("[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/7p/R3K3 b Q - 0 40\"]\n"));
+        assert!(pgn.ends_with("\n40... Ke7 41. O-O-O h1=Q *\n"));
+
+        let parsed = parse_pgn(&pgn, MOVE_GEN)?;
+        assert_eq!(parsed[0].start, start);
+        assert_eq!(parsed[0].moves, game.moves);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_pgn_clocks() -> TestResult {
+        let pgn = "1. e4 {[%clk 0:02:31]} 1... e5 {book [%clk 1:00:00.5] } 2. Nf3 {no clock} *";
+        let games = parse_pgn(pgn, MOVE_GEN)?;
+        assert_eq!(
+            games[0].clocks,
+            vec![
+                Some(Duration::from_secs(151)),
+                Some(Duration::from_millis(3_600_500)),
+                None
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_to_pgn_round_trip() -> TestResult {
+        for game in parse_pgn(TWO_GAMES, MOVE_GEN)? {
+            let pgn = game.to_pgn(MOVE_GEN)?;
+            let parsed = parse_pgn(&pgn, MOVE_GEN)?;
+            assert_eq!(parsed.len(), 1);
+            assert_eq!(parsed[0].moves, game.moves);
+            assert_eq!(parsed[0].clocks, game.clocks);
+            assert_eq!(parsed[0].result, game.result);
+            assert_eq!(parsed[0].tag("Event"), game.tag("Event"));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_to_pgn_wraps_lines() -> TestResult {
+        // Knights going back and forth.
+        let moves = [
+            Move::new(G1, F3),
+            Move::new(G8, F6),
+            Move::new(F3, G1),
+            Move::new(F6, G8),
+        ];
+        let game = PgnGame {
+            tags: vec![],
+            start: Position::start(),
+            moves: moves.repeat(10),
+            clocks: vec![],
+            result: PgnResult::Draw,
+        };
+        let pgn = game.to_pgn(MOVE_GEN)?;
+        let (_, movetext) = pgn.split_once("\n\n").ok_or("no movetext")?;
+        assert!(movetext.lines().count() > 1);
+        assert!(movetext.lines().all(|line| line.len() <= MAX_LINE_LEN));
+        assert_eq!(parse_pgn(&pgn, MOVE_GEN)?[0].moves, game.moves);
+        Ok(())
+    }
+
+    #[test]
+    fn test_to_pgn_illegal_move() {
+        let game = PgnGame {
+            tags: vec![],
+            start: Position::start(),
+            moves: vec![Move::new(E2, E5)],
+            clocks: vec![],
+            result: PgnResult::Unknown,
+        };
+        assert!(matches!(game.to_pgn(MOVE_GEN), Err(PgnError::Export(_))));
+    }
+}
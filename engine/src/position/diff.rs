@@ -0,0 +1,196 @@
+use crate::bitboard::Square;
+use crate::position::{Piece, Position, Side};
+
+/// A change to one piece between two positions, see [`Position::diff`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PieceDelta {
+    Moved {
+        side: Side,
+        piece: Piece,
+        from: Square,
+        to: Square,
+    },
+    /// A pawn moved to `to` and became `piece`.
+    Promoted {
+        side: Side,
+        piece: Piece,
+        from: Square,
+        to: Square,
+    },
+    Removed {
+        side: Side,
+        piece: Piece,
+        square: Square,
+    },
+    Added {
+        side: Side,
+        piece: Piece,
+        square: Square,
+    },
+}
+
+impl Position {
+    /// Pieces that differ between this position and `other`, e.g. to animate a move on a board
+    /// without knowing how castling, en passant or promotion move pieces. Moved and promoted
+    /// pieces come first, then removed pieces, then added pieces.
+    ///
+    /// A piece that left a square and a piece of the same side and type that arrived on another
+    /// count as a move, paired up in square order when there are several. For positions a move
+    /// apart this gives the move's piece, the rook when castling and the captured piece, also
+    /// when captured en passant.
+    pub fn diff(&self, other: &Position) -> Vec<PieceDelta> {
+        let (mut moved, mut removed, mut added) = (vec![], vec![], vec![]);
+        for side in Side::ALL {
+            let (mut side_removed, mut side_added) = (vec![], vec![]);
+            for piece in Piece::ALL {
+                let before = self.get_piece_bb(side, piece);
+                let after = other.get_piece_bb(side, piece);
+                let mut removed_squares = (before & !after).squares();
+                let mut added_squares = (after & !before).squares();
+                loop {
+                    match (removed_squares.next(), added_squares.next()) {
+                        (Some(from), Some(to)) => moved.push(PieceDelta::Moved {
+                            side,
+                            piece,
+                            from,
+                            to,
+                        }),
+                        (Some(square), None) => side_removed.push((piece, square)),
+                        (None, Some(square)) => side_added.push((piece, square)),
+                        (None, None) => break,
+                    }
+                }
+            }
+
+            // A side's own pieces are only removed and added by a move when it's a promotion.
+            if let (&[(Piece::Pawn, from)], &[(piece, to)]) =
+                (side_removed.as_slice(), side_added.as_slice())
+            {
+                moved.push(PieceDelta::Promoted {
+                    side,
+                    piece,
+                    from,
+                    to,
+                });
+            } else {
+                removed.extend(side_removed.into_iter().map(|(piece, square)| {
+                    PieceDelta::Removed {
+                        side,
+                        piece,
+                        square,
+                    }
+                }));
+                added.extend(
+                    side_added
+                        .into_iter()
+                        .map(|(piece, square)| PieceDelta::Added {
+                            side,
+                            piece,
+                            square,
+                        }),
+                );
+            }
+        }
+        moved.append(&mut removed);
+        moved.append(&mut added);
+        moved
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitboard::Square::*;
+    use crate::position::Move;
+    use test_case::test_case;
+    use testresult::TestResult;
+
+    #[test_case(
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        Move::new(E2, E4),
+        &[PieceDelta::Moved { side: Side::White, piece: Piece::Pawn, from: E2, to: E4 }]
+        ; "quiet move"
+    )]
+    #[test_case(
+        "4k3/8/8/3p4/4N3/8/8/4K3 w - - 0 1",
+        Move::new(E4, D6),
+        &[PieceDelta::Moved { side: Side::White, piece: Piece::Knight, from: E4, to: D6 }]
+        ; "knight move"
+    )]
+    #[test_case(
+        "4k3/8/8/3p4/4P3/8/8/4K3 w - - 0 1",
+        Move::new(E4, D5),
+        &[
+            PieceDelta::Moved { side: Side::White, piece: Piece::Pawn, from: E4, to: D5 },
+            PieceDelta::Removed { side: Side::Black, piece: Piece::Pawn, square: D5 },
+        ]
+        ; "capture"
+    )]
+    #[test_case(
+        "4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1",
+        Move::new(E5, D6),
+        &[
+            PieceDelta::Moved { side: Side::White, piece: Piece::Pawn, from: E5, to: D6 },
+            PieceDelta::Removed { side: Side::Black, piece: Piece::Pawn, square: D5 },
+        ]
+        ; "en passant"
+    )]
+    #[test_case(
+        "r3k3/8/8/8/8/8/8/4K2R b q - 0 1",
+        Move::new(E8, C8),
+        &[
+            PieceDelta::Moved { side: Side::Black, piece: Piece::Rook, from: A8, to: D8 },
+            PieceDelta::Moved { side: Side::Black, piece: Piece::King, from: E8, to: C8 },
+        ]
+        ; "castling"
+    )]
+    #[test_case(
+        "1r2k3/P7/8/8/8/8/8/4K3 w - - 0 1",
+        Move::with_promotion(A7, B8, Piece::Knight),
+        &[
+            PieceDelta::Promoted { side: Side::White, piece: Piece::Knight, from: A7, to: B8 },
+            PieceDelta::Removed { side: Side::Black, piece: Piece::Rook, square: B8 },
+        ]
+        ; "promotion capture"
+    )]
+    fn test_diff_after_move(fen: &str, mve: Move, want: &[PieceDelta]) -> TestResult {
+        let position = Position::from_fen(fen)?;
+        let mut after = position.clone();
+        after.make_move(mve);
+        assert_eq!(position.diff(&after), want);
+        Ok(())
+    }
+
+    #[test]
+    fn test_diff_unrelated_positions() -> TestResult {
+        let position = Position::from_fen("4k3/8/8/8/8/8/8/RR2K3 w - - 0 1")?;
+        let other = Position::from_fen("4k3/8/8/8/8/8/8/4K1Bb w - - 0 1")?;
+        assert_eq!(
+            position.diff(&other),
+            [
+                PieceDelta::Removed {
+                    side: Side::White,
+                    piece: Piece::Rook,
+                    square: A1
+                },
+                PieceDelta::Removed {
+                    side: Side::White,
+                    piece: Piece::Rook,
+                    square: B1
+                },
+                PieceDelta::Added {
+                    side: Side::White,
+                    piece: Piece::Bishop,
+                    square: G1
+                },
+                PieceDelta::Added {
+                    side: Side::Black,
+                    piece: Piece::Bishop,
+                    square: H1
+                },
+            ]
+        );
+        assert!(position.diff(&position).is_empty());
+        Ok(())
+    }
+}
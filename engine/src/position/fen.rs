@@ -3,27 +3,36 @@ use arrayvec::ArrayVec;
 use crate::bitboard::Square::*;
 use crate::bitboard::{BitBoard, Square};
 use crate::position::zobrist_hash::ZobristHash;
-use crate::position::{CastlingRights, Piece, Position, Side, State};
+use crate::position::{
+    CastlingRights, Piece, Position, SEVENTY_FIVE_MOVE_RULE_HALF_MOVES, Side, State,
+    can_capture_en_passant,
+};
 use std::str::FromStr;
 
 #[derive(thiserror::Error, Debug)]
 pub enum FenParseError {
-    #[error("num fields: want 6 got {0}")]
+    #[error("num fields: want 4 to 6 got {0}")]
     NumFields(usize),
 
     #[error("piece placement: got {0}, err at {1}")]
     PiecePlacement(String, usize),
 
+    #[error("piece placement: want 8 ranks got {0}")]
+    NumRanks(usize),
+
+    #[error("piece placement: want 8 squares on rank {rank} got {len}")]
+    RankLength { rank: u8, len: usize },
+
     #[error("side to move: want 'w'|'b' got {0}")]
     SideToMove(String),
 
-    #[error("castling rights given: got {0}, err at idx {1}")]
+    #[error("castling rights: invalid or repeated char in {0} at idx {1}")]
     CastlingRights(String, usize),
 
-    #[error("en passant target: got {0}")]
+    #[error("en passant target: want a square on rank 3 or 6 got {0}")]
     EnPassantTarget(String),
 
-    #[error("halfmove clock: want 0 <= x < 50 got {0}")]
+    #[error("halfmove clock: want 0 <= x <= 150 got {0}")]
     HalfmoveClock(String),
 
     #[error("full move counter: want 0 <= x < 65_535 got {0}")]
@@ -31,12 +40,16 @@ pub enum FenParseError {
 }
 
 impl Position {
+    /// Parses a FEN. GUIs and web forms often leave out the half move clock and full move
+    /// counter, so they default to 0 and 1 when missing.
     pub fn from_fen(fen: &str) -> Result<Self, FenParseError> {
-        let fields = fen.split(' ').collect::<Vec<&str>>();
+        let fields = fen.split_whitespace().collect::<Vec<&str>>();
 
-        if fields.len() != 6 {
+        if !(4..=6).contains(&fields.len()) {
             Err(FenParseError::NumFields(fields.len()))?
         }
+        let half_move_clock_str = fields.get(4).copied().unwrap_or("0");
+        let full_move_counter_str = fields.get(5).copied().unwrap_or("1");
 
         let (sides, pieces) = pieces_from_fen(fields[0])?;
 
@@ -46,23 +59,31 @@ impl Position {
             _ => Err(FenParseError::SideToMove(String::from(fields[1])))?,
         };
 
-        let half_move_clock = fields[4]
+        let half_move_clock = half_move_clock_str
             .parse::<u8>()
-            .map_err(|_| FenParseError::HalfmoveClock(fields[4].to_string()))?;
+            .map_err(|_| FenParseError::HalfmoveClock(half_move_clock_str.to_string()))?;
 
-        // Half move counter must be in 0..=49
-        // Don't have to check if less than zero because u8 min value = 0
-        if half_move_clock >= 50 {
-            Err(FenParseError::HalfmoveClock(fields[4].to_string()))?
+        // Positions past the fifty-move rule are still played in until a draw is claimed, but the
+        // seventy-five-move rule ends the game, so the clock can't go any higher.
+        if half_move_clock > SEVENTY_FIVE_MOVE_RULE_HALF_MOVES {
+            Err(FenParseError::HalfmoveClock(
+                half_move_clock_str.to_string(),
+            ))?
         }
 
-        let full_move_counter = fields[5]
+        let full_move_counter = full_move_counter_str
             .parse()
-            .map_err(|_| FenParseError::FullMoveCounter(fields[5].to_string()))?;
-
+            .map_err(|_| FenParseError::FullMoveCounter(full_move_counter_str.to_string()))?;
+
+        // Castling rights for a king or rook that isn't on its home square can never be used,
+        // so drop them rather than trusting the FEN (e.g. material odds positions). Likewise an
+        // en passant target no pawn can capture on is dropped, so the position hashes the same
+        // as when it's reached by playing the double push.
+        let en_passant_target = en_passant_target_from_fen(fields[3])?
+            .filter(|&ep_target| can_capture_en_passant(&pieces, ep_target, to_move));
         let state = State {
-            castling_rights: castling_rights_from_fen(fields[2])?,
-            en_passant_target: en_passant_target_from_fen(fields[3])?,
+            castling_rights: castling_rights_from_fen(fields[2])?.restrict_to_pieces(&pieces),
+            en_passant_target,
             half_move_clock,
             to_move,
             full_move_counter,
@@ -142,7 +163,12 @@ impl Position {
 
         format!(
             "{} {} {} {} {} {}",
-            pieces, side_to_move_char, castling_rights, en_passant, self.state.half_move_clock, 1
+            pieces,
+            side_to_move_char,
+            castling_rights,
+            en_passant,
+            self.state.half_move_clock,
+            self.state.full_move_counter
         )
     }
 }
@@ -220,9 +246,14 @@ fn en_passant_target_from_fen(
     }
 
     // FEN uses lowercase letter for square names, Square uses uppercase
-    Square::from_str(&en_passant_target_str.to_uppercase())
-        .map_err(|_| FenParseError::EnPassantTarget(en_passant_target_str.to_string()))
-        .map(Some)
+    let err = || FenParseError::EnPassantTarget(en_passant_target_str.to_string());
+    let ep_target = Square::from_str(&en_passant_target_str.to_uppercase()).map_err(|_| err())?;
+    // Only a pawn that just moved two squares can be captured en passant, which leaves the
+    // target on the 3rd or 6th rank.
+    match ep_target.to_rank_file() {
+        (2 | 5, _) => Ok(Some(ep_target)),
+        _ => Err(err()),
+    }
 }
 
 const FEN_SQUARE_ORDER: [Square; 64] = [
@@ -234,31 +265,48 @@ const FEN_SQUARE_ORDER: [Square; 64] = [
 fn pieces_from_fen(pieces_str: &str) -> Result<([BitBoard; 2], [BitBoard; 12]), FenParseError> {
     let mut sides = [BitBoard::empty(); 2];
     let mut pieces = [BitBoard::empty(); 12];
-    let mut sq_idx = 0;
+    let ranks = pieces_str.split('/').collect::<Vec<&str>>();
+    if ranks.len() != 8 {
+        Err(FenParseError::NumRanks(ranks.len()))?
+    }
 
-    for (ch_idx, ch) in pieces_str.chars().enumerate() {
-        if let Ok(piece) = Piece::try_from(ch.to_ascii_lowercase()) {
-            let square = FEN_SQUARE_ORDER[sq_idx];
-            let side = if ch.is_uppercase() {
-                Side::White
+    let mut ch_idx = 0;
+    for (rank_idx, rank_str) in ranks.into_iter().enumerate() {
+        // Squares are only set while the rank has room, so too long a rank is counted in full
+        // and then reported.
+        let mut rank_len = 0;
+        for ch in rank_str.chars() {
+            if let Ok(piece) = Piece::try_from(ch.to_ascii_lowercase()) {
+                if rank_len < 8 {
+                    let square = FEN_SQUARE_ORDER[rank_idx * 8 + rank_len];
+                    let side = if ch.is_uppercase() {
+                        Side::White
+                    } else {
+                        Side::Black
+                    };
+
+                    sides[side as usize].set_square(square);
+                    pieces[piece as usize + (side as usize * 6)].set_square(square);
+                }
+                rank_len += 1;
+            } else if let Some(digit @ 1..=8) = ch.to_digit(10) {
+                rank_len += digit as usize;
             } else {
-                Side::Black
-            };
-
-            sides[side as usize].set_square(square);
-            pieces[piece as usize + (side as usize * 6)].set_square(square);
-
-            sq_idx += 1;
-        } else if let Some(digit) = ch.to_digit(10) {
-            sq_idx += digit as usize;
-        } else if ch == '/' {
-            // pass
-        } else {
-            Err(FenParseError::PiecePlacement(
-                pieces_str.to_string(),
-                ch_idx,
-            ))?
+                Err(FenParseError::PiecePlacement(
+                    pieces_str.to_string(),
+                    ch_idx,
+                ))?
+            }
+            ch_idx += 1;
+        }
+        if rank_len != 8 {
+            Err(FenParseError::RankLength {
+                rank: 8 - rank_idx as u8,
+                len: rank_len,
+            })?
         }
+        // Skip the `/`.
+        ch_idx += 1;
     }
 
     Ok((sides, pieces))
@@ -360,10 +408,79 @@ mod tests {
     #[test_case(
         "8/8/8/4k3/8/3P4/5K2/r7 w - - 1 1" ; "random"
     )]
+    #[test_case(
+        "1R2k3/2Q5/8/8/7p/8/5P1P/6K1 b - - 7 42" ; "full move counter"
+    )]
     fn test_to_fen_string(fen: &str) -> TestResult {
         let pos = Position::from_fen(fen)?;
         let got = pos.to_fen();
         assert_eq!(got, fen);
         Ok(())
     }
+
+    #[test_case(
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/1NBQKBNR w KQkq - 0 1",
+        CastlingRights::new(true, false, true, true) ; "missing white queen rook"
+    )]
+    #[test_case(
+        "rnbqkbn1/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        CastlingRights::new(true, true, false, true) ; "missing black king rook"
+    )]
+    #[test_case(
+        "rnbq1bnr/ppppkppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        CastlingRights::new(true, true, false, false) ; "black king moved"
+    )]
+    fn test_from_fen_castling_rights_missing_pieces(fen: &str, want: CastlingRights) -> TestResult {
+        let pos = Position::from_fen(fen)?;
+        assert_eq!(pos.state.castling_rights, want);
+        Ok(())
+    }
+
+    #[test_case("rnbqkbnr/pppp1ppp/8/8/3pP3/8/PPP2PPP/RNBQKBNR b KQkq e3 0 3", Some(E3) ; "capturable")]
+    #[test_case("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1", None ; "no pawn beside")]
+    #[test_case("4k3/8/8/8/2pP4/8/8/4K3 w - d3 0 1", None ; "wrong side to move")]
+    fn test_from_fen_en_passant_target(fen: &str, want: Option<Square>) -> TestResult {
+        let pos = Position::from_fen(fen)?;
+        assert_eq!(pos.state.en_passant_target, want);
+        Ok(())
+    }
+
+    #[test_case("4k3/8/8/8/8/8/4P3/Q3K3 w - - 99 80", 99 ; "before fifty move rule")]
+    #[test_case("4k3/8/8/8/8/8/4P3/Q3K3 w - - 120 80", 120 ; "past fifty move rule")]
+    #[test_case("4k3/8/8/8/8/8/4P3/Q3K3 w - - 150 80", 150 ; "seventy five move rule")]
+    fn test_from_fen_half_move_clock(fen: &str, half_move_clock_want: u8) -> TestResult {
+        let pos = Position::from_fen(fen)?;
+        assert_eq!(pos.state.half_move_clock, half_move_clock_want);
+        Ok(())
+    }
+
+    #[test_case("4k3/8/8/8/8/8/4P3/Q3K3 w - -", "4k3/8/8/8/8/8/4P3/Q3K3 w - - 0 1" ; "no counters")]
+    #[test_case("4k3/8/8/8/8/8/4P3/Q3K3 w - - 12", "4k3/8/8/8/8/8/4P3/Q3K3 w - - 12 1" ; "no full move counter")]
+    #[test_case("  4k3/8/8/8/8/8/4P3/Q3K3  w - -  3 9\n", "4k3/8/8/8/8/8/4P3/Q3K3 w - - 3 9" ; "extra whitespace")]
+    fn test_from_fen_lenient(fen: &str, want: &str) -> TestResult {
+        assert_eq!(Position::from_fen(fen)?, Position::from_fen(want)?);
+        Ok(())
+    }
+
+    #[test_case("4k3/8/8/8/8/8/4P3/Q3K3 w -", FenParseError::NumFields(3) ; "too few fields")]
+    #[test_case("4k3/8/8/8/8/8/4P3/Q3K3 w - - 0 1 1", FenParseError::NumFields(7) ; "too many fields")]
+    #[test_case("4k3/8/8/8/8/8/Q3K3 w - - 0 1", FenParseError::NumRanks(7) ; "too few ranks")]
+    #[test_case("4k3/8/8/8/8/8/4P3/Q3K3/8 w - - 0 1", FenParseError::NumRanks(9) ; "too many ranks")]
+    #[test_case("4k3/8/8/8/8/8/4P3/Q3K4 w - - 0 1", FenParseError::RankLength { rank: 1, len: 9 } ; "long rank")]
+    #[test_case("4k3/8/8/7/8/8/4P3/Q3K3 w - - 0 1", FenParseError::RankLength { rank: 5, len: 7 } ; "short rank")]
+    #[test_case("4k3/8/8/8/8/8/4PPPPP/Q3K3 w - - 0 1", FenParseError::RankLength { rank: 2, len: 9 } ; "too many pieces on rank")]
+    #[test_case("4k3/9/8/8/8/8/4P3/Q3K3 w - - 0 1", FenParseError::PiecePlacement("4k3/9/8/8/8/8/4P3/Q3K3".to_string(), 4) ; "invalid digit")]
+    #[test_case("4k3/8/8/8/8/8/4P3/Q3K3 w KX - 0 1", FenParseError::CastlingRights("KX".to_string(), 1) ; "invalid castling char")]
+    #[test_case("4k3/8/8/8/8/8/4P3/Q3K3 w - e4 0 1", FenParseError::EnPassantTarget("e4".to_string()) ; "en passant target off rank")]
+    fn test_from_fen_invalid(fen: &str, want: FenParseError) {
+        let got = Position::from_fen(fen);
+        assert_eq!(got.map_err(|err| err.to_string()), Err(want.to_string()));
+    }
+
+    #[test_case("4k3/8/8/8/8/8/4P3/Q3K3 w - - 151 80" ; "past seventy five move rule")]
+    #[test_case("4k3/8/8/8/8/8/4P3/Q3K3 w - - 256 80" ; "too large")]
+    fn test_from_fen_half_move_clock_invalid(fen: &str) {
+        let got = Position::from_fen(fen);
+        assert!(matches!(got, Err(FenParseError::HalfmoveClock(_))));
+    }
 }
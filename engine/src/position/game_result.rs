@@ -0,0 +1,96 @@
+use crate::move_gen::GenerateMoves;
+use crate::position::{DrawReason, Position, Side};
+
+/// Whether a game is over in a position, and how, see [`Position::game_result`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameResult {
+    Ongoing,
+    /// The side to move is checkmated, and the other side wins.
+    Checkmate(Side),
+    Stalemate,
+    /// Drawn by a rule, or the side to move can claim a draw if [`DrawReason::is_claimable`].
+    DrawByRule(DrawReason),
+}
+
+impl GameResult {
+    /// Whether the game has ended. Claimable draws don't end it until they're claimed, so play
+    /// can still continue from them.
+    pub fn is_over(self) -> bool {
+        match self {
+            Self::Ongoing => false,
+            Self::DrawByRule(reason) => !reason.is_claimable(),
+            Self::Checkmate(_) | Self::Stalemate => true,
+        }
+    }
+}
+
+impl Position {
+    /// Whether the game is over, and how. Checkmate and stalemate come before draws by rule, so
+    /// a move that mates ends the game even if it also reaches the fifty-move rule. Automatic
+    /// draws come before claimable ones.
+    pub fn game_result(&self, move_gen: impl GenerateMoves) -> GameResult {
+        if move_gen.gen_moves(self).is_empty() {
+            if move_gen.gen_checkers(self).is_empty() {
+                GameResult::Stalemate
+            } else {
+                GameResult::Checkmate(self.state.to_move.opposite_side())
+            }
+        } else if let Some(reason) = self.automatic_draw().or_else(|| self.claimable_draw()) {
+            GameResult::DrawByRule(reason)
+        } else {
+            GameResult::Ongoing
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitboard::Square::*;
+    use crate::move_gen::MOVE_GEN;
+    use crate::position::Move;
+    use test_case::test_case;
+    use testresult::TestResult;
+
+    #[test_case("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", GameResult::Ongoing ; "start")]
+    #[test_case("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3", GameResult::Checkmate(Side::Black) ; "fools mate")]
+    #[test_case("6k1/5ppp/8/8/8/8/8/3R2K1 b - - 0 1", GameResult::Ongoing ; "not mated")]
+    #[test_case("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1", GameResult::Stalemate ; "stalemate")]
+    #[test_case("8/8/4k3/8/8/3K4/8/8 w - - 0 1", GameResult::DrawByRule(DrawReason::InsufficientMaterial) ; "insufficient material")]
+    #[test_case("4k3/8/8/8/8/8/4P3/4K3 w - - 100 80", GameResult::DrawByRule(DrawReason::FiftyMoveRule) ; "fifty move rule")]
+    #[test_case("3R2k1/5ppp/8/8/8/8/8/6K1 b - - 100 80", GameResult::Checkmate(Side::White) ; "mate on the fiftieth move")]
+    fn test_game_result(fen: &str, want: GameResult) -> TestResult {
+        assert_eq!(Position::from_fen(fen)?.game_result(MOVE_GEN), want);
+        Ok(())
+    }
+
+    #[test]
+    fn test_game_result_repetition() {
+        let mut position = Position::start();
+        for _ in 0..2 {
+            for mve in [
+                Move::new(G1, F3),
+                Move::new(G8, F6),
+                Move::new(F3, G1),
+                Move::new(F6, G8),
+            ] {
+                assert_eq!(position.game_result(MOVE_GEN), GameResult::Ongoing);
+                position.make_move(mve);
+            }
+        }
+        assert_eq!(
+            position.game_result(MOVE_GEN),
+            GameResult::DrawByRule(DrawReason::ThreefoldRepetition)
+        );
+        assert!(!position.game_result(MOVE_GEN).is_over());
+    }
+
+    #[test_case(GameResult::Ongoing, false ; "ongoing")]
+    #[test_case(GameResult::Checkmate(Side::White), true ; "checkmate")]
+    #[test_case(GameResult::Stalemate, true ; "stalemate")]
+    #[test_case(GameResult::DrawByRule(DrawReason::InsufficientMaterial), true ; "automatic draw")]
+    #[test_case(GameResult::DrawByRule(DrawReason::FiftyMoveRule), false ; "claimable draw")]
+    fn test_is_over(result: GameResult, want: bool) {
+        assert_eq!(result.is_over(), want);
+    }
+}
@@ -1,19 +1,23 @@
 use std::fmt;
+use std::str::FromStr;
 
 use arrayvec::ArrayVec;
-use strum::IntoEnumIterator;
-use strum_macros::{Display, EnumIter};
-use tracing::debug;
 
 use crate::bitboard::Square::*;
 use crate::bitboard::{BitBoard, Direction, Square};
+use crate::move_gen::GenerateMoves;
 
+mod diff;
 mod fen;
+mod game_result;
 mod zobrist_hash;
 
+pub use diff::PieceDelta;
+pub use fen::FenParseError;
+pub use game_result::GameResult;
 pub use zobrist_hash::ZobristHash;
 
-#[derive(thiserror::Error, Debug)]
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
 pub enum PositionError {
     #[error("char -> piece: got {0}")]
     FromCharPiece(char),
@@ -35,16 +39,28 @@ pub enum PositionError {
 
     #[error("cannot move pawn to last row without promotion: move {0}")]
     PawnMoveMissingPromotion(Move),
+
+    #[error("move isn't legal for the side to move: {0}")]
+    IllegalMove(Move),
+
+    #[error("cannot remove king for material odds: {0}")]
+    OddsRemoveKing(String),
+
+    #[error("invalid move, want long algebraic notation (e.g. e2e4, e7e8q): got {0}")]
+    ParseMove(String),
 }
 
 #[repr(u8)]
-#[derive(Debug, PartialEq, Eq, EnumIter, Clone, Copy, Display)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "strum", derive(strum_macros::EnumIter))]
 pub enum Side {
     White,
     Black,
 }
 
 impl Side {
+    pub const ALL: [Side; 2] = [Side::White, Side::Black];
+
     pub(crate) fn opposite_side(self) -> Side {
         if self == Side::White {
             Side::Black
@@ -54,8 +70,16 @@ impl Side {
     }
 }
 
+/// Displays as the variant name.
+impl fmt::Display for Side {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
 #[repr(u8)]
-#[derive(Debug, PartialEq, Eq, EnumIter, Clone, Copy, Display, Hash, PartialOrd, Ord)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "strum", derive(strum_macros::EnumIter))]
 pub enum Piece {
     Pawn,
     Knight,
@@ -66,6 +90,15 @@ pub enum Piece {
 }
 
 impl Piece {
+    pub const ALL: [Piece; 6] = [
+        Piece::Pawn,
+        Piece::Knight,
+        Piece::Bishop,
+        Piece::Rook,
+        Piece::Queen,
+        Piece::King,
+    ];
+
     pub(crate) fn is_slider(&self) -> bool {
         match self {
             Piece::Pawn | Piece::Knight | Piece::King => false,
@@ -74,14 +107,22 @@ impl Piece {
     }
 
     #[inline(always)]
+    #[cfg_attr(not(feature = "search"), allow(dead_code))]
     pub(crate) fn index(self) -> usize {
         self as usize
     }
 }
 
-impl Into<char> for Piece {
-    fn into(self) -> char {
-        match self {
+/// Displays as the variant name.
+impl fmt::Display for Piece {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+impl From<Piece> for char {
+    fn from(val: Piece) -> Self {
+        match val {
             Piece::Pawn => 'p',
             Piece::Knight => 'n',
             Piece::Bishop => 'b',
@@ -136,8 +177,8 @@ impl Move {
 impl fmt::Debug for Move {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{} -> {}", self.src, self.dest)?;
-        if self.promotion.is_some() {
-            write!(f, " ({})", self.promotion.unwrap())?;
+        if let Some(promotion) = self.promotion {
+            write!(f, " ({})", promotion)?;
         }
         Ok(())
     }
@@ -154,6 +195,32 @@ impl fmt::Display for Move {
     }
 }
 
+/// Parses moves in long algebraic notation as used by UCI, e.g. "e2e4" or "e7e8q".
+impl FromStr for Move {
+    type Err = PositionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || PositionError::ParseMove(s.to_string());
+        if !s.is_ascii() || !(s.len() == 4 || s.len() == 5) {
+            return Err(err());
+        }
+
+        let src = Square::from_str(&s[0..2].to_uppercase()).map_err(|_| err())?;
+        let dest = Square::from_str(&s[2..4].to_uppercase()).map_err(|_| err())?;
+        let promotion = s[4..]
+            .chars()
+            .next()
+            .map(|ch| Piece::try_from(ch.to_ascii_lowercase()).map_err(|_| err()))
+            .transpose()?;
+
+        Ok(Move {
+            src,
+            dest,
+            promotion,
+        })
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct CastlingRights {
     pub white_king_side: bool,
@@ -185,6 +252,30 @@ impl CastlingRights {
             black_queen_side,
         }
     }
+
+    /// Drops any castling rights whose king or rook isn't on its home square.
+    pub(crate) fn restrict_to_pieces(&self, pieces: &[BitBoard; 12]) -> Self {
+        let is_at = |side: Side, piece: Piece, square: Square| {
+            pieces[piece as usize + (side as usize * 6)].is_square_set(square)
+        };
+        let white_king_home = is_at(Side::White, Piece::King, Square::E1);
+        let black_king_home = is_at(Side::Black, Piece::King, Square::E8);
+
+        Self {
+            white_king_side: self.white_king_side
+                && white_king_home
+                && is_at(Side::White, Piece::Rook, Square::H1),
+            white_queen_side: self.white_queen_side
+                && white_king_home
+                && is_at(Side::White, Piece::Rook, Square::A1),
+            black_king_side: self.black_king_side
+                && black_king_home
+                && is_at(Side::Black, Piece::Rook, Square::H8),
+            black_queen_side: self.black_queen_side
+                && black_king_home
+                && is_at(Side::Black, Piece::Rook, Square::A8),
+        }
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -208,6 +299,66 @@ impl State {
     }
 }
 
+/// Whether `to_move` has a pawn beside the pawn that just double pushed past `ep_target`, so it
+/// could capture en passant. Only then is the en passant target part of the position, otherwise
+/// positions which only differ by an unusable target wouldn't count as repetitions. Pins aren't
+/// checked, the same as Polyglot's hashing.
+pub(crate) fn can_capture_en_passant(
+    pieces: &[BitBoard; 12],
+    ep_target: Square,
+    to_move: Side,
+) -> bool {
+    let pushed_dir = if to_move == Side::White {
+        Direction::DecRank
+    } else {
+        Direction::IncRank
+    };
+    let mut pushed_bb = BitBoard::from_square(ep_target);
+    pushed_bb.shift(pushed_dir);
+
+    let mut capturers_bb = pushed_bb;
+    capturers_bb.shift(Direction::IncFile);
+    pushed_bb.shift(Direction::DecFile);
+    capturers_bb |= pushed_bb;
+
+    !(capturers_bb & pieces[Piece::Pawn as usize + (to_move as usize * 6)]).is_empty()
+}
+
+/// Number of half moves without a capture or pawn move after which either player can claim a
+/// draw.
+pub const FIFTY_MOVE_RULE_HALF_MOVES: u8 = 100;
+/// Number of half moves without a capture or pawn move after which the game is drawn.
+pub const SEVENTY_FIVE_MOVE_RULE_HALF_MOVES: u8 = 150;
+
+/// Why a game is drawn, following the FIDE laws of chess.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DrawReason {
+    /// Same position occurred three times, draw can be claimed.
+    ThreefoldRepetition,
+    /// 50 moves by each side without a capture or pawn move, draw can be claimed.
+    FiftyMoveRule,
+    /// Same position occurred five times, the game is drawn.
+    FivefoldRepetition,
+    /// 75 moves by each side without a capture or pawn move, the game is drawn.
+    SeventyFiveMoveRule,
+    /// Neither side has enough material left to mate, the game is drawn.
+    InsufficientMaterial,
+}
+
+impl DrawReason {
+    /// Whether a player has to claim the draw, rather than it ending the game immediately.
+    pub fn is_claimable(self) -> bool {
+        matches!(self, Self::ThreefoldRepetition | Self::FiftyMoveRule)
+    }
+}
+
+/// Displays as the variant name.
+impl fmt::Display for DrawReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct UnmakeMoveState {
     mve: Move,
@@ -272,6 +423,31 @@ impl Position {
         }
     }
 
+    /// The start position with the pieces on `squares` removed, for games played at material
+    /// odds (e.g. `&[B1]` for white playing without the queen's knight). Castling rights are
+    /// dropped for any rook that was removed.
+    pub fn start_with_odds(squares: &[Square]) -> Result<Self, PositionError> {
+        let mut position = Position::start();
+
+        for &square in squares {
+            let (piece, side) = position
+                .is_piece_at_no_side(square)
+                .ok_or_else(|| PositionError::RemoveNoPiece(square.to_string()))?;
+            if piece == Piece::King {
+                return Err(PositionError::OddsRemoveKing(square.to_string()));
+            }
+            position.remove_piece(square, piece, side);
+        }
+
+        position.state.castling_rights = position
+            .state
+            .castling_rights
+            .restrict_to_pieces(&position.pieces);
+        position.zobrist_hash = ZobristHash::calculate(&position.pieces, &position.state);
+
+        Ok(position)
+    }
+
     pub(crate) fn get_piece_bb(&self, side: Side, piece: Piece) -> BitBoard {
         self.pieces[piece as usize + (side as usize * 6)]
     }
@@ -288,7 +464,8 @@ impl Position {
         &mut self.sides[side as usize]
     }
 
-    pub(crate) fn occupancy_bb(&self) -> BitBoard {
+    /// Squares with a piece of either side on them.
+    pub fn occupancy_bb(&self) -> BitBoard {
         self.sides[0] | self.sides[1]
     }
 
@@ -319,11 +496,6 @@ impl Position {
     }
 
     pub fn make_move(&mut self, mve: Move) -> UnmakeMoveState {
-        debug_assert!(
-            self.state.half_move_clock < 50,
-            "Game is over the half move clock"
-        );
-
         let side = self.state.to_move;
         let opp_side = side.opposite_side();
 
@@ -429,12 +601,14 @@ impl Position {
                 ep_target_bb.shift(ep_dir);
                 let ep_target = ep_target_bb.to_square();
 
-                self.state.en_passant_target = Some(ep_target);
-                self.zobrist_hash.flip_en_passant_file(ep_target);
+                if can_capture_en_passant(&self.pieces, ep_target, opp_side) {
+                    self.state.en_passant_target = Some(ep_target);
+                    self.zobrist_hash.flip_en_passant_file(ep_target);
+                }
             }
 
             // Promotion
-            if mve.dest >= A8 || mve.dest <= H1 {
+            if mve.dest.is_promotion_rank(side) {
                 let promotion = mve
                     .promotion
                     .expect("Pawn moved to end of board, expected promotion");
@@ -509,6 +683,32 @@ impl Position {
         unmake_move_state
     }
 
+    /// Like [`Position::make_move`], but checks the move is legal for the side to move first
+    /// instead of panicking or corrupting the position. The position isn't changed if the move
+    /// is rejected.
+    pub fn try_make_move(
+        &mut self,
+        mve: Move,
+        move_gen: impl GenerateMoves,
+    ) -> Result<UnmakeMoveState, PositionError> {
+        let to_move = self.state.to_move;
+        match self.is_piece_at_no_side(mve.src) {
+            None => return Err(PositionError::MoveNoPiece(mve.src.to_string())),
+            Some((Piece::Pawn, side))
+                if side == to_move
+                    && mve.promotion.is_none()
+                    && mve.dest.is_promotion_rank(to_move) =>
+            {
+                return Err(PositionError::PawnMoveMissingPromotion(mve));
+            }
+            _ => {}
+        }
+        if !move_gen.gen_moves(self).contains(&mve) {
+            return Err(PositionError::IllegalMove(mve));
+        }
+        Ok(self.make_move(mve))
+    }
+
     pub fn unmake_move(&mut self, unmake_move_state: UnmakeMoveState) {
         let mve = unmake_move_state.mve;
         let opp_side = self.state.to_move;
@@ -587,7 +787,7 @@ impl Position {
 
         self.state.half_move_clock += 1;
 
-        return prev_en_passant_target;
+        prev_en_passant_target
     }
 
     pub fn unmake_null_move(&mut self, prev_en_passant_target: Option<Square>) {
@@ -608,7 +808,7 @@ impl Position {
 
     fn add_piece(&mut self, square: Square, piece: Piece, side: Side) {
         debug_assert!(
-            !self.is_piece_at(square, side).is_some(),
+            self.is_piece_at(square, side).is_none(),
             "Piece already at {:?}",
             square
         );
@@ -639,7 +839,7 @@ impl Position {
             src_square
         );
         debug_assert!(
-            !self.is_piece_at(dest_square, side).is_some(),
+            self.is_piece_at(dest_square, side).is_none(),
             "Piece already at {:?}",
             dest_square
         );
@@ -657,64 +857,108 @@ impl Position {
         PieceLocsIter::new(self)
     }
 
+    /// Whether the game is drawn or either player could claim a draw. The search scores both the
+    /// same, since claiming a draw is always available to the side it would help.
     pub fn is_draw(&self) -> bool {
-        self.state.half_move_clock >= 50 || self.is_threefold_repetition() || !self.is_sufficient_mating_material()
+        self.automatic_draw().is_some() || self.claimable_draw().is_some()
+    }
+
+    /// Draw that ends the game immediately, without either player claiming it.
+    pub fn automatic_draw(&self) -> Option<DrawReason> {
+        if self.num_previous_occurrences() >= 4 {
+            Some(DrawReason::FivefoldRepetition)
+        } else if self.state.half_move_clock >= SEVENTY_FIVE_MOVE_RULE_HALF_MOVES {
+            Some(DrawReason::SeventyFiveMoveRule)
+        } else if self.is_insufficient_material() {
+            Some(DrawReason::InsufficientMaterial)
+        } else {
+            None
+        }
+    }
+
+    /// Draw the player to move can claim. Automatic draws aren't included.
+    pub fn claimable_draw(&self) -> Option<DrawReason> {
+        if self.automatic_draw().is_some() {
+            None
+        } else if self.is_threefold_repetition() {
+            Some(DrawReason::ThreefoldRepetition)
+        } else if self.state.half_move_clock >= FIFTY_MOVE_RULE_HALF_MOVES {
+            Some(DrawReason::FiftyMoveRule)
+        } else {
+            None
+        }
     }
 
     pub fn is_threefold_repetition(&self) -> bool {
-        let occurences = self
-            .history
+        self.num_previous_occurrences() >= 2
+    }
+
+    fn num_previous_occurrences(&self) -> usize {
+        self.history
             .iter()
             .filter(|&&h| h == self.zobrist_hash)
-            .count();
-        occurences >= 2
+            .count()
     }
 
     pub fn is_repetition_possible(&self) -> bool {
-        self
-            .history
-            .iter()
-            .any(|&h| h == self.zobrist_hash)
+        self.history.contains(&self.zobrist_hash)
     }
 
-    fn has_bishop_pair(&self, side: Side) -> bool {
-        let bishops = self.get_piece_bb(side, Piece::Bishop);
-
-        if bishops.num_squares_set() < 2 {
-            return false;
+    /// Whether the result of searching `plies` ahead from here can depend on how this position
+    /// was reached, rather than only on the position itself. That's the case if the fifty-move
+    /// rule is within reach, or if this position or one since the last capture or pawn move
+    /// has already occurred, so that repeating it (again) can draw.
+    pub fn is_path_dependent_draw_possible(&self, plies: u8) -> bool {
+        if u16::from(self.state.half_move_clock) + u16::from(plies)
+            >= u16::from(FIFTY_MOVE_RULE_HALF_MOVES)
+        {
+            return true;
         }
+        let reversible_history = &self.history[self
+            .history
+            .len()
+            .saturating_sub(self.state.half_move_clock.into())..];
+        self.is_repetition_possible()
+            || reversible_history
+                .iter()
+                .enumerate()
+                .any(|(idx, hash)| reversible_history[idx + 1..].contains(hash))
+    }
 
+    /// Whether neither side can checkmate by any sequence of legal moves, which ends the game in
+    /// a draw: king against king, king and knight against king, or kings and bishops only with
+    /// every bishop on squares of the same color.
+    pub fn is_insufficient_material(&self) -> bool {
         const LIGHT_SQUARES: BitBoard = BitBoard::from_val(0x55AA55AA55AA55AA);
-        const DARK_SQUARES: BitBoard = BitBoard::from_val(0xAA55AA55AA55AA55);
 
-        let has_light_bishop = !(bishops & LIGHT_SQUARES).is_empty();
-        let has_dark_bishop = !(bishops & DARK_SQUARES).is_empty();
+        let pieces =
+            |piece| self.get_piece_bb(Side::White, piece) | self.get_piece_bb(Side::Black, piece);
+        if !(pieces(Piece::Pawn) | pieces(Piece::Rook) | pieces(Piece::Queen)).is_empty() {
+            return false;
+        }
 
-        has_light_bishop && has_dark_bishop
+        let knights = pieces(Piece::Knight);
+        let bishops = pieces(Piece::Bishop);
+        match (knights.num_squares_set(), bishops.num_squares_set()) {
+            (0, 0) | (1, 0) => true,
+            (0, _) => {
+                (bishops & LIGHT_SQUARES).is_empty() || (bishops & !LIGHT_SQUARES).is_empty()
+            }
+            _ => false,
+        }
     }
 
-    /// Source: https://rustic-chess.org/board_functionality/detecting_cant_force_mate.html
-    pub fn is_sufficient_mating_material(&self) -> bool {
-        !self.get_piece_bb(Side::White, Piece::Queen).is_empty()
-            || !self.get_piece_bb(Side::Black, Piece::Queen).is_empty()
-            || !self.get_piece_bb(Side::White, Piece::Rook).is_empty()
-            || !self.get_piece_bb(Side::Black, Piece::Rook).is_empty()
-            || !self.get_piece_bb(Side::White, Piece::Pawn).is_empty()
-            || !self.get_piece_bb(Side::Black, Piece::Pawn).is_empty()
-            || self.has_bishop_pair(Side::White)
-            || self.has_bishop_pair(Side::Black)
-            || (!self.get_piece_bb(Side::White, Piece::Bishop).is_empty() && !self.get_piece_bb(Side::White, Piece::Knight).is_empty())
-            || (!self.get_piece_bb(Side::Black, Piece::Bishop).is_empty() && !self.get_piece_bb(Side::Black, Piece::Knight).is_empty())
-            || self.get_piece_bb(Side::White, Piece::Knight).num_squares_set() >= 3
-            || self.get_piece_bb(Side::Black, Piece::Knight).num_squares_set() >= 3
+    /// `side`'s pieces other than its pawns and king.
+    pub(crate) fn non_pawn_material_bb(&self, side: Side) -> BitBoard {
+        self.get_side_bb(side) & !(self.get_piece_bb(side, Piece::Pawn) | self.get_piece_bb(side, Piece::King))
     }
 
-    pub fn has_non_pawn_material(&self) -> bool {
-        let occ_no_kings = self.occupancy_bb() & !(self.get_piece_bb(Side::White, Piece::King) | self.get_piece_bb(Side::Black, Piece::King));
-        occ_no_kings != (self.get_piece_bb(Side::White, Piece::Pawn) | self.get_piece_bb(Side::Black, Piece::Pawn))
+    /// Whether `side` has a piece other than its pawns and king. A side without one is the one
+    /// most likely to be in zugzwang, where every move it has makes its position worse.
+    pub fn has_non_pawn_material(&self, side: Side) -> bool {
+        !self.non_pawn_material_bb(side).is_empty()
     }
 
-    #[allow(dead_code)]
     pub(crate) fn validate_position(&self, mve: Move) -> Result<(), String> {
         if self.get_piece_bb(Side::White, Piece::King).is_empty() {
             return Err("White king missing".to_string());
@@ -723,9 +967,9 @@ impl Position {
             return Err("Black king missing".to_string());
         }
 
-        let pieces_vec: Vec<_> = Piece::iter().collect();
+        let pieces_vec = Piece::ALL.to_vec();
         // Check that no pieces have the same square set.
-        for side in Side::iter() {
+        for side in Side::ALL {
             for (idx, &piece_outer) in pieces_vec.iter().enumerate() {
                 for &piece_inner in &pieces_vec[idx + 1..] {
                     let bb_outer = self.get_piece_bb(side, piece_outer);
@@ -873,12 +1117,35 @@ impl<'a> Iterator for PieceLocsIter<'a> {
 
 #[cfg(test)]
 mod tests {
-    use std::collections::HashSet;
-
     use super::*;
+    use crate::MOVE_GEN;
     use test_case::test_case;
     use testresult::TestResult;
 
+    #[test_case(Position::start(), Move::new(E2, E4) ; "pawn push")]
+    #[test_case(Position::from_fen("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1").unwrap(), Move::with_promotion(B7, B8, Piece::Queen) ; "promotion")]
+    fn test_try_make_move(mut position: Position, mve: Move) -> TestResult {
+        let mut want = position.clone();
+        want.make_move(mve);
+
+        position.try_make_move(mve, MOVE_GEN)?;
+        assert_eq!(position, want);
+        Ok(())
+    }
+
+    #[test_case(Position::start(), Move::new(E4, E5), PositionError::MoveNoPiece("E4".to_string()) ; "no piece")]
+    #[test_case(Position::start(), Move::new(E7, E5), PositionError::IllegalMove(Move::new(E7, E5)) ; "other side's piece")]
+    #[test_case(Position::start(), Move::new(E2, E5), PositionError::IllegalMove(Move::new(E2, E5)) ; "not a legal destination")]
+    #[test_case(Position::from_fen("4k3/8/8/8/8/8/r7/4K3 w - - 0 1").unwrap(), Move::new(E1, E2), PositionError::IllegalMove(Move::new(E1, E2)) ; "into check")]
+    #[test_case(Position::from_fen("k7/8/8/8/4r3/8/8/4K3 w - - 0 1").unwrap(), Move::new(E1, E2), PositionError::IllegalMove(Move::new(E1, E2)) ; "stays in check")]
+    #[test_case(Position::from_fen("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1").unwrap(), Move::new(B7, B8), PositionError::PawnMoveMissingPromotion(Move::new(B7, B8)) ; "missing promotion")]
+    fn test_try_make_move_rejected(mut position: Position, mve: Move, err_want: PositionError) {
+        let before = position.clone();
+
+        assert_eq!(position.try_make_move(mve, MOVE_GEN).unwrap_err(), err_want);
+        assert_eq!(position, before);
+    }
+
     #[test]
     fn test_state_start() {
         let pos = Position::start();
@@ -893,6 +1160,22 @@ mod tests {
         assert_eq!(pos.state.to_move, Side::White);
     }
 
+    #[test_case(&[B1], "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/R1BQKBNR w KQkq - 0 1" ; "knight odds")]
+    #[test_case(&[A1], "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/1NBQKBNR w Kkq - 0 1" ; "rook odds")]
+    #[test_case(&[F7], "rnbqkbnr/ppppp1pp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1" ; "pawn and move")]
+    fn test_start_with_odds(squares: &[Square], want_fen: &str) -> TestResult {
+        let pos = Position::start_with_odds(squares)?;
+        assert_eq!(pos.to_fen(), want_fen);
+        assert_eq!(pos.zobrist_hash, Position::from_fen(want_fen)?.zobrist_hash);
+        Ok(())
+    }
+
+    #[test_case(&[E1] ; "king")]
+    #[test_case(&[E4] ; "empty square")]
+    fn test_start_with_odds_invalid(squares: &[Square]) {
+        assert!(Position::start_with_odds(squares).is_err());
+    }
+
     #[test_case(Position::start(), Move::new(D2, D4), Side::White)]
     fn test_make_move(mut position: Position, mve: Move, side: Side) {
         assert!(position.is_piece_at(mve.src, side).is_some());
@@ -925,6 +1208,21 @@ mod tests {
         Ok(())
     }
 
+    #[test_case(Position::start(), Move::new(E2, E4) ; "no pawn beside")]
+    #[test_case(Position::from_fen("4k3/8/8/8/1P6/8/P7/4K3 w - - 0 1").unwrap(),
+        Move::new(A2, A4) ; "own pawn beside")]
+    #[test_case(Position::from_fen("4k3/8/8/8/p7/8/7P/4K3 w - - 0 1").unwrap(),
+        Move::new(H2, H4) ; "enemy pawn on other edge")]
+    fn test_make_move_no_ep_target(mut position: Position, mve: Move) -> TestResult {
+        position.make_move(mve);
+        assert_eq!(position.state.en_passant_target, None);
+        assert_eq!(
+            position.zobrist_hash,
+            ZobristHash::calculate(&position.pieces, &position.state)
+        );
+        Ok(())
+    }
+
     #[test_case(Move::new(A1, G7), "A1 -> G7" ; "no promotion")]
     #[test_case(Move::with_promotion(F7, B6, Piece::Queen), "F7 -> B6 (Queen)" ; "with promotion")]
     fn test_move_debug(mve: Move, want: &str) {
@@ -932,6 +1230,20 @@ mod tests {
         assert_eq!(got, want);
     }
 
+    #[test_case("e2e4", Move::new(E2, E4) ; "simple")]
+    #[test_case("E7E8Q", Move::with_promotion(E7, E8, Piece::Queen) ; "uppercase promotion")]
+    fn test_move_from_str(input: &str, want: Move) -> TestResult {
+        assert_eq!(Move::from_str(input)?, want);
+        Ok(())
+    }
+
+    #[test_case("e2" ; "too short")]
+    #[test_case("e2e9" ; "bad square")]
+    #[test_case("e7e8x" ; "bad promotion")]
+    fn test_move_from_str_invalid(input: &str) {
+        assert!(matches!(Move::from_str(input), Err(PositionError::ParseMove(_))));
+    }
+
     #[test_case(Position::from_fen("7k/8/4q3/8/8/4R3/5P2/K7 b - - 0 1").unwrap(), Move::new(E6, E3), true)]
     #[test_case(Position::from_fen("7k/8/4q3/8/8/4R3/5P2/K7 b - - 0 1").unwrap(), Move::new(E6, E4), false)]
     fn test_is_capture(position: Position, mve: Move, is_capture_want: bool) {
@@ -1039,10 +1351,107 @@ mod tests {
         assert_eq!(res_got, res_want);
     }
 
-    #[test_case(Position::from_fen("k7/8/8/3pppp1/1PPPP3/8/8/K7 w - - 0 1").unwrap(), false)]
-    #[test_case(Position::from_fen("k6n/8/8/3pppp1/1PPPP3/8/8/K7 w - - 0 1").unwrap(), true)]
-    fn test_has_non_pawn_material(position: Position, res_want: bool) {
-        let res_got = position.has_non_pawn_material();
+    #[test_case(4, None, Some(DrawReason::ThreefoldRepetition) ; "threefold repetition")]
+    #[test_case(8, Some(DrawReason::FivefoldRepetition), None ; "fivefold repetition")]
+    #[test_case(3, None, None ; "no repetition")]
+    fn test_repetition_draws(
+        num_knight_shuffles: usize,
+        automatic_want: Option<DrawReason>,
+        claimable_want: Option<DrawReason>,
+    ) {
+        let mut position = Position::start();
+        let shuffle = [
+            Move::new(G1, F3),
+            Move::new(G8, F6),
+            Move::new(F3, G1),
+            Move::new(F6, G8),
+        ];
+        for mve in shuffle.iter().cycle().take(num_knight_shuffles * 2) {
+            position.make_move(*mve);
+        }
+
+        assert_eq!(position.automatic_draw(), automatic_want);
+        assert_eq!(position.claimable_draw(), claimable_want);
+        assert_eq!(
+            position.is_draw(),
+            automatic_want.is_some() || claimable_want.is_some()
+        );
+    }
+
+    #[test_case(99, None, None ; "before fifty move rule")]
+    #[test_case(100, None, Some(DrawReason::FiftyMoveRule) ; "fifty move rule")]
+    #[test_case(150, Some(DrawReason::SeventyFiveMoveRule), None ; "seventy five move rule")]
+    fn test_half_move_clock_draws(
+        half_move_clock: u8,
+        automatic_want: Option<DrawReason>,
+        claimable_want: Option<DrawReason>,
+    ) {
+        let mut position = Position::start();
+        position.state.half_move_clock = half_move_clock;
+
+        assert_eq!(position.automatic_draw(), automatic_want);
+        assert_eq!(position.claimable_draw(), claimable_want);
+    }
+
+    #[test_case(0, 2, 10, false ; "no repetitions")]
+    #[test_case(0, 4, 10, true ; "earlier position repeated")]
+    #[test_case(90, 2, 5, false ; "fifty move rule out of reach")]
+    #[test_case(90, 2, 10, true ; "fifty move rule in reach")]
+    fn test_is_path_dependent_draw_possible(
+        initial_half_move_clock: u8,
+        num_knight_moves: usize,
+        plies: u8,
+        res_want: bool,
+    ) {
+        let mut position = Position::start();
+        position.state.half_move_clock = initial_half_move_clock;
+        // Shuffle the knights, then play a new move so the final position itself hasn't
+        // occurred before. An earlier position only repeats once the shuffle is completed.
+        let shuffle = [
+            Move::new(G1, F3),
+            Move::new(G8, F6),
+            Move::new(F3, G1),
+            Move::new(F6, G8),
+        ];
+        for mve in shuffle.iter().cycle().take(num_knight_moves + 1) {
+            position.make_move(*mve);
+        }
+        position.make_move(Move::new(B8, C6));
+
+        assert_eq!(position.is_path_dependent_draw_possible(plies), res_want);
+    }
+
+    #[test]
+    fn test_insufficient_material_is_automatic_draw() {
+        let position = Position::from_fen("k7/8/8/8/8/8/8/K6N w - - 0 1").unwrap();
+        assert_eq!(
+            position.automatic_draw(),
+            Some(DrawReason::InsufficientMaterial)
+        );
+        assert!(!DrawReason::InsufficientMaterial.is_claimable());
+        assert!(DrawReason::ThreefoldRepetition.is_claimable());
+    }
+
+    #[test_case("k7/8/8/8/8/8/8/K7 w - - 0 1", true ; "kings only")]
+    #[test_case("k7/8/8/8/8/8/8/K6N w - - 0 1", true ; "knight")]
+    #[test_case("4k3/8/8/8/8/8/8/K6B w - - 0 1", true ; "bishop")]
+    #[test_case("4k1b1/8/8/8/8/8/8/K6B w - - 0 1", true ; "bishops on same color")]
+    #[test_case("1b2k3/8/8/8/8/8/8/K6B w - - 0 1", false ; "bishops on different colors")]
+    #[test_case("k6n/8/8/8/8/8/8/K6N w - - 0 1", false ; "knight each")]
+    #[test_case("k7/8/8/8/8/8/8/K5NN w - - 0 1", false ; "two knights")]
+    #[test_case("4k3/8/8/8/8/8/8/K5BN w - - 0 1", false ; "bishop and knight")]
+    #[test_case("k7/8/8/8/8/8/P7/K7 w - - 0 1", false ; "pawn")]
+    #[test_case("k7/8/8/8/8/8/8/K6R w - - 0 1", false ; "rook")]
+    fn test_is_insufficient_material(fen: &str, want: bool) -> TestResult {
+        assert_eq!(Position::from_fen(fen)?.is_insufficient_material(), want);
+        Ok(())
+    }
+
+    #[test_case(Position::from_fen("k7/8/8/3pppp1/1PPPP3/8/8/K7 w - - 0 1").unwrap(), Side::White, false)]
+    #[test_case(Position::from_fen("k6n/8/8/3pppp1/1PPPP3/8/8/K7 w - - 0 1").unwrap(), Side::Black, true)]
+    #[test_case(Position::from_fen("k6n/8/8/3pppp1/1PPPP3/8/8/K7 w - - 0 1").unwrap(), Side::White, false ; "only other side")]
+    fn test_has_non_pawn_material(position: Position, side: Side, res_want: bool) {
+        let res_got = position.has_non_pawn_material(side);
 
         assert_eq!(res_got, res_want);
     }
@@ -1,7 +1,5 @@
 use std::fmt::Display;
 
-use strum::IntoEnumIterator;
-
 use crate::{Piece, Side, Square, bitboard::BitBoard, position::State};
 
 const RNG_SEED: u64 = 123456789;
@@ -22,7 +20,7 @@ impl RandomU64Generator {
         x ^= x >> 7;
         x ^= x << 5;
         self.curr = x;
-        return x;
+        x
     }
 }
 
@@ -89,9 +87,9 @@ impl ZobristHash {
 
     pub fn calculate(pieces: &[BitBoard; 12], state: &State) -> ZobristHash {
         let mut hash = 0;
-        for side in Side::iter() {
-            for square in Square::iter() {
-                for piece in Piece::iter() {
+        for side in Side::ALL {
+            for square in Square::ALL {
+                for piece in Piece::ALL {
                     let bb_idx = piece as usize + (side as usize * 6);
                     if pieces[bb_idx].is_square_set(square) {
                         hash ^= ZOBRIST_RANDOM_HASHES.pieces[bb_idx * 64 + (square as usize)];
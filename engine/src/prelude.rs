@@ -0,0 +1,41 @@
+//! The stable API: types and functions downstream users can rely on across releases.
+//!
+//! ```
+//! use std::sync::Arc;
+//! use std::sync::atomic::AtomicBool;
+//!
+//! use engine::prelude::*;
+//!
+//! # #[cfg(feature = "search")] {
+//! let params = SearchParams {
+//!     max_depth: Some(2),
+//!     ..SearchParams::default()
+//! };
+//! let (best_move, _) = search(
+//!     &Position::start(),
+//!     &params,
+//!     MOVE_GEN,
+//!     POSITION_EVALUATOR,
+//!     &mut TranspositionTable::new(),
+//!     Arc::new(AtomicBool::new(false)),
+//! )
+//! .unwrap();
+//! assert!(best_move.is_some());
+//! # }
+//! ```
+//!
+//! Everything exported from the crate root that isn't here is hidden from the docs. It's public
+//! for the cli and tools in this repository, and can change in any release. The search and its
+//! types need the `search` feature, which is on by default.
+
+pub use crate::{
+    AbsoluteEval, BitBoard, DrawReason, Eval, EvalBounds, EvaluatePosition, FenParseError,
+    GameResult, GenerateMoves, MAX_MOVES, MOVE_GEN, Move, MoveGen, MoveList, POSITION_EVALUATOR,
+    ParseSquareError, Piece, PieceDelta, Position, PositionError, RelativeEval, Side, Square,
+    ZobristHash, attacks_from, attacks_to_by,
+};
+#[cfg(feature = "search")]
+pub use crate::{
+    InfoCallback, MoveFilter, PonderHit, SearchError, SearchInfo, SearchParams, SearchResultInfo,
+    SearchSummary, SearchTuning, TranspositionTable, search,
+};
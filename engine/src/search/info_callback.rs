@@ -0,0 +1,142 @@
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::evaluation::RelativeEval;
+use crate::position::Move;
+
+/// Progress of a running search, reported after every completed iteration and periodically
+/// while one is running. Displays as a UCI `info` line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchInfo {
+    pub depth: u8,
+    pub seldepth: u8,
+    pub multi_pv: usize,
+    pub score: RelativeEval,
+    pub nodes: u64,
+    pub nps: f32,
+    pub tt_hitrate: f64,
+    pub time: Duration,
+    /// Empty while an iteration is still running.
+    pub pv: Vec<Move>,
+}
+
+impl fmt::Display for SearchInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "info depth {} seldepth {} multipv {} score {} nodes {} nps {:.0} hashfull {} tbhits {} tthitrate {:.2} time {} pv {}",
+            self.depth,
+            self.seldepth,
+            self.multi_pv,
+            self.score,
+            self.nodes,
+            self.nps,
+            0,
+            0,
+            self.tt_hitrate,
+            self.time.as_millis(),
+            self.pv
+                .iter()
+                .map(|mve| mve.to_string().to_ascii_lowercase())
+                .collect::<Vec<_>>()
+                .join(" "),
+        )
+    }
+}
+
+/// Totals of a finished search, reported once before its best move. Displays as a UCI `info`
+/// line whose string gives the transposition table hits and the best move in SAN.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchSummary {
+    pub nodes: u64,
+    pub time: Duration,
+    pub tt_hits: u64,
+    /// None if there was no legal move.
+    pub best_move_san: Option<String>,
+}
+
+impl fmt::Display for SearchSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "info nodes {} nps {:.0} time {} string tthits {} bestmove {}",
+            self.nodes,
+            self.nodes as f64 / self.time.as_secs_f64().max(f64::EPSILON),
+            self.time.as_millis(),
+            self.tt_hits,
+            self.best_move_san.as_deref().unwrap_or("(none)"),
+        )
+    }
+}
+
+/// Receives the [`SearchInfo`] the main search thread reports. This is how embedders get search
+/// progress, since the engine doesn't print anything itself.
+#[derive(Clone)]
+pub struct InfoCallback(Arc<dyn Fn(&SearchInfo) + Send + Sync>);
+
+impl InfoCallback {
+    pub fn new(callback: impl Fn(&SearchInfo) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(callback))
+    }
+
+    pub(crate) fn report(&self, info: &SearchInfo) {
+        (self.0)(info)
+    }
+}
+
+impl fmt::Debug for InfoCallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("InfoCallback").finish_non_exhaustive()
+    }
+}
+
+/// Callbacks are compared by identity, like [`super::MoveFilter`].
+impl PartialEq for InfoCallback {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for InfoCallback {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Square::*;
+
+    #[test]
+    fn test_search_info_display() {
+        let info = SearchInfo {
+            depth: 3,
+            seldepth: 7,
+            multi_pv: 1,
+            score: RelativeEval::cp(25),
+            nodes: 1200,
+            nps: 60000.4,
+            tt_hitrate: 0.123,
+            time: Duration::from_millis(20),
+            pv: vec![Move::new(E2, E4), Move::new(E7, E5)],
+        };
+
+        assert_eq!(
+            info.to_string(),
+            "info depth 3 seldepth 7 multipv 1 score cp 25 nodes 1200 nps 60000 hashfull 0 tbhits 0 tthitrate 0.12 time 20 pv e2e4 e7e5"
+        );
+    }
+
+    #[test]
+    fn test_search_summary_display() {
+        let summary = SearchSummary {
+            nodes: 1200,
+            time: Duration::from_millis(20),
+            tt_hits: 300,
+            best_move_san: Some("Nf3".to_string()),
+        };
+
+        assert_eq!(
+            summary.to_string(),
+            "info nodes 1200 nps 60000 time 20 string tthits 300 bestmove Nf3"
+        );
+    }
+}
@@ -0,0 +1,375 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
+use crate::move_gen::GenerateMoves;
+use crate::position::{Move, Position, Side, ZobristHash};
+
+/// Finds a forced mate for the side to move within `max_plies` plies, returning the mating line.
+///
+/// Unlike [`crate::search`], this doesn't evaluate positions at all: it's a proof-number search,
+/// which expands whichever position is quickest to prove or disprove, so forcing lines with few
+/// replies like checks are looked at first. The move limit is raised one move at a time so the
+/// shortest mate is found, and positions already solved are reused from a cache. The defender's
+/// moves in the returned line are the ones that delay mate the longest.
+///
+/// Returns none if there's no mate, or an error if more than `max_nodes` positions would have to
+/// be looked at to find out, since every one of them is kept in memory.
+pub fn solve_mate(
+    position: &Position,
+    max_plies: u8,
+    move_gen: impl GenerateMoves + Copy,
+    max_nodes: usize,
+) -> Result<Option<Vec<Move>>, MateSolverError> {
+    let mut position = position.clone();
+    let mut solver = MateSolver::new(position.state.to_move, move_gen, max_nodes);
+    let max_moves = max_plies.div_ceil(2);
+
+    for moves in 1..=max_moves {
+        if solver.prove(&mut position, moves)? {
+            return Ok(Some(solver.mating_line(position, moves)));
+        }
+    }
+    Ok(None)
+}
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum MateSolverError {
+    #[error("looked at the limit of {0} positions without solving")]
+    NodeLimit(usize),
+}
+
+/// Proof and disproof number of a position that's proven or disproven.
+const INFINITY: u32 = u32::MAX;
+
+/// Result of a position once it's proven or disproven, keyed by its hash and the number of moves
+/// the attacker has left.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Solved {
+    /// The attacker mates in `plies`. `mve` is the mating move when the attacker is to move, or
+    /// the defence that delays mate the longest, and none when the defender is already mated.
+    Mate {
+        plies: u8,
+        mve: Option<Move>,
+    },
+    NoMate,
+}
+
+#[derive(Debug)]
+struct Node {
+    /// Move played to reach this node, none for the root.
+    mve: Option<Move>,
+    parent: Option<usize>,
+    /// Indices of the children, which are pushed together when the node is expanded.
+    children: Range<usize>,
+    /// Lower bound on the number of positions which still have to be proven to mate.
+    proof: u32,
+    /// Lower bound on the number of positions which still have to be proven to escape.
+    disproof: u32,
+    /// Plies to mate once proven.
+    mate_plies: u8,
+    hash: ZobristHash,
+    attacker_to_move: bool,
+    moves_left: u8,
+    /// Whether the result relies on a draw by repetition or the fifty-move rule, which depend on
+    /// how the position was reached, so it can't be cached.
+    is_path_dependent: bool,
+}
+
+impl Node {
+    fn is_solved(&self) -> bool {
+        self.proof == 0 || self.disproof == 0
+    }
+}
+
+struct MateSolver<G> {
+    attacker: Side,
+    move_gen: G,
+    solved: HashMap<(ZobristHash, u8), Solved>,
+    /// Nodes created so far, across every move limit tried. Also bounds the size of the cache.
+    num_nodes: usize,
+    max_nodes: usize,
+}
+
+impl<G: GenerateMoves + Copy> MateSolver<G> {
+    fn new(attacker: Side, move_gen: G, max_nodes: usize) -> Self {
+        Self {
+            attacker,
+            move_gen,
+            solved: HashMap::new(),
+            num_nodes: 0,
+            max_nodes,
+        }
+    }
+
+    /// Whether the attacker, to move in `position`, mates within `moves` moves.
+    fn prove(&mut self, position: &mut Position, moves: u8) -> Result<bool, MateSolverError> {
+        let mut nodes = vec![self.new_node(position, None, None, moves)];
+
+        while !nodes[0].is_solved() {
+            // Go down to the most proving node: the child that's cheapest to prove when the
+            // attacker is to move, and cheapest to disprove otherwise.
+            let mut idx = 0;
+            let mut unmake_move_states = vec![];
+            while !nodes[idx].children.is_empty() {
+                let node = &nodes[idx];
+                idx = node
+                    .children
+                    .clone()
+                    .min_by_key(|&child| {
+                        if node.attacker_to_move {
+                            nodes[child].proof
+                        } else {
+                            nodes[child].disproof
+                        }
+                    })
+                    .expect("Expanded nodes have children");
+                unmake_move_states.push(position.make_move(nodes[idx].mve.unwrap()));
+            }
+
+            self.expand(&mut nodes, idx, position);
+            for unmake_move_state in unmake_move_states.into_iter().rev() {
+                position.unmake_move(unmake_move_state);
+            }
+            if self.num_nodes > self.max_nodes {
+                return Err(MateSolverError::NodeLimit(self.max_nodes));
+            }
+
+            let mut maybe_idx = Some(idx);
+            while let Some(idx) = maybe_idx {
+                self.update(&mut nodes, idx);
+                maybe_idx = nodes[idx].parent;
+            }
+        }
+        Ok(nodes[0].proof == 0)
+    }
+
+    /// Creates a node for `position`, solving it straight away if the game is over, the
+    /// attacker is out of moves or it's been solved before.
+    fn new_node(
+        &mut self,
+        position: &Position,
+        mve: Option<Move>,
+        parent: Option<usize>,
+        moves_left: u8,
+    ) -> Node {
+        self.num_nodes += 1;
+        let attacker_to_move = position.state.to_move == self.attacker;
+        let mut node = Node {
+            mve,
+            parent,
+            children: 0..0,
+            proof: 1,
+            disproof: 1,
+            mate_plies: 0,
+            hash: position.zobrist_hash,
+            attacker_to_move,
+            moves_left,
+            is_path_dependent: false,
+        };
+
+        let num_moves = u32::try_from(self.move_gen.gen_moves(position).len()).unwrap();
+        if num_moves == 0 {
+            let is_mated = !self.move_gen.gen_checkers(position).is_empty();
+            let solved = if is_mated && !attacker_to_move {
+                Solved::Mate {
+                    plies: 0,
+                    mve: None,
+                }
+            } else {
+                Solved::NoMate
+            };
+            self.set_solved(&mut node, solved);
+        } else if position.is_draw() || moves_left == 0 {
+            node.proof = INFINITY;
+            node.disproof = 0;
+            node.is_path_dependent = position.is_draw();
+        } else if let Some(&solved) = self.solved.get(&(node.hash, moves_left)) {
+            self.set_solved(&mut node, solved);
+        } else if attacker_to_move {
+            // Positions with fewer replies are quicker to solve.
+            node.disproof = num_moves;
+        } else {
+            node.proof = num_moves;
+        }
+        node
+    }
+
+    /// Marks a node as solved. A position solved before keeps its first result, so following
+    /// the cached moves from any proven position always leads to mate.
+    fn set_solved(&mut self, node: &mut Node, solved: Solved) {
+        let solved = if node.is_path_dependent {
+            solved
+        } else {
+            *self
+                .solved
+                .entry((node.hash, node.moves_left))
+                .or_insert(solved)
+        };
+        match solved {
+            Solved::Mate { plies, .. } => {
+                node.proof = 0;
+                node.disproof = INFINITY;
+                node.mate_plies = plies;
+            }
+            Solved::NoMate => {
+                node.proof = INFINITY;
+                node.disproof = 0;
+            }
+        }
+    }
+
+    fn expand(&mut self, nodes: &mut Vec<Node>, idx: usize, position: &mut Position) {
+        let node = &nodes[idx];
+        // Moves left counts the attacker's moves, so it goes down once the attacker has moved.
+        let child_moves_left = if node.attacker_to_move {
+            node.moves_left - 1
+        } else {
+            node.moves_left
+        };
+
+        let first_child = nodes.len();
+        for mve in self.move_gen.gen_moves(position) {
+            let unmake_move_state = position.make_move(mve);
+            let child = self.new_node(position, Some(mve), Some(idx), child_moves_left);
+            position.unmake_move(unmake_move_state);
+            nodes.push(child);
+        }
+        nodes[idx].children = first_child..nodes.len();
+    }
+
+    /// Recalculates an expanded node's numbers from its children, caching it once it's solved.
+    fn update(&mut self, nodes: &mut [Node], idx: usize) {
+        let node = &nodes[idx];
+        let children = &nodes[node.children.clone()];
+        let min_by = |number: fn(&Node) -> u32| children.iter().map(number).min().unwrap();
+        let sum_by = |number: fn(&Node) -> u32| {
+            children
+                .iter()
+                .map(number)
+                .fold(0, |sum: u32, number| sum.saturating_add(number))
+        };
+        let (proof, disproof) = if node.attacker_to_move {
+            (min_by(|child| child.proof), sum_by(|child| child.disproof))
+        } else {
+            (sum_by(|child| child.proof), min_by(|child| child.disproof))
+        };
+
+        let solved = if proof == 0 {
+            // The attacker plays its quickest proven mate, the defender its slowest.
+            let proven = children.iter().filter(|child| child.proof == 0);
+            let best = if node.attacker_to_move {
+                proven.min_by_key(|child| child.mate_plies)
+            } else {
+                proven.max_by_key(|child| child.mate_plies)
+            }
+            .unwrap();
+            Some(Solved::Mate {
+                plies: best.mate_plies + 1,
+                mve: best.mve,
+            })
+        } else if disproof == 0 {
+            Some(Solved::NoMate)
+        } else {
+            None
+        };
+        // The defender escapes if any of its escapes does, the attacker only if all of them do.
+        let mut disproven = children.iter().filter(|child| child.disproof == 0);
+        let is_path_dependent = if node.attacker_to_move {
+            disproven.any(|child| child.is_path_dependent)
+        } else {
+            disproven.all(|child| child.is_path_dependent)
+        };
+
+        let node = &mut nodes[idx];
+        match solved {
+            Some(solved) => {
+                node.is_path_dependent = solved == Solved::NoMate && is_path_dependent;
+                self.set_solved(node, solved);
+            }
+            None => {
+                node.proof = proof;
+                node.disproof = disproof;
+            }
+        }
+    }
+
+    /// Follows the cached results of a proven position, the attacker to move with `moves` left.
+    fn mating_line(&self, mut position: Position, mut moves_left: u8) -> Vec<Move> {
+        let mut line = vec![];
+        loop {
+            let solved = self.solved.get(&(position.zobrist_hash, moves_left));
+            let Some(&Solved::Mate { mve: Some(mve), .. }) = solved else {
+                break;
+            };
+            if position.state.to_move == self.attacker {
+                moves_left -= 1;
+            }
+            line.push(mve);
+            position.make_move(mve);
+        }
+        line
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MOVE_GEN;
+    use std::str::FromStr;
+    use test_case::test_case;
+
+    const MAX_NODES: usize = 1_000_000;
+
+    #[test_case("k7/6R1/7R/8/8/8/8/3K4 w - - 0 1", 1, 1 ; "rook ladder in 1")]
+    #[test_case("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", 5, 1 ; "back rank finds shortest")]
+    #[test_case("1k6/8/2R5/7R/8/8/8/6K1 w - - 0 1", 3, 3 ; "rook ladder in 2")]
+    #[test_case("8/k7/8/8/r7/5r2/8/6K1 b - - 0 1", 5, 3 ; "rook ladder in 2 black")]
+    #[test_case("8/8/8/8/8/4k3/8/3QK3 w - - 0 1", 9, 9 ; "queen and king in 5")]
+    fn test_solve_mate(fen: &str, max_plies: u8, want_plies: usize) {
+        let mut position = Position::from_fen(fen).unwrap();
+        let line = solve_mate(&position, max_plies, MOVE_GEN, MAX_NODES)
+            .unwrap()
+            .unwrap();
+        assert_eq!(line.len(), want_plies);
+
+        for mve in line {
+            assert!(MOVE_GEN.gen_moves(&position).contains(&mve));
+            position.make_move(mve);
+        }
+        assert!(MOVE_GEN.gen_moves(&position).is_empty());
+        assert!(!MOVE_GEN.gen_checkers(&position).is_empty());
+    }
+
+    #[test_case("k7/8/8/8/8/8/8/K6R w - - 0 1", 3 ; "too deep")]
+    #[test_case("k7/8/1Q6/8/8/8/8/7K w - - 0 1", 1 ; "queen without king support")]
+    fn test_solve_mate_no_mate(fen: &str, max_plies: u8) {
+        let position = Position::from_fen(fen).unwrap();
+        assert_eq!(
+            solve_mate(&position, max_plies, MOVE_GEN, MAX_NODES),
+            Ok(None)
+        );
+    }
+
+    #[test]
+    fn test_solve_mate_node_limit() {
+        let position = Position::from_fen("8/8/8/8/8/4k3/8/3QK3 w - - 0 1").unwrap();
+        assert_eq!(
+            solve_mate(&position, 9, MOVE_GEN, 1_000),
+            Err(MateSolverError::NodeLimit(1_000))
+        );
+    }
+
+    #[test]
+    fn test_repetition_escape_is_not_cached() {
+        // The rook and king shuffle back and forth, so black's only move repeats a position for
+        // the third time. Reached another way, it wouldn't be a draw.
+        let mut position = Position::from_fen("8/7k/8/8/8/8/8/K5R1 w - - 0 1").unwrap();
+        for mve in ["g1g2", "h7h8", "g2g1", "h8h7", "g1g2", "h7h8", "g2g1"] {
+            position.make_move(Move::from_str(mve).unwrap());
+        }
+        let mut solver = MateSolver::new(Side::White, MOVE_GEN, MAX_NODES);
+
+        assert_eq!(solver.prove(&mut position, 1), Ok(false));
+        assert_eq!(solver.solved.get(&(position.zobrist_hash, 1)), None);
+    }
+}
@@ -1,23 +1,48 @@
 use std::collections::HashMap;
 use std::fmt::Display;
-use std::path::PathBuf;
 use std::sync::Arc;
-use std::sync::atomic::AtomicBool;
-use std::time::{Duration, Instant};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::thread;
+use std::time::Duration;
 
 use arrayvec::ArrayVec;
-use tracing::{debug, debug_span, error, info};
-
-use crate::evaluation::{Eval, EvaluatePosition};
+#[cfg(feature = "tracing")]
+use tracing::subscriber::NoSubscriber;
+#[cfg(feature = "tracing")]
+use tracing::{debug, debug_span};
+
+use crate::algebraic_notation::move_to_algebraic_notation;
+use crate::clock::{Clock, StdClock, Stopwatch};
+use crate::evaluation::{EvalBounds, EvaluatePosition, RelativeEval};
+use crate::incr;
 use crate::move_gen::GenerateMoves;
-use crate::position::{Move, Position};
-use crate::search::move_ordering::{ButterflyHistoryState, order_moves};
+use crate::position::{DrawReason, FIFTY_MOVE_RULE_HALF_MOVES, Move, Position};
+use crate::search::move_ordering::{
+    ButterflyHistoryState, ContinuationHistory, KillerMoves, order_moves,
+};
+use crate::search::pv_table::PvTable;
+use crate::search::static_evals::StaticEvals;
+use crate::see::static_exchange_eval;
 use crate::transposition_table::{
-    EvalType, TranspositionTable, clear_transpostion_table_hitrate, get_transposition_table_hitrate,
+    EvalType, TranspositionTable, clear_transpostion_table_hitrate,
+    get_transposition_table_hitrate, get_transposition_table_hits,
 };
-use crate::{Piece, Side, Square};
+use crate::{Piece, Side};
 
+mod info_callback;
+mod mate_solver;
+mod move_filter;
 mod move_ordering;
+mod ponder_hit;
+mod pv_table;
+mod static_evals;
+mod tuning;
+
+pub use info_callback::{InfoCallback, SearchInfo, SearchSummary};
+pub use mate_solver::{MateSolverError, solve_mate};
+pub use move_filter::MoveFilter;
+pub use ponder_hit::PonderHit;
+pub use tuning::SearchTuning;
 
 #[derive(Clone, Debug, Default, PartialEq, Eq)]
 pub struct SearchParams {
@@ -33,6 +58,45 @@ pub struct SearchParams {
     pub mate: Option<u8>,
     pub move_time: Option<Duration>,
     pub infinite: bool,
+    pub move_filter: Option<MoveFilter>,
+    /// Set for ponder searches so that time limits can be applied once the ponder move is
+    /// played.
+    pub ponder_hit: Option<PonderHit>,
+    /// Number of best root moves to report a principal variation for. Defaults to 1.
+    pub multi_pv: Option<u8>,
+    pub tuning: SearchTuning,
+    /// Turns off pruning that can miss tactics, such as null move and (reverse) futility
+    /// pruning, trading speed for reliability when a user is analysing a position.
+    pub analysis_mode: bool,
+    /// Number of threads to search with, using Lazy SMP: helper threads search the same
+    /// position and only share their results through the transposition table. Defaults to 1.
+    /// `max_nodes` only counts the main thread's nodes.
+    pub threads: Option<u16>,
+    /// Called with the search's progress. Nothing is reported without one.
+    pub info_callback: Option<InfoCallback>,
+    /// Nodes between the progress reports made while an iteration is running, on top of the
+    /// one made when each iteration completes. Defaults to
+    /// [`SearchParams::DEFAULT_INFO_INTERVAL_NODES`], and 0 turns them off.
+    pub info_interval_nodes: Option<u64>,
+    /// Percentage of a CPU each search thread may use, from 1 to 100, for running in the
+    /// background. Threads sleep between short bursts of work to stay under it. Time limits
+    /// still count the time spent sleeping, so the search gets less done in the same time.
+    pub max_cpu_percent: Option<u8>,
+}
+
+impl SearchParams {
+    pub const DEFAULT_INFO_INTERVAL_NODES: u64 = 250_000;
+
+    /// Whether a progress report is due while an iteration is running, after `nodes` nodes.
+    fn is_periodic_info_due(&self, nodes: u64) -> bool {
+        match self
+            .info_interval_nodes
+            .unwrap_or(Self::DEFAULT_INFO_INTERVAL_NODES)
+        {
+            0 => false,
+            interval => nodes.is_multiple_of(interval),
+        }
+    }
 }
 
 impl Display for SearchParams {
@@ -89,53 +153,252 @@ impl Display for SearchParams {
         if self.infinite != default.infinite {
             parts.push(format!("infinite: {:?}", self.infinite));
         }
+        if self.move_filter != default.move_filter {
+            parts.push(format!(
+                "move_filter: {:?}",
+                self.move_filter.as_ref().unwrap()
+            ));
+        }
+        if self.multi_pv != default.multi_pv {
+            parts.push(format!("multi_pv: {:?}", self.multi_pv.as_ref().unwrap()));
+        }
+        if self.ponder_hit != default.ponder_hit {
+            parts.push(format!(
+                "ponder_hit: {:?}",
+                self.ponder_hit.as_ref().unwrap().is_triggered()
+            ));
+        }
+        if self.tuning != default.tuning {
+            parts.push(format!("tuning: {:?}", self.tuning));
+        }
+        if self.analysis_mode != default.analysis_mode {
+            parts.push(format!("analysis_mode: {:?}", self.analysis_mode));
+        }
+        if self.threads != default.threads {
+            parts.push(format!("threads: {:?}", self.threads.as_ref().unwrap()));
+        }
+        if self.info_callback != default.info_callback {
+            parts.push("info_callback: set".to_string());
+        }
+        if self.info_interval_nodes != default.info_interval_nodes {
+            parts.push(format!(
+                "info_interval_nodes: {:?}",
+                self.info_interval_nodes.as_ref().unwrap()
+            ));
+        }
+        if self.max_cpu_percent != default.max_cpu_percent {
+            parts.push(format!(
+                "max_cpu_percent: {:?}",
+                self.max_cpu_percent.as_ref().unwrap()
+            ));
+        }
         write!(f, "SearchParams: {}", parts.join(", "))
     }
 }
 
 #[derive(Debug)]
 pub struct SearchResultInfo {
+    /// Positions processed by all threads.
     pub positions_processed: u64,
+    /// Positions processed by each thread, starting with the main thread.
+    pub thread_positions_processed: Vec<u64>,
+    /// Total think time for this move.
     pub time_elapsed: Duration,
-    pub move_evals: HashMap<Move, Eval>,
+    /// Wall-clock time taken by each completed iterative deepening iteration, in order of depth.
+    pub iteration_times: Vec<Duration>,
+    /// Positions the main thread processed in each completed iteration, in order of depth.
+    pub iteration_nodes: Vec<u64>,
+    pub move_evals: HashMap<Move, RelativeEval>,
+    /// Principal variation starting with the best move, up to the depth of the last completed
+    /// iteration.
+    pub pv: Vec<Move>,
+    /// Principal variations for the best `multi_pv` root moves, best first. The first is `pv`.
+    pub lines: Vec<Vec<Move>>,
+    /// Transposition table probes which found an entry, by all threads. Like the hit rate in
+    /// [`SearchInfo`], only counted in debug builds or with the `metrics` feature.
+    pub tt_hits: u64,
 }
 
-#[derive(thiserror::Error, Debug)]
-pub enum SearchError {
-    #[error("Parameters depth and mate are mutually exclusive, both passed: {0}, {1}")]
-    DepthAndMatePassed(u8, u8),
+impl SearchResultInfo {
+    /// Time from the start of the search until each iteration completed, in order of depth.
+    pub fn time_to_depth(&self) -> Vec<Duration> {
+        self.iteration_times
+            .iter()
+            .scan(Duration::ZERO, |total, iteration_time| {
+                *total += *iteration_time;
+                Some(*total)
+            })
+            .collect()
+    }
+
+    /// How many times more positions each iteration took than the one before it, starting at
+    /// depth 2.
+    /// Source: https://www.chessprogramming.org/Branching_Factor#EffectiveBranchingFactor
+    pub fn effective_branching_factors(&self) -> Vec<f64> {
+        self.iteration_nodes
+            .windows(2)
+            .map(|nodes| nodes[1] as f64 / nodes[0].max(1) as f64)
+            .collect()
+    }
+
+    /// Geometric mean of [`Self::effective_branching_factors`], or `None` if fewer than two
+    /// iterations completed.
+    pub fn effective_branching_factor(&self) -> Option<f64> {
+        let (first, last) = (self.iteration_nodes.first()?, self.iteration_nodes.last()?);
+        let num_steps = self.iteration_nodes.len() - 1;
+        if num_steps == 0 {
+            return None;
+        }
+        Some((*last as f64 / (*first).max(1) as f64).powf(1.0 / num_steps as f64))
+    }
 
-    #[error("Couldn't open search file logs: {0}")]
-    OpenSearchLogsFile(PathBuf),
+    /// Summary of the whole search which played `best_move` from `position`, to report once
+    /// it's done.
+    pub fn summary(
+        &self,
+        position: &Position,
+        best_move: Option<Move>,
+        move_gen: impl GenerateMoves,
+    ) -> SearchSummary {
+        SearchSummary {
+            nodes: self.positions_processed,
+            time: self.time_elapsed,
+            tt_hits: self.tt_hits,
+            best_move_san: best_move
+                .and_then(|mve| move_to_algebraic_notation(position, mve, move_gen).ok()),
+        }
+    }
 }
 
+#[derive(thiserror::Error, Debug)]
+pub enum SearchError {}
+
 pub fn search(
+    position: &Position,
+    params: &SearchParams,
+    move_gen: impl GenerateMoves + std::marker::Copy + Send,
+    position_eval: impl EvaluatePosition + std::marker::Copy + Send,
+    transposition_table: &mut TranspositionTable,
+    terminate: Arc<AtomicBool>,
+) -> Result<(Option<Move>, SearchResultInfo), SearchError> {
+    search_with_clock(
+        position,
+        params,
+        move_gen,
+        position_eval,
+        transposition_table,
+        terminate,
+        &StdClock::new(),
+    )
+}
+
+/// Same as [`search`], but all time limits are measured with `clock`.
+pub fn search_with_clock(
+    position: &Position,
+    params: &SearchParams,
+    move_gen: impl GenerateMoves + std::marker::Copy + Send,
+    position_eval: impl EvaluatePosition + std::marker::Copy + Send,
+    transposition_table: &mut TranspositionTable,
+    terminate: Arc<AtomicBool>,
+    clock: &dyn Clock,
+) -> Result<(Option<Move>, SearchResultInfo), SearchError> {
+    let num_threads = params.threads.map_or(1, usize::from).max(1);
+    if num_threads == 1 {
+        return search_main_thread(
+            position,
+            params,
+            move_gen,
+            position_eval,
+            transposition_table,
+            terminate,
+            clock,
+        );
+    }
+
+    let transposition_table: &TranspositionTable = transposition_table;
+    let helpers_stop = Arc::new(AtomicBool::new(false));
+    thread::scope(|scope| {
+        let helpers: Vec<_> = (1..num_threads)
+            .map(|thread_idx| {
+                let helpers_stop = Arc::clone(&helpers_stop);
+                scope.spawn(move || {
+                    // Only the main thread reports on the search.
+                    without_tracing(|| {
+                        search_helper_thread(
+                            position,
+                            params,
+                            thread_idx,
+                            move_gen,
+                            position_eval,
+                            transposition_table,
+                            helpers_stop,
+                            clock,
+                        )
+                    })
+                })
+            })
+            .collect();
+
+        let search_res = search_main_thread(
+            position,
+            params,
+            move_gen,
+            position_eval,
+            transposition_table,
+            terminate,
+            clock,
+        );
+        helpers_stop.store(true, std::sync::atomic::Ordering::Relaxed);
+        let helper_positions_processed = helpers
+            .into_iter()
+            .map(|helper| helper.join().expect("Search helper thread panicked"));
+
+        search_res.map(|(best_move, mut search_info)| {
+            search_info
+                .thread_positions_processed
+                .extend(helper_positions_processed);
+            search_info.positions_processed = search_info.thread_positions_processed.iter().sum();
+            (best_move, search_info)
+        })
+    })
+}
+
+/// Runs `f` without logging anything from this thread.
+#[cfg(feature = "tracing")]
+fn without_tracing<T>(f: impl FnOnce() -> T) -> T {
+    tracing::subscriber::with_default(NoSubscriber::default(), f)
+}
+
+#[cfg(not(feature = "tracing"))]
+fn without_tracing<T>(f: impl FnOnce() -> T) -> T {
+    f()
+}
+
+/// Runs the iterative deepening search which decides on the move and reports on progress.
+fn search_main_thread(
     position: &Position,
     params: &SearchParams,
     move_gen: impl GenerateMoves + std::marker::Copy,
     position_eval: impl EvaluatePosition + std::marker::Copy,
-    transposition_table: &mut TranspositionTable,
+    transposition_table: &TranspositionTable,
     terminate: Arc<AtomicBool>,
+    clock: &dyn Clock,
 ) -> Result<(Option<Move>, SearchResultInfo), SearchError> {
-    debug_span!("search", position = position.to_fen(), params = ?params);
+    #[cfg(feature = "tracing")]
+    let _search_span =
+        debug_span!("search", position = position.to_fen(), params = %params).entered();
     let mut params = params.clone();
     let mut best_move: Option<Move> = None;
 
     let mut positions_processed: u64 = 0;
-    let start = Instant::now();
-    let mut pv_eval = Eval::DRAW;
+    let start = Stopwatch::start(clock);
+    let mut pv_eval = RelativeEval::DRAW;
 
-    let max_depth: usize = match (params.max_depth, params.mate) {
-        (Some(max_depth), None) => max_depth.try_into().unwrap(),
-        (None, Some(mate)) => mate.try_into().unwrap(),
-        (Some(max_depth), Some(mate)) => {
-            return Err(SearchError::DepthAndMatePassed(max_depth, mate));
-        }
-        (None, None) => 20,
-    };
+    let max_depth = get_max_depth(&params);
 
-    let (maybe_soft_time_limit, maybe_hard_time_limit) =
+    let (mut maybe_soft_time_limit, maybe_hard_time_limit) =
         get_time_to_use(&params, position.state.to_move);
+    #[cfg(feature = "tracing")]
     debug!(
         "Time for this move: soft limit={:?} hard limit={:?}",
         maybe_soft_time_limit, maybe_hard_time_limit
@@ -145,11 +408,11 @@ pub fn search(
         params.move_time = Some(hard_time_limit);
     }
 
-    let mut moves = move_gen.gen_moves(position);
-
-    // Filter out moves not in search moves
-    if let Some(search_moves) = &params.search_moves {
-        moves.retain(|mve| search_moves.contains(mve));
+    let mut moves = gen_root_moves(position, &params, move_gen);
+    // A dead position is drawn whatever is played, so there's nothing to think about.
+    let mut is_obvious_move = moves.len() == 1 || position.is_insufficient_material();
+    if is_obvious_move {
+        cap_obvious_move_time(&mut params, &mut maybe_soft_time_limit);
     }
 
     let move_positions: HashMap<Move, Position> = moves
@@ -164,84 +427,86 @@ pub fn search(
 
     let mut final_move_vals = HashMap::new();
     let mut butterfly_history_state = ButterflyHistoryState::new();
+    let mut killer_moves = KillerMoves::new();
+    let mut continuation_history = ContinuationHistory::new();
+    let mut pv_table = PvTable::new();
+    let mut static_evals = StaticEvals::new();
+    let mut path_dependent_draws = 0;
+    let mut nmp_min_ply = 0;
 
     let mut move_vals = HashMap::with_capacity(moves.len());
-
-    'outer: for iterative_deepening_max_depth in 1..=max_depth {
-        let iteration_start_time = Instant::now();
-        debug_span!(
+    // Line after each root move, from the last search of that move.
+    let mut move_lines: HashMap<Move, Vec<Move>> = HashMap::with_capacity(moves.len());
+    let mut pv = vec![];
+    let mut lines = vec![];
+    let mut iteration_times = vec![];
+    let mut iteration_nodes = vec![];
+    // Best root move's eval after each iteration.
+    let mut root_evals = vec![];
+    let num_lines = usize::from(params.multi_pv.unwrap_or(1).max(1));
+    let is_root_move_pruning_enabled =
+        params.tuning.root_move_pruning && num_lines == 1 && !params.analysis_mode;
+    // Number of iterations in a row each root move has scored far below the best move.
+    let mut root_move_low_streaks: HashMap<Move, u8> = HashMap::new();
+
+    'outer: for iterative_deepening_max_depth in 1..=usize::from(max_depth) {
+        let iteration_start_time = Stopwatch::start(clock);
+        let iteration_start_nodes = positions_processed;
+        #[cfg(feature = "tracing")]
+        let _iteration_span = debug_span!(
             "search_iterative_deepening_iteration",
             depth = iterative_deepening_max_depth
-        );
+        )
+        .entered();
+        #[cfg(feature = "tracing")]
         debug!("Iteration: {}/{}", iterative_deepening_max_depth, max_depth);
         let iterative_deepening_max_depth: u8 = iterative_deepening_max_depth.try_into().unwrap();
         let mut max_depth_reached: u8 = 1;
+        let aspiration_delta = calc_aspiration_delta(&root_evals);
 
         for mve in moves.clone() {
             let mut move_position = move_positions[&mve].clone();
-
-            const ASPIRATION_WINDOWS_DELTA: i32 = 50;
-            let (mut alpha, mut beta) = if let Some(prev_move_val) = move_vals.get(&mve)
-                && iterative_deepening_max_depth >= 4
-            {
-                (
-                    *prev_move_val - ASPIRATION_WINDOWS_DELTA,
-                    *prev_move_val + ASPIRATION_WINDOWS_DELTA,
-                )
-            } else {
-                (Eval::MIN, Eval::MAX)
-            };
-
-            loop {
-                let maybe_move_eval = search_helper(
-                    &mut move_position,
-                    &params,
-                    1,
-                    iterative_deepening_max_depth,
-                    &mut max_depth_reached,
-                    &mut positions_processed,
-                    &start,
-                    pv_eval,
-                    alpha,
-                    beta,
-                    move_gen,
-                    position_eval,
-                    transposition_table,
-                    &mut butterfly_history_state,
-                    Arc::clone(&terminate),
-                );
-                if maybe_move_eval.is_none() {
-                    write_search_info(
-                        iterative_deepening_max_depth,
-                        positions_processed,
+            continuation_history.record_played(position, Some(mve), 0);
+
+            let maybe_prev_move_val = move_vals
+                .get(&mve)
+                .filter(|_| iterative_deepening_max_depth >= 4)
+                .copied();
+            let maybe_move_eval = aspiration_search_root_move(
+                maybe_prev_move_val,
+                aspiration_delta,
+                |alpha, beta| {
+                    search_helper(
+                        &mut move_position,
+                        &params,
+                        1,
                         iterative_deepening_max_depth,
+                        &mut max_depth_reached,
+                        &mut positions_processed,
                         &start,
                         pv_eval,
-                        None,
-                    );
-                    break 'outer;
-                }
-
-                let move_eval = maybe_move_eval.unwrap().flip();
-                if alpha != Eval::MIN && move_eval <= alpha {
-                    alpha = Eval::MIN;
-                    // let alpha_val = -(alpha
-                    //     .value()
-                    //     .checked_mul(alpha.value())
-                    //     .unwrap_or(Eval::MIN.value()));
-                    // alpha = Eval(alpha_val);
-                } else if beta != Eval::MAX && move_eval >= beta {
-                    beta = Eval::MAX;
-                    // let beta_val = beta
-                    //     .value()
-                    //     .checked_mul(beta.value())
-                    //     .unwrap_or(Eval::MAX.value());
-                    // beta = Eval(beta_val);
-                } else {
-                    move_vals.insert(mve, move_eval);
-                    break;
-                }
-            }
+                        alpha,
+                        beta,
+                        move_gen,
+                        position_eval,
+                        transposition_table,
+                        &mut butterfly_history_state,
+                        &mut killer_moves,
+                        &mut continuation_history,
+                        &mut pv_table,
+                        &mut static_evals,
+                        &mut path_dependent_draws,
+                        &mut nmp_min_ply,
+                        Arc::clone(&terminate),
+                    )
+                },
+            );
+            // Only completed iterations are reported, the search's summary covers the rest.
+            let Some(move_eval) = maybe_move_eval else {
+                break 'outer;
+            };
+            move_vals.insert(mve, move_eval);
+            move_lines.insert(mve, pv_table.line(1).to_vec());
         }
         final_move_vals = move_vals.clone();
 
@@ -252,20 +517,65 @@ pub fn search(
             val2.partial_cmp(&val1).unwrap()
         });
 
+        iteration_times.push(iteration_start_time.elapsed());
+        iteration_nodes.push(positions_processed - iteration_start_nodes);
+
         // Find best move
         best_move = Some(moves[0]);
-
         pv_eval = move_vals[&best_move.unwrap()];
+        root_evals.push(pv_eval);
+
+        if is_root_move_pruning_enabled && iterative_deepening_max_depth >= ROOT_PRUNE_MIN_DEPTH {
+            prune_root_moves(
+                &mut moves,
+                &move_vals,
+                &mut root_move_low_streaks,
+                params.tuning.root_prune_margin,
+            );
+        }
 
-        write_search_info(
-            iterative_deepening_max_depth,
-            positions_processed,
-            max_depth_reached,
-            &start,
-            pv_eval,
-            best_move,
-        );
+        if !is_obvious_move
+            && iterative_deepening_max_depth >= FORCED_CAPTURE_MIN_DEPTH
+            && is_forced_capture(position, &moves, &move_vals)
+        {
+            is_obvious_move = true;
+            cap_obvious_move_time(&mut params, &mut maybe_soft_time_limit);
+        }
+
+        // Every root move is searched with a full window (aspiration windows are widened on
+        // failure), so the other lines' scores are exact rather than bounds.
+        lines = moves
+            .iter()
+            .take(num_lines)
+            .map(|&mve| {
+                let line: Vec<Move> = std::iter::once(mve)
+                    .chain(move_lines[&mve].iter().copied())
+                    .collect();
+                get_pv(
+                    position,
+                    &line,
+                    transposition_table,
+                    move_gen,
+                    iterative_deepening_max_depth.into(),
+                )
+            })
+            .collect();
+        pv = lines[0].clone();
+
+        for (line_idx, line) in lines.iter().enumerate() {
+            write_search_info(
+                &params,
+                iterative_deepening_max_depth,
+                positions_processed,
+                max_depth_reached,
+                line_idx + 1,
+                &start,
+                move_vals[&line[0]],
+                line,
+            );
+        }
 
+        #[cfg(feature = "tracing")]
         if tracing::enabled!(tracing::Level::DEBUG) {
             let moves_str = moves
                 .iter()
@@ -273,44 +583,229 @@ pub fn search(
                 .collect::<Vec<_>>()
                 .join(" | ");
             debug!("MOVES: {}", moves_str);
+            debug!(
+                "Aspiration delta: {}, re-searches so far: {}",
+                aspiration_delta,
+                get_aspiration_researches()
+            );
         }
 
         // Skip if we've elapsed the max amount of time or that we think the next iteration will
         // definitely go over on time
         let elapsed = start.elapsed();
-        if let Some(soft_time_limit) = maybe_soft_time_limit {
-            if (elapsed + iteration_start_time.elapsed()) > soft_time_limit {
-                debug!(
-                    "Search time exceeded soft limit: {:?} > {:?}",
-                    elapsed, maybe_soft_time_limit
-                );
-                break 'outer;
-            }
+        if let Some(soft_time_limit) = maybe_soft_time_limit
+            && are_time_limits_active(&params)
+            && (elapsed + iteration_start_time.elapsed()) > soft_time_limit
+        {
+            #[cfg(feature = "tracing")]
+            debug!(
+                "Search time exceeded soft limit: {:?} > {:?}",
+                elapsed, maybe_soft_time_limit
+            );
+            break 'outer;
         }
+        #[cfg(feature = "tracing")]
         debug!("Time: {:?} < {:?} to use", elapsed, maybe_soft_time_limit);
     }
 
+    // Ran out of time before the first iteration finished, but any legal move is better than
+    // not moving at all.
+    if best_move.is_none() {
+        best_move = moves
+            .iter()
+            .copied()
+            .filter(|mve| move_vals.contains_key(mve))
+            .max_by_key(|mve| move_vals[mve])
+            .or(moves.first().copied());
+    }
+
     let search_info = SearchResultInfo {
         positions_processed,
+        thread_positions_processed: vec![positions_processed],
         time_elapsed: start.elapsed(),
         move_evals: final_move_vals,
+        pv,
+        lines,
+        iteration_times,
+        iteration_nodes,
+        tt_hits: get_transposition_table_hits(),
     };
     clear_transpostion_table_hitrate();
+    clear_aspiration_researches();
 
     Ok((best_move, search_info))
 }
 
+/// Lazy SMP helper thread. It searches the same root as the main thread until `stop` is set,
+/// only to fill the shared transposition table. Odd helpers start a depth ahead, and each one
+/// searches the root moves in a different order, so they don't all duplicate the main thread's
+/// work. Returns the number of positions processed.
+#[allow(clippy::too_many_arguments)]
+fn search_helper_thread(
+    position: &Position,
+    params: &SearchParams,
+    thread_idx: usize,
+    move_gen: impl GenerateMoves + std::marker::Copy,
+    position_eval: impl EvaluatePosition + std::marker::Copy,
+    transposition_table: &TranspositionTable,
+    stop: Arc<AtomicBool>,
+    clock: &dyn Clock,
+) -> u64 {
+    // The main thread enforces the limits and reports on the search, and stops helpers once
+    // it's done.
+    let params = SearchParams {
+        max_nodes: None,
+        move_time: None,
+        info_callback: None,
+        ..params.clone()
+    };
+    let mut moves = gen_root_moves(position, &params, move_gen);
+    if moves.is_empty() {
+        return 0;
+    }
+    let num_moves = moves.len();
+    moves.rotate_left(thread_idx % num_moves);
+
+    let start = Stopwatch::start(clock);
+    let mut positions_processed = 0;
+    let mut max_depth_reached = 1;
+    let mut butterfly_history_state = ButterflyHistoryState::new();
+    let mut killer_moves = KillerMoves::new();
+    let mut continuation_history = ContinuationHistory::new();
+    let mut pv_table = PvTable::new();
+    let mut static_evals = StaticEvals::new();
+    let mut path_dependent_draws = 0;
+    let mut nmp_min_ply = 0;
+    for depth in (1 + thread_idx as u8 % 2)..=get_max_depth(&params) {
+        for &mve in &moves {
+            let mut move_position = position.clone();
+            move_position.make_move(mve);
+            continuation_history.record_played(position, Some(mve), 0);
+            let maybe_move_eval = search_helper(
+                &mut move_position,
+                &params,
+                1,
+                depth,
+                &mut max_depth_reached,
+                &mut positions_processed,
+                &start,
+                RelativeEval::DRAW,
+                RelativeEval::MIN,
+                RelativeEval::MAX,
+                move_gen,
+                position_eval,
+                transposition_table,
+                &mut butterfly_history_state,
+                &mut killer_moves,
+                &mut continuation_history,
+                &mut pv_table,
+                &mut static_evals,
+                &mut path_dependent_draws,
+                &mut nmp_min_ply,
+                Arc::clone(&stop),
+            );
+            if maybe_move_eval.is_none() {
+                return positions_processed;
+            }
+        }
+    }
+    positions_processed
+}
+
+fn get_max_depth(params: &SearchParams) -> u8 {
+    match (params.max_depth, params.mate) {
+        (Some(max_depth), None) => max_depth,
+        (None, Some(mate)) => mate,
+        // Some GUIs send both, so bound the mate search by the depth.
+        (Some(max_depth), Some(mate)) => max_depth.min(mate),
+        (None, None) => 20,
+    }
+}
+
+/// Legal moves at the root, restricted to the search moves and the move filter.
+fn gen_root_moves(
+    position: &Position,
+    params: &SearchParams,
+    move_gen: impl GenerateMoves,
+) -> ArrayVec<Move, 218> {
+    let mut moves = move_gen.gen_moves(position);
+
+    // Filter out moves not in search moves
+    if let Some(search_moves) = &params.search_moves {
+        moves.retain(|mve| search_moves.contains(mve));
+    }
+
+    if let Some(move_filter) = &params.move_filter {
+        moves.retain(|mve| move_filter.allows(position, *mve));
+    }
+    moves
+}
+
+/// Builds the principal variation from `line`, the moves the PV table collected during the
+/// search, which starts with the root move. The PV table loses the line below TT cutoffs, so the
+/// rest is filled in by following the best moves stored in the transposition table.
+///
+/// Each move after the first is checked to be legal before it's played, since a TT entry can
+/// belong to a different position with the same hash, and GUIs reject the whole info line if
+/// any move in the PV is illegal. The PV is truncated at the first illegal move, or once a
+/// position repeats.
+fn get_pv(
+    position: &Position,
+    line: &[Move],
+    transposition_table: &TranspositionTable,
+    move_gen: impl GenerateMoves,
+    max_len: usize,
+) -> Vec<Move> {
+    let mut position = position.clone();
+    let mut pv = vec![line[0]];
+    let mut seen_hashes = vec![position.zobrist_hash];
+    position.make_move(line[0]);
+
+    let mut line_moves = line[1..].iter();
+    while pv.len() < max_len && !seen_hashes.contains(&position.zobrist_hash) {
+        let mve = match line_moves.next() {
+            Some(&mve) => mve,
+            None => match transposition_table.probe(&position) {
+                Some(tt_entry) => tt_entry.best_move,
+                None => break,
+            },
+        };
+        if !move_gen.gen_moves(&position).contains(&mve) {
+            break;
+        }
+        seen_hashes.push(position.zobrist_hash);
+        position.make_move(mve);
+        pv.push(mve);
+    }
+    pv
+}
+
+/// Ponder searches ignore time limits until the ponder move is played.
+fn are_time_limits_active(params: &SearchParams) -> bool {
+    !params.ponder || params.ponder_hit.as_ref().is_some_and(PonderHit::is_triggered)
+}
+
 fn get_time_to_use(
     params: &SearchParams,
     side_to_move: Side,
 ) -> (Option<Duration>, Option<Duration>) {
     let (soft, mut hard) = match (side_to_move, params.white_time, params.black_time) {
         (Side::White, Some(white_time), _) => {
-            let (soft, hard) = calc_time_to_use(white_time, params.white_inc, params.moves_to_go);
+            let (soft, hard) = calc_time_to_use(
+                white_time,
+                params.white_inc,
+                params.moves_to_go,
+                &params.tuning,
+            );
             (Some(soft), Some(hard))
         }
         (Side::Black, _, Some(black_time)) => {
-            let (soft, hard) = calc_time_to_use(black_time, params.black_inc, params.moves_to_go);
+            let (soft, hard) = calc_time_to_use(
+                black_time,
+                params.black_inc,
+                params.moves_to_go,
+                &params.tuning,
+            );
             (Some(soft), Some(hard))
         }
         (_, _, _) => (None, None),
@@ -321,19 +816,162 @@ fn get_time_to_use(
     (soft, hard)
 }
 
+/// Time limits for an obvious move are divided by this.
+const OBVIOUS_MOVE_TIME_DIVISOR: u32 = 10;
+/// A capture is only considered forced once it's been searched to this depth.
+const FORCED_CAPTURE_MIN_DEPTH: u8 = 5;
+/// A capture is forced if every other move is at least this many centipawns worse.
+const FORCED_CAPTURE_MARGIN: i32 = 200;
+
+/// Cuts the time limits when there's nothing to think about, because there's only one legal
+/// move, a capture is forced or neither side can mate. Only applies when playing on a clock,
+/// since `go movetime` asks for all of the time to be used. The time saved stays on the clock,
+/// so it's shared between the following moves when their time is calculated.
+fn cap_obvious_move_time(params: &mut SearchParams, maybe_soft_time_limit: &mut Option<Duration>) {
+    let Some(soft_time_limit) = maybe_soft_time_limit else {
+        return;
+    };
+    *soft_time_limit /= OBVIOUS_MOVE_TIME_DIVISOR;
+    params.move_time = params
+        .move_time
+        .map(|move_time| move_time.min(*soft_time_limit * 2));
+    #[cfg(feature = "tracing")]
+    debug!("Obvious move, soft limit cut to {:?}", soft_time_limit);
+}
+
+/// Whether the best root move is a capture, usually a recapture, which is so much better than
+/// every alternative that searching deeper won't change it. `moves` must be sorted best first.
+fn is_forced_capture(
+    position: &Position,
+    moves: &[Move],
+    move_vals: &HashMap<Move, RelativeEval>,
+) -> bool {
+    let [best_move, second_best_move, ..] = moves else {
+        return false;
+    };
+    position.is_capture(*best_move)
+        && move_vals[second_best_move] + FORCED_CAPTURE_MARGIN <= move_vals[best_move]
+}
+
+/// Root moves are only pruned based on iterations searched to at least this depth.
+const ROOT_PRUNE_MIN_DEPTH: u8 = 4;
+/// A root move is pruned once it's scored below the best move by more than the margin for
+/// this many iterations in a row.
+const ROOT_PRUNE_ITERATIONS: u8 = 3;
+
+/// Drops root moves that have been far worse than the best move for several iterations, so the
+/// following iterations spend their time on the candidate moves. `moves` must be sorted best
+/// first, and the best move is never dropped.
+fn prune_root_moves(
+    moves: &mut ArrayVec<Move, 218>,
+    move_vals: &HashMap<Move, RelativeEval>,
+    low_streaks: &mut HashMap<Move, u8>,
+    margin: i32,
+) {
+    let best_eval = move_vals[&moves[0]];
+    moves.retain(|mve| {
+        let low_streak = low_streaks.entry(*mve).or_default();
+        if move_vals[mve] + margin < best_eval {
+            *low_streak += 1;
+        } else {
+            *low_streak = 0;
+        }
+        *low_streak < ROOT_PRUNE_ITERATIONS
+    });
+    #[cfg(feature = "tracing")]
+    debug!("Root moves left after pruning: {}", moves.len());
+}
+
+/// Smallest and largest aspiration window on either side of a root move's previous eval.
+const ASPIRATION_MIN_DELTA: i32 = 35;
+const ASPIRATION_MAX_DELTA: i32 = 300;
+/// Number of most recent iterations the root eval's volatility is measured over.
+const ASPIRATION_VOLATILITY_ITERATIONS: usize = 4;
+
+/// Root moves searched again with a full window after failing outside their aspiration window.
+static ASPIRATION_RESEARCHES: AtomicU64 = AtomicU64::new(0);
+
+#[cfg(feature = "tracing")]
+fn get_aspiration_researches() -> u64 {
+    ASPIRATION_RESEARCHES.load(Ordering::Relaxed)
+}
+
+fn clear_aspiration_researches() {
+    ASPIRATION_RESEARCHES.store(0, Ordering::Release);
+}
+
+/// Searches a root move with a window of `delta` on either side of its eval from the previous
+/// iteration, or with the full window if there isn't one. An eval outside the window is only a
+/// bound, so the move is searched again with that side of the window opened up.
+///
+/// `search_child` takes the window from the child's perspective and returns the child's eval,
+/// or `None` if the search was terminated, like in [`pvs_search_child`]. The eval returned is
+/// from the root's perspective.
+fn aspiration_search_root_move(
+    maybe_prev_eval: Option<RelativeEval>,
+    delta: i32,
+    mut search_child: impl FnMut(RelativeEval, RelativeEval) -> Option<RelativeEval>,
+) -> Option<RelativeEval> {
+    let (mut alpha, mut beta) = match maybe_prev_eval {
+        Some(prev_eval) => (prev_eval - delta, prev_eval + delta),
+        None => (RelativeEval::MIN, RelativeEval::MAX),
+    };
+    loop {
+        let move_eval = search_child(beta.flip(), alpha.flip())?.flip();
+        if alpha != RelativeEval::MIN && move_eval <= alpha {
+            alpha = RelativeEval::MIN;
+            incr!(ASPIRATION_RESEARCHES);
+        } else if beta != RelativeEval::MAX && move_eval >= beta {
+            beta = RelativeEval::MAX;
+            incr!(ASPIRATION_RESEARCHES);
+        } else {
+            return Some(move_eval);
+        }
+    }
+}
+
+/// Size of the aspiration window on either side of a root move's previous eval. The window
+/// grows with how much the root eval has swung between the last few iterations, so quiet
+/// positions get a narrow window while tactical ones don't keep failing and searching again.
+fn calc_aspiration_delta(root_evals: &[RelativeEval]) -> i32 {
+    let recent_evals = &root_evals[root_evals
+        .len()
+        .saturating_sub(ASPIRATION_VOLATILITY_ITERATIONS)..];
+    let swings: Vec<i32> = recent_evals
+        .windows(2)
+        .map(|evals| (evals[1].0 - evals[0].0).abs())
+        .collect();
+    let volatility = match swings.len() {
+        0 => 0,
+        num_swings => swings.iter().sum::<i32>() / num_swings as i32,
+    };
+    (ASPIRATION_MIN_DELTA + volatility).min(ASPIRATION_MAX_DELTA)
+}
+
 /// Calculate the time to use during search.
-/// Returns a soft and hard limit time.
+/// Returns a soft and hard limit time, as percentages of the move's share of the clock. Neither
+/// is ever more than the time left, even on the last move before the time control or when the
+/// increment is larger than what's left.
+///
+/// Some GUIs send `movestogo 0` when the time control is reached after this move, so it's
+/// treated like 1. With no time left after the move overhead both limits are zero, and the
+/// search still returns a move.
 fn calc_time_to_use(
     time_left: Duration,
     maybe_time_inc: Option<Duration>,
     maybe_moves_to_go: Option<u16>,
+    tuning: &SearchTuning,
 ) -> (Duration, Duration) {
     let time_inc = maybe_time_inc.unwrap_or(Duration::from_secs(0));
+    let time_left = time_left.saturating_sub(tuning.move_overhead);
     let usable_time = time_left - (time_left / 20);
-    let moves_to_go = maybe_moves_to_go.unwrap_or(40);
-    let soft_limit = (usable_time / moves_to_go.into()) + time_inc;
-    let hard_limit = soft_limit * 2;
-    (soft_limit, hard_limit)
+    let moves_to_go = maybe_moves_to_go.unwrap_or(40).max(1);
+    let move_time = (usable_time / moves_to_go.into()).saturating_add(time_inc);
+    let limit = |percent: u32| (move_time.saturating_mul(percent) / 100).min(usable_time);
+    (
+        limit(tuning.soft_time_percent),
+        limit(tuning.hard_time_percent),
+    )
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -344,47 +982,59 @@ fn search_helper(
     max_depth: u8,
     max_depth_reached: &mut u8,
     positions_processed: &mut u64,
-    start_time: &Instant,
-    pv_eval: Eval,
-    mut alpha: Eval,
-    beta: Eval,
+    start_time: &Stopwatch,
+    pv_eval: RelativeEval,
+    mut alpha: RelativeEval,
+    beta: RelativeEval,
     move_gen: impl GenerateMoves + std::marker::Copy,
     position_eval: impl EvaluatePosition + std::marker::Copy,
-    transposition_table: &mut TranspositionTable,
+    transposition_table: &TranspositionTable,
     butterfly_history_state: &mut ButterflyHistoryState,
+    killer_moves: &mut KillerMoves,
+    continuation_history: &mut ContinuationHistory,
+    pv_table: &mut PvTable,
+    static_evals: &mut StaticEvals,
+    path_dependent_draws: &mut u64,
+    nmp_min_ply: &mut u8,
     terminate: Arc<AtomicBool>,
-) -> Option<Eval> {
+) -> Option<RelativeEval> {
     // If this search has been terminated, return early
     if terminate.load(std::sync::atomic::Ordering::Relaxed) {
         return None;
     }
-    // If this search is at the max number of nodes, return early
-    if let Some(max_nodes) = params.max_nodes {
-        debug_assert!(*positions_processed <= max_nodes);
-        if *positions_processed == max_nodes {
-            return None;
-        }
+    // If this search is at the max number of nodes, return early. Quiescence search doesn't
+    // check the limit, so it can already have been passed.
+    if let Some(max_nodes) = params.max_nodes
+        && *positions_processed >= max_nodes
+    {
+        return None;
     }
     // If search has exceeded total time, return early
-    if let Some(move_time) = params.move_time {
-        if start_time.elapsed() >= move_time {
-            debug!("Search elapsed total time: {:?}", move_time);
-            return None;
-        }
+    if let Some(move_time) = params.move_time
+        && are_time_limits_active(params)
+        && start_time.elapsed() >= move_time
+    {
+        #[cfg(feature = "tracing")]
+        debug!("Search elapsed total time: {:?}", move_time);
+        return None;
     }
     *positions_processed += 1;
+    throttle(params, *positions_processed, start_time);
+    pv_table.clear(curr_depth);
     if curr_depth > *max_depth_reached {
         *max_depth_reached = curr_depth;
     }
 
-    if *positions_processed % 250_000 == 0 {
+    if params.is_periodic_info_due(*positions_processed) {
         write_search_info(
+            params,
             max_depth,
             *positions_processed,
             curr_depth,
+            1,
             start_time,
             pv_eval,
-            None,
+            &[],
         );
     }
 
@@ -404,46 +1054,92 @@ fn search_helper(
             beta,
             move_gen,
             position_eval,
+            path_dependent_draws,
             terminate,
         );
     }
 
-    if position.is_draw() {
-        return Some(Eval::DRAW);
+    if is_draw(position, path_dependent_draws) {
+        return Some(RelativeEval::DRAW);
     }
+    let path_dependent_draws_before = *path_dependent_draws;
+    let remaining_depth = max_depth - curr_depth;
+    let is_pv_node = alpha != beta - 1;
 
-    let maybe_tt_best_move = if let Some(tt_entry) = transposition_table.get(position) {
-        if !position.is_repetition_possible() && tt_entry.depth() >= (max_depth - curr_depth) {
-            let eval_type = tt_entry.eval_type();
-            if eval_type == EvalType::Exact
-                || (eval_type == EvalType::LowerBound && tt_entry.eval >= beta)
-                || (eval_type == EvalType::UpperBound && tt_entry.eval <= alpha)
-            {
-                return Some(tt_entry.eval);
-            }
-        }
-        Some(tt_entry.best_move)
-    } else {
-        None
+    let maybe_tt_best_move = match probe_transposition_table(
+        position,
+        transposition_table,
+        remaining_depth,
+        alpha,
+        beta,
+    ) {
+        TtProbe::Cutoff(eval) => return Some(eval),
+        TtProbe::BestMove(mve) => Some(mve),
+        TtProbe::Miss => None,
     };
 
-    let is_pv_node = alpha != beta - 1;
+    // Internal iterative reductions: without a TT move, move ordering in a PV node is poor, so
+    // search it shallower. The next iteration will then find a TT move from this search.
+    let (max_depth, remaining_depth) = if is_pv_node
+        && maybe_tt_best_move.is_none()
+        && remaining_depth >= INTERNAL_ITERATIVE_REDUCTIONS_MIN_DEPTH
+    {
+        (max_depth - 1, remaining_depth - 1)
+    } else {
+        (max_depth, remaining_depth)
+    };
 
     let checkers = move_gen.gen_checkers(position);
-    let eval = position_eval.evaluate(position, move_gen);
+    let eval = position_eval.evaluate(
+        position,
+        move_gen,
+        Some(EvalBounds {
+            alpha,
+            beta,
+            lazy_margin: params.tuning.lazy_eval_margin,
+        }),
+    );
+    static_evals.set(
+        curr_depth,
+        position.state.to_move,
+        checkers.is_empty().then_some(eval),
+    );
+    let is_improving = static_evals.is_improving(curr_depth);
+
+    // Reverse futility pruning: the side to move is so far ahead that it's assumed to stay above
+    // beta whatever it plays. An improving position is trusted to stay ahead one ply further.
+    if !params.analysis_mode
+        && !is_pv_node
+        && checkers.is_empty()
+        && remaining_depth <= REVERSE_FUTILITY_PRUNING_MAX_DEPTH
+        && beta.is_mate().is_none()
+        && eval
+            - params.tuning.reverse_futility_margin
+                * i32::from(remaining_depth - u8::from(is_improving)).max(1)
+            >= beta
+    {
+        return Some(eval);
+    }
 
-    // Null Move Pruning
-    if !is_pv_node
+    // Null Move Pruning. Passing only gives a lower bound when the side to move has a move that
+    // doesn't make its position worse. A side with only pawns is often in zugzwang without one,
+    // so it never passes.
+    let to_move = position.state.to_move;
+    if !params.analysis_mode
+        && !is_pv_node
         && checkers.is_empty()
         && eval >= beta
         && curr_depth >= NULL_MOVE_PRUNING_DEPTH
-        && position.has_non_pawn_material()
+        && curr_depth >= *nmp_min_ply
+        && position.has_non_pawn_material(to_move)
     {
-        const R: u8 = 2;
-
-        let nmp_depth = curr_depth + R;
+        // Reduce more when improving, since the null move search is then more likely to fail
+        // high anyway.
+        let reduction = NULL_MOVE_PRUNING_REDUCTION + u8::from(is_improving);
+        let nmp_depth = curr_depth + reduction;
 
         if nmp_depth <= max_depth {
+            continuation_history.record_played(position, None, curr_depth);
             let unmake_en_passant_target = position.make_null_move();
 
             let nmp_eval = search_helper(
@@ -461,37 +1157,95 @@ fn search_helper(
                 position_eval,
                 transposition_table,
                 butterfly_history_state,
+                killer_moves,
+                continuation_history,
+                pv_table,
+                static_evals,
+                path_dependent_draws,
+                nmp_min_ply,
                 Arc::clone(&terminate),
             )?
             .flip();
             position.unmake_null_move(unmake_en_passant_target);
 
+            // With a single piece left zugzwang is still likely, so the null move's cutoff is
+            // only trusted if a search of this position at the same reduced depth also fails
+            // high. Null moves are turned off for the first plies of that search, or it would
+            // pass straight away again, and there's no verifying within a verification search.
             if nmp_eval >= beta {
-                return Some(nmp_eval);
+                if *nmp_min_ply != 0
+                    || position.non_pawn_material_bb(to_move).num_squares_set()
+                        > NULL_MOVE_VERIFICATION_MAX_PIECES
+                {
+                    return Some(nmp_eval);
+                }
+                *nmp_min_ply = curr_depth + 1 + 3 * (max_depth - nmp_depth) / 4;
+                let verification_eval = search_helper(
+                    position,
+                    params,
+                    curr_depth,
+                    max_depth - reduction,
+                    max_depth_reached,
+                    positions_processed,
+                    start_time,
+                    pv_eval,
+                    beta - 1,
+                    beta,
+                    move_gen,
+                    position_eval,
+                    transposition_table,
+                    butterfly_history_state,
+                    killer_moves,
+                    continuation_history,
+                    pv_table,
+                    static_evals,
+                    path_dependent_draws,
+                    nmp_min_ply,
+                    Arc::clone(&terminate),
+                );
+                *nmp_min_ply = 0;
+                if verification_eval? >= beta {
+                    return Some(nmp_eval);
+                }
             }
         }
     }
 
     let mut moves = move_gen.gen_moves(position);
     if moves.is_empty() {
+        // Mate scores don't depend on the ply: this is mate in 0 from here, and `RelativeEval::flip`
+        // adds a move for every two plies on the way back up. So they can be stored in the TT
+        // as is.
         if !move_gen.gen_checkers(position).is_empty() {
-            return Some(Eval::MIN);
+            return Some(RelativeEval::MIN);
         }
-        return Some(Eval::DRAW);
+        return Some(RelativeEval::DRAW);
     }
 
-    order_moves(
+    // If the filter doesn't allow any continuation, treat this position as a leaf.
+    if filter_interior_moves(&mut moves, position, params) {
+        return Some(eval);
+    }
+
+    order_moves(
         &mut moves,
         position,
         maybe_tt_best_move,
+        Some(killer_moves.get(curr_depth)),
         Some(butterfly_history_state),
+        Some((continuation_history, curr_depth)),
     );
 
-    let mut best_eval = Eval::MIN;
+    let maybe_futility_eval = futility_eval(params, remaining_depth, is_improving, eval, alpha)
+        .filter(|_| !is_pv_node && checkers.is_empty());
+
+    let mut best_eval = RelativeEval::MIN;
     let mut best_move = moves[0];
     let original_alpha = alpha;
     for (idx, mve) in moves.into_iter().enumerate() {
         butterfly_history_state.record_considered(mve);
+        continuation_history.record_played(position, Some(mve), curr_depth);
+        let is_capture = position.is_capture(mve);
 
         let unmake_move_state = position.make_move(mve);
         #[cfg(debug_assertions)]
@@ -501,9 +1255,22 @@ fn search_helper(
             }
         }
 
-        let got_eval = if idx == 0 {
-            // Reason for `?`: if the child node is signaling search is terminated,
-            // better terminate self.
+        // Futility pruning: a quiet move that doesn't give check can't raise alpha this close
+        // to the horizon. The first move is always searched so the node has a score.
+        if let Some(futility_eval) = maybe_futility_eval
+            && idx > 0
+            && !is_capture
+            && mve.promotion.is_none()
+            && move_gen.gen_checkers(position).is_empty()
+        {
+            position.unmake_move(unmake_move_state);
+            best_eval = best_eval.max(futility_eval);
+            continue;
+        }
+
+        // Reason for `?`: if the child node is signaling search is terminated, better terminate
+        // self.
+        let got_eval = pvs_search_child(idx == 0, alpha, beta, |child_alpha, child_beta| {
             search_helper(
                 position,
                 params,
@@ -513,59 +1280,22 @@ fn search_helper(
                 positions_processed,
                 start_time,
                 pv_eval,
-                beta.flip(),
-                alpha.flip(),
+                child_alpha,
+                child_beta,
                 move_gen,
                 position_eval,
                 transposition_table,
                 butterfly_history_state,
+                killer_moves,
+                continuation_history,
+                pv_table,
+                static_evals,
+                path_dependent_draws,
+                nmp_min_ply,
                 Arc::clone(&terminate),
-            )?
-            .flip()
-        } else {
-            let got_eval = search_helper(
-                position,
-                params,
-                curr_depth + 1,
-                max_depth,
-                max_depth_reached,
-                positions_processed,
-                start_time,
-                pv_eval,
-                alpha.flip() - 1,
-                alpha.flip(),
-                move_gen,
-                position_eval,
-                transposition_table,
-                butterfly_history_state,
-                Arc::clone(&terminate),
-            )?
-            .flip();
-            if alpha < got_eval && got_eval < beta {
-                search_helper(
-                    position,
-                    params,
-                    curr_depth + 1,
-                    max_depth,
-                    max_depth_reached,
-                    positions_processed,
-                    start_time,
-                    pv_eval,
-                    beta.flip(),
-                    alpha.flip(),
-                    move_gen,
-                    position_eval,
-                    transposition_table,
-                    butterfly_history_state,
-                    Arc::clone(&terminate),
-                )?
-                .flip()
-            } else {
-                got_eval
-            }
-        };
+            )
+        })?;
 
-        // Flip value because it was relative to the other side
         position.unmake_move(unmake_move_state);
 
         if got_eval > best_eval {
@@ -573,11 +1303,16 @@ fn search_helper(
             best_move = mve;
             if got_eval > alpha {
                 alpha = got_eval;
+                pv_table.update(curr_depth, mve);
             }
         }
 
         if alpha >= beta {
             butterfly_history_state.record_cutoff(mve, curr_depth);
+            if !is_capture {
+                killer_moves.record_cutoff(mve, curr_depth);
+                continuation_history.record_cutoff(position, mve, curr_depth, curr_depth);
+            }
             break;
         }
     }
@@ -594,12 +1329,165 @@ fn search_helper(
         tt_eval_type,
         best_move,
         max_depth - curr_depth,
+        *path_dependent_draws > path_dependent_draws_before,
     );
 
     Some(best_eval)
 }
 
 const NULL_MOVE_PRUNING_DEPTH: u8 = 3;
+/// Plies the null move search is reduced by, one more when the position is improving.
+const NULL_MOVE_PRUNING_REDUCTION: u8 = 2;
+/// Null move cutoffs are verified when the side to move has at most this many pieces other
+/// than pawns and its king.
+/// Source: https://www.chessprogramming.org/Null_Move_Pruning#Verification_Search
+const NULL_MOVE_VERIFICATION_MAX_PIECES: u8 = 1;
+/// Internal iterative reductions are only used this many plies or more from the horizon.
+/// Source: https://www.chessprogramming.org/Internal_Iterative_Reductions
+const INTERNAL_ITERATIVE_REDUCTIONS_MIN_DEPTH: u8 = 4;
+/// Reverse futility pruning is only used this many plies or fewer from the horizon.
+/// Source: https://www.chessprogramming.org/Reverse_Futility_Pruning
+const REVERSE_FUTILITY_PRUNING_MAX_DEPTH: u8 = 3;
+
+/// Static eval plus the futility margin, if this node is close enough to the horizon for
+/// futility pruning and that can't raise alpha. It's an upper bound on what a quiet move can
+/// score here. An improving position uses the margin for one ply further from the horizon,
+/// since its quiet moves are more likely to gain.
+/// Source: https://www.chessprogramming.org/Futility_Pruning
+fn futility_eval(
+    params: &SearchParams,
+    remaining_depth: u8,
+    is_improving: bool,
+    eval: RelativeEval,
+    alpha: RelativeEval,
+) -> Option<RelativeEval> {
+    if params.analysis_mode || alpha.is_mate().is_some() {
+        return None;
+    }
+    let margin_idx = remaining_depth.checked_sub(1)? + u8::from(is_improving);
+    let margin = *params
+        .tuning
+        .futility_margins
+        .get(usize::from(margin_idx))?;
+    let futility_eval = eval + margin;
+    (futility_eval <= alpha).then_some(futility_eval)
+}
+
+/// What the transposition table says about a node.
+#[derive(Debug, PartialEq)]
+enum TtProbe {
+    /// The stored eval can be returned without searching the node.
+    Cutoff(RelativeEval),
+    /// The best move an earlier search of the node found, to search first.
+    BestMove(Move),
+    Miss,
+}
+
+/// TT evals don't account for repetitions of positions before this one or for the fifty-move
+/// rule, so they're only trusted when neither can affect the result.
+///
+/// An entry can be deeper than this node needs, and if it was searched with a lower half move
+/// clock, its eval can be a win which lies past the fifty-move rule from here. So near the rule
+/// it's only trusted if it was stored with a clock at least as high as this position's.
+fn probe_transposition_table(
+    position: &Position,
+    transposition_table: &TranspositionTable,
+    remaining_depth: u8,
+    alpha: RelativeEval,
+    beta: RelativeEval,
+) -> TtProbe {
+    let Some(tt_entry) = transposition_table.get(position) else {
+        return TtProbe::Miss;
+    };
+    let half_move_clock = position.state.half_move_clock;
+    let is_fifty_move_rule_safe = tt_entry.half_move_clock >= half_move_clock
+        || u16::from(half_move_clock) + u16::from(tt_entry.depth())
+            < u16::from(FIFTY_MOVE_RULE_HALF_MOVES);
+    if tt_entry.depth() >= remaining_depth
+        && !tt_entry.path_dependent
+        && is_fifty_move_rule_safe
+        && !position.is_path_dependent_draw_possible(remaining_depth)
+    {
+        let eval_type = tt_entry.eval_type();
+        if eval_type == EvalType::Exact
+            || (eval_type == EvalType::LowerBound && tt_entry.eval >= beta)
+            || (eval_type == EvalType::UpperBound && tt_entry.eval <= alpha)
+        {
+            return TtProbe::Cutoff(tt_entry.eval);
+        }
+    }
+    TtProbe::BestMove(tt_entry.best_move)
+}
+
+/// Searches a child node with principal variation search. The first move is searched with the
+/// full window. Later moves are expected to be worse, so they're first searched with a zero
+/// window around alpha, which only proves whether they're worse. Only a move which turns out
+/// better than alpha without failing high is searched again with the full window, to get its
+/// exact value.
+///
+/// `search_child` takes the window from the child's perspective and returns the child's eval,
+/// or `None` if the search was terminated. The eval returned is from this node's perspective,
+/// and like the child's may lie outside the window.
+/// Source: https://www.chessprogramming.org/Principal_Variation_Search
+fn pvs_search_child(
+    is_first_move: bool,
+    alpha: RelativeEval,
+    beta: RelativeEval,
+    mut search_child: impl FnMut(RelativeEval, RelativeEval) -> Option<RelativeEval>,
+) -> Option<RelativeEval> {
+    if is_first_move {
+        return Some(search_child(beta.flip(), alpha.flip())?.flip());
+    }
+    let zero_window_eval = search_child(alpha.flip() - 1, alpha.flip())?.flip();
+    if alpha < zero_window_eval && zero_window_eval < beta {
+        return Some(search_child(beta.flip(), alpha.flip())?.flip());
+    }
+    Some(zero_window_eval)
+}
+
+/// Nodes a search thread works for between the pauses which keep it under
+/// [`SearchParams::max_cpu_percent`].
+const THROTTLE_INTERVAL_NODES: u64 = 1_024;
+
+/// Pauses the thread every [`THROTTLE_INTERVAL_NODES`] nodes when its CPU usage is limited.
+/// `nodes` is the number the thread has searched.
+fn throttle(params: &SearchParams, nodes: u64, start_time: &Stopwatch) {
+    if let Some(max_cpu_percent) = params.max_cpu_percent
+        && nodes.is_multiple_of(THROTTLE_INTERVAL_NODES)
+    {
+        start_time.throttle(max_cpu_percent);
+    }
+}
+
+/// Whether the position is drawn. Draws which depend on how the position was reached rather than
+/// only on the position are counted, so TT entries whose evals include them can be marked.
+fn is_draw(position: &Position, path_dependent_draws: &mut u64) -> bool {
+    if !position.is_draw() {
+        return false;
+    }
+    if position.automatic_draw() != Some(DrawReason::InsufficientMaterial) {
+        *path_dependent_draws += 1;
+    }
+    true
+}
+
+/// Applies the search's move filter below the root. Returns true if there were legal moves but
+/// the filter removed all of them.
+fn filter_interior_moves(
+    moves: &mut ArrayVec<Move, 218>,
+    position: &Position,
+    params: &SearchParams,
+) -> bool {
+    let Some(move_filter) = &params.move_filter else {
+        return false;
+    };
+    if move_filter.is_root_only() {
+        return false;
+    }
+    let had_moves = !moves.is_empty();
+    moves.retain(|mve| move_filter.allows(position, *mve));
+    had_moves && moves.is_empty()
+}
 
 /// Source: https://www.chessprogramming.org/Quiescence_Search
 #[allow(clippy::too_many_arguments)]
@@ -610,49 +1498,62 @@ fn quiescence_search(
     max_depth: u8,
     max_depth_reached: &mut u8,
     positions_processed: &mut u64,
-    start_time: &Instant,
-    pv_eval: Eval,
-    mut alpha: Eval,
-    beta: Eval,
+    start_time: &Stopwatch,
+    pv_eval: RelativeEval,
+    mut alpha: RelativeEval,
+    beta: RelativeEval,
     move_gen: impl GenerateMoves + std::marker::Copy,
     position_eval: impl EvaluatePosition + std::marker::Copy,
+    path_dependent_draws: &mut u64,
     terminate: Arc<AtomicBool>,
-) -> Option<Eval> {
+) -> Option<RelativeEval> {
     // If this search has been terminated, return early
     if terminate.load(std::sync::atomic::Ordering::Relaxed) {
         return None;
     }
     // If search has exceeded total time, return early
-    if let Some(move_time) = params.move_time {
-        if start_time.elapsed() >= move_time {
-            return None;
-        }
+    if let Some(move_time) = params.move_time
+        && are_time_limits_active(params)
+        && start_time.elapsed() >= move_time
+    {
+        return None;
     }
     *positions_processed += 1;
+    throttle(params, *positions_processed, start_time);
     if curr_depth > *max_depth_reached {
         *max_depth_reached = curr_depth;
     }
 
-    if *positions_processed % 250_000 == 0 {
+    if params.is_periodic_info_due(*positions_processed) {
         write_search_info(
+            params,
             max_depth,
             *positions_processed,
             *max_depth_reached,
+            1,
             start_time,
             pv_eval,
-            None,
+            &[],
         );
     }
 
-    if position.is_draw() {
-        return Some(Eval::DRAW);
+    if is_draw(position, path_dependent_draws) {
+        return Some(RelativeEval::DRAW);
     }
 
     let checkers = move_gen.gen_checkers(position);
     let mut best_eval = if checkers.is_empty() {
-        let standing_pat = position_eval.evaluate(position, move_gen);
+        let standing_pat = position_eval.evaluate(
+            position,
+            move_gen,
+            Some(EvalBounds {
+                alpha,
+                beta,
+                lazy_margin: params.tuning.lazy_eval_margin,
+            }),
+        );
 
-        if curr_depth >= max_depth * 3 {
+        if curr_depth >= max_depth.saturating_mul(params.tuning.qsearch_depth_factor) {
             return Some(standing_pat);
         }
 
@@ -664,16 +1565,20 @@ fn quiescence_search(
         }
         standing_pat
     } else {
-        Eval::MIN
+        RelativeEval::MIN
     };
 
     let mut moves: ArrayVec<Move, 218> = move_gen.gen_moves(position);
     if moves.is_empty() {
         if !move_gen.gen_checkers(position).is_empty() {
-            return Some(Eval::MIN);
+            return Some(RelativeEval::MIN);
         }
         // Stalemate
-        return Some(Eval::DRAW);
+        return Some(RelativeEval::DRAW);
+    }
+
+    if filter_interior_moves(&mut moves, position, params) {
+        return Some(best_eval);
     }
 
     // Filter out quiet moves, but only if in check.
@@ -688,9 +1593,18 @@ fn quiescence_search(
             .collect();
     }
 
-    order_moves(&mut moves, position, None, None);
+    order_moves(&mut moves, position, None, None, None, None);
 
     for mve in moves {
+        // Skip captures that lose too much material. When in check every move is searched.
+        if checkers.is_empty()
+            && position.is_capture(mve)
+            && static_exchange_eval(position, mve)
+                .is_ok_and(|exchange| exchange.value < -params.tuning.qsearch_see_margin)
+        {
+            continue;
+        }
+
         let unmake_move_state = position.make_move(mve);
         #[cfg(debug_assertions)]
         {
@@ -714,6 +1628,7 @@ fn quiescence_search(
             alpha.flip(),
             move_gen,
             position_eval,
+            path_dependent_draws,
             Arc::clone(&terminate),
         )?;
         // Flip value because it was relative to the other side
@@ -734,37 +1649,41 @@ fn quiescence_search(
     Some(best_eval)
 }
 
+#[allow(clippy::too_many_arguments)]
 fn write_search_info(
+    params: &SearchParams,
     iterative_deepening_max_depth: u8,
     nodes_processed: u64,
     max_depth_reached: u8,
-    start_time: &Instant,
-    pv_eval: Eval,
-    best_move: Option<Move>,
+    multi_pv: usize,
+    start_time: &Stopwatch,
+    pv_eval: RelativeEval,
+    pv: &[Move],
 ) {
-    let nps = nodes_processed as f32 / start_time.elapsed().as_secs_f32();
-    info!(
-        target: "uci",
-        "info depth {} seldepth {} multipv {} score {} nodes {} nps {:.0} hashfull {} tbhits {} tthitrate {:.2} time {} pv {}",
-        iterative_deepening_max_depth,
-        max_depth_reached,
-        1,
-        pv_eval,
-        nodes_processed,
-        nps,
-        0,
-        0,
-        get_transposition_table_hitrate(),
-        start_time.elapsed().as_millis(),
-        best_move.map_or("".to_string(), |mve| mve.to_string().to_ascii_lowercase()),
-    );
+    let Some(info_callback) = &params.info_callback else {
+        return;
+    };
+    let time = start_time.elapsed();
+    info_callback.report(&SearchInfo {
+        depth: iterative_deepening_max_depth,
+        seldepth: max_depth_reached,
+        multi_pv,
+        score: pv_eval,
+        nodes: nodes_processed,
+        nps: nodes_processed as f32 / time.as_secs_f32(),
+        tt_hitrate: get_transposition_table_hitrate(),
+        time,
+        pv: pv.to_vec(),
+    });
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{MOVE_GEN, POSITION_EVALUATOR, Square::*};
+    use crate::testing::ScriptedGameTree;
+    use crate::{MOVE_GEN, MockClock, POSITION_EVALUATOR, Square::*};
     use test_case::test_case;
+    use testresult::TestResult;
 
     #[test_case(Position::from_fen("rnb1kbnr/2q2ppp/pp1p4/2p1p3/8/1P1PP1P1/PBPNNPBP/R2QK2R b KQkq - 0 1").unwrap(), vec![
         Move::new(B8, C6), Move::new(E1, G1), Move::new(C8, B7), Move::new(E2, C3),
@@ -782,13 +1701,13 @@ mod tests {
         Move::new(A7, B8), Move::new(A3, A4), Move::new(E7, D8), Move::new(C2, B3),
         Move::new(B8, C8), Move::new(B3, C2), Move::new(C8, B8), Move::new(C2, B3),
         Move::new(B8, C8), Move::new(B3, C2),
-    ], 2, Move::new(C8, B8), Eval::DRAW)]
+    ], 2, Move::new(C8, B8), RelativeEval::DRAW)]
     fn test_expected_search_result(
         mut position: Position,
         start_moves: Vec<Move>,
         max_depth: u8,
         mve: Move,
-        eval_want: Eval,
+        eval_want: RelativeEval,
     ) {
         for start_mve in start_moves {
             position.make_move(start_mve);
@@ -812,4 +1731,842 @@ mod tests {
 
         assert_eq!(eval_got, eval_want);
     }
+
+    #[test]
+    fn test_get_pv_truncates_at_illegal_tt_move() {
+        let position = Position::start();
+        let first_move = Move::new(E2, E4);
+        let mut after_first_move = position.clone();
+        after_first_move.make_move(first_move);
+        let reply = Move::new(E7, E5);
+        let mut after_reply = after_first_move.clone();
+        after_reply.make_move(reply);
+
+        // Simulate a hash collision: the entry after the reply has a move that belongs to some
+        // other position.
+        let transposition_table = TranspositionTable::with_num_entries_power_of_two(10);
+        transposition_table.store(
+            &after_first_move,
+            RelativeEval::DRAW,
+            EvalType::Exact,
+            reply,
+            2,
+            false,
+        );
+        transposition_table.store(
+            &after_reply,
+            RelativeEval::DRAW,
+            EvalType::Exact,
+            Move::new(A1, A8),
+            1,
+            false,
+        );
+
+        let pv = get_pv(&position, &[first_move], &transposition_table, MOVE_GEN, 10);
+        assert_eq!(pv, vec![first_move, reply]);
+    }
+
+    fn search_with_mock_clock(
+        params: &SearchParams,
+        clock: &MockClock,
+    ) -> (Option<Move>, SearchResultInfo) {
+        search_with_clock(
+            &Position::start(),
+            params,
+            MOVE_GEN,
+            POSITION_EVALUATOR,
+            &mut TranspositionTable::with_num_entries_power_of_two(16),
+            Arc::new(AtomicBool::new(false)),
+            clock,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_search_frozen_clock_ignores_time_limits() {
+        let params = SearchParams {
+            max_depth: Some(3),
+            move_time: Some(Duration::from_millis(1)),
+            white_time: Some(Duration::from_millis(1)),
+            ..Default::default()
+        };
+        let (best_move, search_res) = search_with_mock_clock(&params, &MockClock::new());
+
+        assert!(best_move.is_some());
+        assert_eq!(search_res.iteration_times, vec![Duration::ZERO; 3]);
+        assert_eq!(search_res.time_elapsed, Duration::ZERO);
+    }
+
+    #[test]
+    fn test_search_stops_at_move_time() {
+        let params = SearchParams {
+            move_time: Some(Duration::from_millis(50)),
+            ..Default::default()
+        };
+        let clock = MockClock::auto_advancing(Duration::from_micros(10));
+        let (best_move, search_res) = search_with_mock_clock(&params, &clock);
+
+        assert!(best_move.is_some());
+        assert!(search_res.iteration_times.len() < 20);
+        assert!(search_res.time_elapsed >= Duration::from_millis(50));
+        assert!(search_res.time_elapsed < Duration::from_millis(51));
+    }
+
+    #[test]
+    fn test_search_stops_at_soft_time_limit() {
+        let params = SearchParams {
+            max_depth: Some(10),
+            white_time: Some(Duration::from_secs(4)),
+            ..Default::default()
+        };
+        let (soft_limit, hard_limit) =
+            calc_time_to_use(Duration::from_secs(4), None, None, &SearchTuning::DEFAULT);
+        let clock = MockClock::auto_advancing(Duration::from_micros(100));
+        let (best_move, search_res) = search_with_mock_clock(&params, &clock);
+
+        assert!(best_move.is_some());
+        assert!(search_res.iteration_times.len() < 10);
+        assert!(search_res.time_elapsed > soft_limit);
+        assert!(search_res.time_elapsed < hard_limit);
+    }
+
+    #[test_case("k7/8/8/8/8/8/1q6/K7 w - - 0 1", Move::new(A1, B2) ; "single reply")]
+    #[test_case("4k3/8/8/8/3q4/8/8/3QK3 w - - 0 1", Move::new(D1, D4) ; "forced capture")]
+    fn test_search_obvious_move_saves_time(fen: &str, best_move_want: Move) {
+        let params = SearchParams {
+            max_depth: Some(20),
+            white_time: Some(Duration::from_secs(4)),
+            ..Default::default()
+        };
+        let (soft_limit, _) =
+            calc_time_to_use(Duration::from_secs(4), None, None, &SearchTuning::DEFAULT);
+        let clock = MockClock::auto_advancing(Duration::from_micros(1));
+        let (best_move, search_res) = search_with_clock(
+            &Position::from_fen(fen).unwrap(),
+            &params,
+            MOVE_GEN,
+            POSITION_EVALUATOR,
+            &mut TranspositionTable::with_num_entries_power_of_two(16),
+            Arc::new(AtomicBool::new(false)),
+            &clock,
+        )
+        .unwrap();
+
+        assert_eq!(best_move, Some(best_move_want));
+        assert!(search_res.time_elapsed < soft_limit / 2);
+    }
+
+    #[test]
+    fn test_search_insufficient_material_saves_time() {
+        let params = SearchParams {
+            max_depth: Some(20),
+            white_time: Some(Duration::from_secs(4)),
+            ..Default::default()
+        };
+        let (soft_limit, _) =
+            calc_time_to_use(Duration::from_secs(4), None, None, &SearchTuning::DEFAULT);
+        let clock = MockClock::auto_advancing(Duration::from_micros(1));
+        let (best_move, search_res) = search_with_clock(
+            &Position::from_fen("4k3/8/8/8/8/8/8/K6B w - - 0 1").unwrap(),
+            &params,
+            MOVE_GEN,
+            POSITION_EVALUATOR,
+            &mut TranspositionTable::with_num_entries_power_of_two(16),
+            Arc::new(AtomicBool::new(false)),
+            &clock,
+        )
+        .unwrap();
+
+        assert!(best_move.is_some());
+        assert_eq!(
+            search_res.move_evals[&best_move.unwrap()],
+            RelativeEval::DRAW
+        );
+        assert!(search_res.time_elapsed < soft_limit / 4);
+    }
+
+    #[test]
+    fn test_search_obvious_move_uses_all_of_move_time() {
+        let params = SearchParams {
+            move_time: Some(Duration::from_millis(50)),
+            ..Default::default()
+        };
+        let clock = MockClock::auto_advancing(Duration::from_micros(10));
+        let (best_move, search_res) = search_with_clock(
+            &Position::from_fen("4k3/8/8/8/3q4/8/8/3QK3 w - - 0 1").unwrap(),
+            &params,
+            MOVE_GEN,
+            POSITION_EVALUATOR,
+            &mut TranspositionTable::with_num_entries_power_of_two(16),
+            Arc::new(AtomicBool::new(false)),
+            &clock,
+        )
+        .unwrap();
+
+        assert_eq!(best_move, Some(Move::new(D1, D4)));
+        assert!(search_res.time_elapsed >= Duration::from_millis(50));
+    }
+
+    #[test_case(false, 3 ; "before ponder hit")]
+    #[test_case(true, 0 ; "after ponder hit")]
+    fn test_ponder_search_time_limits(is_ponder_hit: bool, num_iterations_want: usize) {
+        let ponder_hit = PonderHit::new();
+        if is_ponder_hit {
+            ponder_hit.trigger();
+        }
+        let params = SearchParams {
+            max_depth: Some(3),
+            move_time: Some(Duration::from_millis(1)),
+            ponder: true,
+            ponder_hit: Some(ponder_hit),
+            ..Default::default()
+        };
+        let clock = MockClock::auto_advancing(Duration::from_micros(100));
+        let (_, search_res) = search_with_mock_clock(&params, &clock);
+
+        assert_eq!(search_res.iteration_times.len(), num_iterations_want);
+    }
+
+    // Child evals are given from the child's perspective, like a search would return them. The
+    // window is (-50, 50) from this node's perspective.
+    #[test_case(true, &[RelativeEval::cp(-20)], &[(RelativeEval::cp(-50), RelativeEval::cp(50))], Some(RelativeEval::cp(20)) ; "first move full window")]
+    #[test_case(false, &[RelativeEval::cp(80)], &[(RelativeEval::cp(49), RelativeEval::cp(50))], Some(RelativeEval::cp(-80)) ; "fail low returns soft bound")]
+    #[test_case(false, &[RelativeEval::cp(-90)], &[(RelativeEval::cp(49), RelativeEval::cp(50))], Some(RelativeEval::cp(90)) ; "fail high skips re-search")]
+    #[test_case(false, &[RelativeEval::cp(30), RelativeEval::cp(-10)], &[(RelativeEval::cp(49), RelativeEval::cp(50)), (RelativeEval::cp(-50), RelativeEval::cp(50))], Some(RelativeEval::cp(10)) ; "inside window re-searches")]
+    #[test_case(false, &[RelativeEval::cp(-50)], &[(RelativeEval::cp(49), RelativeEval::cp(50))], Some(RelativeEval::cp(50)) ; "equal to beta skips re-search")]
+    fn test_pvs_search_child(
+        is_first_move: bool,
+        child_evals: &[RelativeEval],
+        windows_want: &[(RelativeEval, RelativeEval)],
+        eval_want: Option<RelativeEval>,
+    ) {
+        let mut windows = vec![];
+        let mut child_evals = child_evals.iter().copied();
+        let got = pvs_search_child(
+            is_first_move,
+            RelativeEval::cp(-50),
+            RelativeEval::cp(50),
+            |alpha, beta| {
+                windows.push((alpha, beta));
+                child_evals.next()
+            },
+        );
+
+        assert_eq!(got, eval_want);
+        assert_eq!(windows, windows_want);
+    }
+
+    #[test]
+    fn test_pvs_search_child_terminated() {
+        // The zero window search proves the move is better, but the re-search is terminated.
+        let mut child_evals = [Some(RelativeEval::cp(30)), None].into_iter();
+        let got = pvs_search_child(
+            false,
+            RelativeEval::cp(-50),
+            RelativeEval::cp(50),
+            |_, _| child_evals.next().flatten(),
+        );
+        assert_eq!(got, None);
+    }
+
+    #[test]
+    fn test_pvs_search_child_zero_window_at_mate_bound() {
+        // With no move found yet alpha is the lowest possible eval, and the zero window must
+        // still be valid after flipping it.
+        let mut windows = vec![];
+        pvs_search_child(
+            false,
+            RelativeEval::MIN,
+            RelativeEval::MAX,
+            |alpha, beta| {
+                windows.push((alpha, beta));
+                Some(RelativeEval::DRAW)
+            },
+        );
+        assert_eq!(windows[0], (RelativeEval::MAX - 1, RelativeEval::MAX));
+        assert!(windows[0].0 < windows[0].1);
+    }
+
+    // The window is (-50, 20) and the stored eval is 30, searched to depth 4.
+    #[test_case(EvalType::Exact, 3, true ; "exact")]
+    #[test_case(EvalType::Exact, 5, false ; "too shallow")]
+    #[test_case(EvalType::LowerBound, 3, true ; "lower bound above beta")]
+    #[test_case(EvalType::UpperBound, 3, false ; "upper bound above alpha")]
+    fn test_probe_transposition_table(eval_type: EvalType, remaining_depth: u8, want_cutoff: bool) {
+        let position = Position::start();
+        let transposition_table = TranspositionTable::new();
+        assert_eq!(
+            probe_transposition_table(
+                &position,
+                &transposition_table,
+                remaining_depth,
+                RelativeEval::cp(-50),
+                RelativeEval::cp(20)
+            ),
+            TtProbe::Miss
+        );
+
+        let mve = Move::new(E2, E4);
+        transposition_table.store(&position, RelativeEval::cp(30), eval_type, mve, 4, false);
+        let want = if want_cutoff {
+            TtProbe::Cutoff(RelativeEval::cp(30))
+        } else {
+            TtProbe::BestMove(mve)
+        };
+        assert_eq!(
+            probe_transposition_table(
+                &position,
+                &transposition_table,
+                remaining_depth,
+                RelativeEval::cp(-50),
+                RelativeEval::cp(20)
+            ),
+            want
+        );
+    }
+
+    // The entry is searched to depth 8 and the node needs depth 1.
+    #[test_case(0, 90, true ; "far from fifty move rule")]
+    #[test_case(0, 95, false ; "stored with lower clock near fifty move rule")]
+    #[test_case(96, 95, true ; "stored with higher clock near fifty move rule")]
+    fn test_probe_transposition_table_half_move_clock(
+        stored_half_move_clock: u8,
+        half_move_clock: u8,
+        want_cutoff: bool,
+    ) -> TestResult {
+        let fen =
+            |half_move_clock: u8| format!("4k3/8/8/8/8/8/8/R3K3 w - - {} 80", half_move_clock);
+        let transposition_table = TranspositionTable::new();
+        let mve = Move::new(A1, A7);
+        transposition_table.store(
+            &Position::from_fen(&fen(stored_half_move_clock))?,
+            RelativeEval::mate_in(4),
+            EvalType::Exact,
+            mve,
+            8,
+            false,
+        );
+
+        let want = if want_cutoff {
+            TtProbe::Cutoff(RelativeEval::mate_in(4))
+        } else {
+            TtProbe::BestMove(mve)
+        };
+        assert_eq!(
+            probe_transposition_table(
+                &Position::from_fen(&fen(half_move_clock))?,
+                &transposition_table,
+                1,
+                RelativeEval::MIN,
+                RelativeEval::MAX
+            ),
+            want
+        );
+        Ok(())
+    }
+
+    fn search_scripted_game_tree(
+        tree: &ScriptedGameTree,
+        max_depth: u8,
+    ) -> (Option<Move>, SearchResultInfo) {
+        search(
+            tree.root(),
+            &SearchParams {
+                max_depth: Some(max_depth),
+                ..Default::default()
+            },
+            tree,
+            tree,
+            &mut TranspositionTable::new(),
+            Arc::new(AtomicBool::new(false)),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_search_scripted_game_tree_minimax() {
+        let (e4, d4) = (Move::new(E2, E4), Move::new(D2, D4));
+        let (e5, c5, d5, nf6) = (
+            Move::new(E7, E5),
+            Move::new(C7, C5),
+            Move::new(D7, D5),
+            Move::new(G8, F6),
+        );
+        // Leaves are evaluated for white, who is to move in them. Black answers each move with
+        // the reply that's worst for white.
+        let tree = ScriptedGameTree::new(Position::start())
+            .eval(&[e4, e5], RelativeEval::cp(10))
+            .eval(&[e4, c5], RelativeEval::cp(-40))
+            .eval(&[d4, d5], RelativeEval::cp(20))
+            .eval(&[d4, nf6], RelativeEval::cp(25));
+
+        let (best_move, search_res) = search_scripted_game_tree(&tree, 2);
+
+        assert_eq!(best_move, Some(d4));
+        assert_eq!(search_res.move_evals[&e4], RelativeEval::cp(-40));
+        assert_eq!(search_res.move_evals[&d4], RelativeEval::cp(20));
+        assert_eq!(search_res.pv, vec![d4, d5]);
+    }
+
+    #[test]
+    fn test_search_scripted_game_tree_cutoff() {
+        let e4 = Move::new(E2, E4);
+        let (e5, c5) = (Move::new(E7, E5), Move::new(C7, C5));
+        let (nf3, d4) = (Move::new(G1, F3), Move::new(D2, D4));
+        // Leaves are evaluated for black. After 1...e5 white's best is worth 30, so once either
+        // move after 1...c5 is found to be worth more, black won't play it and the other move
+        // doesn't need to be searched. The static evals make the depth 2 iteration search
+        // 1...e5 first.
+        let tree = ScriptedGameTree::new(Position::start())
+            .eval(&[e4, e5], RelativeEval::cp(30))
+            .eval(&[e4, c5], RelativeEval::cp(50))
+            .eval(&[e4, e5, nf3], RelativeEval::cp(-10))
+            .eval(&[e4, e5, d4], RelativeEval::cp(-30))
+            .eval(&[e4, c5, nf3], RelativeEval::cp(-50))
+            .eval(&[e4, c5, d4], RelativeEval::cp(-40));
+
+        let (_, search_res) = search_scripted_game_tree(&tree, 3);
+
+        assert_eq!(search_res.move_evals[&e4], RelativeEval::cp(30));
+        assert_eq!(tree.num_evaluations(&[e4, e5, nf3]), 1);
+        // 2.d4 is better than 2.Nf3, so its zero window search fails high and it's searched
+        // again with the full window.
+        assert_eq!(tree.num_evaluations(&[e4, e5, d4]), 2);
+        assert_eq!(
+            tree.num_evaluations(&[e4, c5, nf3]) + tree.num_evaluations(&[e4, c5, d4]),
+            1
+        );
+    }
+
+    #[test]
+    fn test_search_result_info_branching_factor() {
+        let search_res = SearchResultInfo {
+            positions_processed: 1110,
+            thread_positions_processed: vec![1110],
+            time_elapsed: Duration::from_millis(111),
+            iteration_times: vec![
+                Duration::from_millis(1),
+                Duration::from_millis(10),
+                Duration::from_millis(100),
+            ],
+            iteration_nodes: vec![10, 100, 1000],
+            move_evals: HashMap::new(),
+            pv: vec![],
+            lines: vec![],
+            tt_hits: 0,
+        };
+
+        assert_eq!(
+            search_res.time_to_depth(),
+            vec![
+                Duration::from_millis(1),
+                Duration::from_millis(11),
+                Duration::from_millis(111)
+            ]
+        );
+        assert_eq!(search_res.effective_branching_factors(), vec![10.0, 10.0]);
+        assert!((search_res.effective_branching_factor().unwrap() - 10.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_search_multi_pv() {
+        let params = SearchParams {
+            max_depth: Some(3),
+            multi_pv: Some(3),
+            ..Default::default()
+        };
+        let (best_move, search_res) = search_with_mock_clock(&params, &MockClock::new());
+
+        assert_eq!(search_res.lines.len(), 3);
+        assert_eq!(search_res.lines[0], search_res.pv);
+        assert_eq!(Some(search_res.lines[0][0]), best_move);
+
+        let line_evals: Vec<_> = search_res
+            .lines
+            .iter()
+            .map(|line| search_res.move_evals[&line[0]])
+            .collect();
+        assert!(line_evals.is_sorted_by(|a, b| a >= b));
+
+        let mut first_moves: Vec<_> = search_res.lines.iter().map(|line| line[0]).collect();
+        first_moves.sort();
+        first_moves.dedup();
+        assert_eq!(first_moves.len(), 3);
+    }
+
+    #[test]
+    fn test_search_pv_survives_tt_replacement() {
+        // The TT is too small to keep the PV, so the moves after the first come from the PV
+        // table.
+        let mut transposition_table = TranspositionTable::with_num_entries_power_of_two(1);
+        let (_, search_res) = search(
+            &Position::start(),
+            &SearchParams {
+                max_depth: Some(4),
+                ..Default::default()
+            },
+            MOVE_GEN,
+            POSITION_EVALUATOR,
+            &mut transposition_table,
+            Arc::new(AtomicBool::new(false)),
+        )
+        .unwrap();
+
+        assert_eq!(search_res.pv.len(), 4);
+        let mut position = Position::start();
+        for mve in search_res.pv {
+            assert!(MOVE_GEN.gen_moves(&position).contains(&mve));
+            position.make_move(mve);
+        }
+    }
+
+    fn search_depth_with_tt(
+        position: &Position,
+        max_depth: u8,
+        transposition_table: &mut TranspositionTable,
+    ) -> RelativeEval {
+        let (best_move, search_res) = search(
+            position,
+            &SearchParams {
+                max_depth: Some(max_depth),
+                ..Default::default()
+            },
+            MOVE_GEN,
+            POSITION_EVALUATOR,
+            transposition_table,
+            Arc::new(AtomicBool::new(false)),
+        )
+        .unwrap();
+        search_res.move_evals[&best_move.unwrap()]
+    }
+
+    // The mate in 2 position is reached two plies into the search of the other one, so its TT
+    // entries are written at a different ply than the one they're read at.
+    #[test_case(false ; "parent searched first")]
+    #[test_case(true ; "child searched first")]
+    fn test_tt_mate_scores_reused_at_different_ply(child_first: bool) {
+        let child = Position::from_fen("1k6/8/2R5/7R/8/8/8/6K1 w - - 0 1").unwrap();
+        let parent = Position::from_fen("k7/8/2R5/7R/8/8/8/5K2 w - - 0 1").unwrap();
+        let child_eval_want = search_depth_with_tt(&child, 4, &mut TranspositionTable::new());
+        let parent_eval_want = search_depth_with_tt(&parent, 6, &mut TranspositionTable::new());
+        assert_eq!(child_eval_want, RelativeEval::mate_in(2));
+
+        let mut transposition_table = TranspositionTable::new();
+        let (child_eval, parent_eval) = if child_first {
+            let child_eval = search_depth_with_tt(&child, 4, &mut transposition_table);
+            (child_eval, search_depth_with_tt(&parent, 6, &mut transposition_table))
+        } else {
+            let parent_eval = search_depth_with_tt(&parent, 6, &mut transposition_table);
+            (search_depth_with_tt(&child, 4, &mut transposition_table), parent_eval)
+        };
+        assert_eq!(child_eval, child_eval_want);
+        assert_eq!(parent_eval, parent_eval_want);
+    }
+
+    #[test]
+    fn test_tt_win_not_reused_when_path_allows_repetition() {
+        let mut position = Position::from_fen("4k1n1/8/8/8/8/3R4/3Q4/K7 w - - 0 1").unwrap();
+        // Shuffle twice, so that black's knight going back to f6 after Kb1 is a threefold
+        // repetition. Without this history, Kb1 just leaves white a rook and queen up.
+        for mve in [
+            Move::new(A1, B1),
+            Move::new(G8, F6),
+            Move::new(B1, A1),
+            Move::new(F6, G8),
+            Move::new(A1, B1),
+            Move::new(G8, F6),
+            Move::new(B1, A1),
+            Move::new(F6, H7),
+        ] {
+            position.make_move(mve);
+        }
+        let without_history = Position::from_fen(&position.to_fen()).unwrap();
+        let mve = Move::new(A1, B1);
+        let search_move_eval =
+            |position: &Position, max_depth: u8, transposition_table: &mut TranspositionTable| {
+                let (_, search_res) = search(
+                    position,
+                    &SearchParams {
+                        max_depth: Some(max_depth),
+                        search_moves: Some(vec![mve]),
+                        ..Default::default()
+                    },
+                    MOVE_GEN,
+                    POSITION_EVALUATOR,
+                    transposition_table,
+                    Arc::new(AtomicBool::new(false)),
+                )
+                .unwrap();
+                search_res.move_evals[&mve]
+            };
+
+        let mut transposition_table = TranspositionTable::new();
+        assert!(
+            search_move_eval(&without_history, 3, &mut transposition_table) > RelativeEval::DRAW
+        );
+        assert_eq!(
+            search_move_eval(&position, 2, &mut TranspositionTable::new()),
+            RelativeEval::DRAW
+        );
+        assert_eq!(
+            search_move_eval(&position, 2, &mut transposition_table),
+            RelativeEval::DRAW
+        );
+    }
+
+    #[test_case(Some(3), None, 3 ; "depth")]
+    #[test_case(None, Some(2), 2 ; "mate")]
+    #[test_case(Some(3), Some(2), 2 ; "mate stricter")]
+    #[test_case(Some(2), Some(5), 2 ; "depth stricter")]
+    fn test_search_depth_and_mate(
+        max_depth: Option<u8>,
+        mate: Option<u8>,
+        num_iterations_want: usize,
+    ) {
+        let (best_move, search_res) = search(
+            &Position::from_fen("1k6/8/2R5/7R/8/8/8/6K1 w - - 0 1").unwrap(),
+            &SearchParams {
+                max_depth,
+                mate,
+                ..Default::default()
+            },
+            MOVE_GEN,
+            POSITION_EVALUATOR,
+            &mut TranspositionTable::new(),
+            Arc::new(AtomicBool::new(false)),
+        )
+        .unwrap();
+        assert!(best_move.is_some());
+        assert_eq!(search_res.iteration_times.len(), num_iterations_want);
+    }
+
+    fn search_with_tuning(fen: &str, max_depth: u8, tuning: SearchTuning) -> (Move, u64) {
+        let (best_move, search_res) = search(
+            &Position::from_fen(fen).unwrap(),
+            &SearchParams {
+                max_depth: Some(max_depth),
+                tuning,
+                ..Default::default()
+            },
+            MOVE_GEN,
+            POSITION_EVALUATOR,
+            &mut TranspositionTable::new(),
+            Arc::new(AtomicBool::new(false)),
+        )
+        .unwrap();
+        (best_move.unwrap(), search_res.positions_processed)
+    }
+
+    #[test]
+    fn test_qsearch_depth_factor() {
+        // Without quiescence search the recapture on d5 isn't seen.
+        let fen = "4k3/8/4p3/3p4/8/8/8/3QK3 w - - 0 1";
+        let (best_move, _) = search_with_tuning(fen, 1, SearchTuning::default());
+        assert_ne!(best_move, Move::new(D1, D5));
+
+        let no_qsearch = SearchTuning {
+            qsearch_depth_factor: 1,
+            ..SearchTuning::default()
+        };
+        let (best_move, _) = search_with_tuning(fen, 1, no_qsearch);
+        assert_eq!(best_move, Move::new(D1, D5));
+    }
+
+    #[test]
+    fn test_qsearch_see_margin() {
+        let fen = "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4";
+        let (_, nodes_pruned) = search_with_tuning(fen, 3, SearchTuning::default());
+        let no_see_pruning = SearchTuning {
+            qsearch_see_margin: 10_000,
+            ..SearchTuning::default()
+        };
+        let (_, nodes_unpruned) = search_with_tuning(fen, 3, no_see_pruning);
+        assert!(nodes_pruned < nodes_unpruned);
+    }
+
+    #[test]
+    fn test_futility_pruning() {
+        // White is a queen up, so close to the horizon most quiet moves can't change the score
+        // enough to matter.
+        let fen = "3rk3/ppp2ppp/8/8/8/8/PPP2PPP/3QKR2 w - - 0 1";
+        let (best_move, nodes_pruned) = search_with_tuning(fen, 4, SearchTuning::default());
+        let no_futility_pruning = SearchTuning {
+            futility_margins: [RelativeEval::MAX.0; 3],
+            ..SearchTuning::default()
+        };
+        let (best_move_unpruned, nodes_unpruned) = search_with_tuning(fen, 4, no_futility_pruning);
+        assert_eq!(best_move, best_move_unpruned);
+        assert!(nodes_pruned < nodes_unpruned);
+    }
+
+    #[test_case(1, false, Some(RelativeEval::cp(-200)) ; "margin for remaining depth")]
+    #[test_case(1, true, Some(RelativeEval::cp(-50)) ; "improving uses next margin")]
+    #[test_case(3, true, None ; "improving past last margin")]
+    fn test_futility_eval(remaining_depth: u8, is_improving: bool, want: Option<RelativeEval>) {
+        let params = SearchParams {
+            tuning: SearchTuning {
+                futility_margins: [100, 250, 400],
+                ..SearchTuning::default()
+            },
+            ..SearchParams::default()
+        };
+        let got = futility_eval(
+            &params,
+            remaining_depth,
+            is_improving,
+            RelativeEval::cp(-300),
+            RelativeEval::cp(0),
+        );
+        assert_eq!(got, want);
+    }
+
+    #[test]
+    fn test_reverse_futility_pruning() {
+        let fen = "3rk3/ppp2ppp/8/8/8/8/PPP2PPP/3QKR2 w - - 0 1";
+        let (best_move, nodes_pruned) = search_with_tuning(fen, 4, SearchTuning::default());
+        let no_reverse_futility_pruning = SearchTuning {
+            reverse_futility_margin: RelativeEval::MAX.0,
+            ..SearchTuning::default()
+        };
+        let (best_move_unpruned, nodes_unpruned) =
+            search_with_tuning(fen, 4, no_reverse_futility_pruning);
+        assert_eq!(best_move, best_move_unpruned);
+        assert!(nodes_pruned < nodes_unpruned);
+    }
+
+    #[test]
+    fn test_analysis_mode_disables_null_move_pruning() {
+        let search_nodes = |analysis_mode| {
+            let (_, search_res) = search(
+                &Position::from_fen("r3k3/pp6/8/8/8/8/6PP/4K2R w - - 0 1").unwrap(),
+                &SearchParams {
+                    max_depth: Some(5),
+                    analysis_mode,
+                    ..Default::default()
+                },
+                MOVE_GEN,
+                POSITION_EVALUATOR,
+                &mut TranspositionTable::new(),
+                Arc::new(AtomicBool::new(false)),
+            )
+            .unwrap();
+            search_res.positions_processed
+        };
+        assert!(search_nodes(false) < search_nodes(true));
+    }
+
+    #[test]
+    fn test_prune_root_moves() {
+        let (best, close, losing) = (Move::new(E2, E4), Move::new(D2, D4), Move::new(F2, F3));
+        let move_vals = HashMap::from([
+            (best, RelativeEval(50)),
+            (close, RelativeEval(-400)),
+            (losing, RelativeEval(-600)),
+        ]);
+        let mut moves = ArrayVec::from_iter([best, close, losing]);
+        let mut low_streaks = HashMap::new();
+
+        for _ in 1..ROOT_PRUNE_ITERATIONS {
+            prune_root_moves(&mut moves, &move_vals, &mut low_streaks, 500);
+            assert_eq!(moves.as_slice(), [best, close, losing]);
+        }
+        prune_root_moves(&mut moves, &move_vals, &mut low_streaks, 500);
+        assert_eq!(moves.as_slice(), [best, close]);
+    }
+
+    #[test]
+    fn test_prune_root_moves_resets_streak() {
+        let (best, other) = (Move::new(E2, E4), Move::new(D2, D4));
+        let mut moves = ArrayVec::from_iter([best, other]);
+        let mut low_streaks = HashMap::new();
+
+        for iteration in 0..ROOT_PRUNE_ITERATIONS * 2 {
+            // The other move only scores badly every other iteration.
+            let other_eval = if iteration % 2 == 0 { -1000 } else { 0 };
+            let move_vals =
+                HashMap::from([(best, RelativeEval(50)), (other, RelativeEval(other_eval))]);
+            prune_root_moves(&mut moves, &move_vals, &mut low_streaks, 500);
+        }
+        assert_eq!(moves.as_slice(), [best, other]);
+    }
+
+    #[test]
+    fn test_root_move_pruning() {
+        // White can win the queen, every other move is far worse.
+        let fen = "4k3/8/8/3q4/8/8/8/3RK3 w - - 0 1";
+        let (best_move, nodes_pruned) = search_with_tuning(fen, 7, SearchTuning::default());
+        let no_root_move_pruning = SearchTuning {
+            root_move_pruning: false,
+            ..SearchTuning::default()
+        };
+        let (best_move_unpruned, nodes_unpruned) = search_with_tuning(fen, 7, no_root_move_pruning);
+        assert_eq!(best_move, Move::new(D1, D5));
+        assert_eq!(best_move, best_move_unpruned);
+        assert!(nodes_pruned < nodes_unpruned);
+    }
+
+    #[test_case(&[], ASPIRATION_MIN_DELTA ; "no iterations")]
+    #[test_case(&[20, 30, 20, 30], ASPIRATION_MIN_DELTA + 10 ; "quiet")]
+    #[test_case(&[500, -500, 20, 30, 20, 30], ASPIRATION_MIN_DELTA + 10 ; "only recent iterations")]
+    #[test_case(&[0, 90, -60, 30], ASPIRATION_MIN_DELTA + 110 ; "volatile")]
+    #[test_case(&[0, 900, -900], ASPIRATION_MAX_DELTA ; "capped")]
+    fn test_calc_aspiration_delta(root_evals: &[i32], delta_want: i32) {
+        let root_evals: Vec<RelativeEval> =
+            root_evals.iter().map(|&eval| RelativeEval(eval)).collect();
+        assert_eq!(calc_aspiration_delta(&root_evals), delta_want);
+    }
+
+    // The previous eval is 50 with a delta of 35, so the first window is (-85, -15) from the
+    // child's side. Windows are listed from the child's side too.
+    #[test_case(Some(50), -40, 40, vec![(-85, -15)] ; "inside window")]
+    #[test_case(Some(50), -200, 200, vec![(-85, -15), (RelativeEval::MAX.flip().0, -15)] ; "fails high")]
+    #[test_case(Some(50), 100, -100, vec![(-85, -15), (-85, RelativeEval::MAX.0)] ; "fails low")]
+    #[test_case(None, -200, 200, vec![(RelativeEval::MAX.flip().0, RelativeEval::MAX.0)] ; "full window")]
+    fn test_aspiration_search_root_move(
+        maybe_prev_eval: Option<i32>,
+        child_eval: i32,
+        eval_want: i32,
+        windows_want: Vec<(i32, i32)>,
+    ) {
+        let mut windows = vec![];
+        let eval =
+            aspiration_search_root_move(maybe_prev_eval.map(RelativeEval), 35, |alpha, beta| {
+                windows.push((alpha.0, beta.0));
+                // Fails hard, so an eval outside the window is only ever returned as a bound.
+                Some(RelativeEval(child_eval).clamp(alpha, beta))
+            });
+        assert_eq!(eval, Some(RelativeEval(eval_want)));
+        assert_eq!(windows, windows_want);
+    }
+
+    const NO_MOVE_OVERHEAD: SearchTuning = SearchTuning {
+        move_overhead: Duration::ZERO,
+        ..SearchTuning::DEFAULT
+    };
+
+    #[test_case(Duration::from_secs(40), None, None, NO_MOVE_OVERHEAD, Duration::from_millis(950), Duration::from_millis(1900) ; "default moves to go")]
+    #[test_case(Duration::from_secs(40), Some(Duration::from_secs(1)), None, NO_MOVE_OVERHEAD, Duration::from_millis(1950), Duration::from_millis(3900) ; "increment")]
+    #[test_case(Duration::from_secs(40), None, Some(10), NO_MOVE_OVERHEAD, Duration::from_millis(3800), Duration::from_millis(7600) ; "moves to go")]
+    #[test_case(Duration::from_secs(40), None, Some(1), NO_MOVE_OVERHEAD, Duration::from_secs(38), Duration::from_secs(38) ; "last move before time control")]
+    #[test_case(Duration::from_secs(1), Some(Duration::from_secs(2)), None, NO_MOVE_OVERHEAD, Duration::from_millis(950), Duration::from_millis(950) ; "increment more than time left")]
+    #[test_case(Duration::from_secs(41), None, None, SearchTuning { move_overhead: Duration::from_secs(1), ..SearchTuning::DEFAULT }, Duration::from_millis(950), Duration::from_millis(1900) ; "move overhead")]
+    #[test_case(Duration::from_secs(40), None, None, SearchTuning { move_overhead: Duration::from_secs(50), ..SearchTuning::DEFAULT }, Duration::ZERO, Duration::ZERO ; "move overhead more than time left")]
+    #[test_case(Duration::from_secs(40), None, None, SearchTuning { soft_time_percent: 50, hard_time_percent: 300, ..NO_MOVE_OVERHEAD }, Duration::from_millis(475), Duration::from_millis(2850) ; "time percents")]
+    #[test_case(Duration::from_secs(40), None, Some(0), NO_MOVE_OVERHEAD, Duration::from_secs(38), Duration::from_secs(38) ; "zero moves to go")]
+    #[test_case(Duration::ZERO, Some(Duration::from_secs(2)), None, NO_MOVE_OVERHEAD, Duration::ZERO, Duration::ZERO ; "no time left")]
+    #[test_case(Duration::from_millis(100), Some(Duration::from_secs(2)), Some(0), SearchTuning::DEFAULT, Duration::from_micros(85_500), Duration::from_micros(85_500) ; "increment on the last move")]
+    #[test_case(Duration::MAX, Some(Duration::MAX), None, SearchTuning { hard_time_percent: u32::MAX, ..NO_MOVE_OVERHEAD }, Duration::MAX / 100, Duration::MAX / 100 ; "huge times")]
+    fn test_calc_time_to_use(
+        time_left: Duration,
+        time_inc: Option<Duration>,
+        moves_to_go: Option<u16>,
+        tuning: SearchTuning,
+        soft_want: Duration,
+        hard_want: Duration,
+    ) {
+        assert_eq!(
+            calc_time_to_use(time_left, time_inc, moves_to_go, &tuning),
+            (soft_want, hard_want)
+        );
+    }
 }
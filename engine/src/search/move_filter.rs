@@ -0,0 +1,101 @@
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use crate::position::{Move, Piece, Position};
+
+type MoveFilterFn = dyn Fn(&Position, Move) -> bool + Send + Sync;
+
+/// Restricts which moves the search is allowed to consider, e.g. for coaching tools that want
+/// to know the best move with a particular piece.
+///
+/// The filter is called with the position before the move is made, and the move is searched
+/// only if it returns true. By default it's only applied at the root.
+#[derive(Clone)]
+pub struct MoveFilter {
+    filter: Arc<MoveFilterFn>,
+    root_only: bool,
+}
+
+impl MoveFilter {
+    /// Filter applied only to the moves at the root of the search.
+    pub fn root(filter: impl Fn(&Position, Move) -> bool + Send + Sync + 'static) -> Self {
+        Self {
+            filter: Arc::new(filter),
+            root_only: true,
+        }
+    }
+
+    /// Filter applied at every node of the search, for both sides.
+    pub fn all_nodes(filter: impl Fn(&Position, Move) -> bool + Send + Sync + 'static) -> Self {
+        Self {
+            filter: Arc::new(filter),
+            root_only: false,
+        }
+    }
+
+    /// Only consider root moves made by the given piece type.
+    pub fn only_piece(piece: Piece) -> Self {
+        Self::root(move |position, mve| {
+            position
+                .is_piece_at(mve.src, position.state.to_move)
+                .is_some_and(|src_piece| src_piece == piece)
+        })
+    }
+
+    /// Never capture a queen, for either side, anywhere in the search.
+    pub fn keep_queens_on() -> Self {
+        Self::all_nodes(|position, mve| {
+            position.is_piece_at(mve.dest, position.state.to_move.opposite_side())
+                != Some(Piece::Queen)
+        })
+    }
+
+    pub fn is_root_only(&self) -> bool {
+        self.root_only
+    }
+
+    pub fn allows(&self, position: &Position, mve: Move) -> bool {
+        (self.filter)(position, mve)
+    }
+}
+
+impl Debug for MoveFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MoveFilter")
+            .field("root_only", &self.root_only)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Filters are compared by identity, since closures can't be compared.
+impl PartialEq for MoveFilter {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.filter, &other.filter) && self.root_only == other.root_only
+    }
+}
+
+impl Eq for MoveFilter {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitboard::Square::*;
+    use test_case::test_case;
+
+    #[test_case(Move::new(B1, C3), true ; "knight")]
+    #[test_case(Move::new(E2, E4), false ; "pawn")]
+    fn test_only_piece(mve: Move, want: bool) {
+        let filter = MoveFilter::only_piece(Piece::Knight);
+        assert_eq!(filter.allows(&Position::start(), mve), want);
+        assert!(filter.is_root_only());
+    }
+
+    #[test_case(Move::new(D1, D8), false ; "queen takes queen")]
+    #[test_case(Move::new(D1, D2), true ; "quiet queen move")]
+    fn test_keep_queens_on(mve: Move, want: bool) {
+        let position = Position::from_fen("3qk3/8/8/8/8/8/8/3QK3 w - - 0 1").unwrap();
+        let filter = MoveFilter::keep_queens_on();
+        assert_eq!(filter.allows(&position, mve), want);
+        assert!(!filter.is_root_only());
+    }
+}
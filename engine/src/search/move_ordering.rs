@@ -1,34 +1,89 @@
-use std::u64;
-
 use arrayvec::ArrayVec;
 
-use crate::position::{Move, Position};
+use crate::Piece;
+use crate::position::{Move, Position, Side};
+use crate::see::static_exchange_eval;
+
+/// Number of killer moves remembered per ply.
+pub(super) const KILLER_MOVES_PER_PLY: usize = 2;
+
+// Moves are ordered in buckets: the TT move, captures that don't lose material, killer moves,
+// captures that lose material and then the remaining quiet moves by their history score.
+const NON_LOSING_CAPTURE_BASE: i64 = 3_000_000;
+const KILLER_MOVE_BASE: i64 = 2_000_000;
+const LOSING_CAPTURE_BASE: i64 = 1_000_000;
 
 pub(super) fn order_moves(
     moves: &mut ArrayVec<Move, 218>,
     position: &Position,
     maybe_tt_best_move: Option<Move>,
+    maybe_killer_moves: Option<&[Option<Move>; KILLER_MOVES_PER_PLY]>,
     maybe_butterfly_history_state: Option<&ButterflyHistoryState>,
+    maybe_continuation_history: Option<(&ContinuationHistory, u8)>,
 ) {
-    moves.sort_by_key(|&mve| -(get_move_sort_key(mve, position, maybe_tt_best_move, maybe_butterfly_history_state) as i64))
+    moves.sort_by_cached_key(|&mve| {
+        -get_move_sort_key(
+            mve,
+            position,
+            maybe_tt_best_move,
+            maybe_killer_moves,
+            maybe_butterfly_history_state,
+            maybe_continuation_history,
+        )
+    })
 }
 
-fn get_move_sort_key(mve: Move, position: &Position, maybe_tt_best_move: Option<Move>, maybe_butterfly_history_state: Option<&ButterflyHistoryState>) -> i64 {
-    if let Some(tt_best_move) = maybe_tt_best_move {
-        if mve == tt_best_move {
-            return i64::MAX;
-        }
+fn get_move_sort_key(
+    mve: Move,
+    position: &Position,
+    maybe_tt_best_move: Option<Move>,
+    maybe_killer_moves: Option<&[Option<Move>; KILLER_MOVES_PER_PLY]>,
+    maybe_butterfly_history_state: Option<&ButterflyHistoryState>,
+    maybe_continuation_history: Option<(&ContinuationHistory, u8)>,
+) -> i64 {
+    if let Some(tt_best_move) = maybe_tt_best_move
+        && mve == tt_best_move
+    {
+        return i64::MAX;
     }
 
     if position.is_capture(mve) {
-        return get_mvv_lva_value(mve, position);
+        let mvv_lva_value = get_mvv_lva_value(mve, position);
+        return if is_non_losing_capture(mve, position) {
+            NON_LOSING_CAPTURE_BASE + mvv_lva_value
+        } else {
+            LOSING_CAPTURE_BASE + mvv_lva_value
+        };
     }
 
-    if let Some(butterfly_history_state) = maybe_butterfly_history_state {
-        return butterfly_history_state.score(mve).into();
-    } else {
-        return 0;
+    if let Some(killer_moves) = maybe_killer_moves
+        && let Some(killer_idx) = killer_moves.iter().position(|&killer| killer == Some(mve))
+    {
+        return KILLER_MOVE_BASE - killer_idx as i64;
     }
+
+    let butterfly_score = maybe_butterfly_history_state.map_or(0, |butterfly_history_state| {
+        butterfly_history_state.score(mve)
+    });
+    let continuation_score = maybe_continuation_history.map_or(0, |(continuation_history, ply)| {
+        continuation_history.score(position, mve, ply)
+    });
+    i64::from(butterfly_score + continuation_score)
+}
+
+/// Capturing a piece at least as valuable as the attacker can't lose material, so the static
+/// exchange only needs to be evaluated for the remaining captures.
+fn is_non_losing_capture(mve: Move, position: &Position) -> bool {
+    let attacker = position
+        .is_piece_at(mve.src, position.state.to_move)
+        .expect("No piece at attacker square");
+    let victim = position
+        .is_piece_at(mve.dest, position.state.to_move.opposite_side())
+        .expect("No piece at victim square");
+    if victim >= attacker || (attacker, victim) == (Piece::Bishop, Piece::Knight) {
+        return true;
+    }
+    static_exchange_eval(position, mve).is_ok_and(|static_exchange| static_exchange.value >= 0)
 }
 
 pub(super) fn get_mvv_lva_value(mve: Move, position: &Position) -> i64 {
@@ -72,11 +127,119 @@ impl ButterflyHistoryState {
     }
 }
 
+/// Cutoffs by quiet moves, keyed by the piece and destination of the move itself and of the move
+/// played one and two plies before it. Unlike butterfly history this captures follow ups, like
+/// the replies that work against a particular threat.
+/// Source: https://www.chessprogramming.org/History_Heuristic
+pub(crate) struct ContinuationHistory {
+    one_ply: Box<[i32]>,
+    two_ply: Box<[i32]>,
+    /// Piece and destination index of the move played at each ply, or `None` for a null move.
+    played: [Option<usize>; u8::MAX as usize + 1],
+}
+
+impl ContinuationHistory {
+    /// Upper bound on a table entry. Bonuses shrink as an entry gets closer to it, so entries
+    /// for old positions don't outweigh the ones for the current search forever.
+    const MAX_SCORE: i32 = 1024;
+    /// Continuation scores are divided by this before being added to the butterfly history
+    /// score.
+    const SCORE_DIVISOR: i32 = 8;
+    const NUM_PIECE_SQUARES: usize = 2 * 6 * 64;
+
+    pub(crate) fn new() -> Self {
+        let table_size = Self::NUM_PIECE_SQUARES * Self::NUM_PIECE_SQUARES;
+        Self {
+            one_ply: vec![0; table_size].into_boxed_slice(),
+            two_ply: vec![0; table_size].into_boxed_slice(),
+            played: [None; u8::MAX as usize + 1],
+        }
+    }
+
+    /// `position` is the position before `mve` is played.
+    fn piece_square_index(position: &Position, mve: Move) -> usize {
+        let side = position.state.to_move;
+        let piece = position
+            .is_piece_at(mve.src, side)
+            .expect("No piece at move source square");
+        let side_offset = if side == Side::White { 0 } else { 6 };
+        (side_offset + piece.index()) * 64 + mve.dest as usize
+    }
+
+    fn previous(&self, ply: u8, plies_back: u8) -> Option<usize> {
+        self.played[usize::from(ply.checked_sub(plies_back)?)]
+    }
+
+    /// Remember the move about to be played at `ply` from `position`, which is `None` for a null
+    /// move.
+    pub(crate) fn record_played(&mut self, position: &Position, maybe_mve: Option<Move>, ply: u8) {
+        self.played[usize::from(ply)] =
+            maybe_mve.map(|mve| Self::piece_square_index(position, mve));
+    }
+
+    pub(crate) fn record_cutoff(&mut self, position: &Position, mve: Move, ply: u8, depth: u8) {
+        let idx = Self::piece_square_index(position, mve);
+        let bonus = (i32::from(depth) * i32::from(depth)).min(Self::MAX_SCORE);
+        let previous = [self.previous(ply, 1), self.previous(ply, 2)];
+        for (table, maybe_previous_idx) in [&mut self.one_ply, &mut self.two_ply]
+            .into_iter()
+            .zip(previous)
+        {
+            let Some(previous_idx) = maybe_previous_idx else {
+                continue;
+            };
+            let entry = &mut table[previous_idx * Self::NUM_PIECE_SQUARES + idx];
+            *entry += bonus - *entry * bonus / Self::MAX_SCORE;
+        }
+    }
+
+    pub(crate) fn score(&self, position: &Position, mve: Move, ply: u8) -> i32 {
+        let idx = Self::piece_square_index(position, mve);
+        let score = [(&self.one_ply, 1), (&self.two_ply, 2)]
+            .into_iter()
+            .filter_map(|(table, plies_back)| {
+                let previous_idx = self.previous(ply, plies_back)?;
+                Some(table[previous_idx * Self::NUM_PIECE_SQUARES + idx])
+            })
+            .sum::<i32>();
+        score / Self::SCORE_DIVISOR
+    }
+}
+
+/// Quiet moves which recently caused a beta cutoff at each ply. They're likely to cause a
+/// cutoff in sibling nodes as well.
+/// Source: https://www.chessprogramming.org/Killer_Heuristic
+pub(crate) struct KillerMoves {
+    killers: [[Option<Move>; KILLER_MOVES_PER_PLY]; u8::MAX as usize + 1],
+}
+
+impl KillerMoves {
+    pub(crate) fn new() -> Self {
+        Self {
+            killers: [[None; KILLER_MOVES_PER_PLY]; u8::MAX as usize + 1],
+        }
+    }
+
+    pub(crate) fn get(&self, ply: u8) -> &[Option<Move>; KILLER_MOVES_PER_PLY] {
+        &self.killers[usize::from(ply)]
+    }
+
+    pub(crate) fn record_cutoff(&mut self, mve: Move, ply: u8) {
+        let killers = &mut self.killers[usize::from(ply)];
+        if killers[0] != Some(mve) {
+            killers.rotate_right(1);
+            killers[0] = Some(mve);
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::Square::*;
+    use crate::move_gen::{GenerateMoves, MOVE_GEN};
     use test_case::test_case;
+    use testresult::TestResult;
 
     #[test_case(Position::from_fen("7k/8/8/8/5q1b/3q1pP1/2r3b1/K3N3 w - - 0 1").unwrap(), 
         Some(Move::new(A1, B1)),
@@ -95,7 +258,7 @@ mod tests {
         mut moves_input: ArrayVec<Move, 218>,
         moves_want: ArrayVec<Move, 218>,
     ) {
-        order_moves(&mut moves_input, &position, maybe_tt_best_move, Some(&ButterflyHistoryState::new()));
+        order_moves(&mut moves_input, &position, maybe_tt_best_move, None, Some(&ButterflyHistoryState::new()), None);
 
         assert_eq!(moves_input, moves_want);
     }
@@ -115,8 +278,152 @@ mod tests {
         mut moves_input: ArrayVec<Move, 218>,
         moves_want: ArrayVec<Move, 218>,
     ) {
-        moves_input.sort_by_key(|&mve| -(get_mvv_lva_value(mve, &position) as i64));
+        moves_input.sort_by_key(|&mve| -get_mvv_lva_value(mve, &position));
 
         assert_eq!(moves_input, moves_want);
     }
+
+    // Each case lists the buckets the legal moves should be ordered into. Moves within a bucket
+    // may come in any order, and moves not in any bucket must come last.
+    #[test_case("7k/8/8/8/5q1b/3q1pP1/2r3b1/K3N3 w - - 0 1",
+        None,
+        [Some(Move::new(G3, G4)), None],
+        vec![
+            vec![Move::new(G3, F4), Move::new(E1, D3), Move::new(E1, C2), Move::new(G3, H4), Move::new(E1, G2)],
+            vec![Move::new(G3, G4)],
+            vec![Move::new(E1, F3)],
+        ] ; "non losing captures then killers then losing captures"
+    )]
+    #[test_case("4k3/8/2p5/3r4/8/5p2/8/3Q2NK w - - 0 1",
+        Some(Move::new(H1, H2)),
+        [None, None],
+        vec![
+            vec![Move::new(H1, H2)],
+            vec![Move::new(G1, F3), Move::new(D1, F3)],
+            vec![Move::new(D1, D5)],
+        ] ; "tt move then winning captures before losing capture of more valuable piece"
+    )]
+    #[test_case("4k3/8/2p5/3n4/4B3/8/8/4K3 w - - 0 1",
+        None,
+        [Some(Move::new(E1, E2)), None],
+        vec![vec![Move::new(E4, D5)], vec![Move::new(E1, E2)]] ; "equal trade isnt losing"
+    )]
+    #[test_case("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        None,
+        [Some(Move::new(G1, F3)), Some(Move::new(B1, C3))],
+        vec![vec![Move::new(G1, F3)], vec![Move::new(B1, C3)]] ; "killers in order"
+    )]
+    #[test_case("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        Some(Move::new(E2, E4)),
+        [Some(Move::new(E2, E4)), Some(Move::new(D2, D4))],
+        vec![vec![Move::new(E2, E4)], vec![Move::new(D2, D4)]] ; "tt move also killer"
+    )]
+    #[test_case("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        None,
+        [Some(Move::new(E4, E5)), Some(Move::new(D2, D4))],
+        vec![vec![Move::new(D2, D4)]] ; "illegal killer ignored"
+    )]
+    fn test_order_moves_buckets(
+        fen: &str,
+        maybe_tt_best_move: Option<Move>,
+        killer_moves: [Option<Move>; KILLER_MOVES_PER_PLY],
+        buckets_want: Vec<Vec<Move>>,
+    ) -> TestResult {
+        let position = Position::from_fen(fen)?;
+        let mut moves = MOVE_GEN.gen_moves(&position);
+        order_moves(
+            &mut moves,
+            &position,
+            maybe_tt_best_move,
+            Some(&killer_moves),
+            Some(&ButterflyHistoryState::new()),
+            None,
+        );
+
+        let mut remaining = moves.as_slice();
+        for bucket_want in buckets_want {
+            let (bucket, rest) = remaining.split_at(bucket_want.len());
+            let mut bucket = bucket.to_vec();
+            let mut bucket_want = bucket_want;
+            bucket.sort();
+            bucket_want.sort();
+            assert_eq!(bucket, bucket_want);
+            remaining = rest;
+        }
+        for &mve in remaining {
+            assert!(!position.is_capture(mve), "{mve:?} should be ordered before quiet moves");
+            assert_ne!(Some(mve), maybe_tt_best_move);
+            assert!(!killer_moves.contains(&Some(mve)));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_continuation_history() {
+        let mut continuation_history = ContinuationHistory::new();
+        let mut position = Position::start();
+        continuation_history.record_played(&position, Some(Move::new(E2, E4)), 0);
+        position.make_move(Move::new(E2, E4));
+        continuation_history.record_played(&position, Some(Move::new(E7, E5)), 1);
+        position.make_move(Move::new(E7, E5));
+
+        continuation_history.record_cutoff(&position, Move::new(G1, F3), 2, 4);
+        let score = continuation_history.score(&position, Move::new(G1, F3), 2);
+        assert!(score > 0);
+        assert_eq!(
+            continuation_history.score(&position, Move::new(B1, C3), 2),
+            0
+        );
+
+        // After a null move only the move two plies back matches, so only that table counts.
+        continuation_history.record_played(&position, None, 1);
+        let two_ply_score = continuation_history.score(&position, Move::new(G1, F3), 2);
+        assert!(0 < two_ply_score && two_ply_score < score);
+
+        // Scores are bounded however many cutoffs there are.
+        for _ in 0..1000 {
+            continuation_history.record_cutoff(&position, Move::new(G1, F3), 2, 60);
+        }
+        assert!(
+            continuation_history.score(&position, Move::new(G1, F3), 2)
+                <= ContinuationHistory::MAX_SCORE / ContinuationHistory::SCORE_DIVISOR
+        );
+    }
+
+    #[test]
+    fn test_order_moves_continuation_history() {
+        let mut continuation_history = ContinuationHistory::new();
+        let mut position = Position::start();
+        continuation_history.record_played(&position, Some(Move::new(E2, E4)), 0);
+        position.make_move(Move::new(E2, E4));
+        continuation_history.record_cutoff(&position, Move::new(C7, C5), 1, 5);
+
+        let mut moves = MOVE_GEN.gen_moves(&position);
+        order_moves(
+            &mut moves,
+            &position,
+            None,
+            None,
+            Some(&ButterflyHistoryState::new()),
+            Some((&continuation_history, 1)),
+        );
+
+        assert_eq!(moves[0], Move::new(C7, C5));
+    }
+
+    #[test]
+    fn test_killer_moves_record_cutoff() {
+        let mut killer_moves = KillerMoves::new();
+        killer_moves.record_cutoff(Move::new(E2, E4), 3);
+        killer_moves.record_cutoff(Move::new(D2, D4), 3);
+        assert_eq!(killer_moves.get(3), &[Some(Move::new(D2, D4)), Some(Move::new(E2, E4))]);
+
+        // Recording the newest killer again doesn't push out the older one.
+        killer_moves.record_cutoff(Move::new(D2, D4), 3);
+        assert_eq!(killer_moves.get(3), &[Some(Move::new(D2, D4)), Some(Move::new(E2, E4))]);
+
+        killer_moves.record_cutoff(Move::new(G1, F3), 3);
+        assert_eq!(killer_moves.get(3), &[Some(Move::new(G1, F3)), Some(Move::new(D2, D4))]);
+        assert_eq!(killer_moves.get(4), &[None, None]);
+    }
 }
@@ -0,0 +1,33 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Tells a running ponder search that the opponent played the move it was pondering on.
+///
+/// Until it's triggered, a search with `ponder` set ignores its time limits. Afterwards the
+/// limits apply as if the search had started when it actually did, so the time spent
+/// pondering counts towards the time for this move instead of restarting the search.
+#[derive(Clone, Debug, Default)]
+pub struct PonderHit(Arc<AtomicBool>);
+
+impl PonderHit {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn trigger(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_triggered(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Signals are compared by identity, like [`super::MoveFilter`].
+impl PartialEq for PonderHit {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for PonderHit {}
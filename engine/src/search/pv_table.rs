@@ -0,0 +1,57 @@
+use crate::position::Move;
+
+/// Triangular PV table: the best line found so far from each ply of the current path. When a
+/// move raises alpha, the line at its ply becomes that move followed by the line the child
+/// node found, so the root ends up with the full principal variation.
+/// Source: https://www.chessprogramming.org/Triangular_PV-Table
+pub(crate) struct PvTable {
+    lines: Vec<Vec<Move>>,
+}
+
+impl PvTable {
+    pub(crate) fn new() -> Self {
+        Self {
+            lines: vec![vec![]; u8::MAX as usize + 2],
+        }
+    }
+
+    /// Called when a node at `ply` is entered. Nodes which return without searching any moves,
+    /// like leaves and TT cutoffs, leave their line empty.
+    pub(crate) fn clear(&mut self, ply: u8) {
+        self.lines[usize::from(ply)].clear();
+    }
+
+    /// Records that `mve` is the new best move at `ply`, using the line its child just found.
+    pub(crate) fn update(&mut self, ply: u8, mve: Move) {
+        let (lines, child_lines) = self.lines.split_at_mut(usize::from(ply) + 1);
+        let line = &mut lines[usize::from(ply)];
+        line.clear();
+        line.push(mve);
+        line.extend_from_slice(&child_lines[0]);
+    }
+
+    pub(crate) fn line(&self, ply: u8) -> &[Move] {
+        &self.lines[usize::from(ply)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Square::*;
+
+    #[test]
+    fn test_update_prepends_child_line() {
+        let mut pv_table = PvTable::new();
+        pv_table.clear(2);
+        pv_table.update(2, Move::new(G1, F3));
+        pv_table.clear(1);
+        pv_table.update(1, Move::new(E7, E5));
+        assert_eq!(pv_table.line(1), &[Move::new(E7, E5), Move::new(G1, F3)]);
+
+        // A better move whose child returned without a line replaces the whole line.
+        pv_table.clear(2);
+        pv_table.update(1, Move::new(D7, D5));
+        assert_eq!(pv_table.line(1), &[Move::new(D7, D5)]);
+    }
+}
@@ -0,0 +1,73 @@
+use crate::evaluation::RelativeEval;
+use crate::position::Side;
+
+/// Static eval of each node on the current path, by ply. Nodes in check don't have a useful
+/// static eval, so they store none.
+pub(crate) struct StaticEvals {
+    evals: [Option<(Side, RelativeEval)>; u8::MAX as usize + 1],
+}
+
+impl StaticEvals {
+    pub(crate) fn new() -> Self {
+        Self {
+            evals: [None; u8::MAX as usize + 1],
+        }
+    }
+
+    pub(crate) fn set(&mut self, ply: u8, to_move: Side, eval: Option<RelativeEval>) {
+        self.evals[usize::from(ply)] = eval.map(|eval| (to_move, eval));
+    }
+
+    /// Whether the side to move's static eval at `ply` is better than at its previous turn two
+    /// plies earlier, or four if it was in check then. Positions getting better for the side to
+    /// move are more likely to fail high, so they can be pruned more and need less effort to
+    /// refute. When there's nothing to compare against, the position is assumed to be
+    /// improving, which prunes less.
+    ///
+    /// Entries are only compared when the same side was to move, since a null move makes the
+    /// ply stop matching the side to move, and the entries at plies below a null move can be
+    /// left over from a sibling subtree.
+    /// Source: https://www.chessprogramming.org/Improving
+    pub(crate) fn is_improving(&self, ply: u8) -> bool {
+        let Some((to_move, eval)) = self.evals[usize::from(ply)] else {
+            return false;
+        };
+        let earlier_eval = |plies_back: u8| {
+            let (side, eval) = self.evals[usize::from(ply.checked_sub(plies_back)?)]?;
+            (side == to_move).then_some(eval)
+        };
+        match earlier_eval(2).or_else(|| earlier_eval(4)) {
+            Some(earlier_eval) => eval > earlier_eval,
+            None => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case(&[(2, Some(10)), (4, Some(20))], 4, true ; "better than two plies ago")]
+    #[test_case(&[(2, Some(20)), (4, Some(10))], 4, false ; "worse than two plies ago")]
+    #[test_case(&[(2, Some(20)), (4, Some(20))], 4, false ; "same as two plies ago")]
+    #[test_case(&[(0, Some(30)), (2, None), (4, Some(20))], 4, false ; "in check two plies ago")]
+    #[test_case(&[(4, Some(20))], 4, true ; "nothing to compare against")]
+    #[test_case(&[(2, Some(10)), (4, None)], 4, false ; "in check")]
+    #[test_case(&[(1, Some(20))], 1, true ; "near the root")]
+    fn test_is_improving(evals: &[(u8, Option<i32>)], ply: u8, improving_want: bool) {
+        let mut static_evals = StaticEvals::new();
+        for &(ply, eval) in evals {
+            static_evals.set(ply, Side::White, eval.map(RelativeEval::cp));
+        }
+        assert_eq!(static_evals.is_improving(ply), improving_want);
+    }
+
+    #[test]
+    fn test_is_improving_ignores_other_side() {
+        let mut static_evals = StaticEvals::new();
+        static_evals.set(2, Side::Black, Some(RelativeEval::cp(50)));
+        static_evals.set(4, Side::White, Some(RelativeEval::cp(20)));
+        assert!(static_evals.is_improving(4));
+    }
+}
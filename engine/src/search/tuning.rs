@@ -0,0 +1,59 @@
+use std::time::Duration;
+
+/// Search limits and pruning margins which are fixed during a search, but can be changed
+/// between searches so they can be tuned without recompiling.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SearchTuning {
+    /// Quiescence search stands pat once the ply reaches the main search depth times this.
+    pub qsearch_depth_factor: u8,
+    /// Captures in quiescence search that lose more than this many centipawns according to
+    /// static exchange evaluation are skipped.
+    pub qsearch_see_margin: i32,
+    /// Futility pruning margins in centipawns, for nodes 1 to 3 plies above the horizon. Quiet
+    /// moves are skipped when the static eval plus the margin can't raise alpha. Improving
+    /// positions use the margin for one ply further from the horizon.
+    pub futility_margins: [i32; 3],
+    /// Reverse futility pruning margin in centipawns per ply to the horizon. A node returns its
+    /// static eval without searching when the eval minus the margin still beats beta. Improving
+    /// positions count one ply fewer, down to one.
+    pub reverse_futility_margin: i32,
+    /// Lazy eval margin in centipawns. The expensive eval terms are skipped when material and
+    /// piece square tables alone are further than this outside the alpha-beta window.
+    pub lazy_eval_margin: i32,
+    /// Drop root moves which have scored worse than the best move by more than
+    /// `root_prune_margin` for several iterations in a row, so later iterations only search the
+    /// candidate moves. Never applies with MultiPV or in analysis mode.
+    pub root_move_pruning: bool,
+    /// Root move pruning margin in centipawns.
+    pub root_prune_margin: i32,
+    /// Time kept in reserve on every move for communication delays between the engine and the
+    /// GUI. Subtracted from the time left on the clock before the time to use is calculated.
+    pub move_overhead: Duration,
+    /// Percentage of the move's share of the clock the search aims to use. Searches stop
+    /// starting new iterations once it's used.
+    pub soft_time_percent: u32,
+    /// Percentage of the move's share of the clock after which the search is stopped, even in
+    /// the middle of an iteration.
+    pub hard_time_percent: u32,
+}
+
+impl SearchTuning {
+    pub const DEFAULT: SearchTuning = SearchTuning {
+        qsearch_depth_factor: 3,
+        qsearch_see_margin: 0,
+        futility_margins: [200, 350, 500],
+        reverse_futility_margin: 120,
+        lazy_eval_margin: 300,
+        root_move_pruning: true,
+        root_prune_margin: 500,
+        move_overhead: Duration::from_millis(10),
+        soft_time_percent: 100,
+        hard_time_percent: 200,
+    };
+}
+
+impl Default for SearchTuning {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
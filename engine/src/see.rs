@@ -0,0 +1,142 @@
+use crate::bitboard::{BitBoard, Square};
+use crate::move_gen::SLIDING_PIECES_MOVE_GEN;
+use crate::move_gen::all_pieces::get_attackers_to;
+use crate::move_gen::leaping_pieces::LEAPING_PIECES;
+use crate::position::{Move, Piece, Position, PositionError, Side};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StaticExchange {
+    /// Net material won in centipawns by the side making the move, assuming each side stops
+    /// capturing as soon as continuing would lose material.
+    pub value: i32,
+    /// Captures played on the destination square, starting with the move itself.
+    pub sequence: Vec<Move>,
+}
+
+/// Static exchange evaluation: plays out all captures on the move's destination square, always
+/// capturing with the least valuable piece, without searching any other moves.
+///
+/// Pins aren't taken into account and promotions are valued as the pawn.
+/// Source: https://www.chessprogramming.org/SEE_-_The_Swap_Algorithm
+pub fn static_exchange_eval(
+    position: &Position,
+    mve: Move,
+) -> Result<StaticExchange, PositionError> {
+    let side = position.state.to_move;
+    let moved_piece = position
+        .is_piece_at(mve.src, side)
+        .ok_or_else(|| PositionError::MoveNoPiece(mve.src.to_string()))?;
+    let target = mve.dest;
+
+    let mut occupancy = position.occupancy_bb();
+    let captured_piece =
+        if moved_piece == Piece::Pawn && position.state.en_passant_target == Some(target) {
+            let captured_square = match side {
+                Side::White => Square::from_u8(target as u8 - 8),
+                Side::Black => Square::from_u8(target as u8 + 8),
+            };
+            occupancy.clear_square(captured_square);
+            Some(Piece::Pawn)
+        } else {
+            position.is_piece_at(target, side.opposite_side())
+        };
+    occupancy.clear_square(mve.src);
+
+    let mut gains = vec![captured_piece.map_or(0, see_value)];
+    let mut sequence = vec![mve];
+    let mut piece_on_target = moved_piece;
+    let mut capturing_side = side.opposite_side();
+
+    loop {
+        let attackers = get_attackers_to(
+            position,
+            target,
+            occupancy,
+            LEAPING_PIECES,
+            SLIDING_PIECES_MOVE_GEN,
+        );
+        let Some((attacker_piece, attacker_square)) =
+            least_valuable_attacker(position, attackers, capturing_side)
+        else {
+            break;
+        };
+        // The king can only recapture if the square isn't defended anymore.
+        if attacker_piece == Piece::King
+            && !(attackers & position.get_side_bb(capturing_side.opposite_side())).is_empty()
+        {
+            break;
+        }
+
+        gains.push(see_value(piece_on_target) - gains.last().unwrap());
+        sequence.push(Move::new(attacker_square, target));
+        occupancy.clear_square(attacker_square);
+        piece_on_target = attacker_piece;
+        capturing_side = capturing_side.opposite_side();
+    }
+
+    // Work backwards, letting each side choose to stop capturing instead.
+    let mut num_captures = sequence.len();
+    for idx in (1..gains.len()).rev() {
+        if -gains[idx - 1] > gains[idx] {
+            num_captures = idx;
+        }
+        gains[idx - 1] = -(-gains[idx - 1]).max(gains[idx]);
+    }
+    sequence.truncate(num_captures);
+
+    Ok(StaticExchange {
+        value: gains[0],
+        sequence,
+    })
+}
+
+fn least_valuable_attacker(
+    position: &Position,
+    attackers: BitBoard,
+    side: Side,
+) -> Option<(Piece, Square)> {
+    Piece::ALL.into_iter().find_map(|piece| {
+        let pieces = attackers & position.get_piece_bb(side, piece);
+        (!pieces.is_empty()).then(|| (piece, pieces.get_lsb()))
+    })
+}
+
+const fn see_value(piece: Piece) -> i32 {
+    match piece {
+        Piece::Pawn => 100,
+        Piece::Knight => 300,
+        Piece::Bishop => 300,
+        Piece::Rook => 500,
+        Piece::Queen => 900,
+        Piece::King => 20_000,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitboard::Square::*;
+    use test_case::test_case;
+    use testresult::TestResult;
+
+    #[test_case("1k1r4/1pp4p/p7/4p3/8/P5P1/1PP4P/2K1R3 w - - 0 1", Move::new(E1, E5), 100, vec![Move::new(E1, E5)] ; "undefended pawn")]
+    #[test_case("1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1", Move::new(D3, E5), -200, vec![Move::new(D3, E5), Move::new(D7, E5)] ; "knight takes defended pawn")]
+    #[test_case("4k3/8/8/3p4/4P3/8/8/4K3 w - - 0 1", Move::new(E4, D5), 100, vec![Move::new(E4, D5)] ; "pawn takes pawn")]
+    #[test_case("4k3/8/2p5/3p4/4P3/8/8/4K3 w - - 0 1", Move::new(E4, D5), 0, vec![Move::new(E4, D5), Move::new(C6, D5)] ; "pawn trade")]
+    #[test_case("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", Move::new(E5, D6), 100, vec![Move::new(E5, D6)] ; "en passant")]
+    #[test_case("8/8/8/3k4/3p4/8/3R4/3RK3 w - - 0 1", Move::new(D2, D4), 100, vec![Move::new(D2, D4)] ; "king cant recapture defended piece")]
+    #[test_case("4k3/3r4/8/3p4/8/8/3R4/3RK3 w - - 0 1", Move::new(D2, D5), 100, vec![Move::new(D2, D5), Move::new(D7, D5), Move::new(D1, D5)] ; "x ray")]
+    #[test_case("4k3/8/4p3/3p4/8/8/8/3QK3 w - - 0 1", Move::new(D1, D5), -800, vec![Move::new(D1, D5), Move::new(E6, D5)] ; "queen takes defended pawn")]
+    fn test_static_exchange_eval(
+        fen: &str,
+        mve: Move,
+        value_want: i32,
+        sequence_want: Vec<Move>,
+    ) -> TestResult {
+        let position = Position::from_fen(fen)?;
+        let got = static_exchange_eval(&position, mve)?;
+        assert_eq!(got.value, value_want);
+        assert_eq!(got.sequence, sequence_want);
+        Ok(())
+    }
+}
@@ -0,0 +1,79 @@
+use rand::Rng;
+use rand::seq::IndexedRandom;
+
+use crate::move_gen::{GenerateMoves, MOVE_GEN};
+use crate::position::{Move, Position};
+
+mod game_tree;
+
+pub use game_tree::ScriptedGameTree;
+
+/// Upper bound on the number of plies played to reach a [`Position::random_legal`] position.
+const RANDOM_LEGAL_MAX_PLIES: usize = 200;
+
+impl Position {
+    /// Generates a random position that's reachable from the starting position.
+    ///
+    /// Positions are reached by playing uniformly random legal moves from the start, so unlike
+    /// random FENs they always have consistent castling rights, en passant squares and clocks.
+    pub fn random_legal(rng: &mut impl Rng) -> Self {
+        let mut position = Position::start();
+        let num_plies = rng.random_range(0..=RANDOM_LEGAL_MAX_PLIES);
+        random_playout(&mut position, num_plies, MOVE_GEN, rng);
+        position
+    }
+}
+
+/// Plays uniformly random legal moves until `max_plies` have been played or the game is over,
+/// returning the moves that were played.
+pub fn random_playout(
+    position: &mut Position,
+    max_plies: usize,
+    move_gen: impl GenerateMoves,
+    rng: &mut impl Rng,
+) -> Vec<Move> {
+    let mut moves_played = Vec::with_capacity(max_plies);
+    while moves_played.len() < max_plies && !position.is_draw() {
+        let Some(&mve) = move_gen.gen_moves(position).choose(rng) else {
+            break;
+        };
+        position.make_move(mve);
+        moves_played.push(mve);
+    }
+    moves_played
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand::rngs::SmallRng;
+
+    #[test]
+    fn test_random_legal_fen_round_trip() {
+        let mut rng = SmallRng::seed_from_u64(0);
+        for _ in 0..50 {
+            let position = Position::random_legal(&mut rng);
+            let fen = position.to_fen();
+            assert_eq!(Position::from_fen(&fen).unwrap().to_fen(), fen);
+        }
+    }
+
+    #[test]
+    fn test_random_playout_unmake_restores_position() {
+        let mut rng = SmallRng::seed_from_u64(1);
+        for _ in 0..50 {
+            let start = Position::random_legal(&mut rng);
+            let mut position = start.clone();
+            let mut unmake_move_states = vec![];
+            for mve in random_playout(&mut start.clone(), 20, MOVE_GEN, &mut rng) {
+                unmake_move_states.push(position.make_move(mve));
+            }
+            while let Some(unmake_move_state) = unmake_move_states.pop() {
+                position.unmake_move(unmake_move_state);
+            }
+            assert_eq!(position, start);
+            assert_eq!(position.zobrist_hash, start.zobrist_hash);
+        }
+    }
+}
@@ -0,0 +1,180 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use arrayvec::ArrayVec;
+
+use crate::bitboard::BitBoard;
+use crate::evaluation::{EvalBounds, EvaluatePosition, RelativeEval};
+use crate::move_gen::{GenerateMoves, MOVE_GEN};
+use crate::position::{Move, Position, ZobristHash};
+
+/// A small game tree scripted line by line, which stands in for both the move generator and the
+/// evaluator so search behaviour can be checked on trees small enough to verify by hand.
+///
+/// Positions are real positions reached from the root, and are identified by their hash, so
+/// lines that transpose share a node. A position's moves are the ones scripted after it, in the
+/// order they were added. Leaves, the positions with no scripted moves, return their real quiet
+/// moves instead, so the search doesn't mistake them for stalemate but quiescence search doesn't
+/// go past them. Checkers are always the real ones.
+///
+/// The tree should be searched to its depth. Positions outside it, reached by searching deeper
+/// or by null move pruning, panic.
+#[derive(Debug)]
+pub struct ScriptedGameTree {
+    root: Position,
+    nodes: HashMap<ZobristHash, ScriptedNode>,
+}
+
+#[derive(Debug, Default)]
+struct ScriptedNode {
+    moves: Vec<Move>,
+    eval: Option<RelativeEval>,
+    num_evaluations: AtomicU64,
+}
+
+impl ScriptedGameTree {
+    pub fn new(root: Position) -> Self {
+        let nodes = HashMap::from([(root.zobrist_hash, ScriptedNode::default())]);
+        Self { root, nodes }
+    }
+
+    pub fn root(&self) -> &Position {
+        &self.root
+    }
+
+    /// Adds every move of `line`, played from the root, as a child of the position it's
+    /// played in.
+    ///
+    /// Panics if a move isn't legal.
+    pub fn line(mut self, line: &[Move]) -> Self {
+        let mut position = self.root.clone();
+        for &mve in line {
+            assert!(
+                MOVE_GEN.gen_moves(&position).contains(&mve),
+                "{} isn't legal in {}",
+                mve,
+                position.to_fen()
+            );
+            let moves = &mut self.nodes.get_mut(&position.zobrist_hash).unwrap().moves;
+            if !moves.contains(&mve) {
+                moves.push(mve);
+            }
+            position.make_move(mve);
+            self.nodes.entry(position.zobrist_hash).or_default();
+        }
+        self
+    }
+
+    /// Adds `line` and sets the static eval of the position it ends in, relative to the side to
+    /// move there. Positions without one evaluate to a draw.
+    pub fn eval(mut self, line: &[Move], eval: RelativeEval) -> Self {
+        self = self.line(line);
+        let hash = self.position_after(line).zobrist_hash;
+        self.nodes.get_mut(&hash).unwrap().eval = Some(eval);
+        self
+    }
+
+    /// How many times the position reached by `line` was evaluated.
+    pub fn num_evaluations(&self, line: &[Move]) -> u64 {
+        self.node(&self.position_after(line))
+            .num_evaluations
+            .load(Ordering::Relaxed)
+    }
+
+    fn position_after(&self, line: &[Move]) -> Position {
+        let mut position = self.root.clone();
+        for &mve in line {
+            position.make_move(mve);
+        }
+        position
+    }
+
+    fn node(&self, position: &Position) -> &ScriptedNode {
+        self.nodes.get(&position.zobrist_hash).unwrap_or_else(|| {
+            panic!(
+                "Position isn't in the scripted game tree: {}",
+                position.to_fen()
+            )
+        })
+    }
+}
+
+impl GenerateMoves for &ScriptedGameTree {
+    fn gen_moves(&self, position: &Position) -> ArrayVec<Move, 218> {
+        let node = self.node(position);
+        if !node.moves.is_empty() {
+            return node.moves.iter().copied().collect();
+        }
+        MOVE_GEN
+            .gen_moves(position)
+            .into_iter()
+            .filter(|&mve| !position.is_capture(mve) && mve.promotion.is_none())
+            .collect()
+    }
+
+    fn gen_checkers(&self, position: &Position) -> BitBoard {
+        MOVE_GEN.gen_checkers(position)
+    }
+}
+
+impl EvaluatePosition for &ScriptedGameTree {
+    fn evaluate(
+        &self,
+        position: &Position,
+        _move_gen: impl GenerateMoves,
+        _bounds: Option<EvalBounds>,
+    ) -> RelativeEval {
+        let node = self.node(position);
+        node.num_evaluations.fetch_add(1, Ordering::Relaxed);
+        node.eval.unwrap_or(RelativeEval::DRAW)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Square::*;
+
+    #[test]
+    fn test_scripted_game_tree() {
+        let e4 = Move::new(E2, E4);
+        let d4 = Move::new(D2, D4);
+        let e5 = Move::new(E7, E5);
+        let tree = ScriptedGameTree::new(Position::start())
+            .eval(&[e4, e5], RelativeEval::cp(30))
+            .line(&[d4]);
+
+        assert_eq!(
+            (&tree).gen_moves(tree.root()).as_slice(),
+            &[e4, d4],
+            "moves are in the order they're added"
+        );
+
+        let mut position = Position::start();
+        position.make_move(e4);
+        assert_eq!((&tree).gen_moves(&position).as_slice(), &[e5]);
+        assert_eq!((&tree).evaluate(&position, &tree, None), RelativeEval::DRAW);
+
+        position.make_move(e5);
+        assert_eq!(
+            (&tree).evaluate(&position, &tree, None),
+            RelativeEval::cp(30)
+        );
+        assert_eq!(tree.num_evaluations(&[e4, e5]), 1);
+        assert!(
+            (&tree)
+                .gen_moves(&position)
+                .iter()
+                .all(|&mve| !position.is_capture(mve))
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "isn't in the scripted game tree")]
+    fn test_scripted_game_tree_unknown_position() {
+        let tree = ScriptedGameTree::new(Position::start());
+        let mut position = Position::start();
+        position.make_move(Move::new(E2, E4));
+        (&tree).evaluate(&position, &tree, None);
+    }
+}
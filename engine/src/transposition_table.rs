@@ -1,42 +1,44 @@
 use std::sync::atomic::{AtomicU64, Ordering};
 
 use crate::bitboard::Square;
-use crate::evaluation::Eval;
+use crate::evaluation::RelativeEval;
 use crate::incr;
-use crate::position::{Move, Position, ZobristHash};
-
-use strum_macros::FromRepr;
+use crate::position::{Move, Piece, Position, ZobristHash};
 
 #[repr(u8)]
-#[derive(Debug, Clone, Copy, FromRepr, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum EvalType {
     Exact,
     UpperBound,
     LowerBound,
 }
 
+impl EvalType {
+    fn from_repr(eval_type: u8) -> Option<Self> {
+        match eval_type {
+            0 => Some(Self::Exact),
+            1 => Some(Self::UpperBound),
+            2 => Some(Self::LowerBound),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct TranspositionTableEntry {
     pub hash: ZobristHash,
-    pub eval: Eval,
+    pub eval: RelativeEval,
     pub best_move: Move,
     pub depth_and_eval_type: u8,
+    /// Set if a draw by repetition or the fifty-move rule was found below this position, so
+    /// the eval might not hold when the position is reached along a different path.
+    pub path_dependent: bool,
+    /// Half move clock of the position when it was searched. The search only saw fifty-move
+    /// draws that were within reach of this clock.
+    pub half_move_clock: u8,
 }
 
 impl TranspositionTableEntry {
-    fn empty() -> Self {
-        Self {
-            hash: ZobristHash::empty(),
-            best_move: Move::new(Square::A1, Square::A1),
-            eval: Eval::DRAW,
-            depth_and_eval_type: 0,
-        }
-    }
-
-    fn is_empty(&self) -> bool {
-        self.best_move.src == Square::A1 && self.best_move.dest == Square::A1
-    }
-
     const DEPTH_MASK: u8 = 0b00111111;
     const EVAL_TYPE_MASK: u8 = 0b11000000;
     fn build_depth_and_eval_type(depth: u8, eval_type: EvalType) -> u8 {
@@ -56,11 +58,101 @@ impl TranspositionTableEntry {
         let eval_type_u8 = self.depth_and_eval_type >> 6;
         EvalType::from_repr(eval_type_u8).expect("Unexpected eval type value")
     }
+
+    // Everything but the hash is packed into 64 bits:
+    // eval (32) | src (6) | dest (6) | promotion (3) | depth and eval type (8) | path dependent (1)
+    // | half move clock (8)
+    const SRC_SHIFT: u32 = 32;
+    const DEST_SHIFT: u32 = 38;
+    const PROMOTION_SHIFT: u32 = 44;
+    const DEPTH_AND_EVAL_TYPE_SHIFT: u32 = 47;
+    const PATH_DEPENDENT_SHIFT: u32 = 55;
+    const HALF_MOVE_CLOCK_SHIFT: u32 = 56;
+    /// Bits of the packed source and destination squares, which are both A1 for empty slots.
+    const EMPTY_MOVE_MASK: u64 = 0b111111_111111 << Self::SRC_SHIFT;
+
+    fn pack(&self) -> u64 {
+        let promotion = self.best_move.promotion.map_or(0, |piece| piece as u64 + 1);
+        u64::from(self.eval.0 as u32)
+            | (self.best_move.src as u64) << Self::SRC_SHIFT
+            | (self.best_move.dest as u64) << Self::DEST_SHIFT
+            | promotion << Self::PROMOTION_SHIFT
+            | u64::from(self.depth_and_eval_type) << Self::DEPTH_AND_EVAL_TYPE_SHIFT
+            | u64::from(self.path_dependent) << Self::PATH_DEPENDENT_SHIFT
+            | u64::from(self.half_move_clock) << Self::HALF_MOVE_CLOCK_SHIFT
+    }
+
+    fn packed_depth(data: u64) -> u8 {
+        (data >> Self::DEPTH_AND_EVAL_TYPE_SHIFT) as u8 & Self::DEPTH_MASK
+    }
+
+    fn unpack(hash: ZobristHash, data: u64) -> Self {
+        let square = |shift: u32| {
+            Square::from_repr(((data >> shift) & 0b111111) as u8).expect("Square is 6 bits")
+        };
+        let promotion = match (data >> Self::PROMOTION_SHIFT) & 0b111 {
+            0 => None,
+            piece => Some(*Piece::ALL.get(piece as usize - 1).expect("Invalid piece")),
+        };
+        Self {
+            hash,
+            eval: RelativeEval(data as u32 as i32),
+            best_move: Move {
+                src: square(Self::SRC_SHIFT),
+                dest: square(Self::DEST_SHIFT),
+                promotion,
+            },
+            depth_and_eval_type: (data >> Self::DEPTH_AND_EVAL_TYPE_SHIFT) as u8,
+            path_dependent: (data >> Self::PATH_DEPENDENT_SHIFT) & 1 == 1,
+            half_move_clock: (data >> Self::HALF_MOVE_CLOCK_SHIFT) as u8,
+        }
+    }
 }
 
-#[derive(Debug, Clone)]
+/// Copy of what the transposition table knows about a position, for analysis tools.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TTEntryView {
+    pub eval: RelativeEval,
+    pub eval_type: EvalType,
+    pub depth: u8,
+    pub best_move: Move,
+    pub path_dependent: bool,
+}
+
+/// Slot for an entry that can be read and written by several search threads at once without
+/// locking. The hash is stored xored with the packed entry, so a slot which was torn by
+/// concurrent writes fails the hash check instead of returning a mix of two entries.
+/// An all zero slot is empty, since no real entry has a move from A1 to A1.
+#[derive(Debug, Default)]
+struct Slot {
+    key: AtomicU64,
+    data: AtomicU64,
+}
+
+impl Slot {
+    fn load(&self) -> Option<(u64, u64)> {
+        let data = self.data.load(Ordering::Relaxed);
+        if data & TranspositionTableEntry::EMPTY_MOVE_MASK == 0 {
+            return None;
+        }
+        Some((self.key.load(Ordering::Relaxed) ^ data, data))
+    }
+
+    fn store(&self, hash: ZobristHash, data: u64) {
+        self.key.store(hash.value() ^ data, Ordering::Relaxed);
+        self.data.store(data, Ordering::Relaxed);
+    }
+
+    fn clear(&self) {
+        self.key.store(0, Ordering::Relaxed);
+        self.data.store(0, Ordering::Relaxed);
+    }
+}
+
+/// Table shared by every thread of a search. Lookups and stores only need a shared reference.
+#[derive(Debug)]
 pub struct TranspositionTable {
-    entries: Box<[TranspositionTableEntry]>,
+    slots: Box<[Slot]>,
 }
 
 static TT_LOOKUPS: AtomicU64 = AtomicU64::new(0);
@@ -74,11 +166,35 @@ pub(crate) fn get_transposition_table_hitrate() -> f64 {
     TT_HITS.load(Ordering::Relaxed) as f64 / lookups as f64
 }
 
+pub(crate) fn get_transposition_table_hits() -> u64 {
+    TT_HITS.load(Ordering::Relaxed)
+}
+
 pub(crate) fn clear_transpostion_table_hitrate() {
     TT_LOOKUPS.store(0, Ordering::Release);
     TT_HITS.store(0, Ordering::Release);
 }
 
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clone for TranspositionTable {
+    fn clone(&self) -> Self {
+        let slots = self
+            .slots
+            .iter()
+            .map(|slot| Slot {
+                key: AtomicU64::new(slot.key.load(Ordering::Relaxed)),
+                data: AtomicU64::new(slot.data.load(Ordering::Relaxed)),
+            })
+            .collect();
+        Self { slots }
+    }
+}
+
 impl TranspositionTable {
     /// Creates a transposition table with size ~64mb
     pub fn new() -> Self {
@@ -88,56 +204,84 @@ impl TranspositionTable {
     pub fn with_num_entries_power_of_two(power_of_two: usize) -> Self {
         let num_entries = 1 << power_of_two;
         Self {
-            entries: vec![TranspositionTableEntry::empty(); num_entries].into_boxed_slice(),
+            slots: (0..num_entries).map(|_| Slot::default()).collect(),
         }
     }
 
     pub fn clear(&mut self) {
-        for e in self.entries.iter_mut() {
-            *e = TranspositionTableEntry::empty();
+        for slot in self.slots.iter() {
+            slot.clear();
         }
     }
 
-    pub fn get(&self, position: &Position) -> Option<&TranspositionTableEntry> {
+    pub fn get(&self, position: &Position) -> Option<TranspositionTableEntry> {
         incr!(TT_LOOKUPS);
-        let entry = &self.entries[self.index(position)];
-        if !entry.is_empty() {
-            if entry.hash == position.zobrist_hash {
-                incr!(TT_HITS);
-                return Some(entry);
-            }
+        let entry = self.lookup(position)?;
+        incr!(TT_HITS);
+        Some(entry)
+    }
+
+    /// Looks up a position without counting towards the hit rate reported during search.
+    pub fn probe(&self, position: &Position) -> Option<TTEntryView> {
+        let entry = self.lookup(position)?;
+        Some(TTEntryView {
+            eval: entry.eval,
+            eval_type: entry.eval_type(),
+            depth: entry.depth(),
+            best_move: entry.best_move,
+            path_dependent: entry.path_dependent,
+        })
+    }
+
+    fn lookup(&self, position: &Position) -> Option<TranspositionTableEntry> {
+        let (hash, data) = self.slots[self.index(position)].load()?;
+        if hash != position.zobrist_hash.value() {
+            return None;
         }
-        None
+        Some(TranspositionTableEntry::unpack(position.zobrist_hash, data))
     }
 
+    /// `eval` must be relative to `position` itself rather than to the root of the search: mate
+    /// scores count the moves to mate from this position and draws are scored as
+    /// [`RelativeEval::DRAW`] with no contempt applied. That way an entry stays valid when the position
+    /// is reached at a different ply, from a different root, or in a later game.
+    ///
+    /// The exception is draws by repetition or the fifty-move rule, which depend on the moves
+    /// that led to a position. `path_dependent` should be set if any were found while searching
+    /// the position, so the eval isn't trusted for cutoffs. The position's half move clock is
+    /// stored too, since a search from a lower clock can find wins that are fifty-move draws
+    /// from a higher one.
     pub fn store(
-        &mut self,
+        &self,
         position: &Position,
-        eval: Eval,
+        eval: RelativeEval,
         eval_type: EvalType,
         best_move: Move,
         depth: u8,
+        path_dependent: bool,
     ) {
-        let idx = self.index(position);
-        let entry = &self.entries[idx];
-        if !entry.is_empty() {
-            if entry.depth() > depth {
-                return;
-            }
+        let slot = &self.slots[self.index(position)];
+        if let Some((_, data)) = slot.load()
+            && TranspositionTableEntry::packed_depth(data) > depth
+        {
+            return;
         }
 
-        self.entries[idx] = TranspositionTableEntry {
+        let entry = TranspositionTableEntry {
             hash: position.zobrist_hash,
             eval,
             best_move,
             depth_and_eval_type: TranspositionTableEntry::build_depth_and_eval_type(
                 depth, eval_type,
             ),
-        }
+            path_dependent,
+            half_move_clock: position.state.half_move_clock,
+        };
+        slot.store(position.zobrist_hash, entry.pack());
     }
 
     fn index(&self, position: &Position) -> usize {
-        (position.zobrist_hash.value() as usize) & (self.entries.len() - 1)
+        (position.zobrist_hash.value() as usize) & (self.slots.len() - 1)
     }
 }
 
@@ -155,9 +299,11 @@ mod tests {
             TranspositionTableEntry::build_depth_and_eval_type(depth, eval_type);
         let tt_entry = TranspositionTableEntry {
             hash: ZobristHash::empty(),
-            eval: Eval::DRAW,
+            eval: RelativeEval::DRAW,
             best_move: Move::new(A1, A1),
             depth_and_eval_type,
+            path_dependent: false,
+            half_move_clock: 0,
         };
 
         let depth_got = tt_entry.depth();
@@ -166,4 +312,65 @@ mod tests {
         assert_eq!(depth_got, depth);
         assert_eq!(eval_type_got, eval_type);
     }
+
+    #[test_case(RelativeEval::mate_in(3).flip(), Move::with_promotion(B7, A8, Piece::Knight), 63, EvalType::LowerBound, true, 150 ; "negative eval with promotion")]
+    #[test_case(RelativeEval(-12), Move::new(H8, H1), 0, EvalType::Exact, false, 0 ; "no promotion")]
+    fn test_pack_round_trip(
+        eval: RelativeEval,
+        best_move: Move,
+        depth: u8,
+        eval_type: EvalType,
+        path_dependent: bool,
+        half_move_clock: u8,
+    ) {
+        let entry = TranspositionTableEntry {
+            hash: ZobristHash::empty(),
+            eval,
+            best_move,
+            depth_and_eval_type: TranspositionTableEntry::build_depth_and_eval_type(
+                depth, eval_type,
+            ),
+            path_dependent,
+            half_move_clock,
+        };
+
+        let got = TranspositionTableEntry::unpack(ZobristHash::empty(), entry.pack());
+
+        assert_eq!(got.eval, eval);
+        assert_eq!(got.best_move, best_move);
+        assert_eq!(got.depth(), depth);
+        assert_eq!(got.eval_type(), eval_type);
+        assert_eq!(got.path_dependent, path_dependent);
+        assert_eq!(got.half_move_clock, half_move_clock);
+    }
+
+    #[test]
+    fn test_probe() {
+        let tt = TranspositionTable::with_num_entries_power_of_two(4);
+        let position = Position::start();
+        assert_eq!(tt.probe(&position), None);
+
+        let best_move = Move::new(E2, E4);
+        tt.store(
+            &position,
+            RelativeEval::DRAW,
+            EvalType::LowerBound,
+            best_move,
+            7,
+            true,
+        );
+
+        let want = TTEntryView {
+            eval: RelativeEval::DRAW,
+            eval_type: EvalType::LowerBound,
+            depth: 7,
+            best_move,
+            path_dependent: true,
+        };
+        assert_eq!(tt.probe(&position), Some(want));
+
+        let mut other_position = position.clone();
+        other_position.make_move(best_move);
+        assert_eq!(tt.probe(&other_position), None);
+    }
 }
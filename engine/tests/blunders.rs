@@ -0,0 +1,52 @@
+use std::{
+    str::FromStr,
+    sync::{Arc, atomic::AtomicBool},
+};
+
+use engine::{
+    MOVE_GEN, Move, POSITION_EVALUATOR, Position, SearchParams, TranspositionTable, search,
+};
+use testresult::TestResult;
+
+const BLUNDERS: &str = include_str!("blunders.txt");
+const BLUNDER_SEARCH_DEPTH: u8 = 4;
+
+#[test]
+fn test_blunders_not_repeated() -> TestResult {
+    let mut repeated = vec![];
+    for line in BLUNDERS
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+    {
+        let mut fields = line.split(';').map(str::trim);
+        let (Some(fen), Some(blunder), source) = (fields.next(), fields.next(), fields.next())
+        else {
+            panic!("Malformed blunder case: `{}`", line);
+        };
+        let position = Position::from_fen(fen)?;
+        let blunder = Move::from_str(blunder)?;
+
+        let (best_move, _) = search(
+            &position,
+            &SearchParams {
+                max_depth: Some(BLUNDER_SEARCH_DEPTH),
+                ..SearchParams::default()
+            },
+            MOVE_GEN,
+            POSITION_EVALUATOR,
+            &mut TranspositionTable::new(),
+            Arc::new(AtomicBool::new(false)),
+        )?;
+        if best_move == Some(blunder) {
+            repeated.push(format!("{} ({}): {}", fen, source.unwrap_or(""), blunder));
+        }
+    }
+
+    assert!(
+        repeated.is_empty(),
+        "Blunders played again:\n{}",
+        repeated.join("\n")
+    );
+    Ok(())
+}
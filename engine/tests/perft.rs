@@ -1,4 +1,4 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 
 use engine::Square::*;
 use engine::{MOVE_GEN, Move, PerftDepthResult, Position, perft, perft_full};
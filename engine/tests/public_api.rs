@@ -0,0 +1,130 @@
+//! Snapshot of the stable API in `engine::prelude`. If one of these tests fails, a change broke
+//! downstream users: either keep the old API working, or update the snapshot on purpose.
+
+#[cfg(feature = "search")]
+use std::sync::Arc;
+#[cfg(feature = "search")]
+use std::sync::atomic::AtomicBool;
+#[cfg(feature = "search")]
+use std::time::Duration;
+
+use engine::prelude::*;
+
+const PRELUDE: &str = include_str!("../src/prelude.rs");
+const SNAPSHOT: &str = include_str!("public_api.txt");
+
+/// Names re-exported by the prelude's `pub use crate::{...}`s.
+fn prelude_names() -> Vec<String> {
+    let mut names: Vec<String> = PRELUDE
+        .split("pub use crate::{")
+        .skip(1)
+        .flat_map(|exports| {
+            let (exports, _) = exports
+                .split_once('}')
+                .expect("Prelude re-export should be closed");
+            exports.split(',')
+        })
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+        .collect();
+    assert!(
+        !names.is_empty(),
+        "Prelude should re-export from the crate root"
+    );
+    names.sort();
+    names
+}
+
+#[test]
+fn test_prelude_matches_snapshot() {
+    let mut want: Vec<String> = SNAPSHOT.lines().map(str::to_string).collect();
+    want.sort();
+    assert_eq!(prelude_names(), want);
+}
+
+/// Doesn't need to run: it stops compiling if a signature in the stable API changes.
+#[allow(dead_code)]
+fn stable_signatures() {
+    let _: fn(&str) -> Result<Position, FenParseError> = Position::from_fen;
+    let _: fn() -> Position = Position::start;
+    let _: fn(&Position) -> String = Position::to_fen;
+    let _: fn(&mut Position, Move) -> _ = Position::make_move;
+    let _: fn(&MoveGen, &Position) -> _ = MoveGen::gen_moves;
+    let _: fn(&MoveGen, &Position, &mut MoveList) = MoveGen::gen_moves_into;
+    let _: usize = MAX_MOVES;
+    let _: fn(Square, Square) -> Move = Move::new;
+    let _: fn(Square, Square, Piece) -> Move = Move::with_promotion;
+    let _: fn(i32) -> Eval = Eval::cp;
+    let _: fn(RelativeEval, Side) -> AbsoluteEval = RelativeEval::to_absolute;
+    let _: fn(AbsoluteEval, Side) -> RelativeEval = AbsoluteEval::to_relative;
+    let _: fn(Square, Side, Piece, BitBoard) -> BitBoard = attacks_from;
+    let _: fn(&Position, Square, Side, Piece) -> BitBoard = attacks_to_by;
+    let _: fn(&Position) -> BitBoard = Position::occupancy_bb;
+    let _: fn(&Position, &Position) -> Vec<PieceDelta> = Position::diff;
+    let _: fn(&Position, MoveGen) -> GameResult = Position::game_result;
+    let _: fn(GameResult) -> bool = GameResult::is_over;
+
+    let _: Eval = POSITION_EVALUATOR.evaluate(
+        &Position::start(),
+        MOVE_GEN,
+        Some(EvalBounds {
+            alpha: Eval::MIN,
+            beta: Eval::MAX,
+            lazy_margin: 0,
+        }),
+    );
+}
+
+/// Same as [`stable_signatures`], for the parts of the stable API behind the `search` feature.
+#[cfg(feature = "search")]
+#[allow(dead_code)]
+fn stable_search_signatures() {
+    let _: fn() -> TranspositionTable = TranspositionTable::new;
+
+    let _ = SearchParams {
+        search_moves: None::<Vec<Move>>,
+        ponder: false,
+        white_time: None::<Duration>,
+        black_time: None::<Duration>,
+        white_inc: None::<Duration>,
+        black_inc: None::<Duration>,
+        moves_to_go: None::<u16>,
+        max_depth: None::<u8>,
+        max_nodes: None::<u64>,
+        mate: None::<u8>,
+        move_time: None::<Duration>,
+        infinite: false,
+        move_filter: None::<MoveFilter>,
+        ponder_hit: None::<PonderHit>,
+        multi_pv: None::<u8>,
+        tuning: SearchTuning::default(),
+        analysis_mode: false,
+        threads: None::<u16>,
+        info_callback: Some(InfoCallback::new(|_: &SearchInfo| {})),
+        info_interval_nodes: None::<u64>,
+        max_cpu_percent: None::<u8>,
+    };
+}
+
+#[cfg(feature = "search")]
+#[test]
+fn test_stable_search() -> Result<(), SearchError> {
+    let (best_move, info): (Option<Move>, SearchResultInfo) = search(
+        &Position::start(),
+        &SearchParams {
+            max_depth: Some(2),
+            ..SearchParams::default()
+        },
+        MOVE_GEN,
+        POSITION_EVALUATOR,
+        &mut TranspositionTable::new(),
+        Arc::new(AtomicBool::new(false)),
+    )?;
+    let best_move = best_move.expect("Start position has legal moves");
+    assert!(MOVE_GEN.gen_moves(&Position::start()).contains(&best_move));
+    assert_eq!(info.pv.first(), Some(&best_move));
+    let _: Eval = info.move_evals[&best_move];
+    let _: SearchSummary = info.summary(&Position::start(), Some(best_move), MOVE_GEN);
+    Ok(())
+}
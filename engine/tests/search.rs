@@ -1,5 +1,4 @@
 use std::{
-    io::Write,
     sync::{Arc, Mutex, atomic::AtomicBool, mpsc},
     thread,
     time::{Duration, Instant},
@@ -8,7 +7,8 @@ use test_case::test_case;
 
 use engine::Square::*;
 use engine::{
-    MOVE_GEN, Move, POSITION_EVALUATOR, Position, SearchParams, TranspositionTable, search,
+    Eval, GenerateMoves, InfoCallback, MOVE_GEN, Move, MoveFilter, POSITION_EVALUATOR, Piece,
+    Position, SearchParams, TranspositionTable, search,
 };
 use testresult::TestResult;
 
@@ -75,9 +75,30 @@ fn test_finds_best_move(position: Position, max_depth: u8, best_move_want: Move)
     Ok(())
 }
 
+// Mate scores count moves from the root, whichever ply the mate was found at.
+#[test_case(Position::from_fen("k7/6R1/7R/8/8/8/8/3K4 w - - 0 1").unwrap(), 4, "mate 1" ; "mate in 1")]
+#[test_case(Position::from_fen("1k6/8/2R5/7R/8/8/8/6K1 w - - 0 1").unwrap(), 4, "mate 2" ; "mate in 2")]
+#[test_case(Position::from_fen("1k6/7R/2R5/8/8/8/8/6K1 b - - 0 1").unwrap(), 4, "mate -1" ; "mated in 1")]
+fn test_reports_mate_score(position: Position, max_depth: u8, score_want: &str) -> TestResult {
+    let (best_move, search_info) = search(
+        &position,
+        &SearchParams {
+            max_depth: Some(max_depth),
+            ..SearchParams::default()
+        },
+        MOVE_GEN,
+        POSITION_EVALUATOR,
+        &mut TranspositionTable::new(),
+        Arc::new(AtomicBool::new(false)),
+    )?;
+    let best_move = best_move.ok_or("no best move")?;
+    assert_eq!(search_info.move_evals[&best_move].to_string(), score_want);
+    Ok(())
+}
+
 #[test_case(Position::from_fen("k7/8/1R6/8/8/8/8/1R1K4 w - - 0 1").unwrap(), Move::new(B6, B7) ; "rook ladder stalemate")]
 fn test_doesnt_find_stalemate(position: Position, stalemate_move_dont_want: Move) -> TestResult {
-    let tt = TranspositionTable::new();
+    let _tt = TranspositionTable::new();
     let search_params = SearchParams {
         max_depth: Some(1),
         ..SearchParams::default()
@@ -114,3 +135,387 @@ fn test_avoids_horizon_effect(position: Position, horizon_effect_move: Move) ->
     assert_ne!(best_move_got, None);
     Ok(())
 }
+
+// Positions where the side to move is, or is about to be, in zugzwang, so a null move search
+// that lets it pass gets the wrong result. The first is from
+// https://www.chessprogramming.org/Null_Move_Test-Positions.
+#[test_case("1q1k4/2Rr4/8/2Q3K1/8/8/8/8 w - - 0 1", 6, Move::new(G5, H6), None ; "queen and rook zugzwang")]
+#[test_case("8/2r5/K7/8/3k3P/8/8/8 b - - 0 1", 8, Move::new(D4, C5), Some("mate 4") ; "pawn only side runs out of moves")]
+#[test_case("8/1K6/8/3P4/k3P3/8/6P1/7b w - - 0 1", 9, Move::new(D5, D6), None ; "pawns against bishop")]
+#[test_case("n7/N7/8/2K5/8/2P5/5k2/8 b - - 0 1", 8, Move::new(A8, C7), None ; "single knight each")]
+fn test_zugzwang(
+    fen: &str,
+    max_depth: u8,
+    best_move_want: Move,
+    score_want: Option<&str>,
+) -> TestResult {
+    let (best_move_got, search_info) = search(
+        &Position::from_fen(fen)?,
+        &SearchParams {
+            max_depth: Some(max_depth),
+            ..SearchParams::default()
+        },
+        MOVE_GEN,
+        POSITION_EVALUATOR,
+        &mut TranspositionTable::new(),
+        Arc::new(AtomicBool::new(false)),
+    )?;
+    assert_eq!(best_move_got, Some(best_move_want));
+    if let Some(score_want) = score_want {
+        assert_eq!(
+            search_info.move_evals[&best_move_want].to_string(),
+            score_want
+        );
+    }
+    Ok(())
+}
+
+#[test_case(Position::from_fen("2k5/q7/8/8/8/8/8/6QK w - - 0 1").unwrap(), MoveFilter::only_piece(Piece::King) ; "only king moves")]
+#[test_case(Position::from_fen("2k5/q7/8/8/8/8/8/6QK w - - 0 1").unwrap(), MoveFilter::keep_queens_on() ; "keep queens on")]
+fn test_search_respects_move_filter(position: Position, move_filter: MoveFilter) -> TestResult {
+    let search_params = SearchParams {
+        max_depth: Some(3),
+        move_filter: Some(move_filter.clone()),
+        ..SearchParams::default()
+    };
+    let (best_move_got, _) = search(
+        &position,
+        &search_params,
+        MOVE_GEN,
+        POSITION_EVALUATOR,
+        &mut TranspositionTable::new(),
+        Arc::new(AtomicBool::new(false)),
+    )?;
+    let best_move_got = best_move_got.expect("Should have found a move");
+    assert!(move_filter.allows(&position, best_move_got));
+    Ok(())
+}
+
+#[test_case(Position::from_fen("1k6/8/2R5/7R/8/8/8/6K1 w - - 0 1").unwrap(), 4 ; "rook ladder")]
+#[test_case(Position::start(), 4 ; "start")]
+fn test_search_pv_is_legal(position: Position, max_depth: u8) -> TestResult {
+    let search_params = SearchParams {
+        max_depth: Some(max_depth),
+        ..SearchParams::default()
+    };
+    let (best_move, search_info) = search(
+        &position,
+        &search_params,
+        MOVE_GEN,
+        POSITION_EVALUATOR,
+        &mut TranspositionTable::new(),
+        Arc::new(AtomicBool::new(false)),
+    )?;
+    assert_eq!(search_info.pv.first().copied(), best_move);
+    assert!(search_info.pv.len() <= max_depth.into());
+
+    let mut pv_position = position.clone();
+    for mve in search_info.pv {
+        assert!(MOVE_GEN.gen_moves(&pv_position).contains(&mve));
+        pv_position.make_move(mve);
+    }
+    Ok(())
+}
+
+// Black is a queen up, so it won't play Ng8 if that repeats the position a third time. The
+// earlier occurrences were played before the root, so only the position's history knows.
+#[test_case(true ; "with history")]
+#[test_case(false ; "without history")]
+fn test_search_scores_threefold_repetition_from_game_history(with_history: bool) -> TestResult {
+    let mut position =
+        Position::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNB1KBNR w KQkq - 0 1")?;
+    let shuffle = [
+        Move::new(G1, F3),
+        Move::new(G8, F6),
+        Move::new(F3, G1),
+        Move::new(F6, G8),
+    ];
+    for &mve in shuffle.iter().cycle().take(7) {
+        position.make_move(mve);
+    }
+    if !with_history {
+        position = Position::from_fen(&position.to_fen())?;
+    }
+
+    let repeat = Move::new(F6, G8);
+    let (best_move, search_info) = search(
+        &position,
+        &SearchParams {
+            max_depth: Some(3),
+            ..SearchParams::default()
+        },
+        MOVE_GEN,
+        POSITION_EVALUATOR,
+        &mut TranspositionTable::new(),
+        Arc::new(AtomicBool::new(false)),
+    )?;
+
+    assert_ne!(best_move, Some(repeat));
+    assert_eq!(search_info.move_evals[&repeat] == Eval::DRAW, with_history);
+    Ok(())
+}
+
+// Every queen or king move lets black claim a draw, so white has to push the pawn to reset the
+// clock.
+#[test_case(100 ; "fifty move rule")]
+// The game is already over, but the engine still has to reply with a move.
+#[test_case(150 ; "seventy five move rule")]
+fn test_search_half_move_clock_draws(half_move_clock: u8) -> TestResult {
+    let position = Position::from_fen(&format!(
+        "4k3/8/8/8/8/8/4P3/Q3K3 w - - {} 80",
+        half_move_clock
+    ))?;
+    assert!(!MOVE_GEN.gen_moves(&position).is_empty());
+
+    let (best_move, search_info) = search(
+        &position,
+        &SearchParams {
+            max_depth: Some(3),
+            ..SearchParams::default()
+        },
+        MOVE_GEN,
+        POSITION_EVALUATOR,
+        &mut TranspositionTable::new(),
+        Arc::new(AtomicBool::new(false)),
+    )?;
+
+    assert!(matches!(best_move, Some(mve) if mve.src == E2));
+    assert_eq!(search_info.move_evals[&Move::new(A1, A2)], Eval::DRAW);
+    Ok(())
+}
+
+// White mates in 3 starting with Kc6, but with the clock at 96 black claims a draw before the
+// rook gets there. The TT holds the mate from searching with a fresh clock, which mustn't be reused.
+#[test]
+fn test_search_ignores_tt_mate_past_fifty_move_rule() -> TestResult {
+    let fen = |half_move_clock: u8| format!("1k6/8/8/2K5/8/8/8/7R w - - {} 80", half_move_clock);
+    let mut transposition_table = TranspositionTable::new();
+    let mut search_fen = |fen: &str, max_depth: u8| {
+        search(
+            &Position::from_fen(fen)?,
+            &SearchParams {
+                max_depth: Some(max_depth),
+                ..SearchParams::default()
+            },
+            MOVE_GEN,
+            POSITION_EVALUATOR,
+            &mut transposition_table,
+            Arc::new(AtomicBool::new(false)),
+        )
+        .map_err(Box::<dyn std::error::Error>::from)
+    };
+
+    let (best_move, search_info) = search_fen(&fen(0), 8)?;
+    assert_eq!(
+        search_info.move_evals[&best_move.ok_or("no best move")?],
+        Eval::mate_in(3)
+    );
+
+    let (_, search_info) = search_fen(&fen(96), 2)?;
+    assert!(
+        search_info
+            .move_evals
+            .values()
+            .all(|eval| eval.is_mate().is_none()),
+        "{:?}",
+        search_info.move_evals
+    );
+    Ok(())
+}
+
+#[test]
+fn test_search_records_iteration_times() -> TestResult {
+    let search_params = SearchParams {
+        max_depth: Some(3),
+        ..SearchParams::default()
+    };
+    let (_, search_info) = search(
+        &Position::start(),
+        &search_params,
+        MOVE_GEN,
+        POSITION_EVALUATOR,
+        &mut TranspositionTable::new(),
+        Arc::new(AtomicBool::new(false)),
+    )?;
+    assert_eq!(search_info.iteration_times.len(), 3);
+    assert!(search_info.iteration_times.iter().sum::<Duration>() <= search_info.time_elapsed);
+    assert_eq!(search_info.iteration_nodes.len(), 3);
+    assert!(search_info.iteration_nodes.iter().sum::<u64>() <= search_info.positions_processed);
+    assert_eq!(search_info.effective_branching_factors().len(), 2);
+    assert!(search_info.effective_branching_factor().is_some());
+    assert_eq!(
+        search_info.time_to_depth().last(),
+        Some(&search_info.iteration_times.iter().sum::<Duration>())
+    );
+    Ok(())
+}
+
+#[test]
+fn test_search_reports_info_to_callback() -> TestResult {
+    let reported = Arc::new(Mutex::new(vec![]));
+    let reported_cloned = Arc::clone(&reported);
+    let search_params = SearchParams {
+        max_depth: Some(3),
+        threads: Some(2),
+        info_callback: Some(InfoCallback::new(move |info| {
+            reported_cloned.lock().unwrap().push(info.clone());
+        })),
+        ..SearchParams::default()
+    };
+    let (best_move, search_info) = search(
+        &Position::start(),
+        &search_params,
+        MOVE_GEN,
+        POSITION_EVALUATOR,
+        &mut TranspositionTable::new(),
+        Arc::new(AtomicBool::new(false)),
+    )?;
+
+    // Only the main thread reports, once per completed iteration.
+    let reported = reported.lock().unwrap();
+    assert_eq!(
+        reported.iter().map(|info| info.depth).collect::<Vec<_>>(),
+        vec![1, 2, 3]
+    );
+    let last = reported.last().unwrap();
+    assert_eq!(last.pv, search_info.pv);
+    assert_eq!(
+        Some(last.score),
+        best_move.map(|mve| search_info.move_evals[&mve])
+    );
+    Ok(())
+}
+
+#[test_case(Some(1_000), true ; "frequent")]
+#[test_case(Some(0), false ; "turned off")]
+fn test_search_reports_periodic_info(
+    info_interval_nodes: Option<u64>,
+    periodic_want: bool,
+) -> TestResult {
+    let reported = Arc::new(Mutex::new(vec![]));
+    let reported_cloned = Arc::clone(&reported);
+    let search_params = SearchParams {
+        max_depth: Some(4),
+        info_interval_nodes,
+        info_callback: Some(InfoCallback::new(move |info| {
+            reported_cloned.lock().unwrap().push(info.clone());
+        })),
+        ..SearchParams::default()
+    };
+    let (_, search_info) = search(
+        &Position::start(),
+        &search_params,
+        MOVE_GEN,
+        POSITION_EVALUATOR,
+        &mut TranspositionTable::new(),
+        Arc::new(AtomicBool::new(false)),
+    )?;
+
+    // Periodic reports are the ones without a PV, and every completed depth is still reported
+    // exactly once.
+    let reported = reported.lock().unwrap();
+    let (periodic, completed): (Vec<_>, Vec<_>) =
+        reported.iter().partition(|info| info.pv.is_empty());
+    assert_eq!(!periodic.is_empty(), periodic_want);
+    assert!(
+        periodic
+            .iter()
+            .all(|info| info.nodes % 1_000 == 0 && info.nodes <= search_info.positions_processed)
+    );
+    assert_eq!(
+        completed.iter().map(|info| info.depth).collect::<Vec<_>>(),
+        vec![1, 2, 3, 4]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_search_summary() -> TestResult {
+    let position = Position::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1")?;
+    let (best_move, search_info) = search(
+        &position,
+        &SearchParams {
+            max_depth: Some(3),
+            ..SearchParams::default()
+        },
+        MOVE_GEN,
+        POSITION_EVALUATOR,
+        &mut TranspositionTable::new(),
+        Arc::new(AtomicBool::new(false)),
+    )?;
+
+    let summary = search_info.summary(&position, best_move, MOVE_GEN);
+    assert_eq!(summary.nodes, search_info.positions_processed);
+    assert_eq!(summary.time, search_info.time_elapsed);
+    assert_eq!(summary.best_move_san.as_deref(), Some("Ra8#"));
+    Ok(())
+}
+
+#[test]
+fn test_search_reproducible_after_clearing_transposition_table() -> TestResult {
+    let position =
+        Position::from_fen("r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4")?;
+    let search_params = SearchParams {
+        max_depth: Some(3),
+        ..SearchParams::default()
+    };
+    let search_position = |transposition_table: &mut TranspositionTable| {
+        search(
+            &position,
+            &search_params,
+            MOVE_GEN,
+            POSITION_EVALUATOR,
+            transposition_table,
+            Arc::new(AtomicBool::new(false)),
+        )
+    };
+    let (best_move_want, search_info_want) = search_position(&mut TranspositionTable::new())?;
+
+    // Same as a new session, once the state left by an earlier search is cleared.
+    let mut transposition_table = TranspositionTable::new();
+    search(
+        &Position::start(),
+        &search_params,
+        MOVE_GEN,
+        POSITION_EVALUATOR,
+        &mut transposition_table,
+        Arc::new(AtomicBool::new(false)),
+    )?;
+    transposition_table.clear();
+    let (best_move, search_info) = search_position(&mut transposition_table)?;
+
+    assert_eq!(best_move, best_move_want);
+    assert_eq!(search_info.move_evals, search_info_want.move_evals);
+    assert_eq!(search_info.pv, search_info_want.pv);
+    assert_eq!(
+        search_info.positions_processed,
+        search_info_want.positions_processed
+    );
+    Ok(())
+}
+
+#[test_case(Position::from_fen("1k6/8/2R5/7R/8/8/8/6K1 w - - 0 1").unwrap(), Move::new(H5, H7) ; "rook ladder in 3")]
+#[test_case(Position::from_fen("2k5/q7/8/8/8/8/8/6QK w - - 0 1").unwrap(), Move::new(G1, A7) ; "obvious queen capture")]
+fn test_lazy_smp_search(position: Position, best_move_want: Move) -> TestResult {
+    let search_params = SearchParams {
+        max_depth: Some(4),
+        threads: Some(4),
+        ..SearchParams::default()
+    };
+    let (best_move, search_info) = search(
+        &position,
+        &search_params,
+        MOVE_GEN,
+        POSITION_EVALUATOR,
+        &mut TranspositionTable::new(),
+        Arc::new(AtomicBool::new(false)),
+    )?;
+
+    assert_eq!(best_move, Some(best_move_want));
+    assert_eq!(search_info.thread_positions_processed.len(), 4);
+    assert_eq!(
+        search_info.positions_processed,
+        search_info.thread_positions_processed.iter().sum::<u64>()
+    );
+    Ok(())
+}
@@ -0,0 +1,191 @@
+use std::{
+    sync::{Arc, atomic::AtomicBool},
+    time::Duration,
+};
+use test_case::test_case;
+
+use engine::{
+    Clock, GenerateMoves, MOVE_GEN, MockClock, POSITION_EVALUATOR, Position, SearchParams,
+    TranspositionTable, search, search_with_clock,
+};
+
+/// Moves played by the engine in each simulated game.
+const NUM_MOVES: u16 = 40;
+/// Simulated time each clock read takes, so time passes in proportion to the nodes searched.
+const CLOCK_STEP: Duration = Duration::from_millis(1);
+
+/// Plays a game where the engine is white on a simulated clock, against an opponent which
+/// replies instantly. With `moves_to_go`, `start_time` is added to the clock after every time
+/// control. Returns the time left on white's clock after each of its moves.
+fn play_on_clock(
+    start_time: Duration,
+    time_inc: Option<Duration>,
+    moves_to_go: Option<u16>,
+) -> Vec<Duration> {
+    let mut position = Position::start();
+    let mut transposition_table = TranspositionTable::with_num_entries_power_of_two(16);
+    let mut time_left = start_time;
+    let mut times_left = vec![];
+
+    for move_idx in 0..NUM_MOVES {
+        // The clock only cares how many moves are played, so start over when a game ends.
+        if MOVE_GEN.gen_moves(&position).is_empty() {
+            position = Position::start();
+        }
+
+        let clock = MockClock::auto_advancing(CLOCK_STEP);
+        let (best_move, _) = search_with_clock(
+            &position,
+            &SearchParams {
+                white_time: Some(time_left),
+                white_inc: time_inc,
+                moves_to_go: moves_to_go.map(|moves_to_go| moves_to_go - move_idx % moves_to_go),
+                ..Default::default()
+            },
+            MOVE_GEN,
+            POSITION_EVALUATOR,
+            &mut transposition_table,
+            Arc::new(AtomicBool::new(false)),
+            &clock,
+        )
+        .unwrap();
+        let time_used = clock.now();
+        assert!(
+            time_used < time_left,
+            "Lost on time on move {}: used {:?} with {:?} left",
+            move_idx + 1,
+            time_used,
+            time_left
+        );
+        time_left = time_left - time_used + time_inc.unwrap_or_default();
+        times_left.push(time_left);
+        if let Some(moves_to_go) = moves_to_go
+            && (move_idx + 1) % moves_to_go == 0
+        {
+            time_left += start_time;
+        }
+        position.make_move(best_move.unwrap());
+
+        if let (Some(reply), _) = search(
+            &position,
+            &SearchParams {
+                max_depth: Some(2),
+                ..Default::default()
+            },
+            MOVE_GEN,
+            POSITION_EVALUATOR,
+            &mut TranspositionTable::with_num_entries_power_of_two(10),
+            Arc::new(AtomicBool::new(false)),
+        )
+        .unwrap()
+        {
+            position.make_move(reply);
+        }
+    }
+    times_left
+}
+
+#[test_case(Duration::from_secs(60), 0.5 ; "one minute")]
+#[test_case(Duration::from_secs(5), 0.5 ; "five seconds")]
+fn test_sudden_death(start_time: Duration, min_fraction_used: f64) {
+    let times_left = play_on_clock(start_time, None, None);
+
+    let time_used = start_time - *times_left.last().unwrap();
+    assert!(
+        time_used.as_secs_f64() >= start_time.as_secs_f64() * min_fraction_used,
+        "Only used {:?} of {:?}",
+        time_used,
+        start_time
+    );
+}
+
+#[test_case(Duration::from_secs(30), Duration::from_millis(500) ; "increment")]
+#[test_case(Duration::from_secs(2), Duration::from_secs(1) ; "increment larger than base")]
+fn test_increment(start_time: Duration, time_inc: Duration) {
+    let times_left = play_on_clock(start_time, Some(time_inc), None);
+
+    // Most of the increment is used every move, rather than being saved up.
+    let time_left = *times_left.last().unwrap();
+    assert!(
+        time_left < start_time + time_inc * u32::from(NUM_MOVES) / 2,
+        "Saved up {:?} from {:?} with {:?} increment",
+        time_left,
+        start_time,
+        time_inc
+    );
+}
+
+#[test_case(Duration::from_secs(60), 40 ; "40 moves")]
+#[test_case(Duration::from_secs(20), 10 ; "10 moves")]
+fn test_moves_to_go(time_per_control: Duration, moves_per_control: u16) {
+    let times_left = play_on_clock(time_per_control, None, Some(moves_per_control));
+
+    let time_left_at_control = times_left[usize::from(moves_per_control) - 1];
+    assert!(
+        time_left_at_control.as_secs_f64() <= time_per_control.as_secs_f64() * 0.2,
+        "{:?} of {:?} left at the time control",
+        time_left_at_control,
+        time_per_control
+    );
+}
+
+#[test_case(SearchParams { white_time: Some(Duration::ZERO), ..Default::default() } ; "no time left")]
+#[test_case(SearchParams { white_time: Some(Duration::from_millis(5)), white_inc: Some(Duration::from_secs(1)), ..Default::default() } ; "time left less than increment")]
+#[test_case(SearchParams { white_time: Some(Duration::from_secs(10)), moves_to_go: Some(0), ..Default::default() } ; "zero moves to go")]
+#[test_case(SearchParams { move_time: Some(Duration::ZERO), ..Default::default() } ; "zero move time")]
+fn test_degenerate_clock(params: SearchParams) {
+    let position = Position::start();
+    let clock = MockClock::auto_advancing(CLOCK_STEP);
+    let (best_move, _) = search_with_clock(
+        &position,
+        &params,
+        MOVE_GEN,
+        POSITION_EVALUATOR,
+        &mut TranspositionTable::with_num_entries_power_of_two(16),
+        Arc::new(AtomicBool::new(false)),
+        &clock,
+    )
+    .unwrap();
+
+    assert!(MOVE_GEN.gen_moves(&position).contains(&best_move.unwrap()));
+    let time_left = params.white_time.unwrap_or(Duration::from_secs(10));
+    assert!(
+        clock.now() <= time_left.max(Duration::from_millis(50)),
+        "Used {:?} with {:?} left",
+        clock.now(),
+        time_left
+    );
+}
+
+#[test]
+fn test_max_cpu_percent() {
+    // Time only passes when the clock is read or the search sleeps. With a move time the clock
+    // is read at every node, so throttling to a quarter of a CPU makes the same search take
+    // about 4 times as long.
+    let time_taken = |max_cpu_percent: Option<u8>| {
+        let clock = MockClock::auto_advancing(Duration::from_micros(1));
+        search_with_clock(
+            &Position::start(),
+            &SearchParams {
+                max_nodes: Some(50_000),
+                move_time: Some(Duration::from_secs(3_600)),
+                max_cpu_percent,
+                ..Default::default()
+            },
+            MOVE_GEN,
+            POSITION_EVALUATOR,
+            &mut TranspositionTable::with_num_entries_power_of_two(16),
+            Arc::new(AtomicBool::new(false)),
+            &clock,
+        )
+        .unwrap();
+        clock.now().as_secs_f64()
+    };
+
+    let ratio = time_taken(Some(25)) / time_taken(None);
+    assert!(
+        (3.5..=4.5).contains(&ratio),
+        "Took {:.2} times as long",
+        ratio
+    );
+}
@@ -0,0 +1,16 @@
+#![no_main]
+
+use engine::{GenerateMoves, MOVE_GEN, Position};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|fen: &str| {
+    let Ok(mut position) = Position::from_fen(fen) else {
+        return;
+    };
+    // Positions that parse must be usable, so exercise the rest of the engine on them too.
+    let _ = position.to_fen();
+    for mve in MOVE_GEN.gen_moves(&position) {
+        let unmake_move_state = position.make_move(mve);
+        position.unmake_move(unmake_move_state);
+    }
+});
@@ -0,0 +1,8 @@
+#![no_main]
+
+use engine::{MOVE_GEN, parse_pgn};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|pgn: &str| {
+    let _ = parse_pgn(pgn, MOVE_GEN);
+});
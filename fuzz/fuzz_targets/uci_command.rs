@@ -0,0 +1,12 @@
+#![no_main]
+
+use std::str::FromStr;
+
+use cli::UCICommand;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: &str| {
+    if let Ok(command) = UCICommand::from_str(input) {
+        let _ = command.to_string();
+    }
+});